@@ -0,0 +1,189 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, spanned::Spanned, Attribute, Data, DeriveInput, Fields, Ident, Index, Member, Meta, NestedMeta};
+
+#[derive(Copy, Clone)]
+enum Layout {
+    Std140,
+    Std430,
+}
+
+impl Layout {
+    fn name(&self) -> &'static str {
+        match self {
+            Layout::Std140 => "std140",
+            Layout::Std430 => "std430",
+        }
+    }
+
+    fn trait_ident(&self) -> Ident {
+        match self {
+            Layout::Std140 => Ident::new("Std140", proc_macro2::Span::call_site()),
+            Layout::Std430 => Ident::new("Std430", proc_macro2::Span::call_site()),
+        }
+    }
+}
+
+/// `#[block(padding)]` marks a field filling the gap before the next member.
+fn is_padding(attrs: &[Attribute]) -> syn::Result<bool> {
+    let mut padding = false;
+    for attr in attrs.iter().filter(|a| a.path.is_ident("block")) {
+        match attr.parse_meta()? {
+            Meta::List(list) => {
+                for nested in list.nested.iter() {
+                    match nested {
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("padding") => padding = true,
+                        nested => return Err(syn::Error::new(nested.span(), "Invalid block option, expected padding.")),
+                    }
+                }
+            }
+            meta => return Err(syn::Error::new(meta.span(), "Expected #[block(padding)].")),
+        }
+    }
+    Ok(padding)
+}
+
+fn is_repr_c(attrs: &[Attribute]) -> bool {
+    attrs.iter().filter(|a| a.path.is_ident("repr")).any(|a| match a.parse_meta() {
+        Ok(Meta::List(list)) => list
+            .nested
+            .iter()
+            .any(|n| matches!(n, NestedMeta::Meta(Meta::Path(p)) if p.is_ident("C"))),
+        _ => false,
+    })
+}
+
+fn impl_block(input: DeriveInput, layout: Layout) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let layout_name = layout.name();
+    let layout_trait = layout.trait_ident();
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new(input.generics.span(), "A block can't be generic."));
+    }
+    if !is_repr_c(&input.attrs) {
+        return Err(syn::Error::new(
+            name.span(),
+            "A block must be #[repr(C)], the field order is the memory layout.",
+        ));
+    }
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => return Err(syn::Error::new(name.span(), "Only a struct can be a block.")),
+    };
+    let members: Vec<Member> = match fields {
+        Fields::Named(named) => named
+            .named
+            .iter()
+            .map(|f| Member::Named(f.ident.clone().unwrap()))
+            .collect(),
+        Fields::Unnamed(unnamed) => (0..unnamed.unnamed.len())
+            .map(|i| Member::Unnamed(Index::from(i)))
+            .collect(),
+        Fields::Unit => Vec::new(),
+    };
+
+    let mut aligns = Vec::new();
+    let mut sizes = Vec::new();
+    let mut checks = Vec::new();
+    for (field, member) in fields.iter().zip(members) {
+        if is_padding(&field.attrs)? {
+            continue;
+        }
+        let ty = &field.ty;
+        let field_name = match &member {
+            Member::Named(ident) => ident.to_string(),
+            Member::Unnamed(index) => index.index.to_string(),
+        };
+        let offset_message = format!(
+            "{}::{} is not at its {} offset, add a #[block(padding)] field before it.",
+            name, field_name, layout_name
+        );
+        let size_message = format!(
+            "{}::{} is not as big as in a {} block, arrays of scalars are padded, use the vectors.",
+            name, field_name, layout_name
+        );
+        aligns.push(quote! {
+            let align = ::rseed_renderapi::block::max(align, <#ty as ::rseed_renderapi::block::#layout_trait>::ALIGN);
+        });
+        sizes.push(quote! {
+            let offset = ::rseed_renderapi::block::align_to(offset, <#ty as ::rseed_renderapi::block::#layout_trait>::ALIGN)
+                + <#ty as ::rseed_renderapi::block::#layout_trait>::SIZE;
+        });
+        checks.push(quote! {
+            let offset = ::rseed_renderapi::block::align_to(offset, <#ty as ::rseed_renderapi::block::#layout_trait>::ALIGN);
+            assert!(::std::mem::offset_of!(#name, #member) == offset, #offset_message);
+            assert!(
+                ::std::mem::size_of::<#ty>() == <#ty as ::rseed_renderapi::block::#layout_trait>::SIZE,
+                #size_message
+            );
+            let offset = offset + <#ty as ::rseed_renderapi::block::#layout_trait>::SIZE;
+        });
+    }
+    let struct_align = match layout {
+        Layout::Std140 => quote! { ::rseed_renderapi::block::align_to(align, 16) },
+        Layout::Std430 => quote! { align },
+    };
+    let size_message = format!(
+        "{} must be padded to a multiple of its {} alignment with #[block(padding)] fields.",
+        name, layout_name
+    );
+
+    Ok(quote! {
+        unsafe impl ::rseed_renderapi::block::#layout_trait for #name {
+            const ALIGN: usize = {
+                let align = 1;
+                #(#aligns)*
+                #struct_align
+            };
+            const SIZE: usize = {
+                let offset = 0;
+                #(#sizes)*
+                ::rseed_renderapi::block::align_to(offset, <Self as ::rseed_renderapi::block::#layout_trait>::ALIGN)
+            };
+        }
+
+        const _: () = {
+            let offset = 0;
+            #(#checks)*
+            let _ = offset;
+            assert!(
+                ::std::mem::size_of::<#name>() == <#name as ::rseed_renderapi::block::#layout_trait>::SIZE,
+                #size_message
+            );
+        };
+    })
+}
+
+fn derive(item: TokenStream, layout: Layout) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    impl_block(input, layout).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+/// Implement `rseed_renderapi::block::Std140` for a `#[repr(C)]` struct laid out as the uniform
+/// block, the compilation fails when a field is not where std140 puts it.
+/// The gaps are filled with fields marked `#[block(padding)]`, they are not block members.
+/// `Mat4` is stored by rows, the blocks holding one must be declared `layout(row_major)`.
+///
+/// ```ignore
+/// #[repr(C)]
+/// #[derive(Copy, Clone, Std140)]
+/// struct Light {
+///     position: Vec3D,
+///     intensity: f32,
+///     color: Vec3D,
+///     #[block(padding)]
+///     _pad: f32,
+/// }
+/// ```
+#[proc_macro_derive(Std140, attributes(block))]
+pub fn std140_derive(item: TokenStream) -> TokenStream {
+    derive(item, Layout::Std140)
+}
+
+/// Implement `rseed_renderapi::block::Std430` for a `#[repr(C)]` struct laid out as the storage
+/// block, as `#[derive(Std140)]` does.
+#[proc_macro_derive(Std430, attributes(block))]
+pub fn std430_derive(item: TokenStream) -> TokenStream {
+    derive(item, Layout::Std430)
+}
@@ -5,8 +5,13 @@ use rseed_core::{
     prelude::*,
     utils::Version,
 };
-use rseed_renderer::{Renderer};
-pub use rseed_renderapi::Backend;
+use rseed_renderer::{
+    shaderlib::{Preprocessor, ShaderLibrary},
+    HotReload,
+    Renderer,
+};
+pub use rseed_renderapi::{Backend, settings::RenderSettings};
+use rseed_renderapi::{context, handle::PipelineHandle, pipeline::PipelineDesc};
 use super::ProjectInfo;
 
 use glutin::{
@@ -41,17 +46,20 @@ pub enum AppError {
 
 pub struct App {
     pub logger: Logger,
-    event_loop: EventLoop<()>,
+    /// `None` for the headless backend, which has no window.
+    event_loop: Option<EventLoop<()>>,
     renderer: Renderer,
-    window: Window,
+    window: Option<Window>,
     data: World,
+    /// Enabled by `hot_reload` in the `[dev]` table of the project.
+    hot_reload: Option<HotReload>,
 }
 
 impl App {
 
     pub fn from_toml_config() -> Result<Self> {
         let manifest_dir = std::path::PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap().as_str());
-        let md_contents = std::fs::read_dir(manifest_dir).unwrap();
+        let md_contents = std::fs::read_dir(&manifest_dir).unwrap();
 
         let ppf = {
         md_contents.filter_map(
@@ -79,16 +87,51 @@ impl App {
         std::io::Read::read_to_end(&mut file, &mut contents)
             .map_err(|e| AppError::IoError(e))?;
         let toml = String::from_utf8(contents).unwrap();
-        let proj : ProjectInfo = from_str(toml.as_str())
+        let mut proj : ProjectInfo = from_str(toml.as_str())
             .map_err(|e| AppError::ProjectFileInvalid(e))?;
-        Self::init(proj.window.width, proj.window.height, proj.name, proj.version, proj.window.render_backend)
+        let asset_root = manifest_dir.join(&proj.asset_root);
+        proj.render.shader_root = asset_root.clone();
+        let mut app = Self::init(
+            proj.window.width,
+            proj.window.height,
+            proj.window.resizable,
+            proj.name,
+            proj.version,
+            proj.window.render_backend,
+            &proj.render,
+        )?;
+        if proj.dev.hot_reload {
+            match ShaderLibrary::new(Preprocessor::new(asset_root)) {
+                Ok(library) => app.hot_reload = Some(HotReload::new(library)),
+                Err(e) => app.logger.error(&format!("The shader hot reload is disabled: {}", e)),
+            }
+        }
+        Ok(app)
     }
 
-    pub fn init(width: u32, height: u32, app_name : String, app_version : Version, backend : Backend) -> Result<Self> {
+    pub fn init(
+        width: u32,
+        height: u32,
+        resizable: bool,
+        app_name : String,
+        app_version : Version,
+        backend : Backend,
+        settings : &RenderSettings,
+    ) -> Result<Self> {
         let logger = Logger::new(String::from_str("RS-eed").unwrap());
+        if let Backend::HEADLESS = backend {
+            return Ok(Self {
+                logger,
+                event_loop: None,
+                renderer: Renderer::init_headless(width, height),
+                window: None,
+                data: World::new(),
+                hot_reload: None,
+            });
+        }
         let event_loop = EventLoop::new();
         let window_builder = WindowBuilder::new()
-            .with_resizable(false)
+            .with_resizable(resizable)
             .with_inner_size(glutin::dpi::Size::Physical(glutin::dpi::PhysicalSize::new(
                 width, height,
             )))
@@ -101,15 +144,17 @@ impl App {
             app_name,
             app_version,
             backend,
+            settings,
         ).unwrap();
 
 
         Ok(Self {
             logger,
-            event_loop,
+            event_loop: Some(event_loop),
             renderer,
-            window,
+            window: Some(window),
             data: World::new(),
+            hot_reload: None,
         })
     }
 
@@ -117,14 +162,62 @@ impl App {
        &self.data
     }
      
+    pub fn renderer(&self) -> &Renderer {
+        &self.renderer
+    }
+
+    pub fn window(&self) -> Option<&Window> {
+        self.window.as_ref()
+    }
+
+    /// Where the shaders are registered for the hot reload, `None` when it is disabled.
+    pub fn hot_reload(&mut self) -> Option<&mut HotReload> {
+        self.hot_reload.as_mut()
+    }
+
+    /// Create a pipeline of the renderer, rebuilt when its shaders change if the hot reload is enabled.
+    pub fn create_pipeline(&mut self, desc: &PipelineDesc) -> context::Result<PipelineHandle> {
+        let ctx = self.renderer.context();
+        let handle = ctx.create_pipeline(desc)?;
+        if let Some(hot_reload) = self.hot_reload.as_mut() {
+            match ctx.pipeline_files(handle) {
+                Ok(files) => hot_reload.watch_pipeline(&desc.shader, handle, files),
+                Err(e) => self.logger.error(&format!(
+                    "The pipeline {} is not reloaded: {}", desc.shader, e
+                )),
+            }
+        }
+        Ok(handle)
+    }
+
+    /// Without window the app draws a single frame and returns.
     pub fn run(self) {
         self.logger.info(&String::from("The app is running!"));
         let renderer = self.renderer;
-        self.event_loop.run(move |event, _, cf| match event {
+        let logger = self.logger;
+        let mut hot_reload = self.hot_reload;
+        let event_loop = match self.event_loop {
+            Some(event_loop) => event_loop,
+            None => return renderer.draw(),
+        };
+        event_loop.run(move |event, _, cf| match event {
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
                 ..
             } => *cf = ControlFlow::Exit,
+            Event::WindowEvent {
+                event: WindowEvent::Resized(size),
+                ..
+            } => renderer.resize(size.width, size.height),
+            Event::WindowEvent {
+                event: WindowEvent::ScaleFactorChanged { new_inner_size, .. },
+                ..
+            } => renderer.resize(new_inner_size.width, new_inner_size.height),
+            Event::MainEventsCleared => {
+                if let Some(hot_reload) = hot_reload.as_mut() {
+                    hot_reload.poll(renderer.context(), &logger);
+                }
+            }
             Event::RedrawRequested(_) => renderer.draw(),
             _ => (),
         })
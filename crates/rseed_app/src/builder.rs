@@ -4,7 +4,7 @@ use rseed_core::{
     utils::Version,
     prelude::*,
 };
-use rseed_renderapi::Backend;
+use rseed_renderapi::{Backend, settings::RenderSettings};
 
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -15,6 +15,10 @@ pub struct ProjectInfo {
     pub asset_root : PathBuf,
     pub main_scene : PathBuf,
     pub window : Window,
+    #[serde(default)]
+    pub render : RenderSettings,
+    #[serde(default)]
+    pub dev : DevSettings,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -26,4 +30,13 @@ pub struct Window {
     pub visible : bool,
     pub resizable : bool,
     pub render_backend : Backend,
-}
\ No newline at end of file
+}
+
+/// Helpers for the development of a project, all disabled by default.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(crate = "serde")]
+pub struct DevSettings {
+    /// Reload the shaders when their files change.
+    #[serde(default)]
+    pub hot_reload : bool,
+}
@@ -1,4 +1,5 @@
 use gl::{self, types::*};
+use rseed_renderapi::block::{std140_bytes, std430_bytes, Std140, Std430};
 use std::mem::size_of;
 
 pub trait BufferType {
@@ -19,6 +20,20 @@ impl BufferType for IndexBufferType {
 
 pub type IndexBuffer = Buffer<IndexBufferType>;
 
+pub struct UniformBufferType;
+impl BufferType for UniformBufferType {
+    const TYPE: GLuint = gl::UNIFORM_BUFFER;
+}
+
+pub type UniformBuffer = Buffer<UniformBufferType>;
+
+pub struct ShaderStorageBufferType;
+impl BufferType for ShaderStorageBufferType {
+    const TYPE: GLuint = gl::SHADER_STORAGE_BUFFER;
+}
+
+pub type ShaderStorageBuffer = Buffer<ShaderStorageBufferType>;
+
 /// Generic Buffer struct for all OpenGL buffer types
 pub struct Buffer<B>
 where
@@ -46,20 +61,42 @@ where
 
     /// Set the buffer data on the GPU
     pub fn set_data<T>(&self, data: &[T]) {
+        self.set_data_with_usage(data, gl::STATIC_DRAW)
+    }
+
+    /// Set the buffer data on the GPU with a usage hint like `gl::DYNAMIC_DRAW`
+    pub fn set_data_with_usage<T>(&self, data: &[T], usage: GLenum) {
         self.bind();
         unsafe {
             self.gl.BufferData(
                 B::TYPE,
                 (data.len() * size_of::<T>()) as GLsizeiptr,
                 data.as_ptr() as *const GLvoid,
-                gl::STATIC_DRAW,
+                usage,
             )
         }
     }
 
+    /// Bind the whole buffer to the binding point `index` of the blocks, for the uniform and storage buffers.
+    pub fn bind_base(&self, index: GLuint) {
+        unsafe { self.gl.BindBufferBase(B::TYPE, index, self.id) };
+    }
+
+    /// Bind `size` bytes from `offset` to the binding point `index` of the blocks.
+    /// The offset must be a multiple of the alignment of the buffer type, see `offset_alignment`.
+    pub fn bind_range(&self, index: GLuint, offset: usize, size: usize) {
+        unsafe {
+            self.gl
+                .BindBufferRange(B::TYPE, index, self.id, offset as GLintptr, size as GLsizeiptr)
+        };
+    }
+
     pub fn id(&self) -> GLuint {
         self.id
     }
+    pub fn gl(&self) -> &gl::Gl {
+        &self.gl
+    }
     pub fn bind(&self) {
         unsafe { self.gl.BindBuffer(B::TYPE, self.id) };
     }
@@ -67,3 +104,38 @@ where
         unsafe { self.gl.BindBuffer(B::TYPE, 0 as GLuint) };
     }
 }
+
+impl UniformBuffer {
+    /// Upload a uniform block.
+    pub fn set_block<T: Std140>(&self, value: &T, usage: GLenum) {
+        self.set_data_with_usage(std140_bytes(value), usage)
+    }
+}
+
+impl ShaderStorageBuffer {
+    /// Upload the array of a storage block.
+    pub fn set_blocks<T: Std430>(&self, values: &[T], usage: GLenum) {
+        self.set_data_with_usage(std430_bytes(values), usage)
+    }
+}
+
+/// The alignment of the offsets bound with `bind_range` for the uniform and storage buffers, 1 for the others.
+pub fn offset_alignment(gl: &gl::Gl, ty: GLenum) -> usize {
+    let name = match ty {
+        gl::UNIFORM_BUFFER => gl::UNIFORM_BUFFER_OFFSET_ALIGNMENT,
+        gl::SHADER_STORAGE_BUFFER => gl::SHADER_STORAGE_BUFFER_OFFSET_ALIGNMENT,
+        _ => return 1,
+    };
+    let mut align = 0;
+    unsafe { gl.GetIntegerv(name, &mut align) };
+    align.max(1) as usize
+}
+
+impl<B> Drop for Buffer<B>
+where
+    B: BufferType,
+{
+    fn drop(&mut self) {
+        unsafe { self.gl.DeleteBuffers(1, &self.id) };
+    }
+}
@@ -1,6 +1,23 @@
 use gl;
+use std::path::PathBuf;
 
 use glutin::{NotCurrent, PossiblyCurrent};
+use rseed_renderapi::{
+    buffer::{BufferUsage, IndexBufferT, IndexData, UniformBufferT, VertexBufferT, VertexLayout},
+    context::{self, ContextT, ResourceError},
+    handle::PipelineHandle,
+    pipeline::PipelineDesc,
+    settings::RenderSettings,
+    target::{RenderTargetDesc, RenderTargetT},
+    texture::{SamplerDesc, SamplerT, TextureDesc, TextureT},
+};
+
+use rseed_shaderlib::Preprocessor;
+
+use super::framebuffer::GlRenderTarget;
+use super::pipeline::{GlPipeline, Pipelines};
+use super::resource::{GlIndexBuffer, GlUniformBuffer, GlVertexBuffer, VertexLayouts};
+use super::texture::{GlSampler, GlTexture};
 
 #[derive(Debug)]
 pub enum ContextError {
@@ -10,9 +27,14 @@ pub enum ContextError {
 pub type Result<T> = std::result::Result<T, ContextError>;
 
 
+/// The programs are deleted before the context is destroyed.
 pub struct GlContext {
+    pub(crate) pipelines : Pipelines,
+    /// Reads the shaders of the pipelines from the shader root.
+    preprocessor : Preprocessor,
     pub gl : gl::Gl,
     pub raw_ctx : glutin::RawContext<PossiblyCurrent>,
+    pub(crate) vertex_layouts : VertexLayouts,
 } 
 
 
@@ -20,7 +42,8 @@ pub struct GlContext {
 impl GlContext
 {
     pub fn init(
-        raw_context : glutin::RawContext<NotCurrent>
+        raw_context : glutin::RawContext<NotCurrent>,
+        settings : &RenderSettings,
     ) -> Result<Self> {
 
         let raw_ctx = unsafe {raw_context.make_current()}
@@ -29,14 +52,84 @@ impl GlContext
         println!("Acitve ctx ? {}", raw_ctx.is_current());
         let gl = gl::Gl::load_with(|s| raw_ctx.get_proc_address(s));
         Ok(Self {
+            pipelines : Pipelines::default(),
+            preprocessor : Preprocessor::new(settings.shader_root.clone()),
             gl,
             raw_ctx,
+            vertex_layouts : VertexLayouts::default(),
         })
     }
 
     pub fn swap_buffers(&self) {
         self.raw_ctx.swap_buffers().unwrap()
     } 
+
+    /// Some platforms need the default framebuffer to be resized with the window.
+    pub fn resize(&self, width : u32, height : u32) {
+        self.raw_ctx.resize(glutin::dpi::PhysicalSize::new(width, height))
+    }
+}
+
+impl ContextT for GlContext {
+    fn swap_buffers(&self) {
+        self.raw_ctx.swap_buffers().unwrap()
+    }
+
+    fn create_vertex_buffer(
+        &self,
+        data: &[u8],
+        layout: VertexLayout,
+        usage: BufferUsage,
+    ) -> context::Result<Box<dyn VertexBufferT>> {
+        Ok(Box::new(GlVertexBuffer::new(&self.gl, &self.vertex_layouts, data, layout, usage)?))
+    }
+
+    fn create_index_buffer(&self, data: IndexData, usage: BufferUsage) -> context::Result<Box<dyn IndexBufferT>> {
+        Ok(Box::new(GlIndexBuffer::new(&self.gl, data, usage)?))
+    }
+
+    fn create_uniform_buffer(&self, data: &[u8], usage: BufferUsage) -> context::Result<Box<dyn UniformBufferT>> {
+        Ok(Box::new(GlUniformBuffer::new(&self.gl, data, usage)))
+    }
+
+    fn create_texture(&self, desc: TextureDesc, data: Option<&[u8]>) -> context::Result<Box<dyn TextureT>> {
+        Ok(Box::new(GlTexture::new(&self.gl, desc, data)?))
+    }
+
+    fn create_sampler(&self, desc: SamplerDesc) -> context::Result<Box<dyn SamplerT>> {
+        Ok(Box::new(GlSampler::new(&self.gl, desc)))
+    }
+
+    fn create_render_target(&self, desc: RenderTargetDesc) -> context::Result<Box<dyn RenderTargetT>> {
+        Ok(Box::new(GlRenderTarget::new(&self.gl, desc)?))
+    }
+
+    fn create_pipeline(&self, desc: &PipelineDesc) -> context::Result<PipelineHandle> {
+        let pipeline = GlPipeline::new(&self.gl, &self.preprocessor, desc)?;
+        let handle = PipelineHandle(pipeline.program.id());
+        self.pipelines.borrow_mut().insert(handle.0, pipeline);
+        Ok(handle)
+    }
+
+    /// The program is linked again in place, its uniforms must be set again.
+    fn rebuild_pipeline(&self, handle: PipelineHandle) -> context::Result<Vec<PathBuf>> {
+        let mut pipelines = self.pipelines.borrow_mut();
+        let pipeline = pipelines
+            .get_mut(&handle.0)
+            .ok_or(ResourceError::NoPipeline(handle.0))?;
+        pipeline
+            .program
+            .reload(&self.preprocessor)
+            .map_err(|e| ResourceError::Backend(e.to_string()))?;
+        Ok(pipeline.program.files().to_vec())
+    }
+
+    fn pipeline_files(&self, handle: PipelineHandle) -> context::Result<Vec<PathBuf>> {
+        match self.pipelines.borrow().get(&handle.0) {
+            Some(pipeline) => Ok(pipeline.program.files().to_vec()),
+            None => Err(ResourceError::NoPipeline(handle.0)),
+        }
+    }
 }
 
 impl Drop for GlContext {
@@ -0,0 +1,295 @@
+// External imports
+use gl::types::*;
+// Crate imports
+use rseed_renderapi::{
+    context::{ResourceError, Result},
+    handle::{RenderTargetHandle, TextureHandle},
+    image::Image,
+    target::{RenderTargetDesc, RenderTargetT},
+    texture::{TextureDesc, TextureFormat, TextureT},
+};
+
+use super::texture::{gl_texture_format, GlTexture};
+
+/// A multisampled attachment, only read by the resolve.
+struct Renderbuffer {
+    gl: gl::Gl,
+    id: GLuint,
+}
+
+impl Renderbuffer {
+    fn new(gl: &gl::Gl, format: TextureFormat, width: u32, height: u32, samples: u32) -> Self {
+        let (internal, _, _) = gl_texture_format(format);
+        let mut id = 0;
+        unsafe {
+            gl.CreateRenderbuffers(1, &mut id);
+            gl.NamedRenderbufferStorageMultisample(
+                id,
+                samples as GLsizei,
+                internal,
+                width as GLsizei,
+                height as GLsizei,
+            );
+        }
+        Self { gl: gl.clone(), id }
+    }
+}
+
+impl Drop for Renderbuffer {
+    fn drop(&mut self) {
+        unsafe { self.gl.DeleteRenderbuffers(1, &self.id) };
+    }
+}
+
+fn depth_attachment(format: TextureFormat) -> GLenum {
+    match format {
+        TextureFormat::Depth24Stencil8 => gl::DEPTH_STENCIL_ATTACHMENT,
+        _ => gl::DEPTH_ATTACHMENT,
+    }
+}
+
+fn color_attachments(count: usize) -> Vec<GLenum> {
+    (0..count as GLenum).map(|i| gl::COLOR_ATTACHMENT0 + i).collect()
+}
+
+/// A framebuffer object with its attachments.
+///
+/// The attachments are textures the next passes can sample. A multisampled target draws
+/// to renderbuffers instead and `resolve` blits them to the textures of a second framebuffer.
+pub struct GlRenderTarget {
+    gl: gl::Gl,
+    /// The framebuffer drawn to.
+    id: GLuint,
+    /// The framebuffer of the textures when it is not the one drawn to.
+    resolve_id: Option<GLuint>,
+    colors: Vec<GlTexture>,
+    depth: Option<GlTexture>,
+    _samples: Vec<Renderbuffer>,
+    desc: RenderTargetDesc,
+}
+
+impl GlRenderTarget {
+    pub fn new(gl: &gl::Gl, desc: RenderTargetDesc) -> Result<Self> {
+        desc.validate()?;
+        let texture = |format| GlTexture::new(gl, TextureDesc::new(desc.width, desc.height, format), None);
+        let colors = desc.colors.iter().map(|f| texture(*f)).collect::<Result<Vec<_>>>()?;
+        let depth = desc.depth.map(texture).transpose()?;
+        let mut target = Self {
+            gl: gl.clone(),
+            id: 0,
+            resolve_id: None,
+            colors,
+            depth,
+            _samples: Vec::new(),
+            desc,
+        };
+        // The textures are attached to the framebuffer drawn to, or to the one they are resolved to.
+        let texture_fbo = target.create_framebuffer();
+        // Owned by the target right away, dropping it on a failed check deletes the framebuffer.
+        if target.desc.is_multisampled() {
+            target.resolve_id = Some(texture_fbo);
+        } else {
+            target.id = texture_fbo;
+        }
+        unsafe {
+            for (i, texture) in target.colors.iter().enumerate() {
+                gl.NamedFramebufferTexture(texture_fbo, gl::COLOR_ATTACHMENT0 + i as GLenum, texture.id(), 0);
+            }
+            if let (Some(texture), Some(format)) = (&target.depth, target.desc.depth) {
+                gl.NamedFramebufferTexture(texture_fbo, depth_attachment(format), texture.id(), 0);
+            }
+        }
+        target.check(texture_fbo)?;
+        if !target.desc.is_multisampled() {
+            return Ok(target);
+        }
+        target.id = target.create_framebuffer();
+        let (width, height, samples) = (target.desc.width, target.desc.height, target.desc.samples);
+        unsafe {
+            for (i, format) in target.desc.colors.iter().enumerate() {
+                let buffer = Renderbuffer::new(gl, *format, width, height, samples);
+                gl.NamedFramebufferRenderbuffer(
+                    target.id,
+                    gl::COLOR_ATTACHMENT0 + i as GLenum,
+                    gl::RENDERBUFFER,
+                    buffer.id,
+                );
+                target._samples.push(buffer);
+            }
+            if let Some(format) = target.desc.depth {
+                let buffer = Renderbuffer::new(gl, format, width, height, samples);
+                gl.NamedFramebufferRenderbuffer(target.id, depth_attachment(format), gl::RENDERBUFFER, buffer.id);
+                target._samples.push(buffer);
+            }
+        }
+        target.check(target.id)?;
+        Ok(target)
+    }
+
+    /// A framebuffer drawing to every color attachment of the target.
+    fn create_framebuffer(&self) -> GLuint {
+        let mut id = 0;
+        let attachments = color_attachments(self.desc.colors.len());
+        unsafe {
+            self.gl.CreateFramebuffers(1, &mut id);
+            if attachments.is_empty() {
+                self.gl.NamedFramebufferDrawBuffer(id, gl::NONE);
+                self.gl.NamedFramebufferReadBuffer(id, gl::NONE);
+            } else {
+                self.gl
+                    .NamedFramebufferDrawBuffers(id, attachments.len() as GLsizei, attachments.as_ptr());
+            }
+        }
+        id
+    }
+
+    fn check(&self, id: GLuint) -> Result<()> {
+        let status = unsafe { self.gl.CheckNamedFramebufferStatus(id, gl::FRAMEBUFFER) };
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(ResourceError::Backend(format!(
+                "Incomplete framebuffer, status 0x{:x}.",
+                status
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    /// Draw to the target, the viewport must be set to its size.
+    pub fn bind(&self) {
+        unsafe { self.gl.BindFramebuffer(gl::FRAMEBUFFER, self.id) };
+    }
+
+    /// Draw to the window again.
+    pub fn unbind(&self) {
+        unsafe { self.gl.BindFramebuffer(gl::FRAMEBUFFER, 0) };
+    }
+
+    pub fn color(&self, index: usize) -> Option<&GlTexture> {
+        self.colors.get(index)
+    }
+
+    pub fn depth(&self) -> Option<&GlTexture> {
+        self.depth.as_ref()
+    }
+}
+
+impl RenderTargetT for GlRenderTarget {
+    fn handle(&self) -> RenderTargetHandle {
+        RenderTargetHandle(self.id)
+    }
+
+    fn desc(&self) -> &RenderTargetDesc {
+        &self.desc
+    }
+
+    fn color_texture(&self, index: usize) -> Option<TextureHandle> {
+        self.colors.get(index).map(|t| t.handle())
+    }
+
+    fn depth_texture(&self) -> Option<TextureHandle> {
+        self.depth.as_ref().map(|t| t.handle())
+    }
+
+    fn resolve(&mut self) {
+        let resolve_id = match self.resolve_id {
+            Some(id) => id,
+            None => return,
+        };
+        let (width, height) = (self.desc.width as GLint, self.desc.height as GLint);
+        unsafe {
+            // A blit copies a single color attachment, the read and draw buffers select it.
+            for attachment in color_attachments(self.desc.colors.len()) {
+                self.gl.NamedFramebufferReadBuffer(self.id, attachment);
+                self.gl.NamedFramebufferDrawBuffer(resolve_id, attachment);
+                self.gl.BlitNamedFramebuffer(
+                    self.id,
+                    resolve_id,
+                    0,
+                    0,
+                    width,
+                    height,
+                    0,
+                    0,
+                    width,
+                    height,
+                    gl::COLOR_BUFFER_BIT,
+                    gl::NEAREST,
+                );
+            }
+            if self.desc.depth.is_some() {
+                self.gl.BlitNamedFramebuffer(
+                    self.id,
+                    resolve_id,
+                    0,
+                    0,
+                    width,
+                    height,
+                    0,
+                    0,
+                    width,
+                    height,
+                    gl::DEPTH_BUFFER_BIT,
+                    gl::NEAREST,
+                );
+            }
+            let attachments = color_attachments(self.desc.colors.len());
+            if !attachments.is_empty() {
+                self.gl.NamedFramebufferDrawBuffers(
+                    resolve_id,
+                    attachments.len() as GLsizei,
+                    attachments.as_ptr(),
+                );
+            }
+        }
+    }
+
+    fn read_pixels(&mut self, index: usize) -> Result<Image> {
+        if index >= self.desc.colors.len() {
+            return Err(ResourceError::OutOfBounds);
+        }
+        self.resolve();
+        let id = self.resolve_id.unwrap_or(self.id);
+        unsafe {
+            self.gl.NamedFramebufferReadBuffer(id, gl::COLOR_ATTACHMENT0 + index as GLenum);
+            self.gl.BindFramebuffer(gl::READ_FRAMEBUFFER, id);
+        }
+        let image = read_pixels(&self.gl, 0, 0, self.desc.width, self.desc.height);
+        unsafe { self.gl.BindFramebuffer(gl::READ_FRAMEBUFFER, 0) };
+        Ok(image)
+    }
+}
+
+impl Drop for GlRenderTarget {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.DeleteFramebuffers(1, &self.id);
+            if let Some(id) = &self.resolve_id {
+                self.gl.DeleteFramebuffers(1, id);
+            }
+        }
+    }
+}
+
+/// Read a region of the bound read framebuffer as RGBA8, the rows from the top.
+/// The color is converted by GL, float attachments are clamped to `[0, 1]`.
+pub fn read_pixels(gl: &gl::Gl, x: i32, y: i32, width: u32, height: u32) -> Image {
+    let mut image = Image::new(width, height);
+    unsafe {
+        gl.PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl.ReadPixels(
+            x,
+            y,
+            width as GLsizei,
+            height as GLsizei,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            image.data.as_mut_ptr() as *mut GLvoid,
+        );
+    }
+    image.flip_vertical();
+    image
+}
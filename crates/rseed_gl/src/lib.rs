@@ -1,26 +1,113 @@
 pub mod context;
 pub mod buffer;
-//pub mod camera;
-//pub mod shader;
-//pub mod texture;
+pub mod framebuffer;
+pub mod pipeline;
+pub mod shader;
+pub mod texture;
 pub mod varray;
+pub mod resource;
+pub mod ring;
 
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+};
 
-use rseed_renderapi::renderer::RendererT;
+use gl::types::*;
+use rseed_log::Logger;
+use rseed_renderapi::{
+    buffer::VertexLayout,
+    context::ContextT,
+    handle::BufferHandle,
+    image::Image,
+    renderer::{Access, CommandBuffer, IndexFormat, Primitive, RenderCommand, RendererT, UniformValue},
+};
 
 
 pub struct GlRenderer {
+    /// Reads the vertex buffers of the draws, dropped before the context.
+    varray : varray::VArray,
+    /// Layout of the buffer at each binding of the vertex array.
+    vertex_layouts : RefCell<HashMap<GLuint, VertexLayout>>,
     ctx : context::GlContext,
+    program : Cell<GLuint>,
+    index_format : Cell<IndexFormat>,
+    logger : Logger,
+    /// The commands the backend can't execute, reported once each.
+    reported : RefCell<HashSet<String>>,
 }
 
 impl GlRenderer {
 
     pub fn new(ctx: context::GlContext) -> Self {
         Self {
+            varray : varray::VArray::new(&ctx.gl),
+            vertex_layouts : RefCell::new(HashMap::new()),
             ctx,
+            program : Cell::new(0),
+            index_format : Cell::new(IndexFormat::U32),
+            logger : Logger::new(String::from("OpenGL")),
+            reported : RefCell::new(HashSet::new()),
         }
     }
 
+    pub fn gl(&self) -> &gl::Gl {
+        &self.ctx.gl
+    }
+
+    /// Log a command the backend ignores, once so the frames do not flood the log.
+    fn report(&self, message : String) {
+        if self.reported.borrow_mut().insert(message.clone()) {
+            self.logger.warn(&message);
+        }
+    }
+
+    /// Set through the program of the bound pipeline, which caches the locations
+    /// and checks the type of the value.
+    fn set_uniform(&self, name : &str, value : &UniformValue) {
+        let pipelines = self.ctx.pipelines.borrow();
+        let pipeline = match pipelines.get(&self.program.get()) {
+            Some(pipeline) => pipeline,
+            None => return self.report(format!(
+                "The uniform {} is set without a pipeline created by the context.",
+                name
+            )),
+        };
+        if let Err(e) = pipeline.program.set_uniform(name, *value) {
+            self.report(e.to_string());
+        }
+    }
+
+    /// The attributes of the buffer previously at the binding stop being read.
+    fn set_vertex_buffer(&self, binding : GLuint, buffer : BufferHandle) {
+        let layout = match self.ctx.vertex_layouts.borrow().get(&buffer.0) {
+            Some(layout) => layout.clone(),
+            None => return,
+        };
+        if let Some(previous) = self.vertex_layouts.borrow_mut().insert(binding, layout.clone()) {
+            self.varray.disable(&previous);
+        }
+        self.varray.set_buffer_id(binding, buffer.0, &layout);
+    }
+
+}
+
+pub fn gl_primitive(primitive : Primitive) -> GLenum {
+    match primitive {
+        Primitive::Points => gl::POINTS,
+        Primitive::Lines => gl::LINES,
+        Primitive::LineStrip => gl::LINE_STRIP,
+        Primitive::Triangles => gl::TRIANGLES,
+        Primitive::TriangleStrip => gl::TRIANGLE_STRIP,
+        Primitive::TriangleFan => gl::TRIANGLE_FAN,
+    }
+}
+
+pub fn gl_index_format(format : IndexFormat) -> (GLenum, usize) {
+    match format {
+        IndexFormat::U16 => (gl::UNSIGNED_SHORT, 2),
+        IndexFormat::U32 => (gl::UNSIGNED_INT, 4),
+    }
 }
 
 impl Drop for GlRenderer {
@@ -33,4 +120,101 @@ impl RendererT for GlRenderer {
     fn swap_buffers(&self) {
         self.ctx.swap_buffers()
     }
-}
\ No newline at end of file
+
+    fn context(&self) -> &dyn ContextT {
+        &self.ctx
+    }
+
+    fn resize(&self, width : u32, height : u32) {
+        self.ctx.resize(width, height);
+        unsafe { self.ctx.gl.Viewport(0, 0, width as GLsizei, height as GLsizei) };
+    }
+
+    /// Read the back buffer of the window, in the area of the viewport.
+    fn read_pixels(&self) -> Option<Image> {
+        let gl = &self.ctx.gl;
+        let mut viewport = [0 as GLint; 4];
+        unsafe {
+            gl.GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+            gl.BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+            gl.ReadBuffer(gl::BACK);
+        }
+        let [x, y, width, height] = viewport;
+        Some(framebuffer::read_pixels(gl, x, y, width.max(0) as u32, height.max(0) as u32))
+    }
+
+    fn submit(&self, commands : &CommandBuffer) {
+        let gl = &self.ctx.gl;
+        self.varray.bind();
+        for command in commands.commands() {
+            unsafe {
+                match command {
+                    // The render target handle is the framebuffer name, 0 is the window.
+                    RenderCommand::SetRenderTarget(target) => {
+                        gl.BindFramebuffer(gl::FRAMEBUFFER, target.map(|t| t.0).unwrap_or(0))
+                    }
+                    RenderCommand::Clear => gl.Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT),
+                    RenderCommand::SetClearColor(c) => gl.ClearColor(c.x, c.y, c.z, c.w),
+                    RenderCommand::SetViewport { x, y, width, height } => {
+                        gl.Viewport(*x, *y, *width as GLsizei, *height as GLsizei)
+                    }
+                    RenderCommand::SetPipeline(pipeline) => {
+                        self.program.set(pipeline.0);
+                        match self.ctx.pipelines.borrow().get(&pipeline.0) {
+                            Some(pipeline) => pipeline.bind(gl),
+                            None => gl.UseProgram(pipeline.0),
+                        }
+                    }
+                    RenderCommand::SetVertexBuffer { binding, buffer } => {
+                        self.set_vertex_buffer(*binding, *buffer)
+                    }
+                    RenderCommand::SetIndexBuffer(buffer, format) => {
+                        self.index_format.set(*format);
+                        gl.VertexArrayElementBuffer(self.varray.id(), buffer.0);
+                    }
+                    RenderCommand::SetUniform(name, value) => self.set_uniform(name, value),
+                    RenderCommand::SetUniformBuffer { slot, buffer } => {
+                        gl.BindBufferBase(gl::UNIFORM_BUFFER, *slot, buffer.0);
+                    }
+                    RenderCommand::SetTexture { slot, texture, sampler } => {
+                        gl.BindTextureUnit(*slot, texture.0);
+                        gl.BindSampler(*slot, sampler.map(|s| s.0).unwrap_or(0));
+                    }
+                    RenderCommand::DrawIndexed {
+                        primitive,
+                        first,
+                        count,
+                        instances,
+                    } => {
+                        let (ty, size) = gl_index_format(self.index_format.get());
+                        gl.DrawElementsInstanced(
+                            gl_primitive(*primitive),
+                            *count as GLsizei,
+                            ty,
+                            (*first as usize * size) as *const GLvoid,
+                            *instances as GLsizei,
+                        )
+                    }
+                    RenderCommand::Draw {
+                        primitive,
+                        first,
+                        count,
+                        instances,
+                    } => gl.DrawArraysInstanced(
+                        gl_primitive(*primitive),
+                        *first as GLint,
+                        *count as GLsizei,
+                        *instances as GLsizei,
+                    ),
+                    RenderCommand::Flush => gl.Flush(),
+                    // The driver tracks the attachments and samplers, only the writes
+                    // to storage images must be made visible by hand.
+                    RenderCommand::Barrier { from: Some(Access::Storage), .. } => {
+                        gl.MemoryBarrier(gl::ALL_BARRIER_BITS)
+                    }
+                    RenderCommand::Barrier { .. } => (),
+                }
+            }
+        }
+    }
+}
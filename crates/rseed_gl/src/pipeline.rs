@@ -0,0 +1,67 @@
+// External imports
+use gl::types::*;
+use std::{cell::RefCell, collections::HashMap};
+// Crate imports
+use rseed_renderapi::{
+    context::{ResourceError, Result},
+    pipeline::{BlendMode, PipelineDesc},
+};
+use rseed_shaderlib::{Preprocessor, VariantKey};
+
+use super::shader::ShaderProgram;
+
+/// The pipelines created by the context, by program name. The program name is the
+/// pipeline handle, `SetPipeline` still binds the programs linked by hand but their
+/// uniforms are set on the `ShaderProgram`.
+pub type Pipelines = RefCell<HashMap<GLuint, GlPipeline>>;
+
+/// A program with the fixed function state GL keeps outside of it.
+pub struct GlPipeline {
+    pub program: ShaderProgram,
+    pub desc: PipelineDesc,
+}
+
+impl GlPipeline {
+    pub fn new(gl: &gl::Gl, res: &Preprocessor, desc: &PipelineDesc) -> Result<Self> {
+        let key = VariantKey::new(desc.features.iter());
+        let program = ShaderProgram::from_res_variant(gl, res, &desc.shader, &key)
+            .map_err(|e| ResourceError::Backend(e.to_string()))?;
+        Ok(Self {
+            program,
+            desc: desc.clone(),
+        })
+    }
+
+    /// Use the program and set the blend, depth and cull state of the pipeline.
+    pub fn bind(&self, gl: &gl::Gl) {
+        self.program.bind();
+        unsafe {
+            match self.desc.blend {
+                BlendMode::Opaque => gl.Disable(gl::BLEND),
+                BlendMode::Alpha => {
+                    gl.Enable(gl::BLEND);
+                    gl.BlendEquation(gl::FUNC_ADD);
+                    gl.BlendFuncSeparate(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA, gl::ONE, gl::ONE_MINUS_SRC_ALPHA);
+                }
+                BlendMode::Additive => {
+                    gl.Enable(gl::BLEND);
+                    gl.BlendEquation(gl::FUNC_ADD);
+                    gl.BlendFunc(gl::ONE, gl::ONE);
+                }
+            }
+            if self.desc.depth_test {
+                gl.Enable(gl::DEPTH_TEST);
+                gl.DepthFunc(gl::LEQUAL);
+            } else {
+                gl.Disable(gl::DEPTH_TEST);
+            }
+            if self.desc.cull {
+                gl.Enable(gl::CULL_FACE);
+                gl.CullFace(gl::BACK);
+                gl.FrontFace(gl::CCW);
+            } else {
+                gl.Disable(gl::CULL_FACE);
+            }
+        }
+    }
+}
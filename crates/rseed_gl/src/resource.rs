@@ -0,0 +1,177 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use gl::types::*;
+use rseed_renderapi::{
+    buffer::{BufferUsage, IndexBufferT, IndexData, UniformBufferT, VertexBufferT, VertexLayout},
+    context::{ResourceError, Result},
+    handle::BufferHandle,
+    renderer::IndexFormat,
+};
+
+use super::buffer::{IndexBuffer, UniformBuffer, VertexBuffer};
+
+pub fn gl_usage(usage: BufferUsage) -> GLenum {
+    match usage {
+        BufferUsage::Static => gl::STATIC_DRAW,
+        BufferUsage::Dynamic => gl::DYNAMIC_DRAW,
+        BufferUsage::Stream => gl::STREAM_DRAW,
+    }
+}
+
+/// The layouts of the live vertex buffers by buffer name,
+/// the renderer reads them to bind a buffer to its vertex array.
+pub type VertexLayouts = Rc<RefCell<HashMap<GLuint, VertexLayout>>>;
+
+/// A vertex buffer, the handle is the buffer name.
+pub struct GlVertexBuffer {
+    buffer: VertexBuffer,
+    layouts: VertexLayouts,
+    layout: VertexLayout,
+    usage: GLenum,
+    len: usize,
+}
+
+impl GlVertexBuffer {
+    pub fn new(
+        gl: &gl::Gl,
+        layouts: &VertexLayouts,
+        data: &[u8],
+        layout: VertexLayout,
+        usage: BufferUsage,
+    ) -> Result<Self> {
+        let mut buffer = Self {
+            buffer: VertexBuffer::new(gl),
+            layouts: layouts.clone(),
+            layout,
+            usage: gl_usage(usage),
+            len: 0,
+        };
+        buffer.set_data(data)?;
+        layouts.borrow_mut().insert(buffer.buffer.id(), buffer.layout.clone());
+        Ok(buffer)
+    }
+}
+
+impl Drop for GlVertexBuffer {
+    fn drop(&mut self) {
+        self.layouts.borrow_mut().remove(&self.buffer.id());
+    }
+}
+
+impl VertexBufferT for GlVertexBuffer {
+    fn handle(&self) -> BufferHandle {
+        BufferHandle(self.buffer.id())
+    }
+
+    fn layout(&self) -> &VertexLayout {
+        &self.layout
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn set_data(&mut self, data: &[u8]) -> Result<()> {
+        let stride = self.layout.stride.max(1) as usize;
+        if !data.len().is_multiple_of(stride) {
+            return Err(ResourceError::SizeMismatch(
+                data.len() / stride * stride,
+                data.len(),
+            ));
+        }
+        self.buffer.set_data_with_usage(data, self.usage);
+        self.buffer.unbind();
+        self.len = data.len() / stride;
+        Ok(())
+    }
+}
+
+pub struct GlIndexBuffer {
+    gl: gl::Gl,
+    buffer: IndexBuffer,
+    format: IndexFormat,
+    usage: GLenum,
+    len: usize,
+}
+
+impl GlIndexBuffer {
+    pub fn new(gl: &gl::Gl, data: IndexData, usage: BufferUsage) -> Result<Self> {
+        let mut buffer = Self {
+            gl: gl.clone(),
+            buffer: IndexBuffer::new(gl),
+            format: data.format(),
+            usage: gl_usage(usage),
+            len: 0,
+        };
+        buffer.set_data(data)?;
+        Ok(buffer)
+    }
+}
+
+impl IndexBufferT for GlIndexBuffer {
+    fn handle(&self) -> BufferHandle {
+        BufferHandle(self.buffer.id())
+    }
+
+    fn format(&self) -> IndexFormat {
+        self.format
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn set_data(&mut self, data: IndexData) -> Result<()> {
+        // The element buffer binding is part of the vertex array state.
+        unsafe { self.gl.BindVertexArray(0) };
+        self.buffer.set_data_with_usage(data.as_bytes(), self.usage);
+        self.buffer.unbind();
+        self.format = data.format();
+        self.len = data.len();
+        Ok(())
+    }
+}
+
+pub struct GlUniformBuffer {
+    buffer: UniformBuffer,
+    size: usize,
+}
+
+impl GlUniformBuffer {
+    pub fn new(gl: &gl::Gl, data: &[u8], usage: BufferUsage) -> Self {
+        let buffer = UniformBuffer::new(gl);
+        buffer.set_data_with_usage(data, gl_usage(usage));
+        buffer.unbind();
+        Self {
+            buffer,
+            size: data.len(),
+        }
+    }
+}
+
+impl UniformBufferT for GlUniformBuffer {
+    fn handle(&self) -> BufferHandle {
+        BufferHandle(self.buffer.id())
+    }
+
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn set_data(&mut self, data: &[u8]) -> Result<()> {
+        if data.len() > self.size {
+            return Err(ResourceError::SizeMismatch(self.size, data.len()));
+        }
+        self.buffer.bind();
+        unsafe {
+            self.buffer.gl().BufferSubData(
+                gl::UNIFORM_BUFFER,
+                0,
+                data.len() as GLsizeiptr,
+                data.as_ptr() as *const GLvoid,
+            )
+        };
+        self.buffer.unbind();
+        Ok(())
+    }
+}
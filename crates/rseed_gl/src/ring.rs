@@ -0,0 +1,155 @@
+use gl::{self, types::*};
+use rseed_renderapi::{
+    block::{align_to, std140_bytes, std430_bytes, Std140, Std430},
+    context::{ResourceError, Result},
+};
+
+use super::buffer::{offset_alignment, BufferType, ShaderStorageBufferType, UniformBufferType};
+
+pub type UniformRing = RingBuffer<UniformBufferType>;
+pub type StorageRing = RingBuffer<ShaderStorageBufferType>;
+
+/// A persistently mapped buffer cut in one segment per frame in flight, for the data written
+/// every frame like the camera and the lights. The CPU fills the segment of a frame while
+/// the GPU still reads the previous ones, a fence per segment keeps it from writing over them.
+pub struct RingBuffer<B: BufferType> {
+    gl: gl::Gl,
+    id: GLuint,
+    data: *mut u8,
+    segment_size: usize,
+    /// The segment written this frame.
+    current: usize,
+    /// Bytes written in the current segment.
+    cursor: usize,
+    /// Signaled when the GPU is done with the draws of a segment, null before its first use.
+    fences: Vec<GLsync>,
+    align: usize,
+    _marker: std::marker::PhantomData<B>,
+}
+
+impl<B: BufferType> RingBuffer<B> {
+    /// `segment_size` bytes per frame, for `frames` frames in flight.
+    /// Fails when the driver can't map the buffer, out of memory or without persistent mappings.
+    pub fn new(gl: &gl::Gl, segment_size: usize, frames: usize) -> Result<Self> {
+        let align = offset_alignment(gl, B::TYPE);
+        let segment_size = align_to(segment_size.max(1), align);
+        let frames = frames.max(1);
+        let size = (segment_size * frames) as GLsizeiptr;
+        let flags = gl::MAP_WRITE_BIT | gl::MAP_PERSISTENT_BIT | gl::MAP_COHERENT_BIT;
+        let mut id = 0;
+        let data = unsafe {
+            gl.CreateBuffers(1, &mut id);
+            gl.NamedBufferStorage(id, size, std::ptr::null(), flags);
+            gl.MapNamedBufferRange(id, 0, size, flags) as *mut u8
+        };
+        if data.is_null() {
+            unsafe { gl.DeleteBuffers(1, &id) };
+            return Err(ResourceError::Backend(format!(
+                "Can't map a ring buffer of {} bytes.",
+                size
+            )));
+        }
+        Ok(Self {
+            gl: gl.clone(),
+            id,
+            data,
+            segment_size,
+            current: frames - 1,
+            cursor: 0,
+            fences: vec![std::ptr::null(); frames],
+            align,
+            _marker: std::marker::PhantomData,
+        })
+    }
+
+    /// Move to the segment of the next frame, waiting until the GPU is done reading it.
+    pub fn begin_frame(&mut self) {
+        self.current = (self.current + 1) % self.fences.len();
+        self.cursor = 0;
+        let fence = std::mem::replace(&mut self.fences[self.current], std::ptr::null());
+        if fence.is_null() {
+            return;
+        }
+        unsafe {
+            loop {
+                let status = self.gl.ClientWaitSync(fence, gl::SYNC_FLUSH_COMMANDS_BIT, 1_000_000_000);
+                if status != gl::TIMEOUT_EXPIRED {
+                    break;
+                }
+            }
+            self.gl.DeleteSync(fence);
+        }
+    }
+
+    /// Call after the last draw reading the segment of this frame.
+    pub fn end_frame(&mut self) {
+        unsafe {
+            let fence = self.gl.FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0);
+            let old = std::mem::replace(&mut self.fences[self.current], fence);
+            if !old.is_null() {
+                self.gl.DeleteSync(old);
+            }
+        }
+    }
+
+    /// Copy the data in the segment of the frame and return its offset in the buffer,
+    /// to bind with `bind_range`. `None` when the segment is full.
+    pub fn push_bytes(&mut self, data: &[u8]) -> Option<usize> {
+        let start = align_to(self.cursor, self.align);
+        if start + data.len() > self.segment_size {
+            return None;
+        }
+        let offset = self.current * self.segment_size + start;
+        unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), self.data.add(offset), data.len()) };
+        self.cursor = start + data.len();
+        Some(offset)
+    }
+
+    /// Bind `size` bytes written at `offset` to the binding point `index` of the blocks.
+    pub fn bind_range(&self, index: GLuint, offset: usize, size: usize) {
+        unsafe {
+            self.gl
+                .BindBufferRange(B::TYPE, index, self.id, offset as GLintptr, size as GLsizeiptr)
+        };
+    }
+
+    pub fn segment_size(&self) -> usize {
+        self.segment_size
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+}
+
+impl RingBuffer<UniformBufferType> {
+    /// Write a uniform block for this frame and bind it to `index`.
+    pub fn push<T: Std140>(&mut self, index: GLuint, value: &T) -> Option<usize> {
+        let data = std140_bytes(value);
+        let offset = self.push_bytes(data)?;
+        self.bind_range(index, offset, data.len());
+        Some(offset)
+    }
+}
+
+impl RingBuffer<ShaderStorageBufferType> {
+    /// Write the array of a storage block for this frame and bind it to `index`.
+    pub fn push<T: Std430>(&mut self, index: GLuint, values: &[T]) -> Option<usize> {
+        let data = std430_bytes(values);
+        let offset = self.push_bytes(data)?;
+        self.bind_range(index, offset, data.len());
+        Some(offset)
+    }
+}
+
+impl<B: BufferType> Drop for RingBuffer<B> {
+    fn drop(&mut self) {
+        unsafe {
+            for fence in self.fences.iter().filter(|f| !f.is_null()) {
+                self.gl.DeleteSync(*fence);
+            }
+            self.gl.UnmapNamedBuffer(self.id);
+            self.gl.DeleteBuffers(1, &self.id);
+        }
+    }
+}
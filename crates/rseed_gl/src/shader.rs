@@ -1,22 +1,44 @@
 // External imports
 use gl::types::*;
-use std::{collections::HashMap, ffi::CString};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ffi::CString,
+    path::{Path, PathBuf},
+};
 // Crate imports
-//use rseed_res::{self, RessourceLoader};
+use rseed_core::prelude::*;
+use rseed_math::{Mat3, Mat4, Vec2D, Vec3D, Vec4D};
+use rseed_renderapi::renderer::UniformValue;
+use rseed_shaderlib::{preprocess::LineMap, Preprocessed, Preprocessor, ShaderError, VariantKey};
 
-#[derive(Debug,Error)]
+#[derive(Debug, Error)]
 pub enum Error {
+    #[error(display = "No shader file found for {}.", name)]
     CanNotDetermineShaderTypeForResource {
         name: String,
     },
+    #[error(display = "Can't load the shader {}: {}", name, inner)]
+    ResourceLoad {
+        name: String,
+        inner: ShaderError,
+    },
+    #[error(display = "Can't compile the shader {}:\n{}", name, message)]
     CompileError {
         name: String,
         message: String,
     },
+    #[error(display = "Can't link the program {}:\n{}", name, message)]
     LinkError {
         name: String,
         message: String,
     },
+    #[error(display = "The uniform {} of GL type {:#x} can't be set from a {}.", name, ty, value)]
+    UniformType {
+        name: String,
+        ty: GLenum,
+        value: &'static str,
+    },
 }
 
 const POSSIBLE_EXT: [(&str, gl::types::GLenum); 6] = [
@@ -28,88 +50,315 @@ const POSSIBLE_EXT: [(&str, gl::types::GLenum); 6] = [
     (".comp", gl::COMPUTE_SHADER),
 ];
 
+/// The opaque types set with an integer, the texture unit or image unit.
+const OPAQUE_TYPES: [GLenum; 20] = [
+    gl::SAMPLER_1D,
+    gl::SAMPLER_2D,
+    gl::SAMPLER_3D,
+    gl::SAMPLER_CUBE,
+    gl::SAMPLER_1D_SHADOW,
+    gl::SAMPLER_2D_SHADOW,
+    gl::SAMPLER_1D_ARRAY,
+    gl::SAMPLER_2D_ARRAY,
+    gl::SAMPLER_2D_ARRAY_SHADOW,
+    gl::SAMPLER_CUBE_SHADOW,
+    gl::SAMPLER_CUBE_MAP_ARRAY,
+    gl::SAMPLER_2D_MULTISAMPLE,
+    gl::SAMPLER_BUFFER,
+    gl::INT_SAMPLER_2D,
+    gl::UNSIGNED_INT_SAMPLER_2D,
+    gl::IMAGE_2D,
+    gl::IMAGE_3D,
+    gl::IMAGE_CUBE,
+    gl::IMAGE_2D_ARRAY,
+    gl::UNSIGNED_INT_IMAGE_2D,
+];
 
+/// A value a uniform can be set from.
+pub trait Uniform {
+    /// Name of the value type, for the errors.
+    fn type_name(&self) -> &'static str;
 
-pub struct ShaderProgram {
-    _id: GLuint,
-    shaders : Vec<Shader>,
-    //uniforms : HashMap<String, UType>,
-    gl: gl::Gl,
+    /// Whether a uniform of this GL type can hold the value.
+    fn accepts(&self, ty: GLenum) -> bool;
+
+    /// Write the value, the program doesn't need to be bound.
+    fn set(&self, gl: &gl::Gl, program: GLuint, location: GLint);
 }
 
-impl ShaderProgram {
-    // pub fn from_res(gl: &gl::Gl, res: &RessourceLoader, name: &str) -> Result<Self, Error> {
-    //     let ressources_names : Vec<(String,GLenum)> = POSSIBLE_EXT
-    //         .iter()
-    //         // get all coresponding names
-    //         .map(|(file_ext, stype)| (format!("{}{}", name, file_ext),*stype) )
-    //         // filter out the ones that don't exists
-    //         .partition(|(name,stype)| res.name_to_path(&(**name).to_owned()).exists())
-    //         .0;
-
-    //     // create the actual shaders from ressources
-    //     let shaders = ressources_names
-    //         .iter()
-    //         .map(|(ressource_name,stype)| Shader::from_res(gl, res, ressource_name, *stype))
-    //         .collect::<Result<Vec<Shader>, Error>>()?;
-    //     // link the shaders into a Progra
-    //     ShaderProgram::from_shaders(gl, shaders).map_err(|message| Error::LinkError {
-    //         name: name.to_owned(),
-    //         message,
-    //     })
-    // }
+impl<T: Uniform + ?Sized> Uniform for &T {
+    fn type_name(&self) -> &'static str {
+        (**self).type_name()
+    }
 
-    pub fn from_shaders(gl: &gl::Gl, shaders: Vec<Shader>) -> Result<Self, String> {
-        let id = unsafe { gl.CreateProgram() };
+    fn accepts(&self, ty: GLenum) -> bool {
+        (**self).accepts(ty)
+    }
 
-        for shader in shaders.clone() {
-            unsafe {
-                gl.AttachShader(id, shader.id());
-            }
+    fn set(&self, gl: &gl::Gl, program: GLuint, location: GLint) {
+        (**self).set(gl, program, location)
+    }
+}
+
+impl Uniform for bool {
+    fn type_name(&self) -> &'static str {
+        "bool"
+    }
+
+    fn accepts(&self, ty: GLenum) -> bool {
+        ty == gl::BOOL
+    }
+
+    fn set(&self, gl: &gl::Gl, program: GLuint, location: GLint) {
+        unsafe { gl.ProgramUniform1i(program, location, *self as GLint) }
+    }
+}
+
+impl Uniform for i32 {
+    fn type_name(&self) -> &'static str {
+        "i32"
+    }
+
+    fn accepts(&self, ty: GLenum) -> bool {
+        ty == gl::INT || ty == gl::BOOL || OPAQUE_TYPES.contains(&ty)
+    }
+
+    fn set(&self, gl: &gl::Gl, program: GLuint, location: GLint) {
+        unsafe { gl.ProgramUniform1i(program, location, *self) }
+    }
+}
+
+impl Uniform for u32 {
+    fn type_name(&self) -> &'static str {
+        "u32"
+    }
+
+    fn accepts(&self, ty: GLenum) -> bool {
+        ty == gl::UNSIGNED_INT || ty == gl::BOOL
+    }
+
+    fn set(&self, gl: &gl::Gl, program: GLuint, location: GLint) {
+        unsafe { gl.ProgramUniform1ui(program, location, *self) }
+    }
+}
+
+impl Uniform for f32 {
+    fn type_name(&self) -> &'static str {
+        "f32"
+    }
+
+    fn accepts(&self, ty: GLenum) -> bool {
+        ty == gl::FLOAT
+    }
+
+    fn set(&self, gl: &gl::Gl, program: GLuint, location: GLint) {
+        unsafe { gl.ProgramUniform1f(program, location, *self) }
+    }
+}
+
+impl Uniform for Vec2D {
+    fn type_name(&self) -> &'static str {
+        "Vec2D"
+    }
+
+    fn accepts(&self, ty: GLenum) -> bool {
+        ty == gl::FLOAT_VEC2
+    }
+
+    fn set(&self, gl: &gl::Gl, program: GLuint, location: GLint) {
+        unsafe { gl.ProgramUniform2f(program, location, self.x, self.y) }
+    }
+}
+
+impl Uniform for Vec3D {
+    fn type_name(&self) -> &'static str {
+        "Vec3D"
+    }
+
+    fn accepts(&self, ty: GLenum) -> bool {
+        ty == gl::FLOAT_VEC3
+    }
+
+    fn set(&self, gl: &gl::Gl, program: GLuint, location: GLint) {
+        unsafe { gl.ProgramUniform3f(program, location, self.x, self.y, self.z) }
+    }
+}
+
+impl Uniform for Vec4D {
+    fn type_name(&self) -> &'static str {
+        "Vec4D"
+    }
+
+    fn accepts(&self, ty: GLenum) -> bool {
+        ty == gl::FLOAT_VEC4
+    }
+
+    fn set(&self, gl: &gl::Gl, program: GLuint, location: GLint) {
+        unsafe { gl.ProgramUniform4f(program, location, self.x, self.y, self.z, self.w) }
+    }
+}
+
+// Our matrices are stored row by row, GL transposes them.
+impl Uniform for Mat3 {
+    fn type_name(&self) -> &'static str {
+        "Mat3"
+    }
+
+    fn accepts(&self, ty: GLenum) -> bool {
+        ty == gl::FLOAT_MAT3
+    }
+
+    fn set(&self, gl: &gl::Gl, program: GLuint, location: GLint) {
+        unsafe { gl.ProgramUniformMatrix3fv(program, location, 1, gl::TRUE, self.to_vec().as_ptr()) }
+    }
+}
+
+impl Uniform for Mat4 {
+    fn type_name(&self) -> &'static str {
+        "Mat4"
+    }
+
+    fn accepts(&self, ty: GLenum) -> bool {
+        ty == gl::FLOAT_MAT4
+    }
+
+    fn set(&self, gl: &gl::Gl, program: GLuint, location: GLint) {
+        unsafe { gl.ProgramUniformMatrix4fv(program, location, 1, gl::TRUE, self.to_vec().as_ptr()) }
+    }
+}
+
+impl Uniform for UniformValue {
+    fn type_name(&self) -> &'static str {
+        match self {
+            UniformValue::Int(v) => v.type_name(),
+            UniformValue::Float(v) => v.type_name(),
+            UniformValue::Vec2(v) => v.type_name(),
+            UniformValue::Vec3(v) => v.type_name(),
+            UniformValue::Vec4(v) => v.type_name(),
+            UniformValue::Mat3(m) => m.type_name(),
+            UniformValue::Mat4(m) => m.type_name(),
         }
-        unsafe {
-            gl.LinkProgram(id);
+    }
+
+    fn accepts(&self, ty: GLenum) -> bool {
+        match self {
+            UniformValue::Int(v) => v.accepts(ty),
+            UniformValue::Float(v) => v.accepts(ty),
+            UniformValue::Vec2(v) => v.accepts(ty),
+            UniformValue::Vec3(v) => v.accepts(ty),
+            UniformValue::Vec4(v) => v.accepts(ty),
+            UniformValue::Mat3(m) => m.accepts(ty),
+            UniformValue::Mat4(m) => m.accepts(ty),
         }
+    }
 
-        let mut success: GLint = 1;
-        unsafe {
-            gl.GetProgramiv(id, gl::LINK_STATUS, &mut success);
+    fn set(&self, gl: &gl::Gl, program: GLuint, location: GLint) {
+        match self {
+            UniformValue::Int(v) => v.set(gl, program, location),
+            UniformValue::Float(v) => v.set(gl, program, location),
+            UniformValue::Vec2(v) => v.set(gl, program, location),
+            UniformValue::Vec3(v) => v.set(gl, program, location),
+            UniformValue::Vec4(v) => v.set(gl, program, location),
+            UniformValue::Mat3(m) => m.set(gl, program, location),
+            UniformValue::Mat4(m) => m.set(gl, program, location),
         }
+    }
+}
 
-        if success == 0 {
-            let mut len: gl::types::GLint = 0;
-            unsafe {
-                gl.GetProgramiv(id, gl::INFO_LOG_LENGTH, &mut len);
-            }
+/// An active uniform of a program, as reported by `glGetActiveUniform`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UniformInfo {
+    pub name: String,
+    pub location: GLint,
+    /// GL type, `gl::FLOAT_MAT4` for a `mat4`.
+    pub ty: GLenum,
+    /// Length of the array, 1 otherwise.
+    pub size: GLint,
+}
 
-            let error = create_empty_cstring_with_len(len as usize);
+pub struct ShaderProgram {
+    _id: GLuint,
+    /// The uniforms outside of the blocks, the only ones with a location.
+    uniforms: Vec<UniformInfo>,
+    /// Locations by name, -1 for the names the program doesn't use.
+    locations: RefCell<HashMap<String, GLint>>,
+    /// Name and variant in the assets, for the programs loaded from there.
+    res: Option<(String, VariantKey)>,
+    /// Every file read by the shaders, includes too.
+    files: Vec<PathBuf>,
+    gl: gl::Gl,
+}
 
-            unsafe {
-                gl.GetProgramInfoLog(
-                    id,
-                    len,
-                    std::ptr::null_mut(),
-                    error.as_ptr() as *mut gl::types::GLchar,
-                );
-            }
+impl ShaderProgram {
+    /// Load the shaders `name.vert`, `name.frag`... found in the assets and link them.
+    pub fn from_res(gl: &gl::Gl, res: &Preprocessor, name: &str) -> Result<Self, Error> {
+        Self::from_res_variant(gl, res, name, &VariantKey::default())
+    }
 
-            return Err(format!("{}", error.to_string_lossy().into_owned()));
-        }
+    /// Same as `from_res` with the features of the variant enabled.
+    pub fn from_res_variant(gl: &gl::Gl, res: &Preprocessor, name: &str, key: &VariantKey) -> Result<Self, Error> {
+        let (shaders, files) = load_res(gl, res, name, key)?;
+        // link the shaders into a Program
+        let mut program = ShaderProgram::from_shaders(gl, shaders).map_err(|message| Error::LinkError {
+            name: name.to_owned(),
+            message,
+        })?;
+        program.res = Some((name.to_owned(), key.clone()));
+        program.files = files;
+        Ok(program)
+    }
 
-        // for shader in shaders {
-        //     unsafe {
-        //         gl.DetachShader(id, shader.id());
-        //     }
-        // }
+    /// Link the shaders, they are deleted once the program is linked.
+    pub fn from_shaders(gl: &gl::Gl, shaders: Vec<Shader>) -> Result<Self, String> {
+        let id = unsafe { gl.CreateProgram() };
+        if let Err(message) = link(gl, id, &shaders) {
+            unsafe { gl.DeleteProgram(id) };
+            return Err(message);
+        }
 
+        let uniforms = active_uniforms(gl, id);
         Ok(Self {
             _id: id,
-            //uniforms : HashMap::new(),
-            shaders,
+            locations: RefCell::new(locations(&uniforms)),
+            uniforms,
+            res: None,
+            files: Vec::new(),
             gl: gl.clone(),
         })
     }
 
+    /// Compile and link the shaders again from the assets, for the hot reload.
+    /// The program is left untouched when they don't build anymore. Otherwise its id
+    /// stays the same, the uniforms are introspected again and must be set again.
+    /// Nothing happens for a program not loaded from the assets.
+    pub fn reload(&mut self, res: &Preprocessor) -> Result<(), Error> {
+        let (name, key) = match &self.res {
+            Some(res) => res.clone(),
+            None => return Ok(()),
+        };
+        let (shaders, files) = load_res(&self.gl, res, &name, &key)?;
+        let link_error = |message| Error::LinkError {
+            name: name.clone(),
+            message,
+        };
+        // A failed link would lose the current binary, try in a scratch program first.
+        let scratch = unsafe { self.gl.CreateProgram() };
+        let linked = link(&self.gl, scratch, &shaders);
+        unsafe { self.gl.DeleteProgram(scratch) };
+        linked.map_err(link_error)?;
+        link(&self.gl, self._id, &shaders).map_err(link_error)?;
+
+        self.uniforms = active_uniforms(&self.gl, self._id);
+        self.locations = RefCell::new(locations(&self.uniforms));
+        self.files = files;
+        Ok(())
+    }
+
+    /// The files to watch for the hot reload, empty for a program not loaded from the assets.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
     pub fn id(&self) -> GLuint {
         self._id
     }
@@ -119,12 +368,59 @@ impl ShaderProgram {
             self.gl.UseProgram(self._id);
         }
     }
-    
+
     pub fn unbind(&self) {
         unsafe {
             self.gl.UseProgram(0 as GLuint);
         }
     }
+
+    /// The active uniforms, without the members of the uniform blocks.
+    pub fn uniforms(&self) -> &[UniformInfo] {
+        &self.uniforms
+    }
+
+    pub fn uniform(&self, name: &str) -> Option<&UniformInfo> {
+        self.uniforms
+            .iter()
+            .find(|u| u.name == name || u.name.strip_suffix("[0]") == Some(name))
+    }
+
+    /// The location of a uniform, asked to GL once per name.
+    /// Array elements and struct members like `u_lights[2].color` are found too.
+    pub fn location(&self, name: &str) -> Option<GLint> {
+        if let Some(location) = self.locations.borrow().get(name) {
+            return Some(*location).filter(|l| *l >= 0);
+        }
+        let location = match CString::new(name) {
+            Ok(cname) => unsafe { self.gl.GetUniformLocation(self._id, cname.as_ptr()) },
+            Err(_) => -1,
+        };
+        self.locations.borrow_mut().insert(name.to_owned(), location);
+        Some(location).filter(|l| *l >= 0)
+    }
+
+    /// Set a uniform, `program.set_uniform("u_mvp", mvp)`.
+    /// The uniforms the program doesn't use are ignored, as GL does,
+    /// but a value of the wrong type is an error.
+    pub fn set_uniform<T: Uniform>(&self, name: &str, value: T) -> Result<(), Error> {
+        let location = match self.location(name) {
+            Some(location) => location,
+            None => return Ok(()),
+        };
+        // Only the whole uniforms are checked, not the elements found by name.
+        if let Some(info) = self.uniforms.iter().find(|u| u.location == location) {
+            if !value.accepts(info.ty) {
+                return Err(Error::UniformType {
+                    name: name.to_owned(),
+                    ty: info.ty,
+                    value: value.type_name(),
+                });
+            }
+        }
+        value.set(&self.gl, self._id, location);
+        Ok(())
+    }
 }
 
 impl Drop for ShaderProgram {
@@ -135,8 +431,142 @@ impl Drop for ShaderProgram {
     }
 }
 
-// shader struct
-#[derive(Clone)]
+/// Preprocess and compile the shaders `name.vert`, `name.frag`... found in the assets.
+/// Returns them with the files they read.
+fn load_res(gl: &gl::Gl, res: &Preprocessor, name: &str, key: &VariantKey) -> Result<(Vec<Shader>, Vec<PathBuf>), Error> {
+    let ressources_names: Vec<(String, GLenum)> = POSSIBLE_EXT
+        .iter()
+        // get all coresponding names
+        .map(|(file_ext, stype)| (format!("{}{}", name, file_ext), *stype))
+        // filter out the ones that don't exists
+        .filter(|(name, _)| res.root().join(name).is_file())
+        .collect();
+    if ressources_names.is_empty() {
+        return Err(Error::CanNotDetermineShaderTypeForResource { name: name.to_owned() });
+    }
+
+    // create the actual shaders from ressources
+    let mut shaders = Vec::with_capacity(ressources_names.len());
+    let mut files: Vec<PathBuf> = Vec::new();
+    for (ressource_name, stype) in ressources_names.iter() {
+        let source = res
+            .process(Path::new(ressource_name), key)
+            .map_err(|e| Error::ResourceLoad {
+                name: ressource_name.clone(),
+                inner: e,
+            })?;
+        for file in source.files.iter() {
+            if !files.contains(file) {
+                files.push(file.clone());
+            }
+        }
+        shaders.push(Shader::from_preprocessed(gl, &source, *stype)?);
+    }
+    Ok((shaders, files))
+}
+
+/// Link the shaders into a program, they are detached afterwards.
+fn link(gl: &gl::Gl, id: GLuint, shaders: &[Shader]) -> Result<(), String> {
+    for shader in shaders.iter() {
+        unsafe {
+            gl.AttachShader(id, shader.id());
+        }
+    }
+    unsafe {
+        gl.LinkProgram(id);
+    }
+    // The program keeps its binary, the shader objects can go.
+    for shader in shaders.iter() {
+        unsafe {
+            gl.DetachShader(id, shader.id());
+        }
+    }
+
+    let mut success: GLint = 1;
+    unsafe {
+        gl.GetProgramiv(id, gl::LINK_STATUS, &mut success);
+    }
+
+    if success == 0 {
+        let mut len: gl::types::GLint = 0;
+        unsafe {
+            gl.GetProgramiv(id, gl::INFO_LOG_LENGTH, &mut len);
+        }
+
+        let error = create_empty_cstring_with_len(len as usize);
+
+        unsafe {
+            gl.GetProgramInfoLog(
+                id,
+                len,
+                std::ptr::null_mut(),
+                error.as_ptr() as *mut gl::types::GLchar,
+            );
+        }
+
+        return Err(error.to_string_lossy().into_owned());
+    }
+    Ok(())
+}
+
+/// Locations by name, the arrays can be set without the `[0]`.
+fn locations(uniforms: &[UniformInfo]) -> HashMap<String, GLint> {
+    let mut locations = HashMap::new();
+    for uniform in uniforms.iter() {
+        locations.insert(uniform.name.clone(), uniform.location);
+        // `u_weights[0]` can be set as `u_weights`.
+        if let Some(base) = uniform.name.strip_suffix("[0]") {
+            locations.insert(base.to_owned(), uniform.location);
+        }
+    }
+    locations
+}
+
+fn active_uniforms(gl: &gl::Gl, program: GLuint) -> Vec<UniformInfo> {
+    let mut count: GLint = 0;
+    let mut max_len: GLint = 0;
+    unsafe {
+        gl.GetProgramiv(program, gl::ACTIVE_UNIFORMS, &mut count);
+        gl.GetProgramiv(program, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_len);
+    }
+    let mut uniforms = Vec::with_capacity(count as usize);
+    for index in 0..count as GLuint {
+        let mut buffer = vec![0u8; max_len.max(1) as usize];
+        let mut len: GLsizei = 0;
+        let mut size: GLint = 0;
+        let mut ty: GLenum = 0;
+        unsafe {
+            gl.GetActiveUniform(
+                program,
+                index,
+                max_len,
+                &mut len,
+                &mut size,
+                &mut ty,
+                buffer.as_mut_ptr() as *mut GLchar,
+            );
+        }
+        buffer.truncate(len as usize);
+        let name = String::from_utf8_lossy(&buffer).into_owned();
+        let location = match CString::new(name.as_str()) {
+            Ok(cname) => unsafe { gl.GetUniformLocation(program, cname.as_ptr()) },
+            Err(_) => -1,
+        };
+        // The members of the uniform blocks are set through buffers.
+        if location < 0 {
+            continue;
+        }
+        uniforms.push(UniformInfo {
+            name,
+            location,
+            ty,
+            size,
+        });
+    }
+    uniforms
+}
+
+// shader struct, not clonable: it deletes its GL object when dropped.
 pub struct Shader {
     _id: GLuint,
     source: CString,
@@ -144,28 +574,35 @@ pub struct Shader {
 }
 
 impl Shader {
+    /// Preprocess a shader of the assets and compile it,
+    /// the errors point at the files and lines before preprocessing.
+    pub fn from_res(gl: &gl::Gl, res: &Preprocessor, name: &str, key: &VariantKey, stype: GLenum) -> Result<Shader, Error> {
+        let source = res.process(Path::new(name), key).map_err(|e| Error::ResourceLoad {
+            name: name.to_owned(),
+            inner: e,
+        })?;
+        Shader::from_preprocessed(gl, &source, stype)
+    }
 
-    // pub fn from_res(gl: &gl::Gl, res: &RessourceLoader, name: &str, stype : GLenum) -> Result<Shader, Error> {
-    //     let source = res.load_cstring(name).map_err(|e| Error::ResourceLoad {
-    //         name: name.to_owned(),
-    //         inner: e,
-    //     })?;
-
-    //     Shader::from_source(gl, source, stype).map_err(|e| Error::CompileError {
-    //         name: name.to_owned(),
-    //         message: e,
-    //     })
-    // }
+    pub fn from_preprocessed(gl: &gl::Gl, source: &Preprocessed, stype: GLenum) -> Result<Shader, Error> {
+        let compile_error = |message: String| Error::CompileError {
+            name: source.name.clone(),
+            message,
+        };
+        let cstring = CString::new(source.source.as_str()).map_err(|e| compile_error(e.to_string()))?;
+        Shader::from_source(gl, cstring, stype).map_err(|e| compile_error(remap_log(&e, &source.lines)))
+    }
 
+    /// Compile a source, the error is the log of the driver.
     pub fn from_source(gl: &gl::Gl, source: CString, shader_type: GLenum) -> Result<Self, String> {
         let id = unsafe { gl.CreateShader(shader_type) };
 
         unsafe {
-            gl.ShaderSource(id, 1, &source[..].as_ptr(), std::ptr::null());
+            gl.ShaderSource(id, 1, &source.as_ptr(), std::ptr::null());
             gl.CompileShader(id);
         }
 
-        // check if the compilation was successfull and print the error message if it isn't.
+        // check if the compilation was successfull and return the error message if it isn't.
         let mut success: GLint = 1;
         unsafe {
             gl.GetShaderiv(id, gl::COMPILE_STATUS, &mut success);
@@ -186,12 +623,10 @@ impl Shader {
                     std::ptr::null_mut(),
                     error.as_ptr() as *mut gl::types::GLchar,
                 );
+                gl.DeleteShader(id);
             }
 
-            return Err(format!(
-                "There was an error in the shader compilation: {}\n",
-                error.to_string_lossy().into_owned()
-            ));
+            return Err(error.to_string_lossy().into_owned());
         }
 
         Ok(Self {
@@ -204,6 +639,10 @@ impl Shader {
     pub fn id(&self) -> GLuint {
         self._id
     }
+
+    pub fn source(&self) -> &CString {
+        &self.source
+    }
 }
 
 impl Drop for Shader {
@@ -214,4 +653,60 @@ impl Drop for Shader {
     }
 }
 
+fn create_empty_cstring_with_len(len: usize) -> CString {
+    // The info log is null terminated, len counts the terminator.
+    let buffer: Vec<u8> = vec![b' '; len.saturating_sub(1)];
+    unsafe { CString::from_vec_unchecked(buffer) }
+}
+
+/// Prefix the messages of a driver log with the file and line they come from.
+fn remap_log(log: &str, lines: &LineMap) -> String {
+    log.lines()
+        .map(|message| match log_line(message).and_then(|line| lines.locate(line)) {
+            Some((file, line)) => format!("{}:{}: {}", file, line, message),
+            None => message.to_owned(),
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
 
+/// The source line of a driver message, `0:12(5): error` on Mesa, `0(12) : error` on Nvidia.
+fn log_line(message: &str) -> Option<u32> {
+    // The first match, the text of a message may hold the other pattern, like `C0000:`.
+    let start = ["0:", "0("].iter().filter_map(|p| message.find(p)).min()? + 2;
+    let digits: String = message[start..].chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn driver_lines() {
+        assert_eq!(log_line("0:12(5): error: `mvp' undeclared"), Some(12));
+        assert_eq!(log_line("ERROR: 0:7: 'vec' : syntax error"), Some(7));
+        assert_eq!(log_line("0(3) : error C0000: syntax error, unexpected '}'"), Some(3));
+        assert_eq!(log_line("error C0000: syntax error"), None);
+        assert_eq!(log_line("Link failed."), None);
+    }
+
+    #[test]
+    fn remapped_log() {
+        let source = "#version 450\nvoid main() {\n    gl_Position = mvp;\n}\n";
+        let preprocessed = Preprocessor::new("shaders")
+            .define("MAX_LIGHTS", "4")
+            .process_source(source, "rect.vert", &VariantKey::default())
+            .unwrap();
+        // The define injected after `#version` moves the source down one line.
+        let log = "0:4(19): error: `mvp' undeclared\nwarning: extension unused\n0:9(1): error: past the end";
+        let file = preprocessed.name.clone();
+        assert_eq!(
+            remap_log(log, &preprocessed.lines),
+            format!(
+                "{}:3: 0:4(19): error: `mvp' undeclared\nwarning: extension unused\n0:9(1): error: past the end",
+                file
+            )
+        );
+    }
+}
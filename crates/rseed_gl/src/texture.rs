@@ -1,100 +1,344 @@
 // External imports
 use gl::types::*;
-use image::io::Reader as ImageReader;
+use std::path::Path;
 // Crate imports
-use crate::utils::color::rgb::RgbColor;
-use crate::ressources::{self, RessourceLoader};
+use rseed_core::prelude::*;
+use rseed_renderapi::{
+    context::{ResourceError, Result},
+    handle::{SamplerHandle, TextureHandle},
+    texture::{Filter, SamplerDesc, SamplerT, TextureDesc, TextureFormat, TextureKind, TextureRegion, TextureT, Wrap},
+};
 
-#[derive(Debug)]
+#[derive(Debug, Error)]
 pub enum Error {
-    ResourceLoad { name: String, inner: ressources::Error },
-    SizeMismatch,
-}
-
-pub struct Texture {
-    id : GLuint,
-    gl : gl::Gl,
-    data : Vec<u8>,
-    width : i32,
-    height : i32,
-}
-
-
-// all the file format supported by the image crate
-const POSSIBLE_EXT: [&str; 6] = [
-            ".png",
-            ".jpg",
-            ".gif",
-            ".ico",
-            ".bmp",
-            ".tiff",
-        ];
-
-impl Texture {
-
-    pub fn from_res(gl : &gl::Gl, res : &RessourceLoader, name : &str) -> Result<Self, Error> {
-        let ressources_names = POSSIBLE_EXT
-            .iter()
-            .map(|(file_ext)| format!("{}{}", name, file_ext));
-        // filter out the ones that don't exists
-        //.partition(|name| res.name_to_path(&(**name).to_owned()).exists())
-        //.0;
-        
-
-        let mut image = ImageReader::open(res.name_to_path(name))
-            .unwrap()
-            .decode()
-            .unwrap()
-            .flipv()
-            .to_rgba8();
-        let flat = image.as_flat_samples_mut();
-        let dims = flat.bounds();
-        let storage_format = match dims.0 {
-            3 => gl::RGB8,
-            4 => gl::RGBA8,
-            _ => return Err(Error::SizeMismatch),
-        };
-        let data_format = match dims.0 {
-            3 => gl::RGB,
-            4 => gl::RGBA,
-            _ => return Err(Error::SizeMismatch),
-        };
+    #[error(display = "Can't load the image {}: {}", name, inner)]
+    ResourceLoad {
+        name: String,
+        inner: image::ImageError,
+    },
+    #[error(display = "The layers of a texture must have the same size, {} is {}x{}.", name, width, height)]
+    SizeMismatch {
+        name: String,
+        width: u32,
+        height: u32,
+    },
+    #[error(display = "{}", _0)]
+    Resource(ResourceError),
+}
+
+// Core only since 4.6, the bindings stop at 4.5.
+const TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FE;
+
+/// Internal format, pixel format and pixel type of a texture format.
+pub fn gl_texture_format(format: TextureFormat) -> (GLenum, GLenum, GLenum) {
+    match format {
+        TextureFormat::R8 => (gl::R8, gl::RED, gl::UNSIGNED_BYTE),
+        TextureFormat::Rg8 => (gl::RG8, gl::RG, gl::UNSIGNED_BYTE),
+        TextureFormat::Rgba8 => (gl::RGBA8, gl::RGBA, gl::UNSIGNED_BYTE),
+        TextureFormat::Srgba8 => (gl::SRGB8_ALPHA8, gl::RGBA, gl::UNSIGNED_BYTE),
+        TextureFormat::R16F => (gl::R16F, gl::RED, gl::HALF_FLOAT),
+        TextureFormat::Rgba16F => (gl::RGBA16F, gl::RGBA, gl::HALF_FLOAT),
+        TextureFormat::R32F => (gl::R32F, gl::RED, gl::FLOAT),
+        TextureFormat::Rgba32F => (gl::RGBA32F, gl::RGBA, gl::FLOAT),
+        TextureFormat::Depth32F => (gl::DEPTH_COMPONENT32F, gl::DEPTH_COMPONENT, gl::FLOAT),
+        TextureFormat::Depth24Stencil8 => {
+            (gl::DEPTH24_STENCIL8, gl::DEPTH_STENCIL, gl::UNSIGNED_INT_24_8)
+        }
+    }
+}
+
+pub fn gl_texture_target(kind: TextureKind) -> GLenum {
+    match kind {
+        TextureKind::D2 => gl::TEXTURE_2D,
+        TextureKind::D2Array => gl::TEXTURE_2D_ARRAY,
+        TextureKind::Cube => gl::TEXTURE_CUBE_MAP,
+    }
+}
+
+pub fn gl_filter(filter: Filter, mip: Option<Filter>) -> GLenum {
+    match (filter, mip) {
+        (Filter::Nearest, None) => gl::NEAREST,
+        (Filter::Linear, None) => gl::LINEAR,
+        (Filter::Nearest, Some(Filter::Nearest)) => gl::NEAREST_MIPMAP_NEAREST,
+        (Filter::Nearest, Some(Filter::Linear)) => gl::NEAREST_MIPMAP_LINEAR,
+        (Filter::Linear, Some(Filter::Nearest)) => gl::LINEAR_MIPMAP_NEAREST,
+        (Filter::Linear, Some(Filter::Linear)) => gl::LINEAR_MIPMAP_LINEAR,
+    }
+}
+
+pub fn gl_wrap(wrap: Wrap) -> GLenum {
+    match wrap {
+        Wrap::Repeat => gl::REPEAT,
+        Wrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+        Wrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+        Wrap::ClampToBorder => gl::CLAMP_TO_BORDER,
+    }
+}
 
-        let width = dims.1 as i32;
-        let height = dims.2 as i32;
+/// How an image file becomes a texture.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ImageOptions {
+    /// The colors are in the sRGB space, as most color maps. Not for the normal maps or masks.
+    pub srgb: bool,
+    /// Allocate and generate the full mip chain.
+    pub mips: bool,
+    /// GL reads the rows from the bottom, the images store them from the top.
+    /// The faces of a cubemap are usually not flipped.
+    pub flip: bool,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        Self {
+            srgb: true,
+            mips: true,
+            flip: true,
+        }
+    }
+}
+
+pub struct GlTexture {
+    gl: gl::Gl,
+    id: GLuint,
+    desc: TextureDesc,
+}
+
+impl GlTexture {
+    /// `data` holds the base level of each layer one after the other.
+    pub fn new(gl: &gl::Gl, desc: TextureDesc, data: Option<&[u8]>) -> Result<Self> {
+        desc.validate()?;
+        let (internal, _, _) = gl_texture_format(desc.format);
+        let levels = desc.mip_levels.max(1) as GLsizei;
         let mut id = 0;
-        let data = flat.as_slice();
         unsafe {
-            gl.CreateTextures(gl::TEXTURE_2D, 1, &mut id);
-            gl.TextureStorage2D(id, 1, storage_format, width, height);
-            gl.TextureParameteri(id, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-            gl.TextureParameteri(id, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
-            gl.TextureSubImage2D(id, 0, 0, 0, width, height, data_format, gl::UNSIGNED_BYTE, data.as_ptr() as *const GLvoid)
+            gl.CreateTextures(gl_texture_target(desc.kind), 1, &mut id);
+            match desc.kind {
+                TextureKind::D2 | TextureKind::Cube => gl.TextureStorage2D(
+                    id,
+                    levels,
+                    internal,
+                    desc.width as GLsizei,
+                    desc.height as GLsizei,
+                ),
+                TextureKind::D2Array => gl.TextureStorage3D(
+                    id,
+                    levels,
+                    internal,
+                    desc.width as GLsizei,
+                    desc.height as GLsizei,
+                    desc.layers as GLsizei,
+                ),
+            }
         }
-
-        Ok(Self {
+        let mut texture = Self {
+            gl: gl.clone(),
             id,
-            gl : gl.clone(),
-            data : data.to_vec(),
-            width,
-            height,
+            desc,
+        };
+        if let Some(data) = data {
+            for (region, pixels) in desc.base_layers(data)? {
+                texture.update(region, pixels)?;
+            }
+            if desc.mip_levels > 1 {
+                texture.generate_mips();
+            }
+        }
+        Ok(texture)
+    }
 
-        })
+    /// A 2D texture from an image file, in RGBA8 or sRGB.
+    pub fn from_file(gl: &gl::Gl, path: &Path, options: ImageOptions) -> std::result::Result<Self, Error> {
+        Self::from_layers(gl, TextureKind::D2, &[path], options)
     }
 
-    pub fn set_pixel(&mut self, i : usize, j : usize, color : RgbColor) {
-        self.data[(i * self.width as usize + j) * 3] = color.red;
-        self.data[(i * self.width as usize + j) * 3 + 1] = color.blue;
-        self.data[(i * self.width as usize + j) * 3 + 2] = color.green;
+    /// An array from image files of the same size, one layer per file.
+    pub fn array_from_files<P: AsRef<Path>>(
+        gl: &gl::Gl,
+        paths: &[P],
+        options: ImageOptions,
+    ) -> std::result::Result<Self, Error> {
+        Self::from_layers(gl, TextureKind::D2Array, paths, options)
     }
 
+    /// A cubemap from six square images, in the order +X, -X, +Y, -Y, +Z, -Z.
+    pub fn cube_from_files<P: AsRef<Path>>(
+        gl: &gl::Gl,
+        faces: &[P; 6],
+        options: ImageOptions,
+    ) -> std::result::Result<Self, Error> {
+        Self::from_layers(gl, TextureKind::Cube, faces, options)
+    }
+
+    fn from_layers<P: AsRef<Path>>(
+        gl: &gl::Gl,
+        kind: TextureKind,
+        paths: &[P],
+        options: ImageOptions,
+    ) -> std::result::Result<Self, Error> {
+        let mut size = None;
+        let mut data = Vec::new();
+        for path in paths.iter().map(|p| p.as_ref()) {
+            let (width, height, pixels) = load_rgba8(path, options.flip)?;
+            match size {
+                None => size = Some((width, height)),
+                Some(size) if size != (width, height) => {
+                    return Err(Error::SizeMismatch {
+                        name: path.to_string_lossy().into_owned(),
+                        width,
+                        height,
+                    })
+                }
+                Some(_) => (),
+            }
+            data.extend_from_slice(&pixels);
+        }
+        let (width, height) = size.unwrap_or((0, 0));
+        let format = if options.srgb {
+            TextureFormat::Srgba8
+        } else {
+            TextureFormat::Rgba8
+        };
+        let mut desc = TextureDesc::new(width, height, format);
+        desc.kind = kind;
+        desc.layers = paths.len() as u32;
+        if options.mips {
+            desc = desc.with_mips();
+        }
+        Self::new(gl, desc, Some(&data)).map_err(Error::Resource)
+    }
 
     pub fn id(&self) -> GLuint {
         self.id
     }
 
-    pub fn bind(&self, slot : GLuint) {
-        unsafe {self.gl.BindTextureUnit(slot, self.id)};
+    pub fn bind(&self, slot: GLuint) {
+        unsafe { self.gl.BindTextureUnit(slot, self.id) };
+    }
+}
+
+impl TextureT for GlTexture {
+    fn handle(&self) -> TextureHandle {
+        TextureHandle(self.id)
     }
 
-}
\ No newline at end of file
+    fn desc(&self) -> &TextureDesc {
+        &self.desc
+    }
+
+    fn update(&mut self, region: TextureRegion, data: &[u8]) -> Result<()> {
+        if !self.desc.contains(&region) {
+            return Err(ResourceError::OutOfBounds);
+        }
+        let expected = (region.width * region.height * self.desc.format.pixel_size()) as usize;
+        if data.len() != expected {
+            return Err(ResourceError::SizeMismatch(expected, data.len()));
+        }
+        let (_, format, ty) = gl_texture_format(self.desc.format);
+        unsafe {
+            // Rows of R8 and RG8 textures are not aligned on 4 bytes.
+            self.gl.PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            match self.desc.kind {
+                TextureKind::D2 => self.gl.TextureSubImage2D(
+                    self.id,
+                    region.level as GLint,
+                    region.x as GLint,
+                    region.y as GLint,
+                    region.width as GLsizei,
+                    region.height as GLsizei,
+                    format,
+                    ty,
+                    data.as_ptr() as *const GLvoid,
+                ),
+                // With the direct state access the faces of a cubemap are layers too.
+                TextureKind::D2Array | TextureKind::Cube => self.gl.TextureSubImage3D(
+                    self.id,
+                    region.level as GLint,
+                    region.x as GLint,
+                    region.y as GLint,
+                    region.layer as GLint,
+                    region.width as GLsizei,
+                    region.height as GLsizei,
+                    1,
+                    format,
+                    ty,
+                    data.as_ptr() as *const GLvoid,
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    fn generate_mips(&mut self) {
+        unsafe { self.gl.GenerateTextureMipmap(self.id) };
+    }
+}
+
+impl Drop for GlTexture {
+    fn drop(&mut self) {
+        unsafe { self.gl.DeleteTextures(1, &self.id) };
+    }
+}
+
+pub struct GlSampler {
+    gl: gl::Gl,
+    id: GLuint,
+    desc: SamplerDesc,
+}
+
+impl GlSampler {
+    pub fn new(gl: &gl::Gl, desc: SamplerDesc) -> Self {
+        let mut id = 0;
+        unsafe {
+            gl.CreateSamplers(1, &mut id);
+            let min = gl_filter(desc.min_filter, desc.mip_filter);
+            gl.SamplerParameteri(id, gl::TEXTURE_MIN_FILTER, min as GLint);
+            gl.SamplerParameteri(id, gl::TEXTURE_MAG_FILTER, gl_filter(desc.mag_filter, None) as GLint);
+            gl.SamplerParameteri(id, gl::TEXTURE_WRAP_S, gl_wrap(desc.wrap_u) as GLint);
+            gl.SamplerParameteri(id, gl::TEXTURE_WRAP_T, gl_wrap(desc.wrap_v) as GLint);
+            gl.SamplerParameteri(id, gl::TEXTURE_WRAP_R, gl_wrap(desc.wrap_w) as GLint);
+            if desc.anisotropy > 1 {
+                gl.SamplerParameterf(id, TEXTURE_MAX_ANISOTROPY, desc.anisotropy as GLfloat);
+            }
+        }
+        Self {
+            gl: gl.clone(),
+            id,
+            desc,
+        }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    pub fn bind(&self, slot: GLuint) {
+        unsafe { self.gl.BindSampler(slot, self.id) };
+    }
+}
+
+impl SamplerT for GlSampler {
+    fn handle(&self) -> SamplerHandle {
+        SamplerHandle(self.id)
+    }
+
+    fn desc(&self) -> &SamplerDesc {
+        &self.desc
+    }
+}
+
+impl Drop for GlSampler {
+    fn drop(&mut self) {
+        unsafe { self.gl.DeleteSamplers(1, &self.id) };
+    }
+}
+
+/// Decode an image file to tightly packed RGBA8 rows.
+fn load_rgba8(path: &Path, flip: bool) -> std::result::Result<(u32, u32, Vec<u8>), Error> {
+    let image = image::open(path).map_err(|e| Error::ResourceLoad {
+        name: path.to_string_lossy().into_owned(),
+        inner: e,
+    })?;
+    let image = if flip { image.flipv() } else { image };
+    let image = image.to_rgba8();
+    Ok((image.width(), image.height(), image.into_raw()))
+}
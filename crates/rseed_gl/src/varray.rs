@@ -1,67 +1,41 @@
 // External imports
 use gl;
 use gl::types::*;
+use rseed_renderapi::{
+    buffer::{Vertex, VertexFormat, VertexLayout},
+    renderer::{IndexFormat, Primitive},
+};
 // Crate imports
 use super::buffer::IndexBuffer;
 use super::buffer::VertexBuffer;
-
-pub struct VbElements {
-    etype: GLenum,
-    count: GLint,
-    normalized: GLboolean,
-}
-
-impl VbElements {
-    pub fn new(etype: GLenum, count: GLint, normalized: GLboolean) -> Self {
-        Self {
-            etype: etype,
-            count: count,
-            normalized: normalized,
+use super::{gl_index_format, gl_primitive};
+
+/// Component type of an attribute and whether it is normalized.
+pub fn gl_vertex_format(format: VertexFormat) -> (GLenum, GLboolean) {
+    let ty = match format {
+        VertexFormat::Float | VertexFormat::Float2 | VertexFormat::Float3 | VertexFormat::Float4 => gl::FLOAT,
+        VertexFormat::Half2 | VertexFormat::Half4 => gl::HALF_FLOAT,
+        VertexFormat::Int | VertexFormat::Int2 | VertexFormat::Int3 | VertexFormat::Int4 => gl::INT,
+        VertexFormat::UInt | VertexFormat::UInt2 | VertexFormat::UInt3 | VertexFormat::UInt4 => {
+            gl::UNSIGNED_INT
         }
-    }
-
-    pub fn get_type_size(etype: GLenum) -> GLuint {
-        match etype {
-            gl::FLOAT => 4,
-            gl::UNSIGNED_INT => 4,
-            gl::UNSIGNED_BYTE => 1,
-            _ => 0,
+        VertexFormat::Short2 | VertexFormat::Short2Norm | VertexFormat::Short4 | VertexFormat::Short4Norm => {
+            gl::SHORT
         }
-    }
-}
-
-pub struct VbLayout {
-    elements: Vec<VbElements>,
-    stride: GLsizei,
-}
-
-impl VbLayout {
-    pub fn new() -> Self {
-        Self {
-            elements: Vec::new(),
-            stride: 0,
+        VertexFormat::UShort2
+        | VertexFormat::UShort2Norm
+        | VertexFormat::UShort4
+        | VertexFormat::UShort4Norm => gl::UNSIGNED_SHORT,
+        VertexFormat::Byte4 | VertexFormat::Byte4Norm => gl::BYTE,
+        VertexFormat::UByte2 | VertexFormat::UByte2Norm | VertexFormat::UByte4 | VertexFormat::UByte4Norm => {
+            gl::UNSIGNED_BYTE
         }
-    }
-
-    pub fn push_f32(&mut self, count: GLint) {
-        self.elements
-            .push(VbElements::new(gl::FLOAT, count, gl::FALSE));
-        self.stride += (count as GLuint * VbElements::get_type_size(gl::FLOAT)) as GLsizei;
-    }
-
-    pub fn push_u32(&mut self, count: GLint) {
-        self.elements
-            .push(VbElements::new(gl::UNSIGNED_INT, count, gl::FALSE));
-        self.stride += (count as GLuint * VbElements::get_type_size(gl::UNSIGNED_INT)) as GLsizei;
-    }
-
-    pub fn push_u8(&mut self, count: GLint) {
-        self.elements
-            .push(VbElements::new(gl::UNSIGNED_BYTE, count, gl::FALSE));
-        self.stride += (count as GLuint * VbElements::get_type_size(gl::UNSIGNED_BYTE)) as GLsizei;
-    }
+    };
+    let normalized = if format.is_normalized() { gl::TRUE } else { gl::FALSE };
+    (ty, normalized)
 }
 
+/// The vertex buffers a draw reads, with the layout of each and the index buffer.
 pub struct VArray {
     _id: GLuint,
     gl: gl::Gl,
@@ -70,48 +44,89 @@ pub struct VArray {
 impl VArray {
     pub fn new(gl: &gl::Gl) -> Self {
         let mut id: GLuint = 0;
-        unsafe { gl.GenVertexArrays(1, &mut id) }
+        unsafe { gl.CreateVertexArrays(1, &mut id) }
         Self {
             _id: id,
             gl: gl.clone(),
         }
     }
 
-    pub fn add_buffer(&self, vertex_buffer: &VertexBuffer, vertex_buffer_layout: &VbLayout) {
-        self.bind();
-        vertex_buffer.bind();
-        let mut offset: GLuint = 0;
-        let mut elems_count: GLuint = 0;
-        for element in vertex_buffer_layout.elements.as_slice() {
-            unsafe {
-                self.gl.EnableVertexAttribArray(elems_count);
-                self.gl.VertexAttribPointer(
-                    elems_count,
-                    element.count,
-                    element.etype,
-                    element.normalized,
-                    vertex_buffer_layout.stride,
-                    offset as *const GLvoid,
-                );
-                elems_count += 1 as GLuint;
-                offset += element.count as GLuint * VbElements::get_type_size(element.etype);
+    /// Read the attributes of a layout from a buffer bound at `binding`.
+    /// Several buffers can feed the same draw, each at its own binding, as long as their
+    /// locations do not overlap. Instanced layouts advance once every `divisor` instances.
+    pub fn set_buffer(&self, binding: GLuint, vertex_buffer: &VertexBuffer, layout: &VertexLayout) {
+        self.set_buffer_id(binding, vertex_buffer.id(), layout);
+    }
+
+    /// Same as `set_buffer`, with the name of the buffer.
+    pub fn set_buffer_id(&self, binding: GLuint, buffer: GLuint, layout: &VertexLayout) {
+        unsafe {
+            self.gl.VertexArrayVertexBuffer(self._id, binding, buffer, 0, layout.stride as GLsizei);
+            self.gl.VertexArrayBindingDivisor(self._id, binding, layout.divisor);
+            for attribute in layout.attributes.iter() {
+                let (etype, normalized) = gl_vertex_format(attribute.format);
+                let count = attribute.format.components() as GLint;
+                self.gl.EnableVertexArrayAttrib(self._id, attribute.location);
+                // Integers converted to floats go through the plain format, like the normalized ones.
+                if attribute.format.is_integer() {
+                    self.gl
+                        .VertexArrayAttribIFormat(self._id, attribute.location, count, etype, attribute.offset);
+                } else {
+                    self.gl.VertexArrayAttribFormat(
+                        self._id,
+                        attribute.location,
+                        count,
+                        etype,
+                        normalized,
+                        attribute.offset,
+                    );
+                }
+                self.gl.VertexArrayAttribBinding(self._id, attribute.location, binding);
             }
         }
     }
 
-    pub fn draw(&self) {
+    /// Stop reading the attributes of a layout, before another buffer takes its binding.
+    pub fn disable(&self, layout: &VertexLayout) {
+        for attribute in layout.attributes.iter() {
+            unsafe { self.gl.DisableVertexArrayAttrib(self._id, attribute.location) };
+        }
+    }
+
+    /// Read a buffer of vertices laid out as `V`.
+    pub fn set_vertices<V: Vertex>(&self, binding: GLuint, vertex_buffer: &VertexBuffer) {
+        self.set_buffer(binding, vertex_buffer, &V::layout());
+    }
+
+    /// The index buffer of the indexed draws, kept by the vertex array.
+    pub fn set_index_buffer(&self, index_buffer: &IndexBuffer) {
+        unsafe { self.gl.VertexArrayElementBuffer(self._id, index_buffer.id()) };
+    }
+
+    pub fn draw(&self, primitive: Primitive, first: u32, count: u32, instances: u32) {
         self.bind();
         unsafe {
-            self.gl.DrawArrays(gl::TRIANGLES, 0, 3);
+            self.gl.DrawArraysInstanced(
+                gl_primitive(primitive),
+                first as GLint,
+                count as GLsizei,
+                instances as GLsizei,
+            );
         }
     }
 
-    pub fn draw_indexed(&self, index_buffer: &IndexBuffer) {
+    /// Draw `count` indices from the index `first` of the index buffer.
+    pub fn draw_indexed(&self, primitive: Primitive, format: IndexFormat, first: u32, count: u32, instances: u32) {
         self.bind();
-        index_buffer.bind();
+        let (ty, size) = gl_index_format(format);
         unsafe {
-            self.gl
-                .DrawElements(gl::TRIANGLES, 6, gl::UNSIGNED_INT, 0 as *const GLvoid);
+            self.gl.DrawElementsInstanced(
+                gl_primitive(primitive),
+                count as GLsizei,
+                ty,
+                (first as usize * size) as *const GLvoid,
+                instances as GLsizei,
+            );
         }
     }
 
@@ -0,0 +1,542 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    convert::TryInto,
+    path::PathBuf,
+    rc::Rc,
+};
+
+use rseed_math::Vec4D;
+use rseed_renderapi::{
+    buffer::{
+        BufferUsage, IndexBufferT, IndexData, UniformBufferT, VertexBufferT, VertexFormat, VertexLayout,
+    },
+    context::{ContextT, ResourceError, Result},
+    handle::{BufferHandle, PipelineHandle, RenderTargetHandle, SamplerHandle, TextureHandle},
+    image::Image,
+    pipeline::PipelineDesc,
+    renderer::IndexFormat,
+    target::{RenderTargetDesc, RenderTargetT},
+    texture::{SamplerDesc, SamplerT, TextureDesc, TextureRegion, TextureT},
+};
+
+use super::{framebuffer::Framebuffer, raster::Viewport};
+
+/// The attributes of a vertex buffer the rasterizer reads, `None` when the layout lacks them.
+/// The positions are in normalized device coordinates.
+pub(crate) struct Vertices {
+    pub positions: Option<Vec<[f32; 3]>>,
+    pub colors: Option<Vec<[f32; 4]>>,
+    pub len: usize,
+}
+
+/// The buffers of every kind share the slots, so their handles never collide.
+pub(crate) enum Buffer {
+    Vertex(Vertices),
+    Index(Vec<u32>),
+    /// Only stored, the rasterizer has no shader to read it.
+    Uniform(Vec<u8>),
+}
+
+pub(crate) struct State {
+    pub framebuffer: Framebuffer,
+    /// Freed buffers leave a hole so the handles stay valid.
+    pub buffers: Vec<Option<Buffer>>,
+    /// The offscreen targets, by handle as the buffers.
+    pub targets: Vec<Option<Framebuffer>>,
+    /// The target drawn to, `None` for the frame.
+    pub target: Option<usize>,
+    pub clear_color: Vec4D,
+    pub viewport: Viewport,
+    /// The vertex buffer at each binding.
+    pub vertex_buffers: BTreeMap<u32, usize>,
+    pub index_buffer: Option<usize>,
+    /// Multiplied with the vertex colors, set through the `u_color` uniform.
+    pub color: Vec4D,
+}
+
+impl State {
+    /// The framebuffer drawn to, `None` when the current target does not exist.
+    pub fn target_mut(&mut self) -> Option<&mut Framebuffer> {
+        match self.target {
+            None => Some(&mut self.framebuffer),
+            Some(target) => self.targets.get_mut(target)?.as_mut(),
+        }
+    }
+
+    fn insert(&mut self, buffer: Buffer) -> usize {
+        match self.buffers.iter().position(|b| b.is_none()) {
+            Some(i) => {
+                self.buffers[i] = Some(buffer);
+                i
+            }
+            None => {
+                self.buffers.push(Some(buffer));
+                self.buffers.len() - 1
+            }
+        }
+    }
+}
+
+fn read_f32(bytes: &[u8], offset: usize) -> f32 {
+    f32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn half_to_f32(half: u16) -> f32 {
+    let sign = if half & 0x8000 != 0 { -1f32 } else { 1f32 };
+    let exponent = ((half >> 10) & 0x1f) as i32;
+    let mantissa = (half & 0x3ff) as f32;
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0f32 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1f32 + mantissa / 1024f32) * 2f32.powi(exponent - 15),
+    }
+}
+
+/// The components of an attribute read as floats, `None` for the integer formats.
+fn read_attribute(bytes: &[u8], format: VertexFormat, offset: usize) -> Option<Vec<f32>> {
+    if format.is_integer() {
+        return None;
+    }
+    let size = format.component_size() as usize;
+    let component = |i: usize| {
+        let o = offset + size * i;
+        let b = &bytes[o..o + size];
+        match format {
+            VertexFormat::Half2 | VertexFormat::Half4 => half_to_f32(u16::from_le_bytes([b[0], b[1]])),
+            VertexFormat::Short2Norm | VertexFormat::Short4Norm => {
+                (i16::from_le_bytes([b[0], b[1]]) as f32 / i16::MAX as f32).max(-1f32)
+            }
+            VertexFormat::UShort2Norm | VertexFormat::UShort4Norm => {
+                u16::from_le_bytes([b[0], b[1]]) as f32 / u16::MAX as f32
+            }
+            VertexFormat::Byte4Norm => (b[0] as i8 as f32 / i8::MAX as f32).max(-1f32),
+            VertexFormat::UByte2Norm | VertexFormat::UByte4Norm => b[0] as f32 / u8::MAX as f32,
+            _ => read_f32(bytes, o),
+        }
+    };
+    Some((0..format.components() as usize).map(component).collect())
+}
+
+/// Read the position at location 0 and the color at location 1, the other attributes
+/// have no meaning for the rasterizer.
+fn decode(data: &[u8], layout: &VertexLayout) -> Result<Vertices> {
+    let stride = layout.stride.max(1) as usize;
+    if !data.len().is_multiple_of(stride) {
+        return Err(ResourceError::SizeMismatch(data.len() / stride * stride, data.len()));
+    }
+    let read = |location: u32, default: f32| {
+        let attribute = layout.attribute(location)?;
+        Some(
+            data.chunks_exact(stride)
+                .map(|v| {
+                    let mut values = [default; 4];
+                    if let Some(read) = read_attribute(v, attribute.format, attribute.offset as usize) {
+                        for (value, read) in values.iter_mut().zip(read) {
+                            *value = read;
+                        }
+                    }
+                    values
+                })
+                .collect::<Vec<[f32; 4]>>(),
+        )
+    };
+    Ok(Vertices {
+        positions: read(0, 0f32).map(|p| p.iter().map(|v| [v[0], v[1], v[2]]).collect()),
+        colors: read(1, 1f32),
+        len: data.len() / stride,
+    })
+}
+
+/// Creates the resources of the headless backend, kept in memory and shared with the renderer.
+pub struct HeadlessContext {
+    pub(crate) state: Rc<RefCell<State>>,
+    next_handle: Cell<u32>,
+    pipelines: Cell<u32>,
+}
+
+impl HeadlessContext {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(State {
+                framebuffer: Framebuffer::new(width, height),
+                buffers: Vec::new(),
+                targets: Vec::new(),
+                target: None,
+                clear_color: Vec4D::null(),
+                viewport: Viewport {
+                    x: 0,
+                    y: 0,
+                    width,
+                    height,
+                },
+                vertex_buffers: BTreeMap::new(),
+                index_buffer: None,
+                color: Vec4D::new(1f32, 1f32, 1f32, 1f32),
+            })),
+            next_handle: Cell::new(0),
+            pipelines: Cell::new(0),
+        }
+    }
+
+    fn next_handle(&self) -> u32 {
+        let next = self.next_handle.get();
+        self.next_handle.set(next + 1);
+        next
+    }
+}
+
+impl ContextT for HeadlessContext {
+    fn swap_buffers(&self) {}
+
+    fn create_vertex_buffer(
+        &self,
+        data: &[u8],
+        layout: VertexLayout,
+        _usage: BufferUsage,
+    ) -> Result<Box<dyn VertexBufferT>> {
+        let vertices = decode(data, &layout)?;
+        let len = vertices.len;
+        let index = self.state.borrow_mut().insert(Buffer::Vertex(vertices));
+        Ok(Box::new(HeadlessVertexBuffer {
+            state: self.state.clone(),
+            index,
+            layout,
+            len,
+        }))
+    }
+
+    fn create_index_buffer(&self, data: IndexData, _usage: BufferUsage) -> Result<Box<dyn IndexBufferT>> {
+        let index = self.state.borrow_mut().insert(Buffer::Index(Vec::new()));
+        let mut buffer = HeadlessIndexBuffer {
+            state: self.state.clone(),
+            index,
+            format: data.format(),
+            len: 0,
+        };
+        buffer.set_data(data)?;
+        Ok(Box::new(buffer))
+    }
+
+    fn create_uniform_buffer(&self, data: &[u8], _usage: BufferUsage) -> Result<Box<dyn UniformBufferT>> {
+        let index = self.state.borrow_mut().insert(Buffer::Uniform(data.to_vec()));
+        Ok(Box::new(HeadlessUniformBuffer {
+            state: self.state.clone(),
+            index,
+            size: data.len(),
+        }))
+    }
+
+    fn create_texture(&self, desc: TextureDesc, data: Option<&[u8]>) -> Result<Box<dyn TextureT>> {
+        desc.validate()?;
+        let mut texture = HeadlessTexture {
+            handle: TextureHandle(self.next_handle()),
+            desc,
+            levels: (0..desc.mip_levels.max(1))
+                .map(|l| vec![0u8; desc.level_size(l) * desc.layers as usize])
+                .collect(),
+        };
+        if let Some(data) = data {
+            for (region, pixels) in desc.base_layers(data)? {
+                texture.update(region, pixels)?;
+            }
+            texture.generate_mips();
+        }
+        Ok(Box::new(texture))
+    }
+
+    fn create_sampler(&self, desc: SamplerDesc) -> Result<Box<dyn SamplerT>> {
+        Ok(Box::new(HeadlessSampler {
+            handle: SamplerHandle(self.next_handle()),
+            desc,
+        }))
+    }
+
+    /// There is no shader on the CPU, pipelines only exist to match the other backends.
+    fn create_pipeline(&self, _desc: &PipelineDesc) -> Result<PipelineHandle> {
+        let pipeline = self.pipelines.get();
+        self.pipelines.set(pipeline + 1);
+        Ok(PipelineHandle(pipeline))
+    }
+
+    fn rebuild_pipeline(&self, handle: PipelineHandle) -> Result<Vec<PathBuf>> {
+        self.pipeline_files(handle)
+    }
+
+    fn pipeline_files(&self, handle: PipelineHandle) -> Result<Vec<PathBuf>> {
+        if handle.0 >= self.pipelines.get() {
+            return Err(ResourceError::NoPipeline(handle.0));
+        }
+        Ok(Vec::new())
+    }
+
+    fn create_render_target(&self, desc: RenderTargetDesc) -> Result<Box<dyn RenderTargetT>> {
+        desc.validate()?;
+        if desc.colors.len() > 1 {
+            return Err(ResourceError::Unsupported("render targets with several color attachments"));
+        }
+        let framebuffer = Framebuffer::new(desc.width, desc.height);
+        let mut state = self.state.borrow_mut();
+        let index = match state.targets.iter().position(|t| t.is_none()) {
+            Some(i) => {
+                state.targets[i] = Some(framebuffer);
+                i
+            }
+            None => {
+                state.targets.push(Some(framebuffer));
+                state.targets.len() - 1
+            }
+        };
+        Ok(Box::new(HeadlessRenderTarget {
+            state: self.state.clone(),
+            index,
+            desc,
+        }))
+    }
+}
+
+/// A framebuffer drawn instead of the frame. The multisampled targets are drawn with a single
+/// sample, and the attachments can only be read back, the rasterizer samples no texture.
+pub struct HeadlessRenderTarget {
+    state: Rc<RefCell<State>>,
+    index: usize,
+    desc: RenderTargetDesc,
+}
+
+impl RenderTargetT for HeadlessRenderTarget {
+    fn handle(&self) -> RenderTargetHandle {
+        RenderTargetHandle(self.index as u32)
+    }
+
+    fn desc(&self) -> &RenderTargetDesc {
+        &self.desc
+    }
+
+    fn color_texture(&self, _index: usize) -> Option<TextureHandle> {
+        None
+    }
+
+    fn depth_texture(&self) -> Option<TextureHandle> {
+        None
+    }
+
+    fn resolve(&mut self) {}
+
+    fn read_pixels(&mut self, index: usize) -> Result<Image> {
+        if index >= self.desc.colors.len() {
+            return Err(ResourceError::OutOfBounds);
+        }
+        match &self.state.borrow().targets[self.index] {
+            Some(framebuffer) => Ok(framebuffer.color.clone()),
+            None => Err(ResourceError::OutOfBounds),
+        }
+    }
+}
+
+impl Drop for HeadlessRenderTarget {
+    fn drop(&mut self) {
+        let mut state = self.state.borrow_mut();
+        state.targets[self.index] = None;
+        // As GL, deleting the bound target draws to the frame again.
+        if state.target == Some(self.index) {
+            state.target = None;
+        }
+    }
+}
+
+pub struct HeadlessVertexBuffer {
+    state: Rc<RefCell<State>>,
+    index: usize,
+    layout: VertexLayout,
+    len: usize,
+}
+
+impl VertexBufferT for HeadlessVertexBuffer {
+    fn handle(&self) -> BufferHandle {
+        BufferHandle(self.index as u32)
+    }
+
+    fn layout(&self) -> &VertexLayout {
+        &self.layout
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn set_data(&mut self, data: &[u8]) -> Result<()> {
+        let vertices = decode(data, &self.layout)?;
+        self.len = vertices.len;
+        self.state.borrow_mut().buffers[self.index] = Some(Buffer::Vertex(vertices));
+        Ok(())
+    }
+}
+
+impl Drop for HeadlessVertexBuffer {
+    fn drop(&mut self) {
+        self.state.borrow_mut().buffers[self.index] = None;
+    }
+}
+
+pub struct HeadlessIndexBuffer {
+    state: Rc<RefCell<State>>,
+    index: usize,
+    format: IndexFormat,
+    len: usize,
+}
+
+impl IndexBufferT for HeadlessIndexBuffer {
+    fn handle(&self) -> BufferHandle {
+        BufferHandle(self.index as u32)
+    }
+
+    fn format(&self) -> IndexFormat {
+        self.format
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn set_data(&mut self, data: IndexData) -> Result<()> {
+        let indices = match data {
+            IndexData::U16(data) => data.iter().map(|i| *i as u32).collect(),
+            IndexData::U32(data) => data.to_vec(),
+        };
+        self.format = data.format();
+        self.len = data.len();
+        self.state.borrow_mut().buffers[self.index] = Some(Buffer::Index(indices));
+        Ok(())
+    }
+}
+
+impl Drop for HeadlessIndexBuffer {
+    fn drop(&mut self) {
+        self.state.borrow_mut().buffers[self.index] = None;
+    }
+}
+
+pub struct HeadlessUniformBuffer {
+    state: Rc<RefCell<State>>,
+    index: usize,
+    size: usize,
+}
+
+impl HeadlessUniformBuffer {
+    pub fn data(&self) -> Vec<u8> {
+        match &self.state.borrow().buffers[self.index] {
+            Some(Buffer::Uniform(data)) => data.clone(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl UniformBufferT for HeadlessUniformBuffer {
+    fn handle(&self) -> BufferHandle {
+        BufferHandle(self.index as u32)
+    }
+
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn set_data(&mut self, data: &[u8]) -> Result<()> {
+        if data.len() > self.size {
+            return Err(ResourceError::SizeMismatch(self.size, data.len()));
+        }
+        if let Some(Buffer::Uniform(stored)) = &mut self.state.borrow_mut().buffers[self.index] {
+            stored[..data.len()].copy_from_slice(data);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for HeadlessUniformBuffer {
+    fn drop(&mut self) {
+        self.state.borrow_mut().buffers[self.index] = None;
+    }
+}
+
+/// Textures are only stored, the rasterizer does not sample them.
+pub struct HeadlessTexture {
+    handle: TextureHandle,
+    desc: TextureDesc,
+    /// Each level holds its layers one after the other.
+    levels: Vec<Vec<u8>>,
+}
+
+impl HeadlessTexture {
+    /// The pixels of every layer of a level.
+    pub fn level(&self, level: u32) -> Option<&[u8]> {
+        self.levels.get(level as usize).map(|l| l.as_slice())
+    }
+}
+
+impl TextureT for HeadlessTexture {
+    fn handle(&self) -> TextureHandle {
+        self.handle
+    }
+
+    fn desc(&self) -> &TextureDesc {
+        &self.desc
+    }
+
+    fn update(&mut self, region: TextureRegion, data: &[u8]) -> Result<()> {
+        if !self.desc.contains(&region) {
+            return Err(ResourceError::OutOfBounds);
+        }
+        let width = self.desc.level_extent(region.level).0;
+        let layer = region.layer as usize * self.desc.level_size(region.level);
+        let pixel = self.desc.format.pixel_size() as usize;
+        let row = region.width as usize * pixel;
+        if data.len() != row * region.height as usize {
+            return Err(ResourceError::SizeMismatch(row * region.height as usize, data.len()));
+        }
+        let level = &mut self.levels[region.level as usize];
+        for (y, src) in data.chunks_exact(row).enumerate() {
+            let start = layer + ((region.y as usize + y) * width as usize + region.x as usize) * pixel;
+            level[start..start + row].copy_from_slice(src);
+        }
+        Ok(())
+    }
+
+    /// Point sampled, good enough to check the level sizes in tests.
+    fn generate_mips(&mut self) {
+        let pixel = self.desc.format.pixel_size() as usize;
+        for l in 1..self.levels.len() {
+            let (src_width, src_height) = self.desc.level_extent(l as u32 - 1);
+            let (src_width, src_height) = (src_width as usize, src_height as usize);
+            let (width, height) = self.desc.level_extent(l as u32);
+            let (width, height) = (width as usize, height as usize);
+            let (src_layer, dst_layer) = (self.desc.level_size(l as u32 - 1), self.desc.level_size(l as u32));
+            let (before, after) = self.levels.split_at_mut(l);
+            for layer in 0..self.desc.layers as usize {
+                for y in 0..height {
+                    for x in 0..width {
+                        let sx = (2 * x).min(src_width - 1);
+                        let sy = (2 * y).min(src_height - 1);
+                        let src = layer * src_layer + (sy * src_width + sx) * pixel;
+                        let dst = layer * dst_layer + (y * width + x) * pixel;
+                        after[0][dst..dst + pixel].copy_from_slice(&before[l - 1][src..src + pixel]);
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub struct HeadlessSampler {
+    handle: SamplerHandle,
+    desc: SamplerDesc,
+}
+
+impl SamplerT for HeadlessSampler {
+    fn handle(&self) -> SamplerHandle {
+        self.handle
+    }
+
+    fn desc(&self) -> &SamplerDesc {
+        &self.desc
+    }
+}
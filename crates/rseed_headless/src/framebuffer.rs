@@ -0,0 +1,45 @@
+use rseed_renderapi::image::Image;
+
+/// RGBA8 color and f32 depth, rows from the top to the bottom.
+pub struct Framebuffer {
+    pub color: Image,
+    pub depth: Vec<f32>,
+}
+
+impl Framebuffer {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            color: Image::new(width, height),
+            depth: vec![1f32; (width * height) as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.color.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.color.height
+    }
+
+    pub fn clear(&mut self, color: [u8; 4]) {
+        self.color
+            .data
+            .chunks_mut(4)
+            .for_each(|p| p.copy_from_slice(&color));
+        self.depth.iter_mut().for_each(|d| *d = 1f32);
+    }
+
+    /// Write the fragment if it passes the depth test.
+    pub fn write(&mut self, x: u32, y: u32, depth: f32, color: [u8; 4]) {
+        if x >= self.width() || y >= self.height() {
+            return;
+        }
+        let i = (y * self.width() + x) as usize;
+        if depth < 0f32 || depth > self.depth[i] {
+            return;
+        }
+        self.depth[i] = depth;
+        self.color.set_pixel(x, y, color);
+    }
+}
@@ -0,0 +1,392 @@
+/// This crate implements the render api on the CPU, without window nor GPU.
+/// The frame can be read back and compared with reference images.
+pub mod context;
+pub mod framebuffer;
+pub mod raster;
+
+use std::{cell::RefCell, collections::HashSet};
+
+use rseed_log::Logger;
+use rseed_renderapi::{
+    context::ContextT,
+    image::Image,
+    renderer::{CommandBuffer, Primitive, RenderCommand, RendererT, UniformValue},
+};
+
+use context::{Buffer, HeadlessContext, State};
+use framebuffer::Framebuffer;
+use raster::{RasterVertex, Viewport};
+
+pub struct HeadlessRenderer {
+    ctx: HeadlessContext,
+    logger: Logger,
+    /// The commands the rasterizer can't execute, reported once each.
+    reported: RefCell<HashSet<String>>,
+}
+
+impl HeadlessRenderer {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            ctx: HeadlessContext::new(width, height),
+            logger: Logger::new(String::from("Headless")),
+            reported: RefCell::new(HashSet::new()),
+        }
+    }
+
+    /// Log a command the rasterizer skips, once so the frames do not flood the log.
+    fn report(&self, message: String) {
+        if self.reported.borrow_mut().insert(message.clone()) {
+            self.logger.warn(&message);
+        }
+    }
+
+    pub fn image(&self) -> Image {
+        self.ctx.state.borrow().framebuffer.color.clone()
+    }
+}
+
+impl State {
+    /// The position and color of a vertex, each read from the buffer at the lowest binding having it.
+    fn fetch(&self, index: u32) -> Option<RasterVertex> {
+        let mut pos = None;
+        let mut color = None;
+        for buffer in self.vertex_buffers.values() {
+            if let Some(Some(Buffer::Vertex(vertices))) = self.buffers.get(*buffer) {
+                let i = index as usize;
+                pos = pos.or_else(|| vertices.positions.as_ref().and_then(|p| p.get(i)).cloned());
+                color = color.or_else(|| vertices.colors.as_ref().and_then(|c| c.get(i)).cloned());
+            }
+        }
+        let mut color = color.unwrap_or([1f32; 4]);
+        color
+            .iter_mut()
+            .zip(&[self.color.x, self.color.y, self.color.z, self.color.w])
+            .for_each(|(c, u)| *c *= u);
+        Some(self.viewport.to_window(pos?, color))
+    }
+
+    fn draw(&mut self, primitive: Primitive, indices: &[u32]) {
+        let vertices: Vec<RasterVertex> = indices.iter().filter_map(|i| self.fetch(*i)).collect();
+        let fb = match self.target_mut() {
+            Some(fb) => fb,
+            None => return,
+        };
+        match primitive {
+            Primitive::Points => vertices.iter().for_each(|v| raster::point(fb, v)),
+            Primitive::Lines => vertices
+                .chunks_exact(2)
+                .for_each(|l| raster::line(fb, &l[0], &l[1])),
+            Primitive::LineStrip => vertices
+                .windows(2)
+                .for_each(|l| raster::line(fb, &l[0], &l[1])),
+            Primitive::Triangles => vertices
+                .chunks_exact(3)
+                .for_each(|t| raster::triangle(fb, &t[0], &t[1], &t[2])),
+            Primitive::TriangleStrip => vertices
+                .windows(3)
+                .for_each(|t| raster::triangle(fb, &t[0], &t[1], &t[2])),
+            Primitive::TriangleFan => {
+                if let Some(first) = vertices.first() {
+                    vertices[1..]
+                        .windows(2)
+                        .for_each(|t| raster::triangle(fb, first, &t[0], &t[1]))
+                }
+            }
+        }
+    }
+}
+
+impl RendererT for HeadlessRenderer {
+    fn swap_buffers(&self) {}
+
+    fn submit(&self, commands: &CommandBuffer) {
+        let mut state = self.ctx.state.borrow_mut();
+        for command in commands.commands() {
+            match command {
+                RenderCommand::Clear => {
+                    let c = state.clear_color;
+                    let to_u8 = |v: f32| (v.clamp(0f32, 1f32) * 255f32 + 0.5) as u8;
+                    if let Some(fb) = state.target_mut() {
+                        fb.clear([to_u8(c.x), to_u8(c.y), to_u8(c.z), to_u8(c.w)]);
+                    }
+                }
+                RenderCommand::SetClearColor(color) => state.clear_color = *color,
+                RenderCommand::SetViewport {
+                    x,
+                    y,
+                    width,
+                    height,
+                } => {
+                    state.viewport = Viewport {
+                        x: *x,
+                        y: *y,
+                        width: *width,
+                        height: *height,
+                    }
+                }
+                RenderCommand::SetPipeline(_) => (),
+                RenderCommand::SetRenderTarget(target) => state.target = target.map(|t| t.0 as usize),
+                RenderCommand::SetVertexBuffer { binding, buffer } => {
+                    state.vertex_buffers.insert(*binding, buffer.0 as usize);
+                }
+                RenderCommand::SetIndexBuffer(buffer, _) => {
+                    state.index_buffer = Some(buffer.0 as usize)
+                }
+                RenderCommand::SetUniform(name, UniformValue::Vec4(color)) if name == "u_color" => {
+                    state.color = *color
+                }
+                RenderCommand::SetUniform(_, _) | RenderCommand::SetUniformBuffer { .. } => (),
+                // Textures are not sampled by the rasterizer.
+                RenderCommand::SetTexture { .. } => (),
+                // Without instance attributes every instance covers the same pixels, one is enough.
+                RenderCommand::Draw { instances: 0, .. } | RenderCommand::DrawIndexed { instances: 0, .. } => (),
+                RenderCommand::Draw {
+                    primitive,
+                    first,
+                    count,
+                    ..
+                } => {
+                    let last = match first.checked_add(*count) {
+                        Some(last) => last,
+                        None => {
+                            self.report(format!(
+                                "The draw of {} vertices from {} overflows, it is skipped.",
+                                count, first
+                            ));
+                            continue;
+                        }
+                    };
+                    let indices: Vec<u32> = (*first..last).collect();
+                    state.draw(*primitive, &indices);
+                }
+                RenderCommand::DrawIndexed {
+                    primitive,
+                    first,
+                    count,
+                    ..
+                } => {
+                    let indices = match state.index_buffer.and_then(|i| state.buffers.get(i)) {
+                        Some(Some(Buffer::Index(indices))) => indices
+                            .iter()
+                            .skip(*first as usize)
+                            .take(*count as usize)
+                            .cloned()
+                            .collect::<Vec<u32>>(),
+                        _ => continue,
+                    };
+                    state.draw(*primitive, &indices);
+                }
+                RenderCommand::Flush => (),
+                // The commands run in order on the CPU, there is nothing to wait for.
+                RenderCommand::Barrier { .. } => (),
+            }
+        }
+    }
+
+    fn context(&self) -> &dyn ContextT {
+        &self.ctx
+    }
+
+    /// The content of the frame is lost.
+    fn resize(&self, width: u32, height: u32) {
+        let mut state = self.ctx.state.borrow_mut();
+        state.framebuffer = Framebuffer::new(width, height);
+        state.viewport = Viewport {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        };
+    }
+
+    fn read_pixels(&self) -> Option<Image> {
+        Some(self.image())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rseed_math::Vec4D;
+    use rseed_renderapi::{
+        buffer::{BufferUsage, IndexData, VertexBufferT, VertexFormat, VertexLayout},
+        handle::PipelineHandle,
+        pipeline::PipelineDesc,
+        target::RenderTargetDesc,
+        texture::TextureFormat,
+    };
+
+    use super::*;
+
+    const BACKGROUND: [u8; 4] = [0, 0, 255, 255];
+    /// Yellow vertices tinted by `u_color = (0.5, 1, 1, 1)`.
+    const TRIANGLE: [u8; 4] = [128, 255, 0, 255];
+
+    /// Build a reference image from rows of `.` for the background and `#` for the triangle.
+    fn reference(rows: &[&str]) -> Image {
+        let mut image = Image::new(rows[0].len() as u32, rows.len() as u32);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let color = if c == '#' { TRIANGLE } else { BACKGROUND };
+                image.set_pixel(x as u32, y as u32, color);
+            }
+        }
+        image
+    }
+
+    fn pipeline(renderer: &HeadlessRenderer) -> PipelineHandle {
+        renderer.context().create_pipeline(&PipelineDesc::new("color")).unwrap()
+    }
+
+    /// A yellow triangle covering the bottom left half of the frame.
+    fn triangle(renderer: &HeadlessRenderer) -> Box<dyn VertexBufferT> {
+        // The top vertex sits below the frame edge so no pixel center lies on the long edge.
+        let vertices: [([f32; 3], [f32; 4]); 3] = [
+            ([-1f32, -1f32, 0f32], [1f32, 1f32, 0f32, 1f32]),
+            ([1f32, -1f32, 0f32], [1f32, 1f32, 0f32, 1f32]),
+            ([-1f32, 0.9, 0f32], [1f32, 1f32, 0f32, 1f32]),
+        ];
+        let data: Vec<u8> = vertices
+            .iter()
+            .flat_map(|(p, c)| p.iter().chain(c.iter()).flat_map(|v| v.to_le_bytes()))
+            .collect();
+        let layout = VertexLayout::new().push(VertexFormat::Float3).push(VertexFormat::Float4);
+        renderer
+            .context()
+            .create_vertex_buffer(&data, layout, BufferUsage::Static)
+            .unwrap()
+    }
+
+    #[test]
+    fn clear_and_triangle() {
+        let renderer = HeadlessRenderer::new(8, 8);
+        let buffer = triangle(&renderer);
+
+        let mut commands = CommandBuffer::new();
+        commands
+            .set_clear_color(Vec4D::new(0f32, 0f32, 1f32, 1f32))
+            .clear()
+            .set_pipeline(pipeline(&renderer))
+            .set_vertex_buffer(0, buffer.handle())
+            .set_uniform("u_color", UniformValue::Vec4(Vec4D::new(0.5, 1f32, 1f32, 1f32)))
+            .draw(Primitive::Triangles, 0, 3);
+        renderer.submit(&commands);
+
+        check_triangle(&renderer.read_pixels().unwrap());
+    }
+
+    #[test]
+    fn offscreen_target() {
+        let renderer = HeadlessRenderer::new(8, 8);
+        let buffer = triangle(&renderer);
+        let mut target = renderer
+            .context()
+            .create_render_target(RenderTargetDesc::new(8, 8).with_color(TextureFormat::Rgba8))
+            .unwrap();
+
+        let mut commands = CommandBuffer::new();
+        commands
+            .set_render_target(Some(target.handle()))
+            .set_clear_color(Vec4D::new(0f32, 0f32, 1f32, 1f32))
+            .clear()
+            .set_pipeline(pipeline(&renderer))
+            .set_vertex_buffer(0, buffer.handle())
+            .set_uniform("u_color", UniformValue::Vec4(Vec4D::new(0.5, 1f32, 1f32, 1f32)))
+            .draw(Primitive::Triangles, 0, 3)
+            .set_render_target(None)
+            .set_clear_color(Vec4D::new(1f32, 0f32, 0f32, 1f32))
+            .clear();
+        renderer.submit(&commands);
+
+        check_triangle(&target.read_pixels(0).unwrap());
+        let frame = renderer.read_pixels().unwrap();
+        assert!(frame.data.chunks_exact(4).all(|p| p == [255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn vertex_buffers_at_two_bindings() {
+        let renderer = HeadlessRenderer::new(8, 8);
+        let positions: Vec<u8> = [[-1f32, -1f32, 0f32], [1f32, -1f32, 0f32], [-1f32, 0.9, 0f32]]
+            .iter()
+            .flat_map(|p| p.iter().flat_map(|v| v.to_le_bytes()))
+            .collect();
+        let colors: Vec<u8> = (0..3)
+            .flat_map(|_| [1f32, 1f32, 0f32, 1f32].iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>())
+            .collect();
+        let ctx = renderer.context();
+        let positions = ctx
+            .create_vertex_buffer(&positions, VertexLayout::new().push(VertexFormat::Float3), BufferUsage::Static)
+            .unwrap();
+        let colors = ctx
+            .create_vertex_buffer(&colors, VertexLayout::new().push_at(1, VertexFormat::Float4), BufferUsage::Static)
+            .unwrap();
+
+        let mut commands = CommandBuffer::new();
+        commands
+            .set_clear_color(Vec4D::new(0f32, 0f32, 1f32, 1f32))
+            .clear()
+            .set_pipeline(pipeline(&renderer))
+            .set_vertex_buffer(1, colors.handle())
+            .set_vertex_buffer(0, positions.handle())
+            .set_uniform("u_color", UniformValue::Vec4(Vec4D::new(0.5, 1f32, 1f32, 1f32)))
+            .draw(Primitive::Triangles, 0, 3);
+        renderer.submit(&commands);
+
+        check_triangle(&renderer.read_pixels().unwrap());
+    }
+
+    #[test]
+    fn buffer_handles_are_unique() {
+        let renderer = HeadlessRenderer::new(8, 8);
+        let ctx = renderer.context();
+        let vertices = triangle(&renderer);
+        let uniforms = ctx.create_uniform_buffer(&[0u8; 16], BufferUsage::Dynamic).unwrap();
+        let indices = ctx.create_index_buffer(IndexData::U32(&[0, 1, 2]), BufferUsage::Static).unwrap();
+        let handles = [vertices.handle(), uniforms.handle(), indices.handle()];
+        assert_ne!(handles[0], handles[1]);
+        assert_ne!(handles[0], handles[2]);
+        assert_ne!(handles[1], handles[2]);
+    }
+
+    #[test]
+    fn overflowing_draw_is_skipped() {
+        let renderer = HeadlessRenderer::new(8, 8);
+        let buffer = triangle(&renderer);
+
+        let mut commands = CommandBuffer::new();
+        commands
+            .set_clear_color(Vec4D::new(0f32, 0f32, 1f32, 1f32))
+            .clear()
+            .set_pipeline(pipeline(&renderer))
+            .set_vertex_buffer(0, buffer.handle())
+            .draw(Primitive::Triangles, u32::MAX - 1, 3);
+        renderer.submit(&commands);
+
+        let frame = renderer.read_pixels().unwrap();
+        assert!(frame.data.chunks_exact(4).all(|p| p == BACKGROUND));
+        assert_eq!(renderer.reported.borrow().len(), 1);
+    }
+
+    #[test]
+    fn only_created_pipelines_rebuild() {
+        let renderer = HeadlessRenderer::new(8, 8);
+        let handle = pipeline(&renderer);
+        assert!(renderer.context().rebuild_pipeline(handle).unwrap().is_empty());
+        assert!(renderer.context().rebuild_pipeline(PipelineHandle(handle.0 + 1)).is_err());
+    }
+
+    fn check_triangle(image: &Image) {
+        let expected = reference(&[
+            "........",
+            "#.......",
+            "##......",
+            "###.....",
+            "####....",
+            "#####...",
+            "######..",
+            "#######.",
+        ]);
+        // The interpolated colors may round one step away from the flat color.
+        let diff = image.diff(&expected).unwrap();
+        assert!(image.matches(&expected, 1), "{} pixels differ, max {}", diff.pixels, diff.max);
+    }
+}
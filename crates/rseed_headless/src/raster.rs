@@ -0,0 +1,101 @@
+use super::framebuffer::Framebuffer;
+
+/// A vertex in window coordinates, depth in `[0, 1]`.
+#[derive(Copy, Clone, Debug)]
+pub struct RasterVertex {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub color: [f32; 4],
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Viewport {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Viewport {
+    /// Map normalized device coordinates to the window, `y` pointing down.
+    pub fn to_window(&self, pos: [f32; 3], color: [f32; 4]) -> RasterVertex {
+        RasterVertex {
+            x: self.x as f32 + (pos[0] + 1f32) * 0.5 * self.width as f32,
+            y: self.y as f32 + (1f32 - pos[1]) * 0.5 * self.height as f32,
+            z: (pos[2] + 1f32) * 0.5,
+            color,
+        }
+    }
+}
+
+fn to_rgba8(color: [f32; 4]) -> [u8; 4] {
+    let c = |v: f32| (v.clamp(0f32, 1f32) * 255f32 + 0.5) as u8;
+    [c(color[0]), c(color[1]), c(color[2]), c(color[3])]
+}
+
+fn edge(a: &RasterVertex, b: &RasterVertex, x: f32, y: f32) -> f32 {
+    (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x)
+}
+
+pub fn point(fb: &mut Framebuffer, v: &RasterVertex) {
+    if v.x < 0f32 || v.y < 0f32 {
+        return;
+    }
+    fb.write(v.x as u32, v.y as u32, v.z, to_rgba8(v.color));
+}
+
+/// DDA line, colors and depth are interpolated.
+pub fn line(fb: &mut Framebuffer, a: &RasterVertex, b: &RasterVertex) {
+    let steps = (b.x - a.x).abs().max((b.y - a.y).abs()).ceil().max(1f32) as u32;
+    for i in 0..=steps {
+        let t = i as f32 / steps as f32;
+        let lerp = |u: f32, v: f32| u + (v - u) * t;
+        let mut color = a.color;
+        color
+            .iter_mut()
+            .zip(&b.color)
+            .for_each(|(c, b)| *c = lerp(*c, *b));
+        point(
+            fb,
+            &RasterVertex {
+                x: lerp(a.x, b.x),
+                y: lerp(a.y, b.y),
+                z: lerp(a.z, b.z),
+                color,
+            },
+        );
+    }
+}
+
+/// Fill the pixels whose center is inside the triangle, both windings are drawn.
+pub fn triangle(fb: &mut Framebuffer, v0: &RasterVertex, v1: &RasterVertex, v2: &RasterVertex) {
+    let area = edge(v0, v1, v2.x, v2.y);
+    if area.abs() < f32::EPSILON {
+        return;
+    }
+    let min_x = v0.x.min(v1.x).min(v2.x).floor().max(0f32) as u32;
+    let min_y = v0.y.min(v1.y).min(v2.y).floor().max(0f32) as u32;
+    let max_x = (v0.x.max(v1.x).max(v2.x).ceil() as i64).min(fb.width() as i64 - 1);
+    let max_y = (v0.y.max(v1.y).max(v2.y).ceil() as i64).min(fb.height() as i64 - 1);
+    if max_x < 0 || max_y < 0 {
+        return;
+    }
+    for y in min_y..=max_y as u32 {
+        for x in min_x..=max_x as u32 {
+            let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+            let w0 = edge(v1, v2, px, py) / area;
+            let w1 = edge(v2, v0, px, py) / area;
+            let w2 = edge(v0, v1, px, py) / area;
+            if w0 < 0f32 || w1 < 0f32 || w2 < 0f32 {
+                continue;
+            }
+            let mut color = [0f32; 4];
+            for (c, color) in color.iter_mut().enumerate() {
+                *color = w0 * v0.color[c] + w1 * v1.color[c] + w2 * v2.color[c];
+            }
+            let depth = w0 * v0.z + w1 * v1.z + w2 * v2.z;
+            fb.write(x, y, depth, to_rgba8(color));
+        }
+    }
+}
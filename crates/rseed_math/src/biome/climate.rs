@@ -0,0 +1,102 @@
+use crate::{noise::FractalNoise, Vec2D};
+
+/// A scalar field that can be sampled anywhere on the world plane.
+/// Noise generators and baked maps implement it to feed the climate.
+pub trait ScalarField2D {
+    fn sample(&self, pos: Vec2D) -> f32;
+}
+
+impl<F> ScalarField2D for F
+where
+    F: Fn(Vec2D) -> f32,
+{
+    fn sample(&self, pos: Vec2D) -> f32 {
+        self(pos)
+    }
+}
+
+/// The climate values at a given point, all expected in `[0, 1]`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ClimateSample {
+    pub temperature: f32,
+    pub moisture: f32,
+    pub height: f32,
+}
+
+impl ClimateSample {
+    pub fn new(temperature: f32, moisture: f32, height: f32) -> Self {
+        Self {
+            temperature,
+            moisture,
+            height,
+        }
+    }
+}
+
+/// Combine a temperature, a moisture and a height map.
+pub struct ClimateMap {
+    temperature: Box<dyn ScalarField2D>,
+    moisture: Box<dyn ScalarField2D>,
+    height: Box<dyn ScalarField2D>,
+}
+
+impl ClimateMap {
+    pub fn new(
+        temperature: Box<dyn ScalarField2D>,
+        moisture: Box<dyn ScalarField2D>,
+        height: Box<dyn ScalarField2D>,
+    ) -> Self {
+        Self {
+            temperature,
+            moisture,
+            height,
+        }
+    }
+
+    /// Three decorrelated fractal noises seeded from `seed`, `frequency` is the one of the
+    /// first octave in periods per world unit.
+    pub fn from_noise(seed: i64, frequency: f32) -> Self {
+        Self::new(
+            Box::new(FractalNoise::new(seed, frequency)),
+            Box::new(FractalNoise::new(seed.wrapping_add(1), frequency)),
+            Box::new(FractalNoise::new(seed.wrapping_add(2), frequency)),
+        )
+    }
+
+    pub fn sample(&self, pos: Vec2D) -> ClimateSample {
+        ClimateSample {
+            temperature: self.temperature.sample(pos),
+            moisture: self.moisture.sample(pos),
+            height: self.height.sample(pos),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn noise_climate_is_deterministic_and_in_range() {
+        let a = ClimateMap::from_noise(42, 0.01);
+        let b = ClimateMap::from_noise(42, 0.01);
+        for i in 0..64 {
+            let pos = Vec2D::new(i as f32 * 13.7, i as f32 * -5.3);
+            let sample = a.sample(pos);
+            assert_eq!(sample, b.sample(pos));
+            for v in [sample.temperature, sample.moisture, sample.height].iter() {
+                assert!((0f32..=1f32).contains(v));
+            }
+        }
+    }
+
+    #[test]
+    fn noise_climate_maps_are_decorrelated() {
+        let map = ClimateMap::from_noise(7, 0.05);
+        let differ = (0..64)
+            .map(|i| map.sample(Vec2D::new(i as f32 * 3.1, i as f32 * 1.7)))
+            .filter(|s| s.temperature != s.moisture)
+            .count();
+        assert!(differ > 32);
+    }
+}
@@ -0,0 +1,49 @@
+pub mod climate;
+pub mod table;
+
+pub use climate::{ClimateMap, ClimateSample, ScalarField2D};
+pub use table::{BiomeDef, BiomeError, BiomeId, BiomeTable, BiomeWeight};
+
+use crate::Vec2D;
+
+/// Classify world positions into biomes from climate maps and a lookup table.
+pub struct BiomeMap {
+    climate: ClimateMap,
+    table: BiomeTable,
+}
+
+impl BiomeMap {
+    pub fn new(climate: ClimateMap, table: BiomeTable) -> Self {
+        Self { climate, table }
+    }
+
+    pub fn climate(&self) -> &ClimateMap {
+        &self.climate
+    }
+
+    pub fn table(&self) -> &BiomeTable {
+        &self.table
+    }
+
+    /// Returns the biome at the world position.
+    pub fn biome_at(&self, pos: Vec2D) -> BiomeId {
+        self.table.classify(&self.climate.sample(pos))
+    }
+
+    /// Returns the blend weights at the world position.
+    pub fn weights_at(&self, pos: Vec2D) -> Vec<BiomeWeight> {
+        self.table.weights(&self.climate.sample(pos))
+    }
+
+    /// Sample a `width * height` grid of biome ids starting at `origin`, row by row.
+    pub fn sample_grid(&self, origin: Vec2D, step: f32, width: usize, height: usize) -> Vec<BiomeId> {
+        let mut ids = Vec::with_capacity(width * height);
+        for j in 0..height {
+            for i in 0..width {
+                let pos = origin + Vec2D::new(i as f32 * step, j as f32 * step);
+                ids.push(self.biome_at(pos));
+            }
+        }
+        ids
+    }
+}
@@ -0,0 +1,270 @@
+use rseed_core::prelude::*;
+use std::convert::TryFrom;
+
+use super::climate::ClimateSample;
+
+pub type BiomeId = u32;
+
+#[derive(Debug, Error)]
+pub enum BiomeError {
+    #[error(display = "Biome table invalid: {}", _0)]
+    TableInvalid(toml::de::Error),
+    #[error(display = "The biome table is empty.")]
+    EmptyTable,
+    #[error(display = "Biome {} has an invalid {} range.", _0, _1)]
+    InvalidRange(String, &'static str),
+    #[error(display = "Io error: {}", _0)]
+    IoError(std::io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, BiomeError>;
+
+fn full_range() -> [f32; 2] {
+    [0f32, 1f32]
+}
+
+fn default_blend() -> f32 {
+    0.05
+}
+
+/// One cell of the Whittaker diagram.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "serde")]
+pub struct BiomeDef {
+    pub name: String,
+    pub id: BiomeId,
+    #[serde(default = "full_range")]
+    pub temperature: [f32; 2],
+    #[serde(default = "full_range")]
+    pub moisture: [f32; 2],
+    #[serde(default = "full_range")]
+    pub height: [f32; 2],
+}
+
+impl BiomeDef {
+    /// Distance from the sample to the biome cell, negative when inside.
+    /// Inside the cell it is minus the distance to the closest border.
+    pub fn signed_distance(&self, sample: &ClimateSample) -> f32 {
+        let axes = [
+            (sample.temperature, self.temperature),
+            (sample.moisture, self.moisture),
+            (sample.height, self.height),
+        ];
+        let mut outside = 0f32;
+        let mut inside = f32::MAX;
+        for (v, [min, max]) in axes.iter() {
+            let d = (min - v).max(v - max);
+            if d > 0f32 {
+                outside += d * d;
+            } else {
+                inside = inside.min(-d);
+            }
+        }
+        if outside > 0f32 {
+            outside.sqrt()
+        } else {
+            -inside
+        }
+    }
+
+    pub fn contains(&self, sample: &ClimateSample) -> bool {
+        self.signed_distance(sample) <= 0f32
+    }
+}
+
+/// The blend weight of a biome at a given sample.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BiomeWeight {
+    pub id: BiomeId,
+    pub weight: f32,
+}
+
+/// The table as written in the file, checked before becoming a `BiomeTable`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "serde")]
+struct BiomeTableDef {
+    #[serde(default = "default_blend")]
+    blend: f32,
+    #[serde(rename = "biome")]
+    biomes: Vec<BiomeDef>,
+}
+
+impl TryFrom<BiomeTableDef> for BiomeTable {
+    type Error = BiomeError;
+
+    fn try_from(def: BiomeTableDef) -> Result<Self> {
+        Self::new(def.blend, def.biomes)
+    }
+}
+
+impl From<BiomeTable> for BiomeTableDef {
+    fn from(table: BiomeTable) -> Self {
+        Self {
+            blend: table.blend,
+            biomes: table.biomes,
+        }
+    }
+}
+
+/// Whittaker-style lookup table mapping a climate sample to a biome.
+/// It always holds at least one biome, every table is validated when built or deserialized.
+///
+/// ```toml
+/// blend = 0.05
+///
+/// [[biome]]
+/// name = "desert"
+/// id = 1
+/// temperature = [0.6, 1.0]
+/// moisture = [0.0, 0.3]
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "serde", try_from = "BiomeTableDef", into = "BiomeTableDef")]
+pub struct BiomeTable {
+    /// Width of the transition band between two biomes.
+    blend: f32,
+    biomes: Vec<BiomeDef>,
+}
+
+impl BiomeTable {
+    pub fn new(blend: f32, biomes: Vec<BiomeDef>) -> Result<Self> {
+        let table = Self { blend, biomes };
+        table.validate()?;
+        Ok(table)
+    }
+
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        from_str(toml).map_err(BiomeError::TableInvalid)
+    }
+
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let toml = std::fs::read_to_string(path).map_err(BiomeError::IoError)?;
+        Self::from_toml(toml.as_str())
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.biomes.is_empty() {
+            return Err(BiomeError::EmptyTable);
+        }
+        for biome in self.biomes.iter() {
+            let ranges = [
+                (biome.temperature, "temperature"),
+                (biome.moisture, "moisture"),
+                (biome.height, "height"),
+            ];
+            for ([min, max], axis) in ranges.iter() {
+                if min > max {
+                    return Err(BiomeError::InvalidRange(biome.name.clone(), axis));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn blend(&self) -> f32 {
+        self.blend
+    }
+
+    pub fn biomes(&self) -> &[BiomeDef] {
+        &self.biomes
+    }
+
+    pub fn get(&self, id: BiomeId) -> Option<&BiomeDef> {
+        self.biomes.iter().find(|b| b.id == id)
+    }
+
+    /// Returns the biome containing the sample, the first one declared wins.
+    /// When no cell contains it, the closest one is picked.
+    pub fn classify(&self, sample: &ClimateSample) -> BiomeId {
+        let mut best = (f32::MAX, self.biomes[0].id);
+        for biome in self.biomes.iter() {
+            let d = biome.signed_distance(sample);
+            if d <= 0f32 {
+                return biome.id;
+            }
+            if d < best.0 {
+                best = (d, biome.id);
+            }
+        }
+        best.1
+    }
+
+    /// Returns the normalized weights of every biome close enough to the sample.
+    /// Weights fade smoothly over `blend` around each border, for texture splatting.
+    pub fn weights(&self, sample: &ClimateSample) -> Vec<BiomeWeight> {
+        let half = self.blend.max(f32::EPSILON) * 0.5;
+        let mut weights: Vec<BiomeWeight> = self
+            .biomes
+            .iter()
+            .map(|b| {
+                let t = ((half - b.signed_distance(sample)) / (2f32 * half)).clamp(0f32, 1f32);
+                BiomeWeight {
+                    id: b.id,
+                    weight: t * t * (3f32 - 2f32 * t),
+                }
+            })
+            .filter(|w| w.weight > 0f32)
+            .collect();
+        let total: f32 = weights.iter().map(|w| w.weight).sum();
+        if total <= 0f32 {
+            return vec![BiomeWeight {
+                id: self.classify(sample),
+                weight: 1f32,
+            }];
+        }
+        weights.iter_mut().for_each(|w| w.weight /= total);
+        weights
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLE: &str = r#"
+        blend = 0.1
+
+        [[biome]]
+        name = "desert"
+        id = 1
+        temperature = [0.6, 1.0]
+        moisture = [0.0, 0.4]
+
+        [[biome]]
+        name = "forest"
+        id = 2
+        moisture = [0.4, 1.0]
+    "#;
+
+    #[test]
+    fn empty_table_is_rejected() {
+        assert!(BiomeTable::from_toml("blend = 0.1\nbiome = []").is_err());
+        assert!(BiomeTable::new(0.1, Vec::new()).is_err());
+    }
+
+    #[test]
+    fn inverted_range_is_rejected() {
+        let toml = "[[biome]]\nname = \"a\"\nid = 0\nheight = [0.8, 0.2]";
+        assert!(BiomeTable::from_toml(toml).is_err());
+    }
+
+    #[test]
+    fn classify_inside_and_outside() {
+        let table = BiomeTable::from_toml(TABLE).unwrap();
+        assert_eq!(table.biomes().len(), 2);
+        assert_eq!(table.classify(&ClimateSample::new(0.9, 0.1, 0.5)), 1);
+        assert_eq!(table.classify(&ClimateSample::new(0.2, 0.8, 0.5)), 2);
+        // Cold and dry is in no cell, the desert is the closest one.
+        assert_eq!(table.classify(&ClimateSample::new(0.5, 0.1, 0.5)), 1);
+    }
+
+    #[test]
+    fn weights_blend_at_the_border() {
+        let table = BiomeTable::from_toml(TABLE).unwrap();
+        let weights = table.weights(&ClimateSample::new(0.9, 0.4, 0.5));
+        assert_eq!(weights.len(), 2);
+        assert!(weights.iter().all(|w| (w.weight - 0.5).abs() < 1e-4));
+        let inside = table.weights(&ClimateSample::new(0.9, 0.1, 0.5));
+        assert_eq!(inside, vec![BiomeWeight { id: 1, weight: 1f32 }]);
+    }
+}
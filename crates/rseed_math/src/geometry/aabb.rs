@@ -0,0 +1,194 @@
+use super::{Sphere, Transform};
+use crate::{Mat3, Vec3D};
+
+/// Axis aligned bounding box.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3D,
+    pub max: Vec3D,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3D, max: Vec3D) -> Self {
+        Self { min, max }
+    }
+
+    /// A box containing nothing, growing it by a point gives that point.
+    pub fn empty() -> Self {
+        Self {
+            min: Vec3D::new(f32::MAX, f32::MAX, f32::MAX),
+            max: Vec3D::new(f32::MIN, f32::MIN, f32::MIN),
+        }
+    }
+
+    pub fn from_center(center: Vec3D, half_extents: Vec3D) -> Self {
+        Self {
+            min: center - half_extents,
+            max: center + half_extents,
+        }
+    }
+
+    pub fn from_points(points: &[Vec3D]) -> Self {
+        let mut aabb = Self::empty();
+        points.iter().for_each(|p| aabb.grow(p));
+        aabb
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn center(&self) -> Vec3D {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn half_extents(&self) -> Vec3D {
+        (self.max - self.min) * 0.5
+    }
+
+    pub fn size(&self) -> Vec3D {
+        self.max - self.min
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        if self.is_empty() {
+            return 0f32;
+        }
+        let d = self.size();
+        2f32 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    pub fn volume(&self) -> f32 {
+        if self.is_empty() {
+            return 0f32;
+        }
+        let d = self.size();
+        d.x * d.y * d.z
+    }
+
+    /// Index of the longest axis.
+    pub fn longest_axis(&self) -> usize {
+        let d = self.size();
+        if d.x >= d.y && d.x >= d.z {
+            0
+        } else if d.y >= d.z {
+            1
+        } else {
+            2
+        }
+    }
+
+    pub fn grow(&mut self, point: &Vec3D) {
+        self.min = self.min.min(point);
+        self.max = self.max.max(point);
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            min: self.min.min(&other.min),
+            max: self.max.max(&other.max),
+        }
+    }
+
+    pub fn contains_point(&self, point: &Vec3D) -> bool {
+        point.x >= self.min.x
+            && point.x <= self.max.x
+            && point.y >= self.min.y
+            && point.y <= self.max.y
+            && point.z >= self.min.z
+            && point.z <= self.max.z
+    }
+
+    pub fn contains_aabb(&self, other: &Self) -> bool {
+        self.contains_point(&other.min) && self.contains_point(&other.max)
+    }
+
+    pub fn intersects_aabb(&self, other: &Self) -> bool {
+        self.min.x <= other.max.x
+            && self.max.x >= other.min.x
+            && self.min.y <= other.max.y
+            && self.max.y >= other.min.y
+            && self.min.z <= other.max.z
+            && self.max.z >= other.min.z
+    }
+
+    pub fn intersects_sphere(&self, sphere: &Sphere) -> bool {
+        (self.closest_point(&sphere.center) - sphere.center).sq_norm()
+            <= sphere.radius * sphere.radius
+    }
+
+    pub fn closest_point(&self, point: &Vec3D) -> Vec3D {
+        point.max(&self.min).min(&self.max)
+    }
+
+    pub fn sq_distance(&self, point: &Vec3D) -> f32 {
+        (self.closest_point(point) - *point).sq_norm()
+    }
+
+    pub fn corners(&self) -> [Vec3D; 8] {
+        let (a, b) = (self.min, self.max);
+        [
+            Vec3D::new(a.x, a.y, a.z),
+            Vec3D::new(b.x, a.y, a.z),
+            Vec3D::new(a.x, b.y, a.z),
+            Vec3D::new(b.x, b.y, a.z),
+            Vec3D::new(a.x, a.y, b.z),
+            Vec3D::new(b.x, a.y, b.z),
+            Vec3D::new(a.x, b.y, b.z),
+            Vec3D::new(b.x, b.y, b.z),
+        ]
+    }
+}
+
+impl Transform for Aabb {
+    /// Returns the box enclosing the transformed box.
+    fn transformed(&self, mat: &Mat3) -> Self {
+        let center = mat.dot(self.center());
+        let e = self.half_extents();
+        let half_extents = Vec3D::new(
+            mat.get(0, 0).abs() * e.x + mat.get(0, 1).abs() * e.y + mat.get(0, 2).abs() * e.z,
+            mat.get(1, 0).abs() * e.x + mat.get(1, 1).abs() * e.y + mat.get(1, 2).abs() * e.z,
+            mat.get(2, 0).abs() * e.x + mat.get(2, 1).abs() * e.y + mat.get(2, 2).abs() * e.z,
+        );
+        Self::from_center(center, half_extents)
+    }
+
+    fn translated(&self, offset: &Vec3D) -> Self {
+        Self {
+            min: self.min + *offset,
+            max: self.max + *offset,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Quat;
+
+    #[test]
+    fn rotated_box_encloses_the_rotated_corners() {
+        let aabb = Aabb::new(Vec3D::new(1f32, 2f32, 3f32), Vec3D::new(2f32, 4f32, 7f32));
+        let quat = Quat::from_axis_angle(&Vec3D::new(0.3, -0.5, 0.8).normalized(), 1.1);
+        let rotated = aabb.rotated(&quat);
+        for corner in aabb.corners().iter() {
+            let c = quat.rotate(corner);
+            assert!(rotated.sq_distance(&c) < 1e-8, "{:?} outside {:?}", c, rotated);
+        }
+    }
+
+    #[test]
+    fn overlaps_and_closest_point() {
+        let a = Aabb::new(Vec3D::null(), Vec3D::new(1f32, 1f32, 1f32));
+        let b = Aabb::new(Vec3D::new(0.5, 0.5, 0.5), Vec3D::new(2f32, 2f32, 2f32));
+        let c = Aabb::new(Vec3D::new(1.5, 0f32, 0f32), Vec3D::new(2f32, 1f32, 1f32));
+        assert!(a.intersects_aabb(&b) && !a.intersects_aabb(&c));
+        assert!(a.union(&c).contains_aabb(&a) && a.union(&c).contains_aabb(&c));
+        assert_eq!(a.closest_point(&Vec3D::new(3f32, 0.5, -1f32)), Vec3D::new(1f32, 0.5, 0f32));
+        assert!(a.intersects_sphere(&Sphere::new(Vec3D::new(1.5, 0.5, 0.5), 0.6)));
+        assert!(!a.intersects_sphere(&Sphere::new(Vec3D::new(2f32, 2f32, 2f32), 1f32)));
+        let s1 = Sphere::new(Vec3D::null(), 1f32);
+        assert!(s1.intersects_sphere(&Sphere::new(Vec3D::new(1.9, 0f32, 0f32), 1f32)));
+        assert!(!s1.intersects_sphere(&Sphere::new(Vec3D::new(2.1, 0f32, 0f32), 1f32)));
+    }
+}
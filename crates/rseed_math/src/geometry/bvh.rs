@@ -0,0 +1,500 @@
+use super::{Aabb, Frustum, Ray, Sphere};
+
+const BINS: usize = 12;
+const MAX_LEAF_SIZE: usize = 4;
+const TRAVERSAL_COST: f32 = 1f32;
+const INTERSECTION_COST: f32 = 1f32;
+
+#[derive(Copy, Clone, Debug)]
+pub struct BvhNode {
+    pub aabb: Aabb,
+    /// Index of the left child for an inner node, the right one follows it.
+    /// For a leaf, index of the first item in the item list.
+    first: usize,
+    /// Number of items for a leaf, `0` for an inner node.
+    count: usize,
+}
+
+impl BvhNode {
+    pub fn is_leaf(&self) -> bool {
+        self.count > 0
+    }
+}
+
+/// Bounding volume hierarchy over a list of boxes, built with the surface area heuristic.
+/// Queries return the indices of the boxes given at build time.
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    items: Vec<usize>,
+    aabbs: Vec<Aabb>,
+}
+
+#[derive(Copy, Clone)]
+struct Bin {
+    aabb: Aabb,
+    count: usize,
+}
+
+impl Bvh {
+    pub fn build(aabbs: &[Aabb]) -> Self {
+        let mut bvh = Self {
+            nodes: Vec::with_capacity(2 * aabbs.len()),
+            items: (0..aabbs.len()).collect(),
+            aabbs: aabbs.to_vec(),
+        };
+        if aabbs.is_empty() {
+            return bvh;
+        }
+        bvh.nodes.push(BvhNode {
+            aabb: Aabb::empty(),
+            first: 0,
+            count: aabbs.len(),
+        });
+        bvh.update_bounds(0);
+        bvh.subdivide(0);
+        bvh
+    }
+
+    pub fn nodes(&self) -> &[BvhNode] {
+        &self.nodes
+    }
+
+    pub fn len(&self) -> usize {
+        self.aabbs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.aabbs.is_empty()
+    }
+
+    pub fn root_aabb(&self) -> Option<Aabb> {
+        self.nodes.first().map(|n| n.aabb)
+    }
+
+    fn update_bounds(&mut self, node: usize) {
+        let n = self.nodes[node];
+        let mut aabb = Aabb::empty();
+        for item in &self.items[n.first..n.first + n.count] {
+            aabb = aabb.union(&self.aabbs[*item]);
+        }
+        self.nodes[node].aabb = aabb;
+    }
+
+    fn subdivide(&mut self, node: usize) {
+        let n = self.nodes[node];
+        if n.count <= MAX_LEAF_SIZE {
+            return;
+        }
+        let (axis, split, cost) = match self.find_split(&n) {
+            Some(s) => s,
+            None => return,
+        };
+        let leaf_cost = n.count as f32 * INTERSECTION_COST * n.aabb.surface_area();
+        if cost >= leaf_cost {
+            return;
+        }
+
+        // Partition the items around the split plane.
+        let mut i = n.first;
+        let mut j = n.first + n.count;
+        while i < j {
+            if self.aabbs[self.items[i]].center()[axis] < split {
+                i += 1;
+            } else {
+                j -= 1;
+                self.items.swap(i, j);
+            }
+        }
+        let left_count = i - n.first;
+        if left_count == 0 || left_count == n.count {
+            return;
+        }
+
+        let left = self.nodes.len();
+        self.nodes.push(BvhNode {
+            aabb: Aabb::empty(),
+            first: n.first,
+            count: left_count,
+        });
+        self.nodes.push(BvhNode {
+            aabb: Aabb::empty(),
+            first: i,
+            count: n.count - left_count,
+        });
+        self.nodes[node].first = left;
+        self.nodes[node].count = 0;
+        self.update_bounds(left);
+        self.update_bounds(left + 1);
+        self.subdivide(left);
+        self.subdivide(left + 1);
+    }
+
+    /// Binned SAH, returns the axis, the position and the cost of the best split.
+    fn find_split(&self, node: &BvhNode) -> Option<(usize, f32, f32)> {
+        let items = &self.items[node.first..node.first + node.count];
+        let mut centroids = Aabb::empty();
+        items
+            .iter()
+            .for_each(|i| centroids.grow(&self.aabbs[*i].center()));
+
+        let mut best: Option<(usize, f32, f32)> = None;
+        for axis in 0..3 {
+            let (min, max) = (centroids.min[axis], centroids.max[axis]);
+            if max - min <= f32::EPSILON {
+                continue;
+            }
+            let mut bins = [Bin {
+                aabb: Aabb::empty(),
+                count: 0,
+            }; BINS];
+            let scale = BINS as f32 / (max - min);
+            for item in items {
+                let aabb = &self.aabbs[*item];
+                let b = (((aabb.center()[axis] - min) * scale) as usize).min(BINS - 1);
+                bins[b].count += 1;
+                bins[b].aabb = bins[b].aabb.union(aabb);
+            }
+
+            // Sweep from both sides to get the cost of each plane between bins.
+            let mut left_area = [0f32; BINS - 1];
+            let mut left_count = [0usize; BINS - 1];
+            let mut right_area = [0f32; BINS - 1];
+            let mut right_count = [0usize; BINS - 1];
+            let (mut left_box, mut right_box) = (Aabb::empty(), Aabb::empty());
+            let (mut left_sum, mut right_sum) = (0, 0);
+            for i in 0..BINS - 1 {
+                left_sum += bins[i].count;
+                left_count[i] = left_sum;
+                left_box = left_box.union(&bins[i].aabb);
+                left_area[i] = left_box.surface_area();
+                right_sum += bins[BINS - 1 - i].count;
+                right_count[BINS - 2 - i] = right_sum;
+                right_box = right_box.union(&bins[BINS - 1 - i].aabb);
+                right_area[BINS - 2 - i] = right_box.surface_area();
+            }
+            for i in 0..BINS - 1 {
+                let cost = TRAVERSAL_COST * node.aabb.surface_area()
+                    + INTERSECTION_COST
+                        * (left_count[i] as f32 * left_area[i]
+                            + right_count[i] as f32 * right_area[i]);
+                if best.is_none_or(|(_, _, c)| cost < c) {
+                    best = Some((axis, min + (i + 1) as f32 / scale, cost));
+                }
+            }
+        }
+        best
+    }
+
+    /// Update the boxes of moving items without rebuilding the tree.
+    /// The tree quality degrades when items move a lot, rebuild it from time to time.
+    pub fn refit(&mut self, aabbs: &[Aabb]) {
+        assert_eq!(
+            aabbs.len(),
+            self.aabbs.len(),
+            "A refit needs the same number of items as the build."
+        );
+        self.aabbs.copy_from_slice(aabbs);
+        // Children are always stored after their parent.
+        for node in (0..self.nodes.len()).rev() {
+            let n = self.nodes[node];
+            if n.is_leaf() {
+                self.update_bounds(node);
+            } else {
+                self.nodes[node].aabb = self.nodes[n.first]
+                    .aabb
+                    .union(&self.nodes[n.first + 1].aabb);
+            }
+        }
+    }
+
+    /// Visit every item whose box passes the test, as well as all its parents.
+    pub fn traverse<T, F>(&self, mut test: T, mut visit: F)
+    where
+        T: FnMut(&Aabb) -> bool,
+        F: FnMut(usize),
+    {
+        if self.nodes.is_empty() {
+            return;
+        }
+        let mut stack = vec![0usize];
+        while let Some(node) = stack.pop() {
+            let n = &self.nodes[node];
+            if !test(&n.aabb) {
+                continue;
+            }
+            if n.is_leaf() {
+                for item in &self.items[n.first..n.first + n.count] {
+                    if test(&self.aabbs[*item]) {
+                        visit(*item);
+                    }
+                }
+            } else {
+                stack.push(n.first + 1);
+                stack.push(n.first);
+            }
+        }
+    }
+
+    fn collect<T: FnMut(&Aabb) -> bool>(&self, test: T) -> Vec<usize> {
+        let mut found = Vec::new();
+        self.traverse(test, |i| found.push(i));
+        found
+    }
+
+    /// Items whose box is hit by the ray before `max_t`.
+    pub fn query_ray(&self, ray: &Ray, max_t: f32) -> Vec<usize> {
+        self.collect(|aabb| ray.intersect_aabb(aabb).is_some_and(|t| t <= max_t))
+    }
+
+    /// Closest item hit by the ray, `hit` returns the exact distance to an item.
+    /// Used for picking, the nodes further than the current hit are skipped.
+    pub fn closest_hit<F>(&self, ray: &Ray, mut hit: F) -> Option<(usize, f32)>
+    where
+        F: FnMut(usize) -> Option<f32>,
+    {
+        let mut best: Option<(usize, f32)> = None;
+        if self.nodes.is_empty() {
+            return best;
+        }
+        let mut stack = vec![(0usize, 0f32)];
+        while let Some((node, entry)) = stack.pop() {
+            if best.is_some_and(|(_, t)| entry > t) {
+                continue;
+            }
+            let n = &self.nodes[node];
+            if n.is_leaf() {
+                for item in &self.items[n.first..n.first + n.count] {
+                    if let Some(t) = hit(*item) {
+                        if best.is_none_or(|(_, bt)| t < bt) {
+                            best = Some((*item, t));
+                        }
+                    }
+                }
+                continue;
+            }
+            let left = ray
+                .intersect_aabb(&self.nodes[n.first].aabb)
+                .map(|t| (n.first, t));
+            let right = ray
+                .intersect_aabb(&self.nodes[n.first + 1].aabb)
+                .map(|t| (n.first + 1, t));
+            // Push the farthest child first so the closest is visited first.
+            match (left, right) {
+                (Some(l), Some(r)) if l.1 <= r.1 => {
+                    stack.push(r);
+                    stack.push(l);
+                }
+                (Some(l), Some(r)) => {
+                    stack.push(l);
+                    stack.push(r);
+                }
+                (Some(c), None) | (None, Some(c)) => stack.push(c),
+                (None, None) => (),
+            }
+        }
+        best
+    }
+
+    pub fn query_aabb(&self, aabb: &Aabb) -> Vec<usize> {
+        self.collect(|b| b.intersects_aabb(aabb))
+    }
+
+    pub fn query_sphere(&self, sphere: &Sphere) -> Vec<usize> {
+        self.collect(|b| b.intersects_sphere(sphere))
+    }
+
+    pub fn query_frustum(&self, frustum: &Frustum) -> Vec<usize> {
+        self.collect(|b| frustum.intersects_aabb(b))
+    }
+
+    /// All the pairs of items whose boxes overlap, for a physics broad phase.
+    pub fn overlapping_pairs(&self) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        for (i, aabb) in self.aabbs.iter().enumerate() {
+            self.traverse(
+                |b| b.intersects_aabb(aabb),
+                |j| {
+                    if i < j {
+                        pairs.push((i, j))
+                    }
+                },
+            );
+        }
+        pairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        geometry::{Transform, Triangle},
+        Vec3D,
+    };
+
+    /// Small deterministic generator, the tests must not depend on a crate for randomness.
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self) -> f32 {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (self.0 >> 40) as f32 / (1u64 << 24) as f32
+        }
+
+        fn range(&mut self, min: f32, max: f32) -> f32 {
+            min + (max - min) * self.next()
+        }
+
+        fn vec(&mut self, extent: f32) -> Vec3D {
+            Vec3D::new(
+                self.range(-extent, extent),
+                self.range(-extent, extent),
+                self.range(-extent, extent),
+            )
+        }
+    }
+
+    fn random_triangles(rng: &mut Lcg, count: usize) -> Vec<Triangle> {
+        (0..count)
+            .map(|_| {
+                let center = rng.vec(50f32);
+                Triangle::new(center + rng.vec(2f32), center + rng.vec(2f32), center + rng.vec(2f32))
+            })
+            .collect()
+    }
+
+    fn random_aabbs(rng: &mut Lcg, count: usize) -> Vec<Aabb> {
+        (0..count)
+            .map(|_| {
+                let half = Vec3D::new(rng.range(0.1, 3f32), rng.range(0.1, 3f32), rng.range(0.1, 3f32));
+                Aabb::from_center(rng.vec(50f32), half)
+            })
+            .collect()
+    }
+
+    fn sorted(mut items: Vec<usize>) -> Vec<usize> {
+        items.sort_unstable();
+        items
+    }
+
+    fn brute<F: Fn(&Aabb) -> bool>(aabbs: &[Aabb], test: F) -> Vec<usize> {
+        (0..aabbs.len()).filter(|i| test(&aabbs[*i])).collect()
+    }
+
+    fn check_queries(bvh: &Bvh, aabbs: &[Aabb], rng: &mut Lcg) {
+        for _ in 0..32 {
+            let query = Aabb::from_center(rng.vec(50f32), Vec3D::new(8f32, 8f32, 8f32));
+            assert_eq!(sorted(bvh.query_aabb(&query)), brute(aabbs, |b| b.intersects_aabb(&query)));
+
+            let sphere = Sphere::new(rng.vec(50f32), rng.range(1f32, 15f32));
+            assert_eq!(sorted(bvh.query_sphere(&sphere)), brute(aabbs, |b| b.intersects_sphere(&sphere)));
+
+            let ray = Ray::new(rng.vec(60f32), rng.vec(1f32));
+            let max_t = rng.range(10f32, 150f32);
+            let expected = brute(aabbs, |b| ray.intersect_aabb(b).is_some_and(|t| t <= max_t));
+            assert_eq!(sorted(bvh.query_ray(&ray, max_t)), expected);
+
+            let frustum = Frustum::from_perspective(rng.vec(60f32), rng.vec(1f32), Vec3D::up(), 1f32, 1.5, 0.1, 80f32);
+            assert_eq!(sorted(bvh.query_frustum(&frustum)), brute(aabbs, |b| frustum.intersects_aabb(b)));
+        }
+
+        let mut expected = Vec::new();
+        for i in 0..aabbs.len() {
+            for j in i + 1..aabbs.len() {
+                if aabbs[i].intersects_aabb(&aabbs[j]) {
+                    expected.push((i, j));
+                }
+            }
+        }
+        let mut pairs = bvh.overlapping_pairs();
+        pairs.sort_unstable();
+        assert_eq!(pairs, expected);
+    }
+
+    fn check_closest_hits(bvh: &Bvh, triangles: &[Triangle], rng: &mut Lcg) {
+        let mut hits = 0;
+        for _ in 0..200 {
+            // Aim at a triangle so most rays hit something.
+            let target = triangles[(rng.next() * triangles.len() as f32) as usize % triangles.len()].centroid();
+            let origin = rng.vec(80f32);
+            let ray = Ray::new(origin, target - origin);
+            let expected = triangles
+                .iter()
+                .enumerate()
+                .filter_map(|(i, t)| ray.intersect_triangle(t).map(|hit| (i, hit.t)))
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+            let found = bvh.closest_hit(&ray, |i| ray.intersect_triangle(&triangles[i]).map(|hit| hit.t));
+            assert_eq!(found.map(|(_, t)| t), expected.map(|(_, t)| t));
+            hits += found.is_some() as usize;
+        }
+        assert!(hits > 100);
+    }
+
+    #[test]
+    fn empty_bvh() {
+        let bvh = Bvh::build(&[]);
+        assert!(bvh.is_empty() && bvh.root_aabb().is_none());
+        assert!(bvh.query_aabb(&Aabb::from_center(Vec3D::null(), Vec3D::new(1f32, 1f32, 1f32))).is_empty());
+        assert_eq!(bvh.closest_hit(&Ray::new(Vec3D::null(), Vec3D::forward()), |_| Some(0f32)), None);
+    }
+
+    #[test]
+    fn queries_match_brute_force() {
+        let mut rng = Lcg(1);
+        let aabbs = random_aabbs(&mut rng, 500);
+        let bvh = Bvh::build(&aabbs);
+        assert!(bvh.nodes().len() > 1);
+        // Every item is in exactly one leaf.
+        let mut items: Vec<usize> = bvh
+            .nodes()
+            .iter()
+            .filter(|n| n.is_leaf())
+            .flat_map(|n| bvh.items[n.first..n.first + n.count].iter().cloned())
+            .collect();
+        items.sort_unstable();
+        assert_eq!(items, (0..aabbs.len()).collect::<Vec<_>>());
+        check_queries(&bvh, &aabbs, &mut rng);
+
+        // A small query tests far fewer boxes than the brute force loop.
+        let query = Aabb::from_center(Vec3D::null(), Vec3D::new(2f32, 2f32, 2f32));
+        let mut tests = 0;
+        bvh.traverse(
+            |b| {
+                tests += 1;
+                b.intersects_aabb(&query)
+            },
+            |_| (),
+        );
+        assert!(tests < aabbs.len() / 4, "{} box tests for {} items", tests, aabbs.len());
+    }
+
+    #[test]
+    fn queries_match_brute_force_after_refit() {
+        let mut rng = Lcg(2);
+        let mut aabbs = random_aabbs(&mut rng, 400);
+        let mut bvh = Bvh::build(&aabbs);
+        for aabb in aabbs.iter_mut() {
+            *aabb = aabb.translated(&rng.vec(20f32));
+        }
+        bvh.refit(&aabbs);
+        let root = bvh.root_aabb().unwrap();
+        assert!(aabbs.iter().all(|b| root.contains_aabb(b)));
+        check_queries(&bvh, &aabbs, &mut rng);
+    }
+
+    #[test]
+    fn closest_hit_matches_brute_force() {
+        let mut rng = Lcg(3);
+        let mut triangles = random_triangles(&mut rng, 300);
+        let aabbs: Vec<Aabb> = triangles.iter().map(|t| t.aabb()).collect();
+        let mut bvh = Bvh::build(&aabbs);
+        check_closest_hits(&bvh, &triangles, &mut rng);
+
+        for triangle in triangles.iter_mut() {
+            *triangle = triangle.translated(&rng.vec(10f32));
+        }
+        let aabbs: Vec<Aabb> = triangles.iter().map(|t| t.aabb()).collect();
+        bvh.refit(&aabbs);
+        check_closest_hits(&bvh, &triangles, &mut rng);
+    }
+}
@@ -0,0 +1,168 @@
+use super::{Aabb, Plane, Sphere, Transform};
+use crate::{Mat3, Mat4, Vec3D, Vec4D};
+
+/// A convex volume bounded by six planes with normals pointing inside,
+/// ordered as left, right, bottom, top, near and far.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Frustum {
+    pub planes: [Plane; 6],
+}
+
+impl Frustum {
+    pub fn new(planes: [Plane; 6]) -> Self {
+        Self { planes }
+    }
+
+    /// The frustum of a perspective camera, `fov_y` in radians.
+    pub fn from_perspective(
+        position: Vec3D,
+        forward: Vec3D,
+        up: Vec3D,
+        fov_y: f32,
+        aspect: f32,
+        near: f32,
+        far: f32,
+    ) -> Self {
+        let f = forward.normalized();
+        let r = f.cross(&up).normalized();
+        let u = r.cross(&f);
+        let half_v = (fov_y * 0.5).tan();
+        let half_h = half_v * aspect;
+        Self {
+            planes: [
+                Plane::from_point_normal(&position, &(f - r * half_h).cross(&u)),
+                Plane::from_point_normal(&position, &u.cross(&(f + r * half_h))),
+                Plane::from_point_normal(&position, &r.cross(&(f - u * half_v))),
+                Plane::from_point_normal(&position, &(f + u * half_v).cross(&r)),
+                Plane::from_point_normal(&(position + f * near), &f),
+                Plane::from_point_normal(&(position + f * far), &-f),
+            ],
+        }
+    }
+
+    /// The frustum of a view projection matrix to the GL clip space, in world space.
+    pub fn from_matrix(view_projection: &Mat4) -> Self {
+        let w = view_projection.row(3);
+        let plane = |r: Vec4D, sign: f32| {
+            Plane::new(
+                Vec3D::new(w.x + sign * r.x, w.y + sign * r.y, w.z + sign * r.z),
+                w.w + sign * r.w,
+            )
+            .normalized()
+        };
+        let (x, y, z) = (view_projection.row(0), view_projection.row(1), view_projection.row(2));
+        Self {
+            planes: [
+                plane(x, 1f32),
+                plane(x, -1f32),
+                plane(y, 1f32),
+                plane(y, -1f32),
+                plane(z, 1f32),
+                plane(z, -1f32),
+            ],
+        }
+    }
+
+    pub fn contains_point(&self, point: &Vec3D) -> bool {
+        self.planes
+            .iter()
+            .all(|p| p.signed_distance(point) >= 0f32)
+    }
+
+    pub fn intersects_sphere(&self, sphere: &Sphere) -> bool {
+        self.planes
+            .iter()
+            .all(|p| p.signed_distance(&sphere.center) >= -sphere.radius)
+    }
+
+    /// Conservative test, some boxes near the corners are reported as intersecting.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|p| {
+            // The corner the furthest along the normal.
+            let positive = Vec3D::new(
+                if p.normal.x >= 0f32 { aabb.max.x } else { aabb.min.x },
+                if p.normal.y >= 0f32 { aabb.max.y } else { aabb.min.y },
+                if p.normal.z >= 0f32 { aabb.max.z } else { aabb.min.z },
+            );
+            p.signed_distance(&positive) >= 0f32
+        })
+    }
+
+    pub fn contains_aabb(&self, aabb: &Aabb) -> bool {
+        aabb.corners().iter().all(|c| self.contains_point(c))
+    }
+}
+
+impl Transform for Frustum {
+    fn transformed(&self, mat: &Mat3) -> Self {
+        let mut planes = self.planes;
+        planes.iter_mut().for_each(|p| *p = p.transformed(mat));
+        Self { planes }
+    }
+
+    fn translated(&self, offset: &Vec3D) -> Self {
+        let mut planes = self.planes;
+        planes.iter_mut().for_each(|p| *p = p.translated(offset));
+        Self { planes }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frustum() -> Frustum {
+        Frustum::from_perspective(
+            Vec3D::null(),
+            Vec3D::forward(),
+            Vec3D::up(),
+            std::f32::consts::FRAC_PI_2,
+            1f32,
+            1f32,
+            100f32,
+        )
+    }
+
+    #[test]
+    fn perspective_contains_points() {
+        let f = frustum();
+        assert!(f.contains_point(&(Vec3D::forward() * 10f32)));
+        // A 90° field of view reaches 45° on each side.
+        assert!(f.contains_point(&(Vec3D::forward() * 10f32 + Vec3D::up() * 9.9)));
+        assert!(!f.contains_point(&(Vec3D::forward() * 10f32 + Vec3D::up() * 10.1)));
+        assert!(!f.contains_point(&(Vec3D::forward() * 0.5)));
+        assert!(!f.contains_point(&(Vec3D::forward() * 101f32)));
+        assert!(!f.contains_point(&(Vec3D::back() * 10f32)));
+    }
+
+    #[test]
+    fn matrix_contains_points() {
+        // The same volume as `frustum`, seen from elsewhere.
+        let eye = Vec3D::new(3f32, -2f32, 5f32);
+        let f = Frustum::from_matrix(
+            &(Mat4::perspective(std::f32::consts::FRAC_PI_2, 1f32, 1f32, 100f32)
+                * Mat4::look_at(&eye, &(eye + Vec3D::forward()), &Vec3D::up())),
+        );
+        assert!(f.contains_point(&(eye + Vec3D::forward() * 10f32)));
+        assert!(f.contains_point(&(eye + Vec3D::forward() * 10f32 + Vec3D::up() * 9.9)));
+        assert!(!f.contains_point(&(eye + Vec3D::forward() * 10f32 + Vec3D::up() * 10.1)));
+        assert!(!f.contains_point(&(eye + Vec3D::forward() * 0.5)));
+        assert!(!f.contains_point(&(eye + Vec3D::forward() * 101f32)));
+        assert!(!f.contains_point(&(eye + Vec3D::back() * 10f32)));
+        let inside = Aabb::from_center(eye + Vec3D::forward() * 10f32, Vec3D::new(1f32, 1f32, 1f32));
+        assert!(f.contains_aabb(&inside));
+    }
+
+    #[test]
+    fn perspective_culls_boxes_and_spheres() {
+        let f = frustum();
+        let inside = Aabb::from_center(Vec3D::forward() * 10f32, Vec3D::new(1f32, 1f32, 1f32));
+        let across = Aabb::from_center(Vec3D::forward() * 100f32, Vec3D::new(1f32, 1f32, 1f32));
+        let behind = Aabb::from_center(Vec3D::back() * 10f32, Vec3D::new(1f32, 1f32, 1f32));
+        assert!(f.contains_aabb(&inside) && f.intersects_aabb(&inside));
+        assert!(!f.contains_aabb(&across) && f.intersects_aabb(&across));
+        assert!(!f.intersects_aabb(&behind));
+        assert!(f.intersects_sphere(&Sphere::new(Vec3D::forward() * 0.5, 1f32)));
+        assert!(!f.intersects_sphere(&Sphere::new(Vec3D::back() * 3f32, 1f32)));
+    }
+}
@@ -0,0 +1,32 @@
+pub mod aabb;
+pub mod bvh;
+pub mod frustum;
+pub mod plane;
+pub mod ray;
+pub mod segment;
+pub mod sphere;
+pub mod triangle;
+
+pub use aabb::Aabb;
+pub use bvh::Bvh;
+pub use frustum::Frustum;
+pub use plane::Plane;
+pub use ray::{Ray, RayHit};
+pub use segment::Segment;
+pub use sphere::Sphere;
+pub use triangle::Triangle;
+
+use crate::{Mat3, Quat, Vec3D};
+
+/// Transformations shared by all the primitives.
+pub trait Transform: Sized {
+    /// Apply the linear transformation `mat`.
+    fn transformed(&self, mat: &Mat3) -> Self;
+
+    fn translated(&self, offset: &Vec3D) -> Self;
+
+    /// Rotate around the origin, `quat` is expected to be unitary.
+    fn rotated(&self, quat: &Quat) -> Self {
+        self.transformed(&quat.to_mat3())
+    }
+}
@@ -0,0 +1,61 @@
+use super::Transform;
+use crate::{Mat3, Vec3D};
+
+/// The plane of the points `p` verifying `normal.dot(p) + d = 0`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Plane {
+    pub normal: Vec3D,
+    pub d: f32,
+}
+
+impl Plane {
+    pub fn new(normal: Vec3D, d: f32) -> Self {
+        Self { normal, d }
+    }
+
+    pub fn from_point_normal(point: &Vec3D, normal: &Vec3D) -> Self {
+        let normal = normal.normalized();
+        Self {
+            normal,
+            d: -normal.dot(point),
+        }
+    }
+
+    /// Plane going through the three points, counter clockwise points face the normal.
+    pub fn from_points(a: &Vec3D, b: &Vec3D, c: &Vec3D) -> Self {
+        Self::from_point_normal(a, &(*b - *a).cross(&(*c - *a)))
+    }
+
+    pub fn normalized(&self) -> Self {
+        let k = 1f32 / self.normal.norm();
+        Self {
+            normal: self.normal * k,
+            d: self.d * k,
+        }
+    }
+
+    /// Positive on the side the normal points to.
+    pub fn signed_distance(&self, point: &Vec3D) -> f32 {
+        self.normal.dot(point) + self.d
+    }
+
+    pub fn closest_point(&self, point: &Vec3D) -> Vec3D {
+        *point - self.normal * self.signed_distance(point)
+    }
+}
+
+impl Transform for Plane {
+    fn transformed(&self, mat: &Mat3) -> Self {
+        let plane = self.normalized();
+        let point = mat.dot(plane.normal * -plane.d);
+        let normal = mat.inversed().transposed().dot(plane.normal);
+        Self::from_point_normal(&point, &normal)
+    }
+
+    fn translated(&self, offset: &Vec3D) -> Self {
+        Self {
+            normal: self.normal,
+            d: self.d - self.normal.dot(offset),
+        }
+    }
+}
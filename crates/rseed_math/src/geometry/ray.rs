@@ -0,0 +1,205 @@
+use super::{Aabb, Plane, Sphere, Transform, Triangle};
+use crate::{Mat3, Vec3D};
+
+const EPSILON: f32 = 1e-7;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3D,
+    pub dir: Vec3D,
+}
+
+/// A ray and triangle hit, `u` and `v` are the barycentric coordinates of the hit.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RayHit {
+    pub t: f32,
+    pub u: f32,
+    pub v: f32,
+}
+
+impl Ray {
+    /// The direction is normalized so `t` is a distance.
+    pub fn new(origin: Vec3D, dir: Vec3D) -> Self {
+        Self {
+            origin,
+            dir: dir.normalized(),
+        }
+    }
+
+    pub fn at(&self, t: f32) -> Vec3D {
+        self.origin + self.dir * t
+    }
+
+    pub fn closest_point(&self, point: &Vec3D) -> Vec3D {
+        let t = (*point - self.origin).dot(&self.dir) / self.dir.sq_norm();
+        self.at(t.max(0f32))
+    }
+
+    /// Slab test, returns the entry distance (0 if the origin is inside).
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+        let mut tmin = 0f32;
+        let mut tmax = f32::MAX;
+        for axis in 0..3 {
+            let o = self.origin[axis];
+            let d = self.dir[axis];
+            if d.abs() < EPSILON {
+                if o < aabb.min[axis] || o > aabb.max[axis] {
+                    return None;
+                }
+            } else {
+                let inv = 1f32 / d;
+                let mut t1 = (aabb.min[axis] - o) * inv;
+                let mut t2 = (aabb.max[axis] - o) * inv;
+                if t1 > t2 {
+                    std::mem::swap(&mut t1, &mut t2);
+                }
+                tmin = tmin.max(t1);
+                tmax = tmax.min(t2);
+                if tmin > tmax {
+                    return None;
+                }
+            }
+        }
+        Some(tmin)
+    }
+
+    pub fn intersect_sphere(&self, sphere: &Sphere) -> Option<f32> {
+        let m = self.origin - sphere.center;
+        let a = self.dir.sq_norm();
+        let b = m.dot(&self.dir);
+        let c = m.sq_norm() - sphere.radius * sphere.radius;
+        // Origin outside and pointing away.
+        if c > 0f32 && b > 0f32 {
+            return None;
+        }
+        let discr = b * b - a * c;
+        if discr < 0f32 {
+            return None;
+        }
+        Some(((-b - discr.sqrt()) / a).max(0f32))
+    }
+
+    pub fn intersect_plane(&self, plane: &Plane) -> Option<f32> {
+        let denom = plane.normal.dot(&self.dir);
+        if denom.abs() < EPSILON {
+            return None;
+        }
+        let t = -plane.signed_distance(&self.origin) / denom;
+        if t >= 0f32 {
+            Some(t)
+        } else {
+            None
+        }
+    }
+
+    /// Möller–Trumbore intersection, both faces are hit.
+    pub fn intersect_triangle(&self, triangle: &Triangle) -> Option<RayHit> {
+        let e1 = triangle.b - triangle.a;
+        let e2 = triangle.c - triangle.a;
+        let p = self.dir.cross(&e2);
+        let det = e1.dot(&p);
+        if det.abs() < EPSILON {
+            return None;
+        }
+        let inv_det = 1f32 / det;
+        let s = self.origin - triangle.a;
+        let u = s.dot(&p) * inv_det;
+        if !(0f32..=1f32).contains(&u) {
+            return None;
+        }
+        let q = s.cross(&e1);
+        let v = self.dir.dot(&q) * inv_det;
+        if v < 0f32 || u + v > 1f32 {
+            return None;
+        }
+        let t = e2.dot(&q) * inv_det;
+        if t < 0f32 {
+            return None;
+        }
+        Some(RayHit { t, u, v })
+    }
+}
+
+impl Transform for Ray {
+    fn transformed(&self, mat: &Mat3) -> Self {
+        Self::new(mat.dot(self.origin), mat.dot(self.dir))
+    }
+
+    fn translated(&self, offset: &Vec3D) -> Self {
+        Self {
+            origin: self.origin + *offset,
+            dir: self.dir,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn ray_aabb_hits_and_misses() {
+        let aabb = Aabb::new(Vec3D::new(1f32, -1f32, -1f32), Vec3D::new(3f32, 1f32, 1f32));
+        let ray = Ray::new(Vec3D::null(), Vec3D::new(1f32, 0f32, 0f32));
+        assert!(close(ray.intersect_aabb(&aabb).unwrap(), 1f32));
+        // Diagonal through the corner region.
+        let diagonal = Ray::new(Vec3D::new(0f32, -2f32, 0f32), Vec3D::new(1f32, 1f32, 0f32));
+        assert!(close(diagonal.intersect_aabb(&aabb).unwrap(), 2f32.sqrt()));
+        // Pointing away, parallel outside a slab, and from inside.
+        assert_eq!(Ray::new(Vec3D::null(), Vec3D::new(-1f32, 0f32, 0f32)).intersect_aabb(&aabb), None);
+        assert_eq!(Ray::new(Vec3D::new(0f32, 2f32, 0f32), Vec3D::new(1f32, 0f32, 0f32)).intersect_aabb(&aabb), None);
+        assert_eq!(Ray::new(Vec3D::new(2f32, 0f32, 0f32), Vec3D::new(0f32, 0f32, 1f32)).intersect_aabb(&aabb), Some(0f32));
+    }
+
+    #[test]
+    fn moller_trumbore_hits() {
+        let triangle = Triangle::new(
+            Vec3D::new(0f32, 0f32, 0f32),
+            Vec3D::new(1f32, 0f32, 0f32),
+            Vec3D::new(0f32, 1f32, 0f32),
+        );
+        let ray = Ray::new(Vec3D::new(0.25, 0.5, 2f32), Vec3D::new(0f32, 0f32, -1f32));
+        let hit = ray.intersect_triangle(&triangle).unwrap();
+        assert!(close(hit.t, 2f32));
+        assert!(close(hit.u, 0.25) && close(hit.v, 0.5));
+        let point = triangle.a * (1f32 - hit.u - hit.v) + triangle.b * hit.u + triangle.c * hit.v;
+        assert!((point - ray.at(hit.t)).norm() < 1e-4);
+        // Back face is hit too.
+        let below = Ray::new(Vec3D::new(0.25, 0.25, -1f32), Vec3D::new(0f32, 0f32, 1f32));
+        assert!(close(below.intersect_triangle(&triangle).unwrap().t, 1f32));
+    }
+
+    #[test]
+    fn moller_trumbore_misses() {
+        let triangle = Triangle::new(
+            Vec3D::new(0f32, 0f32, 0f32),
+            Vec3D::new(1f32, 0f32, 0f32),
+            Vec3D::new(0f32, 1f32, 0f32),
+        );
+        let down = Vec3D::new(0f32, 0f32, -1f32);
+        // Outside the edges.
+        assert!(Ray::new(Vec3D::new(0.75, 0.75, 1f32), down).intersect_triangle(&triangle).is_none());
+        assert!(Ray::new(Vec3D::new(-0.1, 0.5, 1f32), down).intersect_triangle(&triangle).is_none());
+        // Behind the origin and parallel to the plane.
+        assert!(Ray::new(Vec3D::new(0.2, 0.2, -1f32), down).intersect_triangle(&triangle).is_none());
+        assert!(Ray::new(Vec3D::new(-1f32, 0.2, 0f32), Vec3D::new(1f32, 0f32, 0f32))
+            .intersect_triangle(&triangle)
+            .is_none());
+    }
+
+    #[test]
+    fn ray_sphere_and_plane() {
+        let sphere = Sphere::new(Vec3D::new(5f32, 0f32, 0f32), 1f32);
+        let ray = Ray::new(Vec3D::null(), Vec3D::new(1f32, 0f32, 0f32));
+        assert!(close(ray.intersect_sphere(&sphere).unwrap(), 4f32));
+        assert_eq!(Ray::new(Vec3D::null(), Vec3D::new(0f32, 1f32, 0f32)).intersect_sphere(&sphere), None);
+        let plane = Plane::from_point_normal(&Vec3D::new(0f32, 0f32, 3f32), &Vec3D::new(0f32, 0f32, 1f32));
+        let up = Ray::new(Vec3D::null(), Vec3D::new(0f32, 0f32, 1f32));
+        assert!(close(up.intersect_plane(&plane).unwrap(), 3f32));
+        assert_eq!(ray.intersect_plane(&plane), None);
+    }
+}
@@ -0,0 +1,147 @@
+use super::{Aabb, Plane, Ray, Transform, Triangle};
+use crate::{Mat3, Vec3D};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Segment {
+    pub a: Vec3D,
+    pub b: Vec3D,
+}
+
+impl Segment {
+    pub fn new(a: Vec3D, b: Vec3D) -> Self {
+        Self { a, b }
+    }
+
+    pub fn length(&self) -> f32 {
+        (self.b - self.a).norm()
+    }
+
+    pub fn at(&self, t: f32) -> Vec3D {
+        self.a.lerp(&self.b, t)
+    }
+
+    /// Returns the parameter in `[0, 1]` of the closest point.
+    pub fn closest_param(&self, point: &Vec3D) -> f32 {
+        let ab = self.b - self.a;
+        let sq = ab.sq_norm();
+        if sq == 0f32 {
+            return 0f32;
+        }
+        ((*point - self.a).dot(&ab) / sq).clamp(0f32, 1f32)
+    }
+
+    pub fn closest_point(&self, point: &Vec3D) -> Vec3D {
+        self.at(self.closest_param(point))
+    }
+
+    pub fn sq_distance(&self, point: &Vec3D) -> f32 {
+        (self.closest_point(point) - *point).sq_norm()
+    }
+
+    /// Closest points between two segments, as parameters on each of them.
+    pub fn closest_params(&self, other: &Self) -> (f32, f32) {
+        let d1 = self.b - self.a;
+        let d2 = other.b - other.a;
+        let r = self.a - other.a;
+        let a = d1.sq_norm();
+        let e = d2.sq_norm();
+        let f = d2.dot(&r);
+        if a <= f32::EPSILON && e <= f32::EPSILON {
+            return (0f32, 0f32);
+        }
+        if a <= f32::EPSILON {
+            return (0f32, (f / e).clamp(0f32, 1f32));
+        }
+        let c = d1.dot(&r);
+        if e <= f32::EPSILON {
+            return ((-c / a).clamp(0f32, 1f32), 0f32);
+        }
+        let b = d1.dot(&d2);
+        let denom = a * e - b * b;
+        let mut s = if denom != 0f32 {
+            ((b * f - c * e) / denom).clamp(0f32, 1f32)
+        } else {
+            0f32
+        };
+        let mut t = (b * s + f) / e;
+        if t < 0f32 {
+            t = 0f32;
+            s = (-c / a).clamp(0f32, 1f32);
+        } else if t > 1f32 {
+            t = 1f32;
+            s = ((b - c) / a).clamp(0f32, 1f32);
+        }
+        (s, t)
+    }
+
+    fn as_ray(&self) -> (Ray, f32) {
+        (Ray::new(self.a, self.b - self.a), self.length())
+    }
+
+    /// Returns the parameter in `[0, 1]` of the first intersection.
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+        let (ray, len) = self.as_ray();
+        ray.intersect_aabb(aabb)
+            .filter(|t| *t <= len)
+            .map(|t| t / len.max(f32::EPSILON))
+    }
+
+    pub fn intersect_plane(&self, plane: &Plane) -> Option<f32> {
+        let (ray, len) = self.as_ray();
+        ray.intersect_plane(plane)
+            .filter(|t| *t <= len)
+            .map(|t| t / len.max(f32::EPSILON))
+    }
+
+    pub fn intersect_triangle(&self, triangle: &Triangle) -> Option<f32> {
+        let (ray, len) = self.as_ray();
+        ray.intersect_triangle(triangle)
+            .filter(|hit| hit.t <= len)
+            .map(|hit| hit.t / len.max(f32::EPSILON))
+    }
+}
+
+impl Transform for Segment {
+    fn transformed(&self, mat: &Mat3) -> Self {
+        Self {
+            a: mat.dot(self.a),
+            b: mat.dot(self.b),
+        }
+    }
+
+    fn translated(&self, offset: &Vec3D) -> Self {
+        Self {
+            a: self.a + *offset,
+            b: self.b + *offset,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closest_params_of_crossing_and_parallel_segments() {
+        let a = Segment::new(Vec3D::new(-1f32, 0f32, 0f32), Vec3D::new(1f32, 0f32, 0f32));
+        let b = Segment::new(Vec3D::new(0f32, -1f32, 1f32), Vec3D::new(0f32, 1f32, 1f32));
+        assert_eq!(a.closest_params(&b), (0.5, 0.5));
+        let parallel = Segment::new(Vec3D::new(2f32, 1f32, 0f32), Vec3D::new(4f32, 1f32, 0f32));
+        let (s, t) = a.closest_params(&parallel);
+        assert_eq!((a.at(s), parallel.at(t)), (a.b, parallel.a));
+    }
+
+    #[test]
+    fn segment_stops_at_its_end() {
+        let triangle = Triangle::new(
+            Vec3D::new(-1f32, -1f32, 2f32),
+            Vec3D::new(1f32, -1f32, 2f32),
+            Vec3D::new(0f32, 1f32, 2f32),
+        );
+        let short = Segment::new(Vec3D::null(), Vec3D::new(0f32, 0f32, 1f32));
+        let long = Segment::new(Vec3D::null(), Vec3D::new(0f32, 0f32, 4f32));
+        assert_eq!(short.intersect_triangle(&triangle), None);
+        assert!((long.intersect_triangle(&triangle).unwrap() - 0.5).abs() < 1e-4);
+        assert_eq!(short.sq_distance(&Vec3D::new(0f32, 3f32, 5f32)), 25f32);
+    }
+}
@@ -0,0 +1,61 @@
+use super::{Aabb, Transform};
+use crate::{Mat3, Vec3D};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Sphere {
+    pub center: Vec3D,
+    pub radius: f32,
+}
+
+impl Sphere {
+    pub fn new(center: Vec3D, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    pub fn contains_point(&self, point: &Vec3D) -> bool {
+        (*point - self.center).sq_norm() <= self.radius * self.radius
+    }
+
+    pub fn intersects_sphere(&self, other: &Self) -> bool {
+        let r = self.radius + other.radius;
+        (other.center - self.center).sq_norm() <= r * r
+    }
+
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        aabb.intersects_sphere(self)
+    }
+
+    /// Closest point on the sphere surface, or the point itself if inside.
+    pub fn closest_point(&self, point: &Vec3D) -> Vec3D {
+        let d = *point - self.center;
+        let sq = d.sq_norm();
+        if sq <= self.radius * self.radius {
+            return *point;
+        }
+        self.center + d * (self.radius / sq.sqrt())
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        let r = Vec3D::new(self.radius, self.radius, self.radius);
+        Aabb::from_center(self.center, r)
+    }
+}
+
+impl Transform for Sphere {
+    /// Returns the sphere enclosing the transformed sphere.
+    fn transformed(&self, mat: &Mat3) -> Self {
+        let col = |j: usize| Vec3D::new(mat.get(0, j), mat.get(1, j), mat.get(2, j)).sq_norm();
+        let scale = col(0).max(col(1)).max(col(2)).sqrt();
+        Self {
+            center: mat.dot(self.center),
+            radius: self.radius * scale,
+        }
+    }
+
+    fn translated(&self, offset: &Vec3D) -> Self {
+        Self {
+            center: self.center + *offset,
+            radius: self.radius,
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use super::{Aabb, Plane, Transform};
+use crate::{Mat3, Vec3D};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Triangle {
+    pub a: Vec3D,
+    pub b: Vec3D,
+    pub c: Vec3D,
+}
+
+impl Triangle {
+    pub fn new(a: Vec3D, b: Vec3D, c: Vec3D) -> Self {
+        Self { a, b, c }
+    }
+
+    /// Unnormalized normal, facing the side where the points are counter clockwise.
+    pub fn normal(&self) -> Vec3D {
+        (self.b - self.a).cross(&(self.c - self.a))
+    }
+
+    pub fn area(&self) -> f32 {
+        self.normal().norm() * 0.5
+    }
+
+    pub fn centroid(&self) -> Vec3D {
+        (self.a + self.b + self.c) * (1f32 / 3f32)
+    }
+
+    pub fn plane(&self) -> Plane {
+        Plane::from_points(&self.a, &self.b, &self.c)
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        Aabb::from_points(&[self.a, self.b, self.c])
+    }
+
+    /// Barycentric coordinates `(u, v, w)` of the point projected on the triangle plane.
+    pub fn barycentric(&self, point: &Vec3D) -> (f32, f32, f32) {
+        let v0 = self.b - self.a;
+        let v1 = self.c - self.a;
+        let v2 = *point - self.a;
+        let d00 = v0.dot(&v0);
+        let d01 = v0.dot(&v1);
+        let d11 = v1.dot(&v1);
+        let d20 = v2.dot(&v0);
+        let d21 = v2.dot(&v1);
+        let denom = d00 * d11 - d01 * d01;
+        let v = (d11 * d20 - d01 * d21) / denom;
+        let w = (d00 * d21 - d01 * d20) / denom;
+        (1f32 - v - w, v, w)
+    }
+
+    /// Closest point on the triangle, from Ericson's Real-Time Collision Detection.
+    pub fn closest_point(&self, point: &Vec3D) -> Vec3D {
+        let p = *point;
+        let ab = self.b - self.a;
+        let ac = self.c - self.a;
+        let ap = p - self.a;
+        let d1 = ab.dot(&ap);
+        let d2 = ac.dot(&ap);
+        if d1 <= 0f32 && d2 <= 0f32 {
+            return self.a;
+        }
+        let bp = p - self.b;
+        let d3 = ab.dot(&bp);
+        let d4 = ac.dot(&bp);
+        if d3 >= 0f32 && d4 <= d3 {
+            return self.b;
+        }
+        let vc = d1 * d4 - d3 * d2;
+        if vc <= 0f32 && d1 >= 0f32 && d3 <= 0f32 {
+            return self.a + ab * (d1 / (d1 - d3));
+        }
+        let cp = p - self.c;
+        let d5 = ab.dot(&cp);
+        let d6 = ac.dot(&cp);
+        if d6 >= 0f32 && d5 <= d6 {
+            return self.c;
+        }
+        let vb = d5 * d2 - d1 * d6;
+        if vb <= 0f32 && d2 >= 0f32 && d6 <= 0f32 {
+            return self.a + ac * (d2 / (d2 - d6));
+        }
+        let va = d3 * d6 - d5 * d4;
+        if va <= 0f32 && (d4 - d3) >= 0f32 && (d5 - d6) >= 0f32 {
+            return self.b + (self.c - self.b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+        }
+        let denom = 1f32 / (va + vb + vc);
+        self.a + ab * (vb * denom) + ac * (vc * denom)
+    }
+}
+
+impl Transform for Triangle {
+    fn transformed(&self, mat: &Mat3) -> Self {
+        Self {
+            a: mat.dot(self.a),
+            b: mat.dot(self.b),
+            c: mat.dot(self.c),
+        }
+    }
+
+    fn translated(&self, offset: &Vec3D) -> Self {
+        Self {
+            a: self.a + *offset,
+            b: self.b + *offset,
+            c: self.c + *offset,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Quat;
+
+    fn triangle() -> Triangle {
+        Triangle::new(
+            Vec3D::new(0f32, 0f32, 0f32),
+            Vec3D::new(2f32, 0f32, 0f32),
+            Vec3D::new(0f32, 2f32, 0f32),
+        )
+    }
+
+    #[test]
+    fn closest_point_regions() {
+        let t = triangle();
+        assert_eq!(t.closest_point(&Vec3D::new(0.5, 0.5, 3f32)), Vec3D::new(0.5, 0.5, 0f32));
+        assert_eq!(t.closest_point(&Vec3D::new(-1f32, -1f32, 0f32)), t.a);
+        assert_eq!(t.closest_point(&Vec3D::new(3f32, -1f32, 0f32)), t.b);
+        assert_eq!(t.closest_point(&Vec3D::new(1f32, -1f32, 1f32)), Vec3D::new(1f32, 0f32, 0f32));
+        assert_eq!(t.closest_point(&Vec3D::new(2f32, 2f32, 0f32)), Vec3D::new(1f32, 1f32, 0f32));
+    }
+
+    #[test]
+    fn barycentric_of_the_vertices() {
+        let t = triangle();
+        assert_eq!(t.barycentric(&t.a), (1f32, 0f32, 0f32));
+        assert_eq!(t.barycentric(&t.b), (0f32, 1f32, 0f32));
+        assert_eq!(t.barycentric(&t.c), (0f32, 0f32, 1f32));
+        assert_eq!(t.area(), 2f32);
+    }
+
+    #[test]
+    fn rotation_keeps_the_area() {
+        let quat = Quat::from_axis_angle(&Vec3D::new(1f32, 1f32, 0f32).normalized(), 0.7);
+        let rotated = triangle().rotated(&quat);
+        assert!((rotated.area() - 2f32).abs() < 1e-5);
+        assert!((rotated.normal() - quat.rotate(&triangle().normal())).norm() < 1e-5);
+    }
+}
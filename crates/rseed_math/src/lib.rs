@@ -1,11 +1,16 @@
 pub mod mat3;
+pub mod mat4;
 pub mod quat;
 pub mod vectors;
-//pub mod noise; // TODO make it work with f32
+pub mod noise;
 pub mod fct;
+pub mod biome;
+pub mod geometry;
+pub mod spline;
 
 // exposing structs and fct
 pub use mat3::Mat3;
+pub use mat4::Mat4;
 pub use quat::Quat;
 pub use vectors::Vec2D;
 pub use vectors::Vec3D;
@@ -17,7 +17,7 @@ impl Mat3 {
         let mut m = Self::new();
         m.m_data[0] = 1f32;
         m.m_data[4] = 1f32;
-        m.m_data[7] = 1f32;
+        m.m_data[8] = 1f32;
         m
     }
     pub fn get(&self, i: usize, j: usize) -> f32 {
@@ -49,7 +49,7 @@ impl Mat3 {
         let g = self.get(0, 1) * self.get(1, 2) - self.get(1, 1) * self.get(0, 2);
         let h = self.get(1, 0) * self.get(0, 2) - self.get(0, 0) * self.get(1, 2);
         let i = self.get(0, 0) * self.get(1, 1) - self.get(0, 1) * self.get(1, 0);
-        let k = self.get(0, 0) * a + self.get(0, 1) * b + self.get(0, 2) * c;
+        let k = 1f32 / (self.get(0, 0) * a + self.get(0, 1) * b + self.get(0, 2) * c);
         *self = Self {
             m_data: [
                 a * k,
@@ -65,6 +65,29 @@ impl Mat3 {
         }
     }
 
+    /// The coefficients, row by row.
+    pub fn to_vec(&self) -> Vec<f32> {
+        self.m_data.to_vec()
+    }
+
+    pub fn determinant(&self) -> f32 {
+        self.get(0, 0) * (self.get(1, 1) * self.get(2, 2) - self.get(1, 2) * self.get(2, 1))
+            - self.get(0, 1) * (self.get(1, 0) * self.get(2, 2) - self.get(1, 2) * self.get(2, 0))
+            + self.get(0, 2) * (self.get(1, 0) * self.get(2, 1) - self.get(1, 1) * self.get(2, 0))
+    }
+
+    pub fn transposed(&self) -> Self {
+        let mut m = *self;
+        m.traspose();
+        m
+    }
+
+    pub fn inversed(&self) -> Self {
+        let mut m = *self;
+        m.inverse();
+        m
+    }
+
     pub fn dot(&self, vec: Vec3D) -> Vec3D {
         Vec3D {
             y: self.get(1, 0) * vec.x + self.get(1, 1) * vec.y + self.get(1, 2) * vec.z,
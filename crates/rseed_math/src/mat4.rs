@@ -0,0 +1,238 @@
+use super::{Mat3, Vec3D, Vec4D};
+/// Stored by rows, laid out as `[f32; 16]`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Mat4 {
+    pub(crate) m_data: [f32; 16],
+}
+
+impl Mat4 {
+    pub fn new() -> Self {
+        Self { m_data: [0f32; 16] }
+    }
+
+    pub fn from_data(data: [f32; 16]) -> Self {
+        Self { m_data: data }
+    }
+
+    pub fn id() -> Self {
+        let mut m = Self::new();
+        m.m_data[0] = 1f32;
+        m.m_data[5] = 1f32;
+        m.m_data[10] = 1f32;
+        m.m_data[15] = 1f32;
+        m
+    }
+
+    /// The rotation or scale of a 3x3 matrix, without translation.
+    pub fn from_mat3(m: &Mat3) -> Self {
+        let mut r = Self::id();
+        for i in 0..3 {
+            for j in 0..3 {
+                r.set(i, j, &m.get(i, j));
+            }
+        }
+        r
+    }
+
+    pub fn translation(t: &Vec3D) -> Self {
+        let mut m = Self::id();
+        m.set(0, 3, &t.x);
+        m.set(1, 3, &t.y);
+        m.set(2, 3, &t.z);
+        m
+    }
+
+    pub fn scale(s: &Vec3D) -> Self {
+        let mut m = Self::id();
+        m.set(0, 0, &s.x);
+        m.set(1, 1, &s.y);
+        m.set(2, 2, &s.z);
+        m
+    }
+
+    /// A right handed view matrix, the camera looks down -Z with Y up in view space.
+    pub fn look_at(eye: &Vec3D, target: &Vec3D, up: &Vec3D) -> Self {
+        let f = (*target - *eye).normalized();
+        let s = f.cross(up).normalized();
+        let u = s.cross(&f);
+        Self::from_data([
+            s.x, s.y, s.z, -s.dot(eye),
+            u.x, u.y, u.z, -u.dot(eye),
+            -f.x, -f.y, -f.z, f.dot(eye),
+            0f32, 0f32, 0f32, 1f32,
+        ])
+    }
+
+    /// A perspective projection to the GL clip space, the depth in `[-1, 1]`. `fov_y` in radians.
+    pub fn perspective(fov_y: f32, aspect: f32, near: f32, far: f32) -> Self {
+        let f = 1f32 / (fov_y * 0.5).tan();
+        let mut m = Self::new();
+        m.set(0, 0, &(f / aspect));
+        m.set(1, 1, &f);
+        m.set(2, 2, &((far + near) / (near - far)));
+        m.set(2, 3, &(2f32 * far * near / (near - far)));
+        m.set(3, 2, &-1f32);
+        m
+    }
+
+    /// An orthographic projection to the GL clip space, the depth in `[-1, 1]`.
+    pub fn orthographic(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Self {
+        let mut m = Self::id();
+        m.set(0, 0, &(2f32 / (right - left)));
+        m.set(1, 1, &(2f32 / (top - bottom)));
+        m.set(2, 2, &(-2f32 / (far - near)));
+        m.set(0, 3, &(-(right + left) / (right - left)));
+        m.set(1, 3, &(-(top + bottom) / (top - bottom)));
+        m.set(2, 3, &(-(far + near) / (far - near)));
+        m
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> f32 {
+        self.m_data[4 * i + j]
+    }
+    pub fn set(&mut self, i: usize, j: usize, value: &f32) {
+        self.m_data[4 * i + j] = *value;
+    }
+
+    pub fn traspose(&mut self) {
+        for i in 0..4 {
+            for j in (i + 1)..4 {
+                self.m_data.swap(4 * i + j, 4 * j + i);
+            }
+        }
+    }
+
+    pub fn transposed(&self) -> Self {
+        let mut m = *self;
+        m.traspose();
+        m
+    }
+
+    pub fn row(&self, i: usize) -> Vec4D {
+        Vec4D::new(self.get(i, 0), self.get(i, 1), self.get(i, 2), self.get(i, 3))
+    }
+
+    pub fn determinant(&self) -> f32 {
+        let mut det = 0f32;
+        for j in 0..4 {
+            det += self.get(0, j) * self.cofactor(0, j);
+        }
+        det
+    }
+
+    /// The singular matrices have no inverse, their coefficients become infinite.
+    pub fn inverse(&mut self) {
+        *self = self.inversed();
+    }
+
+    pub fn inversed(&self) -> Self {
+        let mut m = Self::new();
+        for i in 0..4 {
+            for j in 0..4 {
+                // The adjugate is the transpose of the cofactor matrix.
+                m.set(j, i, &self.cofactor(i, j));
+            }
+        }
+        let inv_det = 1f32 / self.determinant();
+        m.m_data.iter_mut().for_each(|c| *c *= inv_det);
+        m
+    }
+
+    fn cofactor(&self, i: usize, j: usize) -> f32 {
+        let mut minor = Mat3::new();
+        for (mi, r) in (0..4).filter(|r| *r != i).enumerate() {
+            for (mj, c) in (0..4).filter(|c| *c != j).enumerate() {
+                minor.set(mi, mj, &self.get(r, c));
+            }
+        }
+        let sign = if (i + j).is_multiple_of(2) { 1f32 } else { -1f32 };
+        sign * minor.determinant()
+    }
+
+    /// Transform a point and divide by `w`, for the projections.
+    pub fn project_point(&self, p: &Vec3D) -> Vec3D {
+        let v = self.dot(Vec4D::new(p.x, p.y, p.z, 1f32));
+        Vec3D::new(v.x / v.w, v.y / v.w, v.z / v.w)
+    }
+
+    /// The coefficients, row by row.
+    pub fn to_vec(&self) -> Vec<f32> {
+        self.m_data.to_vec()
+    }
+
+    pub fn dot(&self, vec: Vec4D) -> Vec4D {
+        let row = |i: usize| {
+            self.get(i, 0) * vec.x + self.get(i, 1) * vec.y + self.get(i, 2) * vec.z + self.get(i, 3) * vec.w
+        };
+        Vec4D::new(row(0), row(1), row(2), row(3))
+    }
+
+    /// Transform a point, the translation applies.
+    pub fn transform_point(&self, p: &Vec3D) -> Vec3D {
+        let v = self.dot(Vec4D::new(p.x, p.y, p.z, 1f32));
+        Vec3D::new(v.x, v.y, v.z)
+    }
+
+    /// Transform a direction, the translation doesn't apply.
+    pub fn transform_vector(&self, v: &Vec3D) -> Vec3D {
+        let v = self.dot(Vec4D::new(v.x, v.y, v.z, 0f32));
+        Vec3D::new(v.x, v.y, v.z)
+    }
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::ops::Mul<Mat4> for Mat4 {
+    type Output = Mat4;
+    fn mul(self, other: Mat4) -> Mat4 {
+        let mut m = Mat4::new();
+        for i in 0..4 {
+            for j in 0..4 {
+                let mut c = 0f32;
+                for k in 0..4 {
+                    c += self.get(i, k) * other.get(k, j);
+                }
+                m.set(i, j, &c);
+            }
+        }
+        m
+    }
+}
+
+impl std::ops::MulAssign<Mat4> for Mat4 {
+    fn mul_assign(&mut self, other: Mat4) {
+        *self = *self * other;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Quat;
+
+    fn close(a: &Mat4, b: &Mat4) -> bool {
+        a.m_data.iter().zip(b.m_data.iter()).all(|(x, y)| (x - y).abs() < 1e-4)
+    }
+
+    #[test]
+    fn inversed_round_trip() {
+        let rotation = Quat::from_axis_angle(&Vec3D::new(1f32, 2f32, 3f32).normalized(), 0.7);
+        let model = Mat4::translation(&Vec3D::new(4f32, -2f32, 7f32))
+            * Mat4::from_mat3(&rotation.to_mat3())
+            * Mat4::scale(&Vec3D::new(2f32, 0.5, 3f32));
+        let view_projection = Mat4::perspective(1f32, 1.5, 0.1, 100f32)
+            * Mat4::look_at(&Vec3D::new(3f32, 4f32, 5f32), &Vec3D::null(), &Vec3D::up());
+        for m in [model, view_projection].iter() {
+            assert!(close(&(*m * m.inversed()), &Mat4::id()), "{:?}", *m * m.inversed());
+            assert!(close(&(m.inversed() * *m), &Mat4::id()));
+        }
+        let p = Vec3D::new(1f32, -3f32, 2f32);
+        let back = model.inversed().transform_point(&model.transform_point(&p));
+        assert!((back - p).norm() < 1e-4);
+    }
+}
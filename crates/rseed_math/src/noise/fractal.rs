@@ -0,0 +1,60 @@
+use super::OpenSimplex;
+use crate::biome::ScalarField2D;
+use crate::Vec2D;
+
+/// Octaves of OpenSimplex noise summed into a field in `[0, 1]`, the source of the climate maps.
+pub struct FractalNoise {
+    noise: Box<OpenSimplex>,
+    /// Frequency of the first octave, in periods per world unit.
+    pub frequency: f32,
+    pub octaves: u32,
+    /// Frequency factor from an octave to the next.
+    pub lacunarity: f32,
+    /// Amplitude factor from an octave to the next.
+    pub gain: f32,
+}
+
+impl FractalNoise {
+    /// Four octaves doubling the frequency and halving the amplitude each time.
+    pub fn new(seed: i64, frequency: f32) -> Self {
+        Self {
+            noise: Box::new(OpenSimplex::new(seed)),
+            frequency,
+            octaves: 4,
+            lacunarity: 2f32,
+            gain: 0.5,
+        }
+    }
+
+    pub fn with_octaves(mut self, octaves: u32, lacunarity: f32, gain: f32) -> Self {
+        self.octaves = octaves.max(1);
+        self.lacunarity = lacunarity;
+        self.gain = gain;
+        self
+    }
+
+    pub fn seed(&self) -> i64 {
+        self.noise.get_seed()
+    }
+
+    /// The sum of the octaves in `[-1, 1]`.
+    pub fn eval(&self, pos: Vec2D) -> f32 {
+        let mut frequency = self.frequency;
+        let mut amplitude = 1f32;
+        let mut total = 0f32;
+        let mut norm = 0f32;
+        for _ in 0..self.octaves {
+            total += amplitude * self.noise.eval2(&(pos * frequency));
+            norm += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.gain;
+        }
+        total / norm
+    }
+}
+
+impl ScalarField2D for FractalNoise {
+    fn sample(&self, pos: Vec2D) -> f32 {
+        (0.5 * (self.eval(pos) + 1f32)).clamp(0f32, 1f32)
+    }
+}
@@ -1,2 +1,4 @@
+pub mod fractal;
 pub mod open_simplex;
-pub use open_simplex::OpenSimplex;
\ No newline at end of file
+pub use fractal::FractalNoise;
+pub use open_simplex::OpenSimplex;
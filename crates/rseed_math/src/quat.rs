@@ -1,4 +1,5 @@
 use super::fct::fast_isqrt;
+use super::Mat3;
 use super::Vec3D;
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -15,6 +16,21 @@ impl Quat {
         }
     }
 
+    pub fn id() -> Self {
+        Self {
+            s: 1_f32,
+            v: Vec3D::null(),
+        }
+    }
+
+    /// Rotation of `angle` radians around `axis`.
+    pub fn from_axis_angle(axis: &Vec3D, angle: f32) -> Self {
+        Self {
+            s: (angle * 0.5).cos(),
+            v: (angle * 0.5).sin() * axis.normalized(),
+        }
+    }
+
     pub fn sq_norm(&self) -> f32 {
         self.s * self.s + self.v.sq_norm()
     }
@@ -51,6 +67,28 @@ impl Quat {
         *self = self.inv();
     }
 
+    /// Rotate the vector, the quaternion is expected to be unitary.
+    pub fn rotate(&self, vec: &Vec3D) -> Vec3D {
+        let t = 2_f32 * self.v.cross(vec);
+        *vec + self.s * t + self.v.cross(&t)
+    }
+
+    /// Rotation matrix of the quaternion, expected to be unitary.
+    pub fn to_mat3(&self) -> Mat3 {
+        let (s, x, y, z) = (self.s, self.v.x, self.v.y, self.v.z);
+        Mat3::from_data([
+            1_f32 - 2_f32 * (y * y + z * z),
+            2_f32 * (x * y - s * z),
+            2_f32 * (x * z + s * y),
+            2_f32 * (x * y + s * z),
+            1_f32 - 2_f32 * (x * x + z * z),
+            2_f32 * (y * z - s * x),
+            2_f32 * (x * z - s * y),
+            2_f32 * (y * z + s * x),
+            1_f32 - 2_f32 * (x * x + y * y),
+        ])
+    }
+
     pub fn to_vec(&self) -> Vec<f32> {
         vec![self.s, self.v.x, self.v.y, self.v.z]
     }
@@ -0,0 +1,130 @@
+use super::{Curve, SplinePoint};
+
+/// Cumulated lengths along a curve, to move along it at constant speed.
+#[derive(Clone, Debug)]
+pub struct ArcLengthTable {
+    params: Vec<f32>,
+    lengths: Vec<f32>,
+}
+
+impl ArcLengthTable {
+    /// Approximate the curve with `samples` chords per segment.
+    pub fn new<T: SplinePoint, C: Curve<T>>(curve: &C, samples: usize) -> Self {
+        let count = samples.max(1) * curve.segment_count().max(1);
+        let mut params = Vec::with_capacity(count + 1);
+        let mut lengths = Vec::with_capacity(count + 1);
+        let mut prev = curve.point(0f32);
+        let mut total = 0f32;
+        params.push(0f32);
+        lengths.push(0f32);
+        for i in 1..=count {
+            let t = i as f32 / count as f32;
+            let p = curve.point(t);
+            total += p.sq_distance(&prev).sqrt();
+            params.push(t);
+            lengths.push(total);
+            prev = p;
+        }
+        Self { params, lengths }
+    }
+
+    pub fn length(&self) -> f32 {
+        *self.lengths.last().unwrap()
+    }
+
+    /// Parameter of the point at the distance `s` from the start.
+    pub fn param_at(&self, s: f32) -> f32 {
+        let s = s.clamp(0f32, self.length());
+        let i = match self
+            .lengths
+            .binary_search_by(|l| l.partial_cmp(&s).unwrap())
+        {
+            Ok(i) => return self.params[i],
+            Err(i) => i.max(1),
+        };
+        let (l0, l1) = (self.lengths[i - 1], self.lengths[i]);
+        let k = if l1 > l0 { (s - l0) / (l1 - l0) } else { 0f32 };
+        self.params[i - 1] + (self.params[i] - self.params[i - 1]) * k
+    }
+
+    /// Distance from the start to the point at the parameter `t`.
+    pub fn length_at(&self, t: f32) -> f32 {
+        let t = t.clamp(0f32, 1f32);
+        let n = self.params.len() - 1;
+        let x = t * n as f32;
+        let i = (x as usize).min(n - 1);
+        self.lengths[i] + (self.lengths[i + 1] - self.lengths[i]) * (x - i as f32)
+    }
+
+    /// Polyline of points spaced by `step` along the curve, ends included.
+    pub fn sample_by_distance<T: SplinePoint, C: Curve<T>>(&self, curve: &C, step: f32) -> Vec<T> {
+        let length = self.length();
+        let count = ((length / step.max(f32::EPSILON)).ceil() as usize).max(1);
+        (0..=count)
+            .map(|i| curve.point(self.param_at(length * i as f32 / count as f32)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        spline::{CatmullRom, CubicBezier},
+        Vec2D,
+    };
+
+    #[test]
+    fn reparametrization_moves_at_constant_speed() {
+        // A straight line whose parameter speeds up towards the end.
+        let curve = CubicBezier::new(vec![
+            Vec2D::new(0f32, 0f32),
+            Vec2D::new(0.05, 0f32),
+            Vec2D::new(0.1, 0f32),
+            Vec2D::new(4f32, 0f32),
+        ]);
+        let table = ArcLengthTable::new(&curve, 64);
+        assert!((table.length() - 4f32).abs() < 1e-4);
+        for i in 0..=20 {
+            let s = 4f32 * i as f32 / 20f32;
+            let point = curve.point(table.param_at(s));
+            assert!((point.x - s).abs() < 5e-3, "{} at the distance {}", point.x, s);
+            assert!((table.length_at(table.param_at(s)) - s).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn quarter_circle_length_and_spacing() {
+        // Bézier approximation of the unit quarter circle, within 3e-4 of the radius.
+        let k = 0.552_284_8;
+        let curve = CubicBezier::new(vec![
+            Vec2D::new(1f32, 0f32),
+            Vec2D::new(1f32, k),
+            Vec2D::new(k, 1f32),
+            Vec2D::new(0f32, 1f32),
+        ]);
+        let table = ArcLengthTable::new(&curve, 128);
+        assert!((table.length() - std::f32::consts::FRAC_PI_2).abs() < 1e-3);
+        let points = table.sample_by_distance(&curve, 0.1);
+        let step = points[0].sq_distance(&points[1]).sqrt();
+        for pair in points.windows(2) {
+            assert!((pair[0].sq_distance(&pair[1]).sqrt() - step).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn catmull_rom_goes_through_its_points() {
+        let points = vec![
+            Vec2D::new(0f32, 0f32),
+            Vec2D::new(1f32, 2f32),
+            Vec2D::new(3f32, 1f32),
+            Vec2D::new(4f32, 4f32),
+        ];
+        let curve = CatmullRom::new(points.clone(), false);
+        for (i, p) in points.iter().enumerate() {
+            assert!(curve.point(i as f32 / 3f32).sq_distance(p) < 1e-8);
+        }
+        let closest = curve.closest_param(&Vec2D::new(1.1, 2.2));
+        assert!((closest - 1f32 / 3f32).abs() < 0.05);
+    }
+}
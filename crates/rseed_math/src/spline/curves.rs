@@ -0,0 +1,185 @@
+use super::{Curve, SplinePoint};
+
+/// Piecewise cubic Bézier curve, made of `3n + 1` control points.
+/// Each segment shares its last point with the next one.
+#[derive(Clone, Debug)]
+pub struct CubicBezier<T: SplinePoint> {
+    pub points: Vec<T>,
+}
+
+impl<T: SplinePoint> CubicBezier<T> {
+    pub fn new(points: Vec<T>) -> Self {
+        assert!(
+            points.len() >= 4 && (points.len() - 1).is_multiple_of(3),
+            "A cubic Bézier curve needs 3n + 1 control points."
+        );
+        Self { points }
+    }
+}
+
+impl<T: SplinePoint> Curve<T> for CubicBezier<T> {
+    fn segment_count(&self) -> usize {
+        (self.points.len() - 1) / 3
+    }
+
+    fn segment_point(&self, seg: usize, u: f32) -> T {
+        let p = &self.points[3 * seg..3 * seg + 4];
+        let v = 1f32 - u;
+        p[0] * (v * v * v) + p[1] * (3f32 * v * v * u) + p[2] * (3f32 * v * u * u) + p[3] * (u * u * u)
+    }
+
+    fn segment_derivative(&self, seg: usize, u: f32) -> T {
+        let p = &self.points[3 * seg..3 * seg + 4];
+        let v = 1f32 - u;
+        (p[1] - p[0]) * (3f32 * v * v) + (p[2] - p[1]) * (6f32 * v * u) + (p[3] - p[2]) * (3f32 * u * u)
+    }
+}
+
+/// Uniform Catmull-Rom spline going through all its points.
+#[derive(Clone, Debug)]
+pub struct CatmullRom<T: SplinePoint> {
+    pub points: Vec<T>,
+    /// Loop back from the last point to the first one.
+    pub closed: bool,
+}
+
+impl<T: SplinePoint> CatmullRom<T> {
+    pub fn new(points: Vec<T>, closed: bool) -> Self {
+        assert!(points.len() >= 2, "A Catmull-Rom spline needs 2 points.");
+        Self { points, closed }
+    }
+
+    /// The four control points of a segment, the ends are clamped on open splines.
+    fn controls(&self, seg: usize) -> [T; 4] {
+        let n = self.points.len() as isize;
+        let get = |i: isize| {
+            let i = if self.closed {
+                i.rem_euclid(n)
+            } else {
+                i.clamp(0, n - 1)
+            };
+            self.points[i as usize]
+        };
+        let s = seg as isize;
+        [get(s - 1), get(s), get(s + 1), get(s + 2)]
+    }
+}
+
+impl<T: SplinePoint> Curve<T> for CatmullRom<T> {
+    fn segment_count(&self) -> usize {
+        if self.closed {
+            self.points.len()
+        } else {
+            self.points.len() - 1
+        }
+    }
+
+    fn segment_point(&self, seg: usize, u: f32) -> T {
+        let [p0, p1, p2, p3] = self.controls(seg);
+        let (u2, u3) = (u * u, u * u * u);
+        (p1 * 2f32
+            + (p2 - p0) * u
+            + (p0 * 2f32 - p1 * 5f32 + p2 * 4f32 - p3) * u2
+            + (p1 * 3f32 - p0 - p2 * 3f32 + p3) * u3)
+            * 0.5
+    }
+
+    fn segment_derivative(&self, seg: usize, u: f32) -> T {
+        let [p0, p1, p2, p3] = self.controls(seg);
+        ((p2 - p0)
+            + (p0 * 2f32 - p1 * 5f32 + p2 * 4f32 - p3) * (2f32 * u)
+            + (p1 * 3f32 - p0 - p2 * 3f32 + p3) * (3f32 * u * u))
+            * 0.5
+    }
+}
+
+/// Uniform cubic B-spline, smooth but not going through its points.
+#[derive(Clone, Debug)]
+pub struct BSpline<T: SplinePoint> {
+    pub points: Vec<T>,
+    pub closed: bool,
+}
+
+impl<T: SplinePoint> BSpline<T> {
+    pub fn new(points: Vec<T>, closed: bool) -> Self {
+        assert!(points.len() >= 4, "A cubic B-spline needs 4 points.");
+        Self { points, closed }
+    }
+
+    fn controls(&self, seg: usize) -> [T; 4] {
+        let n = self.points.len();
+        let get = |i: usize| self.points[i % n];
+        [get(seg), get(seg + 1), get(seg + 2), get(seg + 3)]
+    }
+}
+
+impl<T: SplinePoint> Curve<T> for BSpline<T> {
+    fn segment_count(&self) -> usize {
+        if self.closed {
+            self.points.len()
+        } else {
+            self.points.len() - 3
+        }
+    }
+
+    fn segment_point(&self, seg: usize, u: f32) -> T {
+        let [p0, p1, p2, p3] = self.controls(seg);
+        let (u2, u3, v) = (u * u, u * u * u, 1f32 - u);
+        (p0 * (v * v * v)
+            + p1 * (3f32 * u3 - 6f32 * u2 + 4f32)
+            + p2 * (-3f32 * u3 + 3f32 * u2 + 3f32 * u + 1f32)
+            + p3 * u3)
+            * (1f32 / 6f32)
+    }
+
+    fn segment_derivative(&self, seg: usize, u: f32) -> T {
+        let [p0, p1, p2, p3] = self.controls(seg);
+        let (u2, v) = (u * u, 1f32 - u);
+        (p0 * (-3f32 * v * v)
+            + p1 * (9f32 * u2 - 12f32 * u)
+            + p2 * (-9f32 * u2 + 6f32 * u + 3f32)
+            + p3 * (3f32 * u2))
+            * (1f32 / 6f32)
+    }
+}
+
+/// Cubic Hermite spline, each point comes with its tangent.
+#[derive(Clone, Debug)]
+pub struct Hermite<T: SplinePoint> {
+    pub points: Vec<T>,
+    pub tangents: Vec<T>,
+}
+
+impl<T: SplinePoint> Hermite<T> {
+    pub fn new(points: Vec<T>, tangents: Vec<T>) -> Self {
+        assert!(points.len() >= 2, "A Hermite spline needs 2 points.");
+        assert_eq!(
+            points.len(),
+            tangents.len(),
+            "A Hermite spline needs one tangent per point."
+        );
+        Self { points, tangents }
+    }
+}
+
+impl<T: SplinePoint> Curve<T> for Hermite<T> {
+    fn segment_count(&self) -> usize {
+        self.points.len() - 1
+    }
+
+    fn segment_point(&self, seg: usize, u: f32) -> T {
+        let (u2, u3) = (u * u, u * u * u);
+        self.points[seg] * (2f32 * u3 - 3f32 * u2 + 1f32)
+            + self.tangents[seg] * (u3 - 2f32 * u2 + u)
+            + self.points[seg + 1] * (-2f32 * u3 + 3f32 * u2)
+            + self.tangents[seg + 1] * (u3 - u2)
+    }
+
+    fn segment_derivative(&self, seg: usize, u: f32) -> T {
+        let u2 = u * u;
+        self.points[seg] * (6f32 * u2 - 6f32 * u)
+            + self.tangents[seg] * (3f32 * u2 - 4f32 * u + 1f32)
+            + self.points[seg + 1] * (-6f32 * u2 + 6f32 * u)
+            + self.tangents[seg + 1] * (3f32 * u2 - 2f32 * u)
+    }
+}
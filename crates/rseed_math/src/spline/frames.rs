@@ -0,0 +1,164 @@
+use super::Curve;
+use crate::{Vec2D, Vec3D};
+
+/// An orthonormal frame along a 3D curve.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub position: Vec3D,
+    pub tangent: Vec3D,
+    pub normal: Vec3D,
+    pub binormal: Vec3D,
+}
+
+impl Frame {
+    /// Position of a profile point, `x` goes along the binormal and `y` along the normal.
+    pub fn place(&self, point: &Vec2D) -> Vec3D {
+        self.position + self.binormal * point.x + self.normal * point.y
+    }
+}
+
+fn orthogonal_to(tangent: &Vec3D, up: &Vec3D) -> Vec3D {
+    let n = *up - *tangent * tangent.dot(up);
+    if n.sq_norm() > 1e-8 {
+        return n.normalized();
+    }
+    // The up vector is along the tangent, any other axis does.
+    let axis = if tangent.x.abs() < 0.9 {
+        Vec3D::forward()
+    } else {
+        Vec3D::left()
+    };
+    tangent.cross(&axis).normalized()
+}
+
+/// Rotation minimizing frames at the parameters `params`, by the double reflection method.
+/// The first normal is `up` made orthogonal to the tangent.
+pub fn rotation_minimizing_frames<C: Curve<Vec3D>>(
+    curve: &C,
+    params: &[f32],
+    up: &Vec3D,
+) -> Vec<Frame> {
+    let mut frames: Vec<Frame> = Vec::with_capacity(params.len());
+    for t in params {
+        let position = curve.point(*t);
+        let tangent = curve.tangent(*t).normalized();
+        let normal = match frames.last() {
+            None => orthogonal_to(&tangent, up),
+            Some(prev) => {
+                let v1 = position - prev.position;
+                let c1 = v1.sq_norm();
+                if c1 < 1e-12 {
+                    orthogonal_to(&tangent, &prev.normal)
+                } else {
+                    let r_l = prev.normal - v1 * (2f32 / c1 * v1.dot(&prev.normal));
+                    let t_l = prev.tangent - v1 * (2f32 / c1 * v1.dot(&prev.tangent));
+                    let v2 = tangent - t_l;
+                    let c2 = v2.sq_norm();
+                    let r = if c2 < 1e-12 {
+                        r_l
+                    } else {
+                        r_l - v2 * (2f32 / c2 * v2.dot(&r_l))
+                    };
+                    orthogonal_to(&tangent, &r)
+                }
+            }
+        };
+        frames.push(Frame {
+            position,
+            tangent,
+            normal,
+            binormal: tangent.cross(&normal),
+        });
+    }
+    frames
+}
+
+/// Sweep a 2D profile along the frames, returns the vertices and the triangle indices.
+/// A closed profile also links its last point to the first one.
+pub fn extrude(frames: &[Frame], profile: &[Vec2D], closed_profile: bool) -> (Vec<Vec3D>, Vec<u32>) {
+    let ring = profile.len();
+    let mut vertices = Vec::with_capacity(frames.len() * ring);
+    for frame in frames {
+        vertices.extend(profile.iter().map(|p| frame.place(p)));
+    }
+    let edges = if closed_profile { ring } else { ring.saturating_sub(1) };
+    let mut indices = Vec::with_capacity(frames.len().saturating_sub(1) * edges * 6);
+    for i in 0..frames.len().saturating_sub(1) {
+        for j in 0..edges {
+            let a = (i * ring + j) as u32;
+            let b = (i * ring + (j + 1) % ring) as u32;
+            let c = a + ring as u32;
+            let d = b + ring as u32;
+            indices.extend_from_slice(&[a, c, b, b, c, d]);
+        }
+    }
+    (vertices, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spline::{CatmullRom, Hermite};
+
+    fn helix() -> CatmullRom<Vec3D> {
+        let points = (0..24)
+            .map(|i| {
+                let a = i as f32 * 0.5;
+                Vec3D::new(a.cos() * 3f32, a.sin() * 3f32, i as f32 * 0.4)
+            })
+            .collect();
+        CatmullRom::new(points, false)
+    }
+
+    fn params(count: usize) -> Vec<f32> {
+        (0..count).map(|i| i as f32 / (count - 1) as f32).collect()
+    }
+
+    #[test]
+    fn frames_are_orthonormal() {
+        let frames = rotation_minimizing_frames(&helix(), &params(200), &Vec3D::up());
+        for f in frames.iter() {
+            for v in [f.tangent, f.normal, f.binormal].iter() {
+                assert!((v.norm() - 1f32).abs() < 1e-3);
+            }
+            assert!(f.tangent.dot(&f.normal).abs() < 1e-3);
+            assert!(f.tangent.dot(&f.binormal).abs() < 1e-3);
+            assert!(f.normal.dot(&f.binormal).abs() < 1e-3);
+            // Right handed.
+            assert!((f.tangent.cross(&f.normal) - f.binormal).norm() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn planar_curve_does_not_twist() {
+        // An S in the XY plane, the normal must stay along Z the whole way.
+        let curve = Hermite::new(
+            vec![Vec3D::new(0f32, 0f32, 0f32), Vec3D::new(4f32, 2f32, 0f32), Vec3D::new(8f32, 0f32, 0f32)],
+            vec![Vec3D::new(4f32, 4f32, 0f32), Vec3D::new(4f32, 0f32, 0f32), Vec3D::new(4f32, -4f32, 0f32)],
+        );
+        let frames = rotation_minimizing_frames(&curve, &params(100), &Vec3D::up());
+        for f in frames.iter() {
+            assert!((f.normal - Vec3D::up()).norm() < 1e-3, "{:?}", f.normal);
+        }
+    }
+
+    #[test]
+    fn extrusion_counts() {
+        let frames = rotation_minimizing_frames(&helix(), &params(10), &Vec3D::up());
+        let square = [
+            Vec2D::new(-1f32, -1f32),
+            Vec2D::new(1f32, -1f32),
+            Vec2D::new(1f32, 1f32),
+            Vec2D::new(-1f32, 1f32),
+        ];
+        let (vertices, indices) = extrude(&frames, &square, true);
+        assert_eq!(vertices.len(), 40);
+        assert_eq!(indices.len(), 9 * 4 * 6);
+        let (_, open) = extrude(&frames, &square, false);
+        assert_eq!(open.len(), 9 * 3 * 6);
+        assert!(indices.iter().all(|i| (*i as usize) < vertices.len()));
+        // The profile is placed around the curve.
+        let center = vertices[..4].iter().fold(Vec3D::null(), |a, b| a + *b) * 0.25;
+        assert!((center - frames[0].position).norm() < 1e-4);
+    }
+}
@@ -0,0 +1,113 @@
+pub mod arc_length;
+pub mod curves;
+pub mod frames;
+
+pub use arc_length::ArcLengthTable;
+pub use curves::{BSpline, CatmullRom, CubicBezier, Hermite};
+pub use frames::{extrude, rotation_minimizing_frames, Frame};
+
+use crate::{Vec2D, Vec3D};
+
+/// The points a spline can interpolate.
+pub trait SplinePoint:
+    Copy
+    + std::ops::Add<Output = Self>
+    + std::ops::Sub<Output = Self>
+    + std::ops::Mul<f32, Output = Self>
+{
+    fn dot(&self, other: &Self) -> f32;
+
+    fn sq_distance(&self, other: &Self) -> f32 {
+        let d = *self - *other;
+        d.dot(&d)
+    }
+}
+
+impl SplinePoint for Vec2D {
+    fn dot(&self, other: &Self) -> f32 {
+        Vec2D::dot(self, other)
+    }
+}
+
+impl SplinePoint for Vec3D {
+    fn dot(&self, other: &Self) -> f32 {
+        Vec3D::dot(self, other)
+    }
+}
+
+/// A piecewise cubic curve, `t` goes from `0` to `1` along the whole curve.
+pub trait Curve<T: SplinePoint> {
+    fn segment_count(&self) -> usize;
+
+    /// Point on the segment `seg` at the local parameter `u` in `[0, 1]`.
+    fn segment_point(&self, seg: usize, u: f32) -> T;
+
+    /// Derivative relative to `u` on the segment `seg`.
+    fn segment_derivative(&self, seg: usize, u: f32) -> T;
+
+    /// Split the global parameter into a segment and a local parameter.
+    fn locate(&self, t: f32) -> (usize, f32) {
+        let n = self.segment_count();
+        if n == 0 {
+            return (0, 0f32);
+        }
+        let x = t.clamp(0f32, 1f32) * n as f32;
+        let seg = (x as usize).min(n - 1);
+        (seg, x - seg as f32)
+    }
+
+    fn point(&self, t: f32) -> T {
+        let (seg, u) = self.locate(t);
+        self.segment_point(seg, u)
+    }
+
+    /// Derivative relative to the global parameter `t`.
+    fn derivative(&self, t: f32) -> T {
+        let (seg, u) = self.locate(t);
+        self.segment_derivative(seg, u) * self.segment_count() as f32
+    }
+
+    /// Unnormalized tangent, use `derivative` for the speed too.
+    fn tangent(&self, t: f32) -> T {
+        self.derivative(t)
+    }
+
+    /// Sample `count` points evenly spaced in parameter.
+    fn sample(&self, count: usize) -> Vec<T> {
+        let last = (count.max(2) - 1) as f32;
+        (0..count.max(2))
+            .map(|i| self.point(i as f32 / last))
+            .collect()
+    }
+
+    /// Returns the parameter of the point of the curve closest to `point`.
+    fn closest_param(&self, point: &T) -> f32 {
+        let steps = 16 * self.segment_count().max(1);
+        let mut best = (f32::MAX, 0f32);
+        for i in 0..=steps {
+            let t = i as f32 / steps as f32;
+            let d = self.point(t).sq_distance(point);
+            if d < best.0 {
+                best = (d, t);
+            }
+        }
+        // Golden section search around the best sample.
+        let dt = 1f32 / steps as f32;
+        let (mut a, mut b) = ((best.1 - dt).max(0f32), (best.1 + dt).min(1f32));
+        let ratio = 0.5 * (5f32.sqrt() - 1f32);
+        for _ in 0..24 {
+            let c = b - (b - a) * ratio;
+            let d = a + (b - a) * ratio;
+            if self.point(c).sq_distance(point) < self.point(d).sq_distance(point) {
+                b = d;
+            } else {
+                a = c;
+            }
+        }
+        (a + b) * 0.5
+    }
+
+    fn closest_point(&self, point: &T) -> T {
+        self.point(self.closest_param(point))
+    }
+}
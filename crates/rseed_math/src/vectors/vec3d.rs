@@ -1,5 +1,7 @@
 use super::super::fct::fast_isqrt;
 use super::super::quat::Quat;
+/// Laid out as `[f32; 3]`, it can be uploaded to the GPU as it is.
+#[repr(C)]
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Vec3D {
     pub x: f32,
@@ -40,11 +42,41 @@ impl Vec3D {
     pub fn cross(&self, v2: &Self) -> Self {
         Self {
             x: self.y * v2.z - self.z * v2.y,
-            y: self.x * v2.z - self.z * v2.x,
+            y: self.z * v2.x - self.x * v2.z,
             z: self.x * v2.y - self.y * v2.x,
         }
     }
 
+    /// Component wise minimum.
+    pub fn min(&self, v2: &Self) -> Self {
+        Self {
+            x: self.x.min(v2.x),
+            y: self.y.min(v2.y),
+            z: self.z.min(v2.z),
+        }
+    }
+
+    /// Component wise maximum.
+    pub fn max(&self, v2: &Self) -> Self {
+        Self {
+            x: self.x.max(v2.x),
+            y: self.y.max(v2.y),
+            z: self.z.max(v2.z),
+        }
+    }
+
+    pub fn abs(&self) -> Self {
+        Self {
+            x: self.x.abs(),
+            y: self.y.abs(),
+            z: self.z.abs(),
+        }
+    }
+
+    pub fn lerp(&self, v2: &Self, t: f32) -> Self {
+        *self + (*v2 - *self) * t
+    }
+
     pub fn angle(&self, v2: &Self) -> f32 {
         let cnorm = self.cross(v2).norm();
         cnorm * self.inv_norm() * v2.inv_norm()
@@ -183,6 +215,27 @@ impl std::ops::Mul<Vec3D> for f32 {
         }
     }
 }
+impl std::ops::Div<f32> for Vec3D {
+    type Output = Vec3D;
+    fn div(self, other: f32) -> Self {
+        Self {
+            x: self.x / other,
+            y: self.y / other,
+            z: self.z / other,
+        }
+    }
+}
+impl std::ops::Index<usize> for Vec3D {
+    type Output = f32;
+    fn index(&self, i: usize) -> &f32 {
+        match i {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Vec3D index out of bounds: {}", i),
+        }
+    }
+}
 impl std::fmt::Display for Vec3D {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "({},{},{})", self.x, self.y, self.z)
@@ -1,4 +1,6 @@
 use super::super::fct::fast_isqrt;
+/// Laid out as `[f32; 4]`, it can be uploaded to the GPU as it is.
+#[repr(C)]
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Vec4D {
     pub x: f32,
@@ -0,0 +1,97 @@
+use rseed_math::{Mat4, Vec2D, Vec3D, Vec4D};
+
+/// `#[derive(Std140)]` and `#[derive(Std430)]` check that a `#[repr(C)]` struct is laid out
+/// as the GLSL block would be, the compilation fails on the first field out of place.
+pub use block_derive::{Std140, Std430};
+
+/// A type laid out by the std140 rules, the layout of the uniform blocks.
+/// Arrays and structs are aligned on 16 bytes, the vectors of 3 components on 16 too.
+///
+/// # Safety
+/// The bytes of the type must be the bytes of the GLSL type, without implicit padding.
+pub unsafe trait Std140: Copy {
+    const ALIGN: usize;
+    /// Size in a block, the next member may start before the alignment of the type.
+    const SIZE: usize;
+}
+
+/// A type laid out by the std430 rules, the layout of the storage blocks.
+/// As std140 without rounding the alignment of arrays and structs to 16 bytes.
+///
+/// # Safety
+/// The bytes of the type must be the bytes of the GLSL type, without implicit padding.
+pub unsafe trait Std430: Copy {
+    const ALIGN: usize;
+    const SIZE: usize;
+}
+
+/// Round an offset up to the next multiple of the alignment.
+pub const fn align_to(offset: usize, align: usize) -> usize {
+    offset.div_ceil(align) * align
+}
+
+pub const fn max(a: usize, b: usize) -> usize {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
+macro_rules! block_types {
+    ($($ty:ty => $align:expr, $size:expr);* $(;)?) => {
+        $(
+            unsafe impl Std140 for $ty {
+                const ALIGN: usize = $align;
+                const SIZE: usize = $size;
+            }
+
+            unsafe impl Std430 for $ty {
+                const ALIGN: usize = $align;
+                const SIZE: usize = $size;
+            }
+        )*
+    };
+}
+
+// A GLSL bool is 4 bytes, use an u32 for it. Mat3 has no impl, its columns are padded to vec4.
+// The matrices are stored by rows, the blocks declaring them must be `layout(row_major)`.
+block_types!(
+    f32 => 4, 4;
+    i32 => 4, 4;
+    u32 => 4, 4;
+    Vec2D => 8, 8;
+    Vec3D => 16, 12;
+    Vec4D => 16, 16;
+    Mat4 => 16, 64;
+);
+
+/// The elements of an array are at least 16 bytes apart, `[f32; 4]` is 64 bytes, use `Vec4D`.
+unsafe impl<T: Std140, const N: usize> Std140 for [T; N] {
+    const ALIGN: usize = align_to(T::ALIGN, 16);
+    const SIZE: usize = N * align_to(T::SIZE, Self::ALIGN);
+}
+
+unsafe impl<T: Std430, const N: usize> Std430 for [T; N] {
+    const ALIGN: usize = T::ALIGN;
+    const SIZE: usize = N * align_to(T::SIZE, T::ALIGN);
+}
+
+/// The bytes of a uniform block to upload.
+pub fn std140_bytes<T: Std140>(value: &T) -> &[u8] {
+    unsafe { crate::buffer::as_bytes(std::slice::from_ref(value)) }
+}
+
+/// The bytes of a storage block holding an array of elements, as `T data[];` in GLSL.
+///
+/// # Panics
+/// If the elements are not as far apart as in the block, when the size of `T` is not a multiple
+/// of its alignment, like `Vec3D`.
+pub fn std430_bytes<T: Std430>(values: &[T]) -> &[u8] {
+    assert!(
+        T::SIZE.is_multiple_of(T::ALIGN),
+        "The elements of a std430 array are {} bytes apart, pad the type.",
+        align_to(T::SIZE, T::ALIGN)
+    );
+    unsafe { crate::buffer::as_bytes(values) }
+}
@@ -1,26 +1,358 @@
-use rseed_math::Vec3D;
+use rseed_math::{Vec2D, Vec3D, Vec4D};
 
-pub trait VertexArrayT {
+use crate::{context::Result, handle::BufferHandle, renderer::IndexFormat};
 
-    fn bind();
+/// `#[derive(Vertex)]` computes the layout from the fields of a `#[repr(C)]` struct.
+pub use vertex_derive::Vertex;
 
-    fn unbind();
+/// How often the content of a buffer is expected to change.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BufferUsage {
+    /// Written once, drawn many times.
+    Static,
+    /// Updated from time to time.
+    Dynamic,
+    /// Written every frame.
+    Stream,
 }
 
+/// Format of a vertex attribute. The `Norm` formats are read as floats in `[0, 1]`,
+/// or `[-1, 1]` when signed, the other integer formats reach the shader as integers.
+/// Formats of three small components are left out, few devices support them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum VertexFormat {
+    Float,
+    Float2,
+    Float3,
+    Float4,
+    /// Half floats, read as floats.
+    Half2,
+    Half4,
+    Int,
+    Int2,
+    Int3,
+    Int4,
+    UInt,
+    UInt2,
+    UInt3,
+    UInt4,
+    Short2,
+    Short2Norm,
+    Short4,
+    Short4Norm,
+    UShort2,
+    UShort2Norm,
+    UShort4,
+    UShort4Norm,
+    Byte4,
+    Byte4Norm,
+    UByte2,
+    UByte2Norm,
+    UByte4,
+    /// Four bytes read as floats in `[0, 1]`, for packed colors.
+    UByte4Norm,
+}
+
+impl VertexFormat {
+    pub fn components(&self) -> u32 {
+        match self {
+            VertexFormat::Float | VertexFormat::Int | VertexFormat::UInt => 1,
+            VertexFormat::Float2
+            | VertexFormat::Half2
+            | VertexFormat::Int2
+            | VertexFormat::UInt2
+            | VertexFormat::Short2
+            | VertexFormat::Short2Norm
+            | VertexFormat::UShort2
+            | VertexFormat::UShort2Norm
+            | VertexFormat::UByte2
+            | VertexFormat::UByte2Norm => 2,
+            VertexFormat::Float3 | VertexFormat::Int3 | VertexFormat::UInt3 => 3,
+            VertexFormat::Float4
+            | VertexFormat::Half4
+            | VertexFormat::Int4
+            | VertexFormat::UInt4
+            | VertexFormat::Short4
+            | VertexFormat::Short4Norm
+            | VertexFormat::UShort4
+            | VertexFormat::UShort4Norm
+            | VertexFormat::Byte4
+            | VertexFormat::Byte4Norm
+            | VertexFormat::UByte4
+            | VertexFormat::UByte4Norm => 4,
+        }
+    }
+
+    /// Size of one component in bytes.
+    pub fn component_size(&self) -> u32 {
+        match self {
+            VertexFormat::Half2
+            | VertexFormat::Half4
+            | VertexFormat::Short2
+            | VertexFormat::Short2Norm
+            | VertexFormat::Short4
+            | VertexFormat::Short4Norm
+            | VertexFormat::UShort2
+            | VertexFormat::UShort2Norm
+            | VertexFormat::UShort4
+            | VertexFormat::UShort4Norm => 2,
+            VertexFormat::Byte4
+            | VertexFormat::Byte4Norm
+            | VertexFormat::UByte2
+            | VertexFormat::UByte2Norm
+            | VertexFormat::UByte4
+            | VertexFormat::UByte4Norm => 1,
+            _ => 4,
+        }
+    }
+
+    /// Size of the attribute in bytes.
+    pub fn size(&self) -> u32 {
+        self.components() * self.component_size()
+    }
+
+    /// Integer components converted to floats in `[0, 1]` or `[-1, 1]`.
+    pub fn is_normalized(&self) -> bool {
+        matches!(
+            self,
+            VertexFormat::Short2Norm
+                | VertexFormat::Short4Norm
+                | VertexFormat::UShort2Norm
+                | VertexFormat::UShort4Norm
+                | VertexFormat::Byte4Norm
+                | VertexFormat::UByte2Norm
+                | VertexFormat::UByte4Norm
+        )
+    }
+
+    /// Integer attributes that reach the shader without conversion to float.
+    pub fn is_integer(&self) -> bool {
+        !self.is_normalized()
+            && !matches!(
+                self,
+                VertexFormat::Float
+                    | VertexFormat::Float2
+                    | VertexFormat::Float3
+                    | VertexFormat::Float4
+                    | VertexFormat::Half2
+                    | VertexFormat::Half4
+            )
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VertexAttribute {
+    pub location: u32,
+    pub format: VertexFormat,
+    /// Offset in bytes from the start of the vertex.
+    pub offset: u32,
+}
+
+/// Describes how the attributes are laid out in an interleaved vertex buffer.
+/// A draw can read several buffers, each with its own layout, the locations must not overlap.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct VertexLayout {
+    pub attributes: Vec<VertexAttribute>,
+    pub stride: u32,
+    /// Number of instances drawn before the attributes advance,
+    /// 0 to advance them with each vertex.
+    pub divisor: u32,
+}
+
+impl VertexLayout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append an attribute right after the previous one, at the location following the last one.
+    pub fn push(self, format: VertexFormat) -> Self {
+        let location = self.next_location();
+        self.push_at(location, format)
+    }
+
+    /// Append an attribute right after the previous one, at an explicit location.
+    pub fn push_at(mut self, location: u32, format: VertexFormat) -> Self {
+        self.attributes.push(VertexAttribute {
+            location,
+            format,
+            offset: self.stride,
+        });
+        self.stride += format.size();
+        self
+    }
+
+    /// Advance the attributes once every `divisor` instances instead of with each vertex.
+    pub fn instanced(mut self, divisor: u32) -> Self {
+        self.divisor = divisor;
+        self
+    }
+
+    /// The layout of a vertex type.
+    pub fn of<V: Vertex>() -> Self {
+        V::layout()
+    }
+
+    pub fn attribute(&self, location: u32) -> Option<&VertexAttribute> {
+        self.attributes.iter().find(|a| a.location == location)
+    }
+
+    /// The location after the highest one, to chain the layouts of several buffers.
+    pub fn next_location(&self) -> u32 {
+        self.attributes
+            .iter()
+            .map(|a| a.location + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn is_instanced(&self) -> bool {
+        self.divisor > 0
+    }
+}
+
+/// A vertex type with a known layout, so buffers can be created from a slice of it.
+///
+/// # Safety
+/// The type must be `#[repr(C)]` without padding, its bytes are uploaded as they are.
+pub unsafe trait Vertex: Copy {
+    fn layout() -> VertexLayout;
+}
+
+/// The format of a vertex field of this type, for `#[derive(Vertex)]`.
+pub trait AttributeFormat {
+    const FORMAT: VertexFormat;
+}
+
+/// The format of an integer vertex field read as floats, for `#[vertex(normalized)]`.
+pub trait NormalizedFormat {
+    const FORMAT: VertexFormat;
+}
+
+macro_rules! attribute_formats {
+    ($trait:ident, $($ty:ty => $format:ident),* $(,)?) => {
+        $(
+            impl $trait for $ty {
+                const FORMAT: VertexFormat = VertexFormat::$format;
+            }
+        )*
+    };
+}
+
+attribute_formats!(AttributeFormat,
+    f32 => Float,
+    [f32; 2] => Float2,
+    [f32; 3] => Float3,
+    [f32; 4] => Float4,
+    Vec2D => Float2,
+    Vec3D => Float3,
+    Vec4D => Float4,
+    i32 => Int,
+    [i32; 2] => Int2,
+    [i32; 3] => Int3,
+    [i32; 4] => Int4,
+    u32 => UInt,
+    [u32; 2] => UInt2,
+    [u32; 3] => UInt3,
+    [u32; 4] => UInt4,
+    [i16; 2] => Short2,
+    [i16; 4] => Short4,
+    [u16; 2] => UShort2,
+    [u16; 4] => UShort4,
+    [i8; 4] => Byte4,
+    [u8; 2] => UByte2,
+    [u8; 4] => UByte4,
+);
+
+attribute_formats!(NormalizedFormat,
+    [i16; 2] => Short2Norm,
+    [i16; 4] => Short4Norm,
+    [u16; 2] => UShort2Norm,
+    [u16; 4] => UShort4Norm,
+    [i8; 4] => Byte4Norm,
+    [u8; 2] => UByte2Norm,
+    [u8; 4] => UByte4Norm,
+);
+
+/// View a slice of plain data as bytes to upload it.
+///
+/// # Safety
+/// `T` must have no padding bytes.
+pub unsafe fn as_bytes<T: Copy>(data: &[T]) -> &[u8] {
+    std::slice::from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of_val(data))
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum IndexData<'a> {
+    U16(&'a [u16]),
+    U32(&'a [u32]),
+}
+
+impl<'a> IndexData<'a> {
+    pub fn format(&self) -> IndexFormat {
+        match self {
+            IndexData::U16(_) => IndexFormat::U16,
+            IndexData::U32(_) => IndexFormat::U32,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            IndexData::U16(data) => data.len(),
+            IndexData::U32(data) => data.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        match self {
+            IndexData::U16(data) => unsafe { as_bytes(data) },
+            IndexData::U32(data) => unsafe { as_bytes(data) },
+        }
+    }
+}
 
 pub trait VertexBufferT {
+    fn handle(&self) -> BufferHandle;
 
-    fn set_data(&self, data : &[Vec3D]);
+    fn layout(&self) -> &VertexLayout;
 
-    fn bind();
+    /// Number of vertices in the buffer.
+    fn len(&self) -> usize;
 
-    fn unbind();
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Replace the content of the buffer, the data must follow the layout.
+    fn set_data(&mut self, data: &[u8]) -> Result<()>;
 }
 
 pub trait IndexBufferT {
-    fn set_data(&self, data : &[usize]);
-    
-    fn bind();
+    fn handle(&self) -> BufferHandle;
+
+    fn format(&self) -> IndexFormat;
+
+    /// Number of indices in the buffer.
+    fn len(&self) -> usize;
 
-    fn unbind();
-}
\ No newline at end of file
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn set_data(&mut self, data: IndexData) -> Result<()>;
+}
+
+/// Constants shared by the draws, bound to a slot with `RenderCommand::SetUniformBuffer`.
+/// `block::std140_bytes` gives the data of a struct deriving `Std140`.
+pub trait UniformBufferT {
+    fn handle(&self) -> BufferHandle;
+
+    /// Size in bytes.
+    fn size(&self) -> usize;
+
+    /// Write from the start of the buffer, the data can't be bigger than it.
+    fn set_data(&mut self, data: &[u8]) -> Result<()>;
+}
@@ -1,5 +1,80 @@
+use std::path::PathBuf;
 
+use rseed_core::prelude::*;
+
+use crate::{
+    buffer::{BufferUsage, IndexBufferT, IndexData, UniformBufferT, VertexBufferT, VertexLayout},
+    handle::PipelineHandle,
+    pipeline::PipelineDesc,
+    target::{RenderTargetDesc, RenderTargetT},
+    texture::{SamplerDesc, SamplerT, TextureDesc, TextureT},
+};
+
+#[derive(Debug, Error)]
+pub enum ResourceError {
+    #[error(display = "The backend does not support {} yet.", _0)]
+    Unsupported(&'static str),
+    #[error(display = "Expected {} bytes of data, got {}.", _0, _1)]
+    SizeMismatch(usize, usize),
+    #[error(display = "The region is out of the texture.")]
+    OutOfBounds,
+    #[error(display = "Invalid texture: {}", _0)]
+    InvalidTexture(&'static str),
+    #[error(display = "Invalid buffer: {}", _0)]
+    InvalidBuffer(&'static str),
+    #[error(display = "There is no pipeline with the handle {}.", _0)]
+    NoPipeline(u32),
+    #[error(display = "The backend failed to create the resource: {}", _0)]
+    Backend(String),
+}
+
+pub type Result<T> = std::result::Result<T, ResourceError>;
+
+/// Creates the GPU resources of a backend. The resources are freed when dropped.
 pub trait ContextT {
     fn swap_buffers(&self);
 
+    fn create_vertex_buffer(
+        &self,
+        data: &[u8],
+        layout: VertexLayout,
+        usage: BufferUsage,
+    ) -> Result<Box<dyn VertexBufferT>>;
+
+    fn create_index_buffer(&self, data: IndexData, usage: BufferUsage) -> Result<Box<dyn IndexBufferT>>;
+
+    fn create_uniform_buffer(&self, data: &[u8], usage: BufferUsage) -> Result<Box<dyn UniformBufferT>>;
+
+    /// `data` holds the base level of each layer one after the other,
+    /// the texture is left uninitialized without it.
+    fn create_texture(&self, desc: TextureDesc, data: Option<&[u8]>) -> Result<Box<dyn TextureT>>;
+
+    fn create_sampler(&self, desc: SamplerDesc) -> Result<Box<dyn SamplerT>>;
+
+    fn create_render_target(&self, _desc: RenderTargetDesc) -> Result<Box<dyn RenderTargetT>> {
+        Err(ResourceError::Unsupported("render targets"))
+    }
+
+    /// The pipelines live as long as the context, the shaders are compiled from the shader root
+    /// of the settings.
+    fn create_pipeline(&self, _desc: &PipelineDesc) -> Result<PipelineHandle> {
+        Err(ResourceError::Unsupported("pipelines"))
+    }
+
+    /// Compile the shaders of a pipeline again, for the hot reload. The pipeline keeps working
+    /// as it was when they don't build anymore, the handle stays the same otherwise.
+    /// Returns the files the shaders read now.
+    fn rebuild_pipeline(&self, _handle: PipelineHandle) -> Result<Vec<PathBuf>> {
+        Err(ResourceError::Unsupported("pipelines"))
+    }
+
+    /// The files read by the shaders of a pipeline, the ones to watch for the hot reload.
+    fn pipeline_files(&self, _handle: PipelineHandle) -> Result<Vec<PathBuf>> {
+        Err(ResourceError::Unsupported("pipelines"))
+    }
+
+    /// Whether the textures can be indexed from a single array in the shaders.
+    fn supports_bindless(&self) -> bool {
+        false
+    }
 }
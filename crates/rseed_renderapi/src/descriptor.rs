@@ -0,0 +1,64 @@
+use std::ops::BitOr;
+
+/// Kind of resource a shader binding expects.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BindingType {
+    UniformBuffer,
+    StorageBuffer,
+    Texture,
+    Sampler,
+    /// A texture and its sampler in a single binding.
+    TextureSampler,
+}
+
+/// Set of shader stages a binding is visible from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ShaderStages(u32);
+
+impl ShaderStages {
+    pub const VERTEX: Self = Self(1);
+    pub const FRAGMENT: Self = Self(1 << 1);
+    pub const COMPUTE: Self = Self(1 << 2);
+    pub const GEOMETRY: Self = Self(1 << 3);
+    pub const TESS_CONTROL: Self = Self(1 << 4);
+    pub const TESS_EVALUATION: Self = Self(1 << 5);
+    pub const GRAPHICS: Self = Self(Self::VERTEX.0 | Self::FRAGMENT.0);
+
+    pub fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for ShaderStages {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// A binding of a shader, the backends group them in descriptor sets or texture units.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BindingDesc {
+    pub binding: u32,
+    pub ty: BindingType,
+    /// Number of elements for arrays of resources.
+    pub count: u32,
+    pub stages: ShaderStages,
+}
+
+impl BindingDesc {
+    pub fn new(binding: u32, ty: BindingType, stages: ShaderStages) -> Self {
+        Self {
+            binding,
+            ty,
+            count: 1,
+            stages,
+        }
+    }
+
+    pub fn with_count(mut self, count: u32) -> Self {
+        self.count = count;
+        self
+    }
+}
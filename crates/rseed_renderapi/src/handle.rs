@@ -0,0 +1,18 @@
+/// Handles to the objects owned by a backend.
+/// Their value is defined by the backend that created them: the object name for GL,
+/// an index in the backend tables for the others.
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PipelineHandle(pub u32);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BufferHandle(pub u32);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TextureHandle(pub u32);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SamplerHandle(pub u32);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RenderTargetHandle(pub u32);
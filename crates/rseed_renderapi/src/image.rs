@@ -0,0 +1,89 @@
+/// A CPU side RGBA8 image, rows stored from the top to the bottom.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+/// Differences between two images, channel values in `[0, 255]`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ImageDiff {
+    pub max: u8,
+    pub mean: f32,
+    /// Number of pixels with at least one channel different.
+    pub pixels: usize,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![0u8; (width * height * 4) as usize],
+        }
+    }
+
+    pub fn from_data(width: u32, height: u32, data: Vec<u8>) -> Option<Self> {
+        if data.len() != (width * height * 4) as usize {
+            return None;
+        }
+        Some(Self {
+            width,
+            height,
+            data,
+        })
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = ((y * self.width + x) * 4) as usize;
+        [self.data[i], self.data[i + 1], self.data[i + 2], self.data[i + 3]]
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: [u8; 4]) {
+        let i = ((y * self.width + x) * 4) as usize;
+        self.data[i..i + 4].copy_from_slice(&color);
+    }
+
+    /// Flip the rows, GL reads the images from the bottom.
+    pub fn flip_vertical(&mut self) {
+        let row = (self.width * 4) as usize;
+        let h = self.height as usize;
+        for y in 0..h / 2 {
+            let (top, bottom) = self.data.split_at_mut((h - 1 - y) * row);
+            top[y * row..(y + 1) * row].swap_with_slice(&mut bottom[..row]);
+        }
+    }
+
+    /// Compare with a reference image, `None` if the sizes differ.
+    pub fn diff(&self, reference: &Self) -> Option<ImageDiff> {
+        if self.width != reference.width || self.height != reference.height {
+            return None;
+        }
+        let mut max = 0u8;
+        let mut sum = 0u64;
+        let mut pixels = 0;
+        for (a, b) in self.data.chunks(4).zip(reference.data.chunks(4)) {
+            let mut differs = false;
+            for c in 0..4 {
+                let d = (a[c] as i16 - b[c] as i16).unsigned_abs() as u8;
+                max = max.max(d);
+                sum += d as u64;
+                differs |= d > 0;
+            }
+            if differs {
+                pixels += 1;
+            }
+        }
+        Some(ImageDiff {
+            max,
+            mean: sum as f32 / self.data.len().max(1) as f32,
+            pixels,
+        })
+    }
+
+    /// True if no channel differs from the reference by more than `tolerance`.
+    pub fn matches(&self, reference: &Self, tolerance: u8) -> bool {
+        self.diff(reference).is_some_and(|d| d.max <= tolerance)
+    }
+}
@@ -2,7 +2,14 @@
 pub mod context;
 pub mod renderer;
 pub mod buffer;
+pub mod block;
+pub mod descriptor;
 pub mod texture;
+pub mod target;
+pub mod handle;
+pub mod image;
+pub mod pipeline;
+pub mod settings;
 
 use rseed_core::prelude::*;
 
@@ -11,5 +18,7 @@ use rseed_core::prelude::*;
 pub enum Backend {
     GL,
     VK,
+    /// Software rasterizer without window, for tests and CI.
+    HEADLESS,
     UNDEFINED,
 }
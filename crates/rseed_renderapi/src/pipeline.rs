@@ -0,0 +1,73 @@
+use crate::{buffer::VertexLayout, renderer::Primitive};
+
+/// How the color of a fragment is combined with the one in the target.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    Opaque,
+    /// Premultiplied alpha is not assumed, the color is multiplied by its alpha.
+    Alpha,
+    Additive,
+}
+
+/// A pipeline built by the context from the shaders of the assets, bound with
+/// `RenderCommand::SetPipeline`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PipelineDesc {
+    /// The shaders `shader.vert`, `shader.frag`... found in the shader root of the settings.
+    pub shader: String,
+    /// Features of the variant, declared by the shaders.
+    pub features: Vec<String>,
+    /// Layout of the vertex buffer at each binding, reflected from the vertex shader when empty.
+    pub vertex_layouts: Vec<VertexLayout>,
+    /// Vulkan builds the topology in the pipeline, the draws must use this primitive.
+    pub primitive: Primitive,
+    pub blend: BlendMode,
+    /// Test and write the depth, the closest fragment wins.
+    pub depth_test: bool,
+    /// Discard the triangles facing away, counter clockwise ones face the camera.
+    pub cull: bool,
+}
+
+impl PipelineDesc {
+    pub fn new(shader: &str) -> Self {
+        Self {
+            shader: shader.to_owned(),
+            features: Vec::new(),
+            vertex_layouts: Vec::new(),
+            primitive: Primitive::Triangles,
+            blend: BlendMode::Opaque,
+            depth_test: true,
+            cull: true,
+        }
+    }
+
+    pub fn with_feature(mut self, feature: &str) -> Self {
+        self.features.push(feature.to_owned());
+        self
+    }
+
+    pub fn with_vertex_layout(mut self, layout: VertexLayout) -> Self {
+        self.vertex_layouts.push(layout);
+        self
+    }
+
+    pub fn with_primitive(mut self, primitive: Primitive) -> Self {
+        self.primitive = primitive;
+        self
+    }
+
+    pub fn with_blend(mut self, blend: BlendMode) -> Self {
+        self.blend = blend;
+        self
+    }
+
+    pub fn with_depth_test(mut self, depth_test: bool) -> Self {
+        self.depth_test = depth_test;
+        self
+    }
+
+    pub fn with_cull(mut self, cull: bool) -> Self {
+        self.cull = cull;
+        self
+    }
+}
@@ -1,15 +1,256 @@
+use rseed_math::{Mat3, Mat4, Vec2D, Vec3D, Vec4D};
+
+use crate::context::ContextT;
+use crate::handle::{BufferHandle, PipelineHandle, RenderTargetHandle, SamplerHandle, TextureHandle};
+use crate::image::Image;
+
 /// All the renderer platform supported by the engine at the moment.
 
 pub trait RendererT {
     fn swap_buffers(&self);
 
+    /// Execute the recorded commands on the backend.
+    fn submit(&self, commands: &CommandBuffer);
+
+    /// The context creating the resources used by the commands.
+    fn context(&self) -> &dyn ContextT;
+
+    /// The window was resized, the backbuffer must follow.
+    fn resize(&self, _width: u32, _height: u32) {}
+
+    /// Read back the color of the frame being drawn, if the backend supports it.
+    fn read_pixels(&self) -> Option<Image> {
+        None
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IndexFormat {
+    U16,
+    U32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Primitive {
+    Points,
+    Lines,
+    LineStrip,
+    Triangles,
+    TriangleStrip,
+    TriangleFan,
+}
+
+/// How a texture is used, gives the layout and stages of the Vulkan barriers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Access {
+    ColorAttachment,
+    DepthAttachment,
+    /// Sampled from a shader.
+    ShaderRead,
+    /// Read and written as a storage image.
+    Storage,
+    TransferSrc,
+    TransferDst,
+    Present,
 }
 
+impl Access {
+    pub fn is_write(&self) -> bool {
+        matches!(
+            self,
+            Access::ColorAttachment | Access::DepthAttachment | Access::Storage | Access::TransferDst
+        )
+    }
+}
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum UniformValue {
+    Int(i32),
+    Float(f32),
+    Vec2(Vec2D),
+    Vec3(Vec3D),
+    Vec4(Vec4D),
+    Mat3(Mat3),
+    Mat4(Mat4),
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum RenderCommand {
+    /// Draw to an offscreen target, or back to the window with `None`.
+    SetRenderTarget(Option<RenderTargetHandle>),
+    /// Clear the color and depth of the current target.
     Clear,
-    SetClearColor,
-    DrawIndexed,
-    Draw,
+    SetClearColor(Vec4D),
+    SetViewport {
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    },
+    SetPipeline(PipelineHandle),
+    /// Read the attributes of a buffer's layout from the buffer, several buffers
+    /// at different bindings can feed the same draw.
+    SetVertexBuffer {
+        binding: u32,
+        buffer: BufferHandle,
+    },
+    SetIndexBuffer(BufferHandle, IndexFormat),
+    /// Set a uniform of the current pipeline.
+    SetUniform(String, UniformValue),
+    SetUniformBuffer {
+        slot: u32,
+        buffer: BufferHandle,
+    },
+    /// Bind a texture to a slot, with the default sampling of the backend if no sampler is given.
+    SetTexture {
+        slot: u32,
+        texture: TextureHandle,
+        sampler: Option<SamplerHandle>,
+    },
+    DrawIndexed {
+        primitive: Primitive,
+        first: u32,
+        count: u32,
+        /// 1 for a plain draw.
+        instances: u32,
+    },
+    Draw {
+        primitive: Primitive,
+        first: u32,
+        count: u32,
+        /// 1 for a plain draw.
+        instances: u32,
+    },
     Flush,
-}
\ No newline at end of file
+    /// Wait for the previous uses of a texture and move it to a new access,
+    /// `from` is `None` when its content can be discarded.
+    Barrier {
+        texture: TextureHandle,
+        from: Option<Access>,
+        to: Access,
+    },
+}
+
+/// A list of commands recorded by the game and submitted to the renderer.
+#[derive(Clone, Debug, Default)]
+pub struct CommandBuffer {
+    commands: Vec<RenderCommand>,
+}
+
+impl CommandBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn commands(&self) -> &[RenderCommand] {
+        &self.commands
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Remove all the recorded commands to reuse the buffer.
+    pub fn reset(&mut self) {
+        self.commands.clear();
+    }
+
+    pub fn push(&mut self, command: RenderCommand) -> &mut Self {
+        self.commands.push(command);
+        self
+    }
+
+    pub fn clear(&mut self) -> &mut Self {
+        self.push(RenderCommand::Clear)
+    }
+
+    pub fn set_clear_color(&mut self, color: Vec4D) -> &mut Self {
+        self.push(RenderCommand::SetClearColor(color))
+    }
+
+    pub fn set_viewport(&mut self, x: i32, y: i32, width: u32, height: u32) -> &mut Self {
+        self.push(RenderCommand::SetViewport {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+
+    pub fn set_pipeline(&mut self, pipeline: PipelineHandle) -> &mut Self {
+        self.push(RenderCommand::SetPipeline(pipeline))
+    }
+
+    pub fn set_vertex_buffer(&mut self, binding: u32, buffer: BufferHandle) -> &mut Self {
+        self.push(RenderCommand::SetVertexBuffer { binding, buffer })
+    }
+
+    pub fn set_index_buffer(&mut self, buffer: BufferHandle, format: IndexFormat) -> &mut Self {
+        self.push(RenderCommand::SetIndexBuffer(buffer, format))
+    }
+
+    pub fn set_uniform(&mut self, name: &str, value: UniformValue) -> &mut Self {
+        self.push(RenderCommand::SetUniform(name.to_owned(), value))
+    }
+
+    pub fn set_uniform_buffer(&mut self, slot: u32, buffer: BufferHandle) -> &mut Self {
+        self.push(RenderCommand::SetUniformBuffer { slot, buffer })
+    }
+
+    pub fn set_texture(
+        &mut self,
+        slot: u32,
+        texture: TextureHandle,
+        sampler: Option<SamplerHandle>,
+    ) -> &mut Self {
+        self.push(RenderCommand::SetTexture {
+            slot,
+            texture,
+            sampler,
+        })
+    }
+
+    pub fn draw(&mut self, primitive: Primitive, first: u32, count: u32) -> &mut Self {
+        self.draw_instanced(primitive, first, count, 1)
+    }
+
+    pub fn draw_instanced(&mut self, primitive: Primitive, first: u32, count: u32, instances: u32) -> &mut Self {
+        self.push(RenderCommand::Draw {
+            primitive,
+            first,
+            count,
+            instances,
+        })
+    }
+
+    pub fn draw_indexed(&mut self, primitive: Primitive, first: u32, count: u32) -> &mut Self {
+        self.draw_indexed_instanced(primitive, first, count, 1)
+    }
+
+    pub fn draw_indexed_instanced(
+        &mut self,
+        primitive: Primitive,
+        first: u32,
+        count: u32,
+        instances: u32,
+    ) -> &mut Self {
+        self.push(RenderCommand::DrawIndexed {
+            primitive,
+            first,
+            count,
+            instances,
+        })
+    }
+
+    pub fn set_render_target(&mut self, target: Option<RenderTargetHandle>) -> &mut Self {
+        self.push(RenderCommand::SetRenderTarget(target))
+    }
+
+    pub fn flush(&mut self) -> &mut Self {
+        self.push(RenderCommand::Flush)
+    }
+
+    pub fn barrier(&mut self, texture: TextureHandle, from: Option<Access>, to: Access) -> &mut Self {
+        self.push(RenderCommand::Barrier { texture, from, to })
+    }
+}
@@ -0,0 +1,48 @@
+use std::path::PathBuf;
+
+use rseed_core::prelude::*;
+
+/// How the frames are queued for presentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(crate = "serde")]
+pub enum PresentMode {
+    /// Wait for the vertical blank, no tearing. Always supported.
+    #[default]
+    FIFO,
+    /// Replace the queued frame with the newest one, no tearing and low latency.
+    MAILBOX,
+    /// Present as soon as possible, may tear.
+    IMMEDIATE,
+}
+
+/// Pick the GPU instead of letting the backend choose.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(crate = "serde", untagged)]
+pub enum DeviceSelector {
+    /// Index in the list of devices reported by the backend.
+    Index(usize),
+    /// Part of the device name, case insensitive.
+    Name(String),
+}
+
+impl DeviceSelector {
+    pub fn matches(&self, index: usize, name: &str) -> bool {
+        match self {
+            DeviceSelector::Index(i) => *i == index,
+            DeviceSelector::Name(n) => name.to_lowercase().contains(&n.to_lowercase()),
+        }
+    }
+}
+
+/// The `[render]` section of the project file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(crate = "serde", default)]
+pub struct RenderSettings {
+    /// Falls back to `FIFO` when the backend does not support it.
+    pub present_mode: PresentMode,
+    /// Ignored when the device does not exist or can't render to the window.
+    pub device: Option<DeviceSelector>,
+    /// Where the pipelines find their shaders, the app sets it to the asset root of the project.
+    #[serde(skip)]
+    pub shader_root: PathBuf,
+}
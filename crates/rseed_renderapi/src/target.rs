@@ -0,0 +1,84 @@
+use crate::{
+    context::{ResourceError, Result},
+    handle::{RenderTargetHandle, TextureHandle},
+    image::Image,
+    texture::TextureFormat,
+};
+
+/// Attachments of an offscreen render target, drawn with `RenderCommand::SetRenderTarget`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RenderTargetDesc {
+    pub width: u32,
+    pub height: u32,
+    /// One texture per color output of the fragment shader, by location.
+    pub colors: Vec<TextureFormat>,
+    pub depth: Option<TextureFormat>,
+    /// Multisampled targets draw to hidden attachments resolved into the textures.
+    pub samples: u32,
+}
+
+impl RenderTargetDesc {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            colors: Vec::new(),
+            depth: None,
+            samples: 1,
+        }
+    }
+
+    pub fn with_color(mut self, format: TextureFormat) -> Self {
+        self.colors.push(format);
+        self
+    }
+
+    pub fn with_depth(mut self, format: TextureFormat) -> Self {
+        self.depth = Some(format);
+        self
+    }
+
+    pub fn with_samples(mut self, samples: u32) -> Self {
+        self.samples = samples.max(1);
+        self
+    }
+
+    pub fn is_multisampled(&self) -> bool {
+        self.samples > 1
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.width == 0 || self.height == 0 {
+            return Err(ResourceError::InvalidTexture("a render target can't be empty"));
+        }
+        if self.colors.is_empty() && self.depth.is_none() {
+            return Err(ResourceError::InvalidTexture("a render target needs an attachment"));
+        }
+        if self.colors.iter().any(|f| f.is_depth()) {
+            return Err(ResourceError::InvalidTexture("a color attachment can't have a depth format"));
+        }
+        if self.depth.map(|f| !f.is_depth()).unwrap_or(false) {
+            return Err(ResourceError::InvalidTexture("the depth attachment needs a depth format"));
+        }
+        Ok(())
+    }
+}
+
+/// A target drawn instead of the window, for post-processing, picking and screenshots.
+pub trait RenderTargetT {
+    fn handle(&self) -> RenderTargetHandle;
+
+    fn desc(&self) -> &RenderTargetDesc;
+
+    /// The texture of a color attachment, to sample it in a later pass.
+    /// Multisampled targets must be resolved first.
+    fn color_texture(&self, index: usize) -> Option<TextureHandle>;
+
+    fn depth_texture(&self) -> Option<TextureHandle>;
+
+    /// Copy the multisampled attachments to their textures, nothing to do otherwise.
+    fn resolve(&mut self);
+
+    /// Read back a color attachment as RGBA8, resolved first if needed.
+    fn read_pixels(&mut self, index: usize) -> Result<Image>;
+}
@@ -1,4 +1,237 @@
+use crate::{
+    context::{ResourceError, Result},
+    handle::{SamplerHandle, TextureHandle},
+};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TextureFormat {
+    R8,
+    Rg8,
+    Rgba8,
+    /// RGBA8 with the color channels in the sRGB color space.
+    Srgba8,
+    R16F,
+    Rgba16F,
+    R32F,
+    Rgba32F,
+    Depth32F,
+    Depth24Stencil8,
+}
+
+impl TextureFormat {
+    /// Size of a pixel in bytes.
+    pub fn pixel_size(&self) -> u32 {
+        match self {
+            TextureFormat::R8 => 1,
+            TextureFormat::Rg8 | TextureFormat::R16F => 2,
+            TextureFormat::Rgba8
+            | TextureFormat::Srgba8
+            | TextureFormat::R32F
+            | TextureFormat::Depth32F
+            | TextureFormat::Depth24Stencil8 => 4,
+            TextureFormat::Rgba16F => 8,
+            TextureFormat::Rgba32F => 16,
+        }
+    }
+
+    pub fn is_depth(&self) -> bool {
+        matches!(self, TextureFormat::Depth32F | TextureFormat::Depth24Stencil8)
+    }
+}
+
+/// How the layers of a texture are sampled.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TextureKind {
+    D2,
+    /// Layers sampled by index, `sampler2DArray` in GLSL.
+    D2Array,
+    /// Six square layers, in the order +X, -X, +Y, -Y, +Z, -Z.
+    Cube,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TextureDesc {
+    pub width: u32,
+    pub height: u32,
+    pub format: TextureFormat,
+    pub mip_levels: u32,
+    pub kind: TextureKind,
+    /// 1 for a 2D texture, 6 for a cubemap.
+    pub layers: u32,
+}
+
+impl TextureDesc {
+    pub fn new(width: u32, height: u32, format: TextureFormat) -> Self {
+        Self {
+            width,
+            height,
+            format,
+            mip_levels: 1,
+            kind: TextureKind::D2,
+            layers: 1,
+        }
+    }
+
+    pub fn array(width: u32, height: u32, layers: u32, format: TextureFormat) -> Self {
+        Self {
+            kind: TextureKind::D2Array,
+            layers: layers.max(1),
+            ..Self::new(width, height, format)
+        }
+    }
+
+    pub fn cube(size: u32, format: TextureFormat) -> Self {
+        Self {
+            kind: TextureKind::Cube,
+            layers: 6,
+            ..Self::new(size, size, format)
+        }
+    }
+
+    /// Number of levels of a full mip chain down to 1x1.
+    pub fn with_mips(mut self) -> Self {
+        self.mip_levels = 32 - self.width.max(self.height).max(1).leading_zeros();
+        self
+    }
+
+    /// Size in bytes of the pixels of a level, for a single layer.
+    pub fn level_size(&self, level: u32) -> usize {
+        let (width, height) = self.level_extent(level);
+        (width * height * self.format.pixel_size()) as usize
+    }
+
+    /// Width and height of a level.
+    pub fn level_extent(&self, level: u32) -> (u32, u32) {
+        ((self.width >> level).max(1), (self.height >> level).max(1))
+    }
+
+    /// A cubemap has six square layers, the other kinds at least one.
+    pub fn validate(&self) -> Result<()> {
+        match self.kind {
+            TextureKind::Cube if self.width != self.height => {
+                Err(ResourceError::InvalidTexture("the faces of a cubemap must be square"))
+            }
+            TextureKind::Cube if self.layers != 6 => {
+                Err(ResourceError::InvalidTexture("a cubemap has 6 layers"))
+            }
+            TextureKind::D2 if self.layers != 1 => {
+                Err(ResourceError::InvalidTexture("a 2D texture has a single layer"))
+            }
+            _ if self.width == 0 || self.height == 0 || self.layers == 0 => {
+                Err(ResourceError::InvalidTexture("the texture is empty"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Split the base level of each layer, as given to `ContextT::create_texture`.
+    pub fn base_layers<'a>(&self, data: &'a [u8]) -> Result<Vec<(TextureRegion, &'a [u8])>> {
+        // An empty level would make `chunks_exact` panic.
+        self.validate()?;
+        let size = self.level_size(0);
+        let expected = size * self.layers.max(1) as usize;
+        if data.len() != expected {
+            return Err(ResourceError::SizeMismatch(expected, data.len()));
+        }
+        Ok(data
+            .chunks_exact(size)
+            .enumerate()
+            .map(|(layer, pixels)| (TextureRegion::level(self, 0, layer as u32), pixels))
+            .collect())
+    }
+
+    /// Whether the region fits in the texture.
+    pub fn contains(&self, region: &TextureRegion) -> bool {
+        let (width, height) = self.level_extent(region.level);
+        region.level < self.mip_levels.max(1)
+            && region.layer < self.layers.max(1)
+            && matches!(region.x.checked_add(region.width), Some(right) if right <= width)
+            && matches!(region.y.checked_add(region.height), Some(bottom) if bottom <= height)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Filter {
+    Nearest,
+    Linear,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Wrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+    ClampToBorder,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SamplerDesc {
+    pub min_filter: Filter,
+    pub mag_filter: Filter,
+    /// Filter between mip levels, `None` samples only the base level.
+    pub mip_filter: Option<Filter>,
+    pub wrap_u: Wrap,
+    pub wrap_v: Wrap,
+    pub wrap_w: Wrap,
+    /// Maximum anisotropy, `1` disables anisotropic filtering.
+    pub anisotropy: u32,
+}
+
+impl Default for SamplerDesc {
+    fn default() -> Self {
+        Self {
+            min_filter: Filter::Linear,
+            mag_filter: Filter::Linear,
+            mip_filter: Some(Filter::Linear),
+            wrap_u: Wrap::Repeat,
+            wrap_v: Wrap::Repeat,
+            wrap_w: Wrap::Repeat,
+            anisotropy: 1,
+        }
+    }
+}
+
+/// A rectangle of pixels in a level of a texture layer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TextureRegion {
+    pub level: u32,
+    /// Index in the array, or face of the cubemap.
+    pub layer: u32,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl TextureRegion {
+    /// A whole level of a layer.
+    pub fn level(desc: &TextureDesc, level: u32, layer: u32) -> Self {
+        let (width, height) = desc.level_extent(level);
+        Self {
+            level,
+            layer,
+            x: 0,
+            y: 0,
+            width,
+            height,
+        }
+    }
+}
 
 pub trait TextureT {
-    
-}
\ No newline at end of file
+    fn handle(&self) -> TextureHandle;
+
+    fn desc(&self) -> &TextureDesc;
+
+    /// Upload the tightly packed pixels of a region, starting with the row at `y`.
+    fn update(&mut self, region: TextureRegion, data: &[u8]) -> Result<()>;
+
+    /// Fill the levels below the base one from it.
+    fn generate_mips(&mut self);
+}
+
+pub trait SamplerT {
+    fn handle(&self) -> SamplerHandle;
+
+    fn desc(&self) -> &SamplerDesc;
+}
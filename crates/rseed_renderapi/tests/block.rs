@@ -0,0 +1,42 @@
+use rseed_math::{Mat4, Vec3D};
+use rseed_renderapi::block::{std140_bytes, Std140};
+
+#[repr(C)]
+#[derive(Copy, Clone, Std140)]
+struct Light {
+    position: Vec3D,
+    intensity: f32,
+    color: Vec3D,
+    #[block(padding)]
+    _pad: f32,
+}
+
+#[test]
+fn std140_light() {
+    assert_eq!(<Light as Std140>::SIZE, 32);
+    assert_eq!(<Light as Std140>::ALIGN, 16);
+    let light = Light {
+        position: Vec3D::new(1f32, 2f32, 3f32),
+        intensity: 4f32,
+        color: Vec3D::new(5f32, 6f32, 7f32),
+        _pad: 0f32,
+    };
+    let bytes = std140_bytes(&light);
+    assert_eq!(bytes.len(), 32);
+    let floats: Vec<f32> = bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect();
+    assert_eq!(floats, vec![1f32, 2f32, 3f32, 4f32, 5f32, 6f32, 7f32, 0f32]);
+}
+
+#[test]
+fn mat4_uploaded_by_rows() {
+    let data: [f32; 16] = [
+        1f32, 2f32, 3f32, 4f32, 5f32, 6f32, 7f32, 8f32, 9f32, 10f32, 11f32, 12f32, 13f32, 14f32, 15f32, 16f32,
+    ];
+    let m = Mat4::from_data(data);
+    let bytes = std140_bytes(&m);
+    let expected: Vec<u8> = data.iter().flat_map(|v| v.to_le_bytes()).collect();
+    assert_eq!(bytes, &expected[..]);
+}
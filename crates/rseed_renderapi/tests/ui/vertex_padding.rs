@@ -0,0 +1,10 @@
+use rseed_renderapi::buffer::Vertex;
+
+#[repr(C)]
+#[derive(Copy, Clone, Vertex)]
+struct Padded {
+    position: [f32; 3],
+    color: [u8; 2],
+}
+
+fn main() {}
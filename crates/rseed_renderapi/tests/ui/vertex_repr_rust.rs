@@ -0,0 +1,9 @@
+use rseed_renderapi::buffer::Vertex;
+
+#[derive(Copy, Clone, Vertex)]
+struct Unordered {
+    position: [f32; 3],
+    color: [u8; 4],
+}
+
+fn main() {}
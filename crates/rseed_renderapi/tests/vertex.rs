@@ -0,0 +1,58 @@
+use rseed_math::{Vec2D, Vec3D};
+use rseed_renderapi::buffer::{Vertex, VertexAttribute, VertexFormat};
+
+#[repr(C)]
+#[derive(Copy, Clone, Vertex)]
+struct ColorVertex {
+    position: Vec3D,
+    #[vertex(normalized)]
+    color: [u8; 4],
+    uv: Vec2D,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Vertex)]
+#[vertex(instanced)]
+struct Instance {
+    #[vertex(location = 4)]
+    offset: [f32; 2],
+    #[vertex(skip)]
+    _id: u32,
+    #[vertex(format = "Half2")]
+    scale: [u16; 2],
+}
+
+#[test]
+fn layout_from_the_fields() {
+    let layout = ColorVertex::layout();
+    assert_eq!(
+        layout.attributes,
+        vec![
+            VertexAttribute { location: 0, format: VertexFormat::Float3, offset: 0 },
+            VertexAttribute { location: 1, format: VertexFormat::UByte4Norm, offset: 12 },
+            VertexAttribute { location: 2, format: VertexFormat::Float2, offset: 16 },
+        ]
+    );
+    assert_eq!(layout.stride, 24);
+    assert_eq!(layout.divisor, 0);
+}
+
+#[test]
+fn field_options() {
+    let layout = Instance::layout();
+    assert_eq!(
+        layout.attributes,
+        vec![
+            VertexAttribute { location: 4, format: VertexFormat::Float2, offset: 0 },
+            VertexAttribute { location: 5, format: VertexFormat::Half2, offset: 12 },
+        ]
+    );
+    assert_eq!(layout.stride, 16);
+    assert_eq!(layout.divisor, 1);
+}
+
+#[test]
+fn rejected_structs() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/vertex_*.rs");
+}
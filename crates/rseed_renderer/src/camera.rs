@@ -0,0 +1,191 @@
+use rseed_math::{
+    geometry::{Frustum, Ray},
+    Mat4, Vec3D,
+};
+use rseed_renderapi::Backend;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Projection {
+    /// `fov_y` is the vertical field of view in radians.
+    Perspective { fov_y: f32, near: f32, far: f32 },
+    /// `height` is the height of the visible volume in world units, its width follows the aspect.
+    Orthographic { height: f32, near: f32, far: f32 },
+}
+
+/// The clip space the projection matrix maps to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ClipSpace {
+    /// Depth in `[-1, 1]`, Y up.
+    Gl,
+    /// Depth in `[0, 1]`, Y down.
+    Vulkan,
+}
+
+impl ClipSpace {
+    pub fn of(backend: Backend) -> Self {
+        match backend {
+            Backend::VK => ClipSpace::Vulkan,
+            _ => ClipSpace::Gl,
+        }
+    }
+
+    /// From the GL clip space to this one.
+    fn correction(&self) -> Mat4 {
+        match self {
+            ClipSpace::Gl => Mat4::id(),
+            ClipSpace::Vulkan => {
+                let mut m = Mat4::id();
+                m.set(1, 1, &-1f32);
+                m.set(2, 2, &0.5f32);
+                m.set(2, 3, &0.5f32);
+                m
+            }
+        }
+    }
+}
+
+/// A camera independent from the backend, the shaders receive its matrices.
+///
+/// The projection follows the size of the viewport, `resize` must be called with the window.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Camera {
+    pub position: Vec3D,
+    pub target: Vec3D,
+    pub up: Vec3D,
+    pub projection: Projection,
+    pub clip_space: ClipSpace,
+    viewport: (u32, u32),
+}
+
+impl Camera {
+    pub fn new(position: Vec3D, target: Vec3D, projection: Projection) -> Self {
+        Self {
+            position,
+            target,
+            up: Vec3D::up(),
+            projection,
+            clip_space: ClipSpace::Gl,
+            viewport: (1, 1),
+        }
+    }
+
+    /// A perspective camera at the origin looking forward, `fov_y` in radians.
+    pub fn perspective(fov_y: f32, near: f32, far: f32) -> Self {
+        Self::new(Vec3D::null(), Vec3D::forward(), Projection::Perspective { fov_y, near, far })
+    }
+
+    /// An orthographic camera at the origin looking forward, `height` in world units.
+    pub fn orthographic(height: f32, near: f32, far: f32) -> Self {
+        Self::new(Vec3D::null(), Vec3D::forward(), Projection::Orthographic { height, near, far })
+    }
+
+    pub fn with_clip_space(mut self, clip_space: ClipSpace) -> Self {
+        self.clip_space = clip_space;
+        self
+    }
+
+    pub fn look_at(&mut self, target: Vec3D) {
+        self.target = target;
+    }
+
+    /// Move the camera and its target together.
+    pub fn translate(&mut self, offset: Vec3D) {
+        self.position += offset;
+        self.target += offset;
+    }
+
+    /// The viewport changed size, the aspect follows it.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.viewport = (width.max(1), height.max(1));
+    }
+
+    pub fn viewport(&self) -> (u32, u32) {
+        self.viewport
+    }
+
+    pub fn aspect(&self) -> f32 {
+        self.viewport.0 as f32 / self.viewport.1 as f32
+    }
+
+    pub fn forward(&self) -> Vec3D {
+        (self.target - self.position).normalized()
+    }
+
+    /// From world space to view space.
+    pub fn view(&self) -> Mat4 {
+        Mat4::look_at(&self.position, &self.target, &self.up)
+    }
+
+    /// From view space to the clip space of the camera.
+    pub fn projection(&self) -> Mat4 {
+        self.clip_space.correction() * self.gl_projection()
+    }
+
+    pub fn view_projection(&self) -> Mat4 {
+        self.projection() * self.view()
+    }
+
+    /// The projection to the GL clip space, the unprojection and the frustum are computed in it.
+    fn gl_projection(&self) -> Mat4 {
+        match self.projection {
+            Projection::Perspective { fov_y, near, far } => Mat4::perspective(fov_y, self.aspect(), near, far),
+            Projection::Orthographic { height, near, far } => {
+                let (h, w) = (height * 0.5, height * 0.5 * self.aspect());
+                Mat4::orthographic(-w, w, -h, h, near, far)
+            }
+        }
+    }
+
+    /// The ray going through a pixel of the viewport, from the near plane.
+    /// `x` and `y` are in pixels from the top left corner, as the cursor position.
+    pub fn screen_ray(&self, x: f32, y: f32) -> Ray {
+        let ndc_x = 2f32 * x / self.viewport.0 as f32 - 1f32;
+        let ndc_y = 1f32 - 2f32 * y / self.viewport.1 as f32;
+        let inverse = (self.gl_projection() * self.view()).inversed();
+        let near = inverse.project_point(&Vec3D::new(ndc_x, ndc_y, -1f32));
+        let far = inverse.project_point(&Vec3D::new(ndc_x, ndc_y, 1f32));
+        Ray::new(near, far - near)
+    }
+
+    /// The visible volume in world space, for the culling.
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_matrix(&(self.gl_projection() * self.view()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: &Vec3D, b: &Vec3D) -> bool {
+        (*a - *b).norm() < 1e-3
+    }
+
+    fn camera(clip_space: ClipSpace) -> Camera {
+        let mut camera = Camera::perspective(1f32, 0.5, 50f32).with_clip_space(clip_space);
+        camera.position = Vec3D::new(1f32, 2f32, 3f32);
+        camera.target = Vec3D::new(4f32, 2f32, -1f32);
+        camera.resize(800, 600);
+        camera
+    }
+
+    #[test]
+    fn screen_ray_through_the_center() {
+        // The ray does not depend on the clip space of the backend.
+        for clip_space in [ClipSpace::Gl, ClipSpace::Vulkan].iter() {
+            let camera = camera(*clip_space);
+            let ray = camera.screen_ray(400f32, 300f32);
+            assert!(close(&ray.dir, &camera.forward()), "{:?}", ray);
+            assert!(close(&ray.origin, &(camera.position + camera.forward() * 0.5)), "{:?}", ray);
+        }
+    }
+
+    #[test]
+    fn frustum_contains_the_target() {
+        let camera = camera(ClipSpace::Vulkan);
+        let frustum = camera.frustum();
+        assert!(frustum.contains_point(&camera.target));
+        assert!(!frustum.contains_point(&camera.position));
+        assert!(!frustum.contains_point(&(camera.position + camera.forward() * 60f32)));
+    }
+}
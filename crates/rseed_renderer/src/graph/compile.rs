@@ -0,0 +1,485 @@
+use std::collections::BTreeSet;
+
+use rseed_renderapi::{
+    context::ContextT,
+    handle::TextureHandle,
+    renderer::CommandBuffer,
+    texture::TextureT,
+    Backend,
+};
+
+use super::{Access, GraphError, PassId, PassResources, RenderGraph, ResourceId, Result, TextureDesc};
+use crate::Renderer;
+
+/// Transition of a resource before a pass, `from` is `None` when the content can be discarded.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Barrier {
+    pub resource: ResourceId,
+    pub from: Option<Access>,
+    pub to: Access,
+}
+
+/// A graph ready to be executed every frame until the passes change.
+pub struct CompiledGraph {
+    graph: RenderGraph,
+    order: Vec<PassId>,
+    culled: Vec<PassId>,
+    /// First and last position in `order` where each resource is used.
+    lifetimes: Vec<Option<(usize, usize)>>,
+    /// Physical texture backing each transient resource.
+    physical: Vec<Option<usize>>,
+    slots: Vec<TextureDesc>,
+    /// Barriers to insert before each pass, indexed by pass.
+    barriers: Vec<Vec<Barrier>>,
+    final_barriers: Vec<Barrier>,
+    /// The physical textures, created on the first execution.
+    textures: Vec<Box<dyn TextureT>>,
+    /// Texture of each resource, `None` for the imports not bound yet.
+    handles: Vec<Option<TextureHandle>>,
+}
+
+struct Dependencies {
+    /// Every ordering constraint, read after write, write after write and write after read.
+    all: Vec<BTreeSet<usize>>,
+    /// Only the passes producing the data a pass consumes, used to cull.
+    data: Vec<BTreeSet<usize>>,
+}
+
+impl RenderGraph {
+    /// Schedule the passes, cull the ones contributing to no output and alias the
+    /// transient textures. Barriers are only computed for backends that need them.
+    pub fn compile(self, backend: Backend) -> Result<CompiledGraph> {
+        self.validate()?;
+        let deps = self.dependencies();
+        let kept = self.kept(&deps);
+        let order = self.schedule(&deps, &kept)?;
+        let culled = (0..self.passes.len())
+            .filter(|p| !kept[*p])
+            .map(PassId)
+            .collect();
+        let lifetimes = self.lifetimes(&order);
+        let (physical, slots) = self.alias(&lifetimes);
+        let (barriers, final_barriers) = match backend {
+            Backend::VK => self.barriers(&order),
+            _ => (vec![Vec::new(); self.passes.len()], Vec::new()),
+        };
+        let handles = vec![None; self.resources.len()];
+        Ok(CompiledGraph {
+            graph: self,
+            order,
+            culled,
+            lifetimes,
+            physical,
+            slots,
+            barriers,
+            final_barriers,
+            textures: Vec::new(),
+            handles,
+        })
+    }
+
+    fn validate(&self) -> Result<()> {
+        for pass in self.passes.iter() {
+            let unknown = pass
+                .reads
+                .iter()
+                .chain(pass.writes.iter())
+                .any(|(r, _)| r.0 >= self.resources.len());
+            if unknown {
+                return Err(GraphError::UnknownResource(pass.name.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    fn reads(&self, pass: usize, resource: ResourceId) -> bool {
+        self.passes[pass].reads.iter().any(|(r, _)| *r == resource)
+    }
+
+    fn writes(&self, pass: usize, resource: ResourceId) -> bool {
+        self.passes[pass].writes.iter().any(|(r, _)| *r == resource)
+    }
+
+    /// Resources are versioned in declaration order: a reader depends on the last
+    /// writer declared before it, or on the first one after it if there is none.
+    fn dependencies(&self) -> Dependencies {
+        let n = self.passes.len();
+        let mut deps = Dependencies {
+            all: vec![BTreeSet::new(); n],
+            data: vec![BTreeSet::new(); n],
+        };
+        for r in (0..self.resources.len()).map(ResourceId) {
+            let first_writer = (0..n).find(|p| self.writes(*p, r));
+            let mut last_writer: Option<usize> = None;
+            let mut readers: Vec<usize> = Vec::new();
+            for p in 0..n {
+                let (reads, writes) = (self.reads(p, r), self.writes(p, r));
+                if reads {
+                    if let Some(w) = last_writer.or(first_writer).filter(|w| *w != p) {
+                        deps.all[p].insert(w);
+                        deps.data[p].insert(w);
+                    }
+                }
+                if writes {
+                    if let Some(w) = last_writer {
+                        deps.all[p].insert(w);
+                        deps.data[p].insert(w);
+                    }
+                    deps.all[p].extend(readers.drain(..).filter(|q| *q != p));
+                    last_writer = Some(p);
+                } else if reads && last_writer.is_some() {
+                    readers.push(p);
+                }
+            }
+        }
+        deps
+    }
+
+    /// Passes with side effects or writing an imported resource, and everything they need.
+    fn kept(&self, deps: &Dependencies) -> Vec<bool> {
+        let mut kept = vec![false; self.passes.len()];
+        let mut stack: Vec<usize> = (0..self.passes.len())
+            .filter(|p| {
+                let pass = &self.passes[*p];
+                pass.side_effect
+                    || pass
+                        .writes
+                        .iter()
+                        .any(|(r, _)| !self.resources[r.0].is_transient())
+            })
+            .collect();
+        while let Some(p) = stack.pop() {
+            if kept[p] {
+                continue;
+            }
+            kept[p] = true;
+            stack.extend(deps.data[p].iter().filter(|d| !kept[**d]));
+        }
+        kept
+    }
+
+    /// Topological sort, the passes ready at the same time keep their declaration order.
+    fn schedule(&self, deps: &Dependencies, kept: &[bool]) -> Result<Vec<PassId>> {
+        let n = self.passes.len();
+        let mut remaining: Vec<usize> = (0..n)
+            .map(|p| deps.all[p].iter().filter(|d| kept[**d]).count())
+            .collect();
+        let mut ready: BTreeSet<usize> = (0..n).filter(|p| kept[*p] && remaining[*p] == 0).collect();
+        let mut order = Vec::new();
+        while let Some(p) = ready.iter().next().cloned() {
+            ready.remove(&p);
+            order.push(PassId(p));
+            for q in (0..n).filter(|q| kept[*q] && deps.all[*q].contains(&p)) {
+                remaining[q] -= 1;
+                if remaining[q] == 0 {
+                    ready.insert(q);
+                }
+            }
+        }
+        let scheduled = kept.iter().filter(|k| **k).count();
+        if order.len() < scheduled {
+            let names: Vec<&str> = (0..n)
+                .filter(|p| kept[*p] && remaining[*p] > 0)
+                .map(|p| self.passes[p].name.as_str())
+                .collect();
+            return Err(GraphError::Cycle(names.join(", ")));
+        }
+        Ok(order)
+    }
+
+    fn lifetimes(&self, order: &[PassId]) -> Vec<Option<(usize, usize)>> {
+        let mut lifetimes: Vec<Option<(usize, usize)>> = vec![None; self.resources.len()];
+        for (i, pass) in order.iter().enumerate() {
+            let pass = &self.passes[pass.0];
+            for (r, _) in pass.reads.iter().chain(pass.writes.iter()) {
+                let lifetime = lifetimes[r.0].get_or_insert((i, i));
+                lifetime.1 = i;
+            }
+        }
+        lifetimes
+    }
+
+    /// Greedy aliasing, a transient reuses the texture of one with the same description
+    /// whose last use comes before its first use.
+    fn alias(&self, lifetimes: &[Option<(usize, usize)>]) -> (Vec<Option<usize>>, Vec<TextureDesc>) {
+        let mut transients: Vec<(usize, TextureDesc, (usize, usize))> = self
+            .resources
+            .iter()
+            .enumerate()
+            .filter_map(|(r, res)| Some((r, res.desc?, lifetimes[r]?)))
+            .collect();
+        transients.sort_by_key(|(_, _, (first, _))| *first);
+
+        let mut physical = vec![None; self.resources.len()];
+        let mut slots: Vec<TextureDesc> = Vec::new();
+        let mut slot_end: Vec<usize> = Vec::new();
+        for (r, desc, (first, last)) in transients {
+            let free = (0..slots.len()).find(|s| slots[*s] == desc && slot_end[*s] < first);
+            let slot = match free {
+                Some(s) => s,
+                None => {
+                    slots.push(desc);
+                    slot_end.push(0);
+                    slots.len() - 1
+                }
+            };
+            slot_end[slot] = last;
+            physical[r] = Some(slot);
+        }
+        (physical, slots)
+    }
+
+    fn barriers(&self, order: &[PassId]) -> (Vec<Vec<Barrier>>, Vec<Barrier>) {
+        let mut state: Vec<Option<Access>> = vec![None; self.resources.len()];
+        let mut barriers = vec![Vec::new(); self.passes.len()];
+        for pass in order {
+            let p = &self.passes[pass.0];
+            // A resource both read and written by a pass is transitioned to its write access.
+            let mut uses: Vec<(ResourceId, Access)> = p.writes.clone();
+            for (r, access) in p.reads.iter() {
+                if !uses.iter().any(|(u, _)| u == r) {
+                    uses.push((*r, *access));
+                }
+            }
+            for (r, access) in uses {
+                let from = state[r.0];
+                if from != Some(access) || access.is_write() {
+                    barriers[pass.0].push(Barrier {
+                        resource: r,
+                        // Nothing to keep on the first use, an aliased transient may hold
+                        // another resource at that point.
+                        from,
+                        to: access,
+                    });
+                }
+                state[r.0] = Some(access);
+            }
+        }
+        let final_barriers = self
+            .resources
+            .iter()
+            .enumerate()
+            .filter_map(|(r, res)| {
+                let to = res.final_access?;
+                (state[r] != Some(to)).then(|| Barrier {
+                    resource: ResourceId(r),
+                    from: state[r],
+                    to,
+                })
+            })
+            .collect();
+        (barriers, final_barriers)
+    }
+}
+
+impl CompiledGraph {
+    pub fn graph(&self) -> &RenderGraph {
+        &self.graph
+    }
+
+    /// Passes in execution order, without the culled ones.
+    pub fn order(&self) -> &[PassId] {
+        &self.order
+    }
+
+    pub fn culled(&self) -> &[PassId] {
+        &self.culled
+    }
+
+    pub fn is_culled(&self, pass: PassId) -> bool {
+        self.culled.contains(&pass)
+    }
+
+    /// Index of the physical texture backing a transient resource.
+    pub fn physical(&self, resource: ResourceId) -> Option<usize> {
+        self.physical[resource.0]
+    }
+
+    /// Descriptions of the physical textures to allocate for the transients.
+    pub fn physical_textures(&self) -> &[TextureDesc] {
+        &self.slots
+    }
+
+    /// First and last position in the execution order where the resource is used.
+    pub fn lifetime(&self, resource: ResourceId) -> Option<(usize, usize)> {
+        self.lifetimes[resource.0]
+    }
+
+    pub fn barriers(&self, pass: PassId) -> &[Barrier] {
+        &self.barriers[pass.0]
+    }
+
+    /// Transitions of the imported resources to their final access, after the last pass.
+    pub fn final_barriers(&self) -> &[Barrier] {
+        &self.final_barriers
+    }
+
+    /// Bind the texture of an imported resource, its barriers are recorded from then on.
+    pub fn import_texture(&mut self, resource: ResourceId, texture: TextureHandle) {
+        if !self.graph.resources[resource.0].is_transient() {
+            self.handles[resource.0] = Some(texture);
+        }
+    }
+
+    /// Texture backing a resource once the graph is allocated or the import bound.
+    pub fn texture(&self, resource: ResourceId) -> Option<TextureHandle> {
+        self.handles[resource.0]
+    }
+
+    /// Create the physical textures and give them to the transients aliasing them,
+    /// nothing is done if they already exist.
+    pub fn allocate(&mut self, ctx: &dyn ContextT) -> Result<()> {
+        if self.textures.len() == self.slots.len() {
+            return Ok(());
+        }
+        for desc in self.slots[self.textures.len()..].iter() {
+            let texture = ctx.create_texture(*desc, None).map_err(GraphError::Texture)?;
+            self.textures.push(texture);
+        }
+        for (handle, slot) in self.handles.iter_mut().zip(self.physical.iter()) {
+            if let Some(slot) = slot {
+                *handle = Some(self.textures[*slot].handle());
+            }
+        }
+        Ok(())
+    }
+
+    /// Record every scheduled pass in order, each after the barriers of the resources
+    /// it uses. The resources without texture have no barrier.
+    pub fn record(&mut self, commands: &mut CommandBuffer) {
+        let handles = &self.handles;
+        let barrier = |commands: &mut CommandBuffer, barrier: &Barrier| {
+            if let Some(texture) = handles[barrier.resource.0] {
+                commands.barrier(texture, barrier.from, barrier.to);
+            }
+        };
+        let resources = PassResources { textures: handles };
+        for pass in self.order.iter() {
+            self.barriers[pass.0].iter().for_each(|b| barrier(commands, b));
+            if let Some(callback) = self.graph.passes[pass.0].callback.as_mut() {
+                callback(commands, &resources);
+            }
+        }
+        self.final_barriers.iter().for_each(|b| barrier(commands, b));
+    }
+
+    /// Allocate the transients on the first call, then record and submit the frame.
+    pub fn execute(&mut self, renderer: &Renderer) -> Result<()> {
+        self.allocate(renderer.context())?;
+        let mut commands = CommandBuffer::new();
+        self.record(&mut commands);
+        renderer.submit(&commands);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rseed_headless::context::HeadlessContext;
+    use rseed_renderapi::{handle::PipelineHandle, renderer::RenderCommand};
+
+    use super::super::TextureFormat;
+    use super::*;
+
+    /// Passes declared out of order, `debug` feeds nothing and `ldr` can reuse the texture of `gbuffer`.
+    fn deferred() -> (RenderGraph, [ResourceId; 5]) {
+        let mut graph = RenderGraph::new();
+        let desc = TextureDesc::new(64, 64, TextureFormat::Rgba8);
+        let backbuffer = graph.import("backbuffer", Some(Access::Present));
+        let gbuffer = graph.create_texture("gbuffer", desc);
+        let lit = graph.create_texture("lit", desc);
+        let ldr = graph.create_texture("ldr", desc);
+        let unused = graph.create_texture("unused", desc);
+        // Each pass sets the pipeline of its index to be found in the commands.
+        let mark = |pass: u32| move |c: &mut CommandBuffer, _: &PassResources| {
+            c.set_pipeline(PipelineHandle(pass));
+        };
+        graph
+            .add_pass("present")
+            .read(ldr, Access::ShaderRead)
+            .write(backbuffer, Access::ColorAttachment)
+            .execute(mark(0));
+        graph
+            .add_pass("tonemap")
+            .read(lit, Access::ShaderRead)
+            .write(ldr, Access::ColorAttachment)
+            .execute(mark(1));
+        graph
+            .add_pass("debug")
+            .read(gbuffer, Access::ShaderRead)
+            .write(unused, Access::ColorAttachment)
+            .execute(mark(2));
+        graph
+            .add_pass("lighting")
+            .read(gbuffer, Access::ShaderRead)
+            .write(lit, Access::ColorAttachment)
+            .execute(mark(3));
+        graph
+            .add_pass("gbuffer")
+            .write(gbuffer, Access::ColorAttachment)
+            .execute(mark(4));
+        (graph, [backbuffer, gbuffer, lit, ldr, unused])
+    }
+
+    #[test]
+    fn order_culling_and_aliasing() {
+        let (graph, [_, gbuffer, lit, ldr, unused]) = deferred();
+        let compiled = graph.compile(Backend::GL).unwrap();
+        assert_eq!(compiled.order(), &[PassId(4), PassId(3), PassId(1), PassId(0)]);
+        assert_eq!(compiled.culled(), &[PassId(2)]);
+        assert_eq!(compiled.physical_textures().len(), 2);
+        assert_eq!(compiled.physical(ldr), compiled.physical(gbuffer));
+        assert_ne!(compiled.physical(lit), compiled.physical(gbuffer));
+        assert_eq!(compiled.physical(unused), None);
+        // Only Vulkan needs barriers.
+        assert!(compiled.order().iter().all(|p| compiled.barriers(*p).is_empty()));
+        assert!(compiled.final_barriers().is_empty());
+    }
+
+    #[test]
+    fn barriers_recorded_before_the_passes() {
+        let (graph, [backbuffer, gbuffer, lit, ldr, _]) = deferred();
+        let mut compiled = graph.compile(Backend::VK).unwrap();
+        let barrier = |resource, from, to| Barrier { resource, from, to };
+        assert_eq!(
+            compiled.barriers(PassId(3)),
+            &[
+                barrier(lit, None, Access::ColorAttachment),
+                barrier(gbuffer, Some(Access::ColorAttachment), Access::ShaderRead),
+            ]
+        );
+        assert_eq!(
+            compiled.final_barriers(),
+            &[barrier(backbuffer, Some(Access::ColorAttachment), Access::Present)]
+        );
+
+        compiled.allocate(&HeadlessContext::new(8, 8)).unwrap();
+        compiled.import_texture(backbuffer, TextureHandle(99));
+        let (g, l) = (compiled.texture(gbuffer).unwrap(), compiled.texture(lit).unwrap());
+        assert_eq!(compiled.texture(ldr), Some(g));
+
+        let mut commands = CommandBuffer::new();
+        compiled.record(&mut commands);
+        let b = |texture, from, to| RenderCommand::Barrier { texture, from, to };
+        let pass = |p| RenderCommand::SetPipeline(PipelineHandle(p));
+        let (color, read) = (Some(Access::ColorAttachment), Access::ShaderRead);
+        let back = TextureHandle(99);
+        assert_eq!(
+            commands.commands(),
+            &[
+                b(g, None, Access::ColorAttachment),
+                pass(4),
+                b(l, None, Access::ColorAttachment),
+                b(g, color, read),
+                pass(3),
+                b(g, None, Access::ColorAttachment),
+                b(l, color, read),
+                pass(1),
+                b(back, None, Access::ColorAttachment),
+                b(g, color, read),
+                pass(0),
+                b(back, color, Access::Present),
+            ]
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use std::fmt::Write;
+
+use super::{CompiledGraph, PassId};
+
+impl CompiledGraph {
+    /// Graphviz dump of the compiled graph, render it with `dot -Tsvg`.
+    /// Culled passes are dashed, transients show their physical texture and
+    /// edges are labelled with the access and the barrier inserted, if any.
+    pub fn to_dot(&self) -> String {
+        let graph = self.graph();
+        let mut dot = String::from("digraph render_graph {\n    rankdir = LR;\n");
+
+        for (i, pass) in self.order().iter().enumerate() {
+            let _ = writeln!(
+                dot,
+                "    p{} [shape = box, style = filled, fillcolor = lightblue, label = \"{}: {}\"];",
+                pass.0,
+                i,
+                graph.pass(*pass).name
+            );
+        }
+        for pass in self.culled() {
+            let _ = writeln!(
+                dot,
+                "    p{} [shape = box, style = dashed, color = gray, label = \"{} (culled)\"];",
+                pass.0,
+                graph.pass(*pass).name
+            );
+        }
+
+        for (r, resource) in graph.resources.iter().enumerate() {
+            let label = match (resource.desc, self.physical(super::ResourceId(r))) {
+                (Some(desc), Some(slot)) => format!(
+                    "{}\\n{}x{} {:?}\\ntexture {}",
+                    resource.name, desc.width, desc.height, desc.format, slot
+                ),
+                (Some(desc), None) => format!(
+                    "{}\\n{}x{} {:?}\\nunused",
+                    resource.name, desc.width, desc.height, desc.format
+                ),
+                (None, _) => format!("{}\\nimported", resource.name),
+            };
+            let shape = if resource.is_transient() { "ellipse" } else { "doubleoctagon" };
+            let _ = writeln!(dot, "    r{} [shape = {}, label = \"{}\"];", r, shape, label);
+        }
+
+        for (p, pass) in graph.passes.iter().enumerate() {
+            let barrier = |r: usize| {
+                self.barriers(PassId(p))
+                    .iter()
+                    .find(|b| b.resource.0 == r)
+                    .map(|b| match b.from {
+                        Some(from) => format!("\\n{:?} -> {:?}", from, b.to),
+                        None => format!("\\nundefined -> {:?}", b.to),
+                    })
+                    .unwrap_or_default()
+            };
+            for (r, access) in pass.reads.iter() {
+                let _ = writeln!(
+                    dot,
+                    "    r{} -> p{} [label = \"{:?}{}\"];",
+                    r.0,
+                    p,
+                    access,
+                    barrier(r.0)
+                );
+            }
+            for (r, access) in pass.writes.iter() {
+                let _ = writeln!(
+                    dot,
+                    "    p{} -> r{} [color = red, label = \"{:?}{}\"];",
+                    p,
+                    r.0,
+                    access,
+                    barrier(r.0)
+                );
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
@@ -0,0 +1,157 @@
+/// Frame graph: passes declare the resources they read and write,
+/// the graph orders them, culls the useless ones and aliases the transient attachments.
+mod compile;
+mod dot;
+
+pub use compile::*;
+
+use rseed_core::prelude::*;
+pub use rseed_renderapi::renderer::Access;
+use rseed_renderapi::{context::ResourceError, handle::TextureHandle, renderer::CommandBuffer};
+pub use rseed_renderapi::texture::{TextureDesc, TextureFormat};
+
+#[derive(Debug, Error)]
+pub enum GraphError {
+    #[error(display = "Passes {} form a dependency cycle.", _0)]
+    Cycle(String),
+    #[error(display = "Pass {} uses a resource that does not exist.", _0)]
+    UnknownResource(String),
+    #[error(display = "A transient texture could not be created: {}", _0)]
+    Texture(ResourceError),
+}
+
+pub type Result<T> = std::result::Result<T, GraphError>;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ResourceId(pub usize);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PassId(pub usize);
+
+pub struct Resource {
+    pub name: String,
+    /// `None` for imported resources, which are owned outside the graph.
+    pub desc: Option<TextureDesc>,
+    /// State an imported resource must be left in at the end of the frame.
+    pub final_access: Option<Access>,
+}
+
+impl Resource {
+    pub fn is_transient(&self) -> bool {
+        self.desc.is_some()
+    }
+}
+
+/// The textures backing the resources while a pass records its commands.
+pub struct PassResources<'a> {
+    pub(crate) textures: &'a [Option<TextureHandle>],
+}
+
+impl<'a> PassResources<'a> {
+    /// `None` for an imported resource never bound to a texture, like the swapchain image.
+    pub fn texture(&self, resource: ResourceId) -> Option<TextureHandle> {
+        self.textures.get(resource.0).cloned().flatten()
+    }
+}
+
+pub type PassCallback = Box<dyn FnMut(&mut CommandBuffer, &PassResources)>;
+
+pub struct Pass {
+    pub name: String,
+    pub reads: Vec<(ResourceId, Access)>,
+    pub writes: Vec<(ResourceId, Access)>,
+    /// Passes with side effects are never culled, even if nothing reads their outputs.
+    pub side_effect: bool,
+    pub(crate) callback: Option<PassCallback>,
+}
+
+/// Passes and resources of one frame, compiled into a [`CompiledGraph`].
+#[derive(Default)]
+pub struct RenderGraph {
+    pub(crate) resources: Vec<Resource>,
+    pub(crate) passes: Vec<Pass>,
+}
+
+impl RenderGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare a texture that only lives during the frame, its memory can be shared
+    /// with other transient textures whose lifetimes don't overlap.
+    pub fn create_texture(&mut self, name: &str, desc: TextureDesc) -> ResourceId {
+        self.resources.push(Resource {
+            name: name.to_string(),
+            desc: Some(desc),
+            final_access: None,
+        });
+        ResourceId(self.resources.len() - 1)
+    }
+
+    /// Declare a resource owned outside the graph, like the swapchain image.
+    /// Passes writing imported resources are always kept.
+    pub fn import(&mut self, name: &str, final_access: Option<Access>) -> ResourceId {
+        self.resources.push(Resource {
+            name: name.to_string(),
+            desc: None,
+            final_access,
+        });
+        ResourceId(self.resources.len() - 1)
+    }
+
+    pub fn add_pass(&mut self, name: &str) -> PassBuilder<'_> {
+        self.passes.push(Pass {
+            name: name.to_string(),
+            reads: Vec::new(),
+            writes: Vec::new(),
+            side_effect: false,
+            callback: None,
+        });
+        let id = PassId(self.passes.len() - 1);
+        PassBuilder { graph: self, id }
+    }
+
+    pub fn resource(&self, id: ResourceId) -> &Resource {
+        &self.resources[id.0]
+    }
+
+    pub fn pass(&self, id: PassId) -> &Pass {
+        &self.passes[id.0]
+    }
+}
+
+pub struct PassBuilder<'a> {
+    graph: &'a mut RenderGraph,
+    id: PassId,
+}
+
+impl<'a> PassBuilder<'a> {
+    pub fn id(&self) -> PassId {
+        self.id
+    }
+
+    fn pass(&mut self) -> &mut Pass {
+        &mut self.graph.passes[self.id.0]
+    }
+
+    pub fn read(mut self, resource: ResourceId, access: Access) -> Self {
+        self.pass().reads.push((resource, access));
+        self
+    }
+
+    pub fn write(mut self, resource: ResourceId, access: Access) -> Self {
+        self.pass().writes.push((resource, access));
+        self
+    }
+
+    pub fn side_effect(mut self) -> Self {
+        self.pass().side_effect = true;
+        self
+    }
+
+    /// Record the commands of the pass when the graph is executed.
+    pub fn execute<F: FnMut(&mut CommandBuffer, &PassResources) + 'static>(mut self, callback: F) -> PassId {
+        self.pass().callback = Some(Box::new(callback));
+        self.id
+    }
+}
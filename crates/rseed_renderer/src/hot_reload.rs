@@ -0,0 +1,140 @@
+use std::{
+    cell::RefCell,
+    path::PathBuf,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use rseed_gl::shader::ShaderProgram;
+use rseed_log::Logger;
+use rseed_renderapi::{context::ContextT, handle::PipelineHandle};
+use rseed_shaderlib::ShaderLibrary;
+
+/// Builds a shader again and returns the files it reads now.
+/// It must keep the last working version when the new one fails,
+/// as `ShaderProgram::reload` and `ContextT::rebuild_pipeline` do.
+/// The variants of the library reading a changed file are already forgotten,
+/// `ShaderLibrary::spirv` compiles them again.
+pub type ReloadFn = Box<dyn FnMut(&mut ShaderLibrary, &dyn ContextT) -> Result<Vec<PathBuf>, String>>;
+
+struct WatchedShader {
+    name: String,
+    files: Vec<PathBuf>,
+    reload: ReloadFn,
+}
+
+/// Reloads the shaders when their files change, during development.
+/// The files are watched by the shader library, which also drops its stale variants.
+///
+/// The pipelines belong to the context, which builds them again. `App::create_pipeline`
+/// registers the ones it creates, the others are registered by hand:
+///
+/// ```ignore
+/// let ctx = renderer.context();
+/// let handle = ctx.create_pipeline(&PipelineDesc::new("rect"))?;
+/// hot_reload.watch_pipeline("rect", handle, ctx.pipeline_files(handle)?);
+/// // Once per frame, with the context of the pipelines.
+/// hot_reload.poll(renderer.context(), &logger);
+/// ```
+///
+/// The GL programs linked outside of the context are registered with `watch_program`.
+pub struct HotReload {
+    library: ShaderLibrary,
+    shaders: Vec<WatchedShader>,
+    interval: Duration,
+    last_poll: Instant,
+}
+
+impl HotReload {
+    pub fn new(library: ShaderLibrary) -> Self {
+        Self {
+            library,
+            shaders: Vec::new(),
+            interval: Duration::from_millis(500),
+            last_poll: Instant::now(),
+        }
+    }
+
+    /// Time between two checks of the files, half a second by default.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// The library the reloaded shaders are compiled with.
+    pub fn library(&mut self) -> &mut ShaderLibrary {
+        &mut self.library
+    }
+
+    /// Call `reload` when one of the files changes.
+    pub fn watch<F>(&mut self, name: &str, files: Vec<PathBuf>, reload: F)
+    where
+        F: FnMut(&mut ShaderLibrary, &dyn ContextT) -> Result<Vec<PathBuf>, String> + 'static,
+    {
+        for file in files.iter() {
+            self.library.watch(file);
+        }
+        self.shaders.push(WatchedShader {
+            name: name.to_owned(),
+            files,
+            reload: Box::new(reload),
+        });
+    }
+
+    /// Link a GL program loaded from the assets again when its sources change.
+    pub fn watch_program(&mut self, name: &str, program: Rc<RefCell<ShaderProgram>>) {
+        let files = program.borrow().files().to_vec();
+        self.watch(name, files, move |library, _| {
+            let mut program = program.borrow_mut();
+            program.reload(library.preprocessor()).map_err(|e| e.to_string())?;
+            Ok(program.files().to_vec())
+        });
+    }
+
+    /// Build a pipeline of the context again when the files of its shaders change,
+    /// `files` are given by `ContextT::pipeline_files`.
+    pub fn watch_pipeline(&mut self, name: &str, handle: PipelineHandle, files: Vec<PathBuf>) {
+        self.watch(name, files, move |_, ctx| {
+            ctx.rebuild_pipeline(handle).map_err(|e| e.to_string())
+        });
+    }
+
+    /// Reload the shaders reading a file modified since the last poll,
+    /// at most once per interval. The failures are logged and the shader stays as it was.
+    /// `ctx` is the context the watched pipelines were created by.
+    pub fn poll(&mut self, ctx: &dyn ContextT, logger: &Logger) {
+        if self.last_poll.elapsed() < self.interval {
+            return;
+        }
+        self.last_poll = Instant::now();
+        let changed = self.library.changed_files();
+        if changed.is_empty() {
+            return;
+        }
+        let library = &mut self.library;
+        for shader in self.shaders.iter_mut() {
+            if !shader.files.iter().any(|f| changed.contains(f)) {
+                continue;
+            }
+            match (shader.reload)(library, ctx) {
+                Ok(files) => {
+                    // An include may have been added.
+                    for file in files.iter() {
+                        library.watch(file);
+                    }
+                    shader.files = files;
+                    logger.info(&format!("Reloaded the shader {}.", shader.name));
+                }
+                Err(e) => {
+                    logger.error(&format!(
+                        "Can't reload the shader {}, the last working version is kept.",
+                        shader.name
+                    ));
+                    for line in e.lines() {
+                        logger.error(&line);
+                    }
+                }
+            }
+        }
+    }
+}
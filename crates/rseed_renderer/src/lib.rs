@@ -1,10 +1,24 @@
+pub mod camera;
+pub mod graph;
+pub mod hot_reload;
+
 use std::ops::Deref;
 
 use rseed_core::utils::Version;
 use rseed_vk as vk;
 use rseed_gl as gl;
-use rseed_renderapi::{renderer::RendererT, Backend};
+use rseed_headless as headless;
+use rseed_renderapi::{
+    buffer::{as_bytes, BufferUsage, IndexBufferT, IndexData, Vertex, VertexBufferT},
+    context,
+    renderer::{CommandBuffer, RendererT},
+    settings::{PresentMode, RenderSettings},
+    Backend,
+};
 pub use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
+pub use rseed_shaderlib as shaderlib;
+pub use camera::{Camera, ClipSpace, Projection};
+pub use hot_reload::HotReload;
 
 use glutin::{
     window::{
@@ -18,6 +32,8 @@ use glutin::{
 #[derive(Debug)]
 pub enum RendererError {
      NoBackendDefined,
+     /// The headless backend has no window, use `Renderer::init_headless`.
+     HeadlessWithWindow,
 }
 
 
@@ -54,13 +70,20 @@ impl Renderer
         app_name : String,
         app_version : Version,
         backend : Backend,
+        settings : &RenderSettings,
     ) -> Result<(Self, Window)> {
         match backend {
             Backend::VK => { 
                 let window = window_builder.build(event_loop).unwrap();
+                let size = window.inner_size();
                 let ctx = unsafe {
-                    vk::context::VkContext::init(app_name, app_version, &window)
-                        .unwrap()
+                    vk::context::VkContext::init(
+                        app_name,
+                        app_version,
+                        &window,
+                        (size.width, size.height),
+                        settings,
+                    ).unwrap()
                 };
                 let renderer = Self::new(Box::new(vk::VkRenderer::new(ctx)));
                 return Ok((renderer, window));
@@ -68,19 +91,49 @@ impl Renderer
 
             Backend::GL => {
                 let (raw_context, window) = unsafe {
+                    // GL only knows about vsync, mailbox is not available.
                     glutin::ContextBuilder::new()
+                        .with_vsync(settings.present_mode == PresentMode::FIFO)
                         .build_windowed(window_builder, &event_loop)
                         .unwrap()
                         .split()
                 };
-                let ctx = rseed_gl::context::GlContext::init(raw_context).unwrap();
+                let ctx = rseed_gl::context::GlContext::init(raw_context, settings).unwrap();
                 let renderer = Self::new(Box::new(gl::GlRenderer::new(ctx)));
                 return Ok((renderer, window));
             }
+            Backend::HEADLESS => return Err(RendererError::HeadlessWithWindow),
             Backend::UNDEFINED => return Err(RendererError::NoBackendDefined)
         }
     }
 
+    /// Render on the CPU without window, the frame is read back with `read_pixels`.
+    pub fn init_headless(width : u32, height : u32) -> Self {
+        Self::new(Box::new(headless::HeadlessRenderer::new(width, height)))
+    }
+
+
+    /// Upload typed vertices, the layout comes from the vertex type.
+    pub fn create_vertex_buffer<V: Vertex>(
+        &self,
+        vertices : &[V],
+        usage : BufferUsage,
+    ) -> context::Result<Box<dyn VertexBufferT>> {
+        let data = unsafe { as_bytes(vertices) };
+        self.inner.context().create_vertex_buffer(data, V::layout(), usage)
+    }
+
+    pub fn create_index_buffer(
+        &self,
+        indices : IndexData,
+        usage : BufferUsage,
+    ) -> context::Result<Box<dyn IndexBufferT>> {
+        self.inner.context().create_index_buffer(indices, usage)
+    }
+
+    pub fn submit(&self, commands : &CommandBuffer) {
+        self.inner.submit(commands);
+    }
 
     pub fn draw(&self) {
         self.inner.swap_buffers();
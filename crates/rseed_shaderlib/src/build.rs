@@ -0,0 +1,45 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    compiler::ShaderCompiler,
+    preprocess::{Preprocessor, VariantKey},
+    Result, ShaderError, ShaderStage,
+};
+
+/// Compile every shader of `src` to `out`, `rect.vert` becoming `rect.vert.spv`.
+/// The includes are searched in `src` and no feature is enabled.
+/// Cargo is told to run the script again when a shader or an include changes,
+/// and the warnings are forwarded to it. Returns the written files.
+pub fn compile_dir(src: &Path, out: &Path) -> Result<Vec<PathBuf>> {
+    let io_error = |path: &Path| {
+        let name = path.to_string_lossy().into_owned();
+        move |e| ShaderError::Io(name, e)
+    };
+    std::fs::create_dir_all(out).map_err(io_error(out))?;
+    println!("cargo:rerun-if-changed={}", src.display());
+
+    let preprocessor = Preprocessor::new(src);
+    let mut compiler = ShaderCompiler::new()?;
+    let mut written = Vec::new();
+    for entry in std::fs::read_dir(src).map_err(io_error(src))? {
+        let path = entry.map_err(io_error(src))?.path();
+        let stage = match ShaderStage::from_path(&path) {
+            Some(stage) => stage,
+            None => continue,
+        };
+        let source = preprocessor.process(Path::new(path.file_name().unwrap()), &VariantKey::default())?;
+        for file in source.files.iter() {
+            println!("cargo:rerun-if-changed={}", file.display());
+        }
+        let shader = compiler.compile_preprocessed(&source, stage)?;
+        for warning in shader.warnings.iter() {
+            println!("cargo:warning={}", warning);
+        }
+        let mut name = path.file_name().unwrap().to_os_string();
+        name.push(".spv");
+        let target = out.join(name);
+        std::fs::write(&target, shader.bytes()).map_err(io_error(&target))?;
+        written.push(target);
+    }
+    Ok(written)
+}
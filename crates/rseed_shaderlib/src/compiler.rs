@@ -0,0 +1,219 @@
+use std::path::Path;
+
+use crate::{
+    preprocess::Preprocessed, reflect::Reflection, CompileErrors, Diagnostic, Result, ShaderError,
+    ShaderStage,
+};
+
+/// A shader compiled to SPIR-V, with its interface.
+#[derive(Clone, Debug)]
+pub struct CompiledShader {
+    pub stage: ShaderStage,
+    pub spirv: Vec<u32>,
+    pub reflection: Reflection,
+    /// Warnings of the compiler.
+    pub warnings: Vec<Diagnostic>,
+}
+
+impl CompiledShader {
+    /// The SPIR-V words as bytes, the form the Vulkan pipelines take.
+    pub fn bytes(&self) -> Vec<u8> {
+        self.spirv.iter().flat_map(|w| w.to_le_bytes().to_vec()).collect()
+    }
+}
+
+/// Compiles GLSL to SPIR-V for Vulkan 1.1, the GL backend keeps compiling the sources itself.
+pub struct ShaderCompiler {
+    compiler: shaderc::Compiler,
+    /// Optimize for performance, otherwise keep the debug information.
+    pub optimize: bool,
+}
+
+impl ShaderCompiler {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            compiler: shaderc::Compiler::new().ok_or(ShaderError::NoCompiler)?,
+            optimize: !cfg!(debug_assertions),
+        })
+    }
+
+    /// `name` is the file reported by the errors.
+    pub fn compile(
+        &mut self,
+        source: &str,
+        stage: ShaderStage,
+        name: &str,
+        defines: &[(&str, Option<&str>)],
+    ) -> Result<CompiledShader> {
+        let mut options = shaderc::CompileOptions::new().ok_or(ShaderError::NoCompiler)?;
+        options.set_target_env(shaderc::TargetEnv::Vulkan, shaderc::EnvVersion::Vulkan1_1 as u32);
+        options.set_source_language(shaderc::SourceLanguage::GLSL);
+        if self.optimize {
+            options.set_optimization_level(shaderc::OptimizationLevel::Performance);
+        } else {
+            options.set_generate_debug_info();
+        }
+        // The GL style sources don't give a location to every block and sampler.
+        options.set_auto_bind_uniforms(true);
+        options.set_auto_map_locations(true);
+        for (name, value) in defines {
+            options.add_macro_definition(name, *value);
+        }
+
+        let artifact = self
+            .compiler
+            .compile_into_spirv(source, shader_kind(stage), name, "main", Some(&options))
+            .map_err(|e| ShaderError::Compile(CompileErrors(parse_errors(&e, name))))?;
+        let spirv = artifact.as_binary().to_vec();
+        let reflection = Reflection::parse(&spirv, stage).map_err(ShaderError::Reflect)?;
+        let warnings = if artifact.get_num_warnings() > 0 {
+            parse_messages(&artifact.get_warning_messages(), name)
+        } else {
+            Vec::new()
+        };
+        Ok(CompiledShader {
+            stage,
+            spirv,
+            reflection,
+            warnings,
+        })
+    }
+
+    /// Compile the output of the preprocessor, the messages point at the original files.
+    pub fn compile_preprocessed(&mut self, source: &Preprocessed, stage: ShaderStage) -> Result<CompiledShader> {
+        let remap = |diagnostics: Vec<Diagnostic>| -> Vec<Diagnostic> {
+            diagnostics.iter().map(|d| source.lines.remap(d)).collect()
+        };
+        match self.compile(&source.source, stage, &source.name, &[]) {
+            Ok(mut shader) => {
+                shader.warnings = remap(shader.warnings);
+                Ok(shader)
+            }
+            Err(ShaderError::Compile(errors)) => Err(ShaderError::Compile(CompileErrors(remap(errors.0)))),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The stage is guessed from the extension of the file.
+    pub fn compile_file(&mut self, path: &Path) -> Result<CompiledShader> {
+        let name = path.to_string_lossy().into_owned();
+        let stage = ShaderStage::from_path(path).ok_or_else(|| ShaderError::UnknownStage(name.clone()))?;
+        let source = std::fs::read_to_string(path).map_err(|e| ShaderError::Io(name.clone(), e))?;
+        self.compile(&source, stage, &name, &[])
+    }
+}
+
+fn shader_kind(stage: ShaderStage) -> shaderc::ShaderKind {
+    match stage {
+        ShaderStage::Vertex => shaderc::ShaderKind::Vertex,
+        ShaderStage::TessControl => shaderc::ShaderKind::TessControl,
+        ShaderStage::TessEvaluation => shaderc::ShaderKind::TessEvaluation,
+        ShaderStage::Geometry => shaderc::ShaderKind::Geometry,
+        ShaderStage::Fragment => shaderc::ShaderKind::Fragment,
+        ShaderStage::Compute => shaderc::ShaderKind::Compute,
+    }
+}
+
+fn parse_errors(error: &shaderc::Error, name: &str) -> Vec<Diagnostic> {
+    let diagnostics = match error {
+        shaderc::Error::CompilationError(_, messages) => parse_messages(messages, name),
+        _ => Vec::new(),
+    };
+    if diagnostics.is_empty() {
+        vec![Diagnostic {
+            file: name.to_owned(),
+            line: 0,
+            message: error.to_string(),
+        }]
+    } else {
+        diagnostics
+    }
+}
+
+/// The messages look like `file:line: error: message`, one per line.
+pub(crate) fn parse_messages(messages: &str, name: &str) -> Vec<Diagnostic> {
+    messages
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| parse_message(l, name))
+        .collect()
+}
+
+fn parse_message(message: &str, name: &str) -> Diagnostic {
+    // Paths can hold colons on Windows, the name given to the compiler is tried first.
+    let (file, rest) = match message.strip_prefix(name).and_then(|r| r.strip_prefix(':')) {
+        Some(rest) => (name, rest),
+        None => message.split_once(':').unwrap_or((name, message)),
+    };
+    match rest.split_once(':') {
+        Some((line, text)) if line.trim().parse::<u32>().is_ok() => Diagnostic {
+            file: file.to_owned(),
+            line: line.trim().parse().unwrap(),
+            message: text.trim().to_owned(),
+        },
+        _ => Diagnostic {
+            file: name.to_owned(),
+            line: 0,
+            message: message.trim().to_owned(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostic(file: &str, line: u32, message: &str) -> Diagnostic {
+        Diagnostic {
+            file: file.to_owned(),
+            line,
+            message: message.to_owned(),
+        }
+    }
+
+    #[test]
+    fn message_with_line() {
+        assert_eq!(
+            parse_message("rect.vert:12: error: 'mvp' : undeclared identifier", "rect.vert"),
+            diagnostic("rect.vert", 12, "error: 'mvp' : undeclared identifier")
+        );
+        // An included file keeps its own name.
+        assert_eq!(
+            parse_message("common.glsl:3: warning: unused", "rect.frag"),
+            diagnostic("common.glsl", 3, "warning: unused")
+        );
+    }
+
+    #[test]
+    fn windows_path() {
+        let name = "C:\\shaders\\rect.frag";
+        assert_eq!(
+            parse_message("C:\\shaders\\rect.frag:7: error: syntax error", name),
+            diagnostic(name, 7, "error: syntax error")
+        );
+    }
+
+    #[test]
+    fn message_without_line() {
+        assert_eq!(
+            parse_message("error: 1 compilation errors.  No code generated.", "rect.vert"),
+            diagnostic("rect.vert", 0, "error: 1 compilation errors.  No code generated.")
+        );
+        assert_eq!(
+            parse_message("rect.vert: error: no main", "rect.vert"),
+            diagnostic("rect.vert", 0, "rect.vert: error: no main")
+        );
+    }
+
+    #[test]
+    fn blank_lines_skipped() {
+        let messages = "rect.vert:1: error: first\n\n  \nrect.vert:2: error: second\n";
+        assert_eq!(
+            parse_messages(messages, "rect.vert"),
+            vec![
+                diagnostic("rect.vert", 1, "error: first"),
+                diagnostic("rect.vert", 2, "error: second"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,117 @@
+/// Compiles the GLSL shaders to SPIR-V, at runtime or from a build script,
+/// and reflects their interface into metadata the backends can build layouts from.
+pub mod build;
+pub mod compiler;
+pub mod library;
+pub mod preprocess;
+pub mod reflect;
+pub mod watch;
+
+use std::{fmt, path::Path};
+
+use rseed_core::prelude::*;
+use rseed_renderapi::descriptor::ShaderStages;
+
+pub use compiler::{CompiledShader, ShaderCompiler};
+pub use library::ShaderLibrary;
+pub use preprocess::{Preprocessed, Preprocessor, VariantKey};
+pub use reflect::Reflection;
+pub use watch::FileWatcher;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ShaderStage {
+    Vertex,
+    TessControl,
+    TessEvaluation,
+    Geometry,
+    Fragment,
+    Compute,
+}
+
+/// Extensions of the shader files, the same as the GL backend.
+const EXTENSIONS: [(&str, ShaderStage); 6] = [
+    ("vert", ShaderStage::Vertex),
+    ("tesc", ShaderStage::TessControl),
+    ("tese", ShaderStage::TessEvaluation),
+    ("geom", ShaderStage::Geometry),
+    ("frag", ShaderStage::Fragment),
+    ("comp", ShaderStage::Compute),
+];
+
+impl ShaderStage {
+    /// The stage of a shader file, from its extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?;
+        EXTENSIONS
+            .iter()
+            .find(|(e, _)| *e == ext)
+            .map(|(_, stage)| *stage)
+    }
+
+    pub fn extension(&self) -> &'static str {
+        EXTENSIONS
+            .iter()
+            .find(|(_, stage)| stage == self)
+            .map(|(e, _)| *e)
+            .unwrap()
+    }
+
+    pub fn stages(&self) -> ShaderStages {
+        match self {
+            ShaderStage::Vertex => ShaderStages::VERTEX,
+            ShaderStage::TessControl => ShaderStages::TESS_CONTROL,
+            ShaderStage::TessEvaluation => ShaderStages::TESS_EVALUATION,
+            ShaderStage::Geometry => ShaderStages::GEOMETRY,
+            ShaderStage::Fragment => ShaderStages::FRAGMENT,
+            ShaderStage::Compute => ShaderStages::COMPUTE,
+        }
+    }
+}
+
+/// A compiler message, the line starts at 1 and is 0 when unknown.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub file: String,
+    pub line: u32,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file, self.line, self.message)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ShaderError {
+    #[error(display = "Can't read the shader {}: {}", _0, _1)]
+    Io(String, std::io::Error),
+    #[error(display = "Can't guess the stage of the shader {} from its extension.", _0)]
+    UnknownStage(String),
+    #[error(display = "The shader compiler can't be created.")]
+    NoCompiler,
+    #[error(display = "{}", _0)]
+    Preprocess(Diagnostic),
+    #[error(display = "{}", _0)]
+    Compile(CompileErrors),
+    #[error(display = "Invalid SPIR-V: {}", _0)]
+    Reflect(reflect::ReflectError),
+}
+
+/// The errors of a compilation, at least one.
+#[derive(Clone, Debug)]
+pub struct CompileErrors(pub Vec<Diagnostic>);
+
+impl fmt::Display for CompileErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, diagnostic) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ShaderError>;
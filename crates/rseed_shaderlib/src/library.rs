@@ -0,0 +1,105 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    compiler::{CompiledShader, ShaderCompiler},
+    preprocess::{Preprocessed, Preprocessor, VariantKey},
+    watch::FileWatcher,
+    Result, ShaderError, ShaderStage,
+};
+
+/// The shaders of the asset root, preprocessed and compiled on first use.
+/// Each variant is cached by its file and enabled features.
+pub struct ShaderLibrary {
+    preprocessor: Preprocessor,
+    compiler: ShaderCompiler,
+    sources: HashMap<(PathBuf, VariantKey), Preprocessed>,
+    variants: HashMap<(PathBuf, VariantKey), CompiledShader>,
+    /// Every file read by the cached sources.
+    watcher: FileWatcher,
+}
+
+impl ShaderLibrary {
+    pub fn new(preprocessor: Preprocessor) -> Result<Self> {
+        Ok(Self {
+            preprocessor,
+            compiler: ShaderCompiler::new()?,
+            sources: HashMap::new(),
+            variants: HashMap::new(),
+            watcher: FileWatcher::new(),
+        })
+    }
+
+    pub fn preprocessor(&self) -> &Preprocessor {
+        &self.preprocessor
+    }
+
+    /// The preprocessed GLSL of a variant, for the backends compiling it themselves.
+    pub fn source(&mut self, path: &Path, key: &VariantKey) -> Result<&Preprocessed> {
+        let entry = (path.to_path_buf(), key.clone());
+        if !self.sources.contains_key(&entry) {
+            let source = self.preprocessor.process(path, key)?;
+            for file in source.files.iter() {
+                self.watcher.watch(file);
+            }
+            self.sources.insert(entry.clone(), source);
+        }
+        Ok(&self.sources[&entry])
+    }
+
+    /// The SPIR-V of a variant, the stage is guessed from the extension.
+    pub fn spirv(&mut self, path: &Path, key: &VariantKey) -> Result<&CompiledShader> {
+        let entry = (path.to_path_buf(), key.clone());
+        if !self.variants.contains_key(&entry) {
+            let stage = ShaderStage::from_path(path)
+                .ok_or_else(|| ShaderError::UnknownStage(path.to_string_lossy().into_owned()))?;
+            self.source(path, key)?;
+            let shader = self.compiler.compile_preprocessed(&self.sources[&entry], stage)?;
+            self.variants.insert(entry.clone(), shader);
+        }
+        Ok(&self.variants[&entry])
+    }
+
+    /// Forget the variants reading a file, directly or through an include.
+    /// Returns the shaders to reload.
+    pub fn invalidate(&mut self, file: &Path) -> Vec<PathBuf> {
+        let file = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+        let stale: Vec<(PathBuf, VariantKey)> = self
+            .sources
+            .iter()
+            .filter(|(_, source)| {
+                source
+                    .files
+                    .iter()
+                    .any(|f| f.canonicalize().ok().as_ref() == Some(&file))
+            })
+            .map(|(entry, _)| entry.clone())
+            .collect();
+        let mut shaders: Vec<PathBuf> = Vec::new();
+        for entry in stale {
+            self.sources.remove(&entry);
+            self.variants.remove(&entry);
+            if !shaders.contains(&entry.0) {
+                shaders.push(entry.0);
+            }
+        }
+        shaders
+    }
+
+    /// Watch a file read outside of the library, like the sources a backend
+    /// preprocesses itself. The files of the cached variants are always watched.
+    pub fn watch(&mut self, file: &Path) {
+        self.watcher.watch(file);
+    }
+
+    /// The files modified since the last call, the variants reading them are forgotten.
+    pub fn changed_files(&mut self) -> Vec<PathBuf> {
+        let changed = self.watcher.poll();
+        for file in changed.iter() {
+            self.invalidate(file);
+        }
+        changed
+    }
+}
@@ -0,0 +1,425 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use crate::{Diagnostic, Result, ShaderError};
+
+/// File name given to the lines of the injected defines.
+const DEFINES_FILE: &str = "<defines>";
+
+/// The enabled features of a variant, sorted so a set of features always gives the same key.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VariantKey(Vec<String>);
+
+impl VariantKey {
+    pub fn new<I, S>(features: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut features: Vec<String> = features.into_iter().map(Into::into).collect();
+        features.sort();
+        features.dedup();
+        Self(features)
+    }
+
+    pub fn features(&self) -> &[String] {
+        &self.0
+    }
+
+    pub fn contains(&self, feature: &str) -> bool {
+        self.0.iter().any(|f| f == feature)
+    }
+}
+
+/// Where each line of a preprocessed source comes from.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LineMap {
+    files: Vec<String>,
+    /// File index and line, starting at 1, of each output line.
+    lines: Vec<(usize, u32)>,
+}
+
+impl LineMap {
+    fn push(&mut self, file: &str, line: u32) {
+        let index = match self.files.iter().position(|f| f == file) {
+            Some(index) => index,
+            None => {
+                self.files.push(file.to_owned());
+                self.files.len() - 1
+            }
+        };
+        self.lines.push((index, line));
+    }
+
+    /// The file and line of a line of the output, both starting at 1.
+    pub fn locate(&self, line: u32) -> Option<(&str, u32)> {
+        let (file, line) = self.lines.get((line as usize).checked_sub(1)?)?;
+        Some((&self.files[*file], *line))
+    }
+
+    /// Point a compiler message at the original file.
+    pub fn remap(&self, diagnostic: &Diagnostic) -> Diagnostic {
+        match self.locate(diagnostic.line) {
+            Some((file, line)) => Diagnostic {
+                file: file.to_owned(),
+                line,
+                message: diagnostic.message.clone(),
+            },
+            None => diagnostic.clone(),
+        }
+    }
+}
+
+/// A source ready for the compilers, with everything needed to trace it back.
+#[derive(Clone, Debug)]
+pub struct Preprocessed {
+    /// Name of the main file, used by the compilers.
+    pub name: String,
+    pub source: String,
+    pub lines: LineMap,
+    /// Features declared with `#pragma features`, by the main file and its includes.
+    pub features: Vec<String>,
+    /// Every file read, the main one first.
+    pub files: Vec<PathBuf>,
+}
+
+/// Expands the `#include "file"` and `#include <file>` directives and injects the defines
+/// of the variant after the `#version` line.
+///
+/// Quoted includes are searched next to the including file then in the root,
+/// the others only in the root. Includes are expanded even inside disabled `#if` blocks,
+/// `#pragma once` keeps a file from being included twice.
+/// `#pragma features A B` declares the keywords the variants can enable, an enabled
+/// feature is defined to `1`.
+#[derive(Clone, Debug)]
+pub struct Preprocessor {
+    root: PathBuf,
+    defines: Vec<(String, String)>,
+}
+
+struct State {
+    out: Preprocessed,
+    /// Canonical paths of the files being expanded, to catch recursive includes.
+    stack: Vec<PathBuf>,
+    /// Canonical paths of the files with `#pragma once` already included.
+    once: HashSet<PathBuf>,
+}
+
+impl Preprocessor {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            root: root.into(),
+            defines: Vec::new(),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Define a macro in every shader.
+    pub fn define<N: Into<String>, V: Into<String>>(mut self, name: N, value: V) -> Self {
+        self.defines.push((name.into(), value.into()));
+        self
+    }
+
+    /// Process a file, the path is relative to the root.
+    pub fn process(&self, path: &Path, key: &VariantKey) -> Result<Preprocessed> {
+        let full = self.root.join(path);
+        let source = read(&full)?;
+        self.run(&source, &full, key)
+    }
+
+    /// Process a source without file, its includes are searched in the root.
+    pub fn process_source(&self, source: &str, name: &str, key: &VariantKey) -> Result<Preprocessed> {
+        self.run(source, &self.root.join(name), key)
+    }
+
+    fn run(&self, source: &str, path: &Path, key: &VariantKey) -> Result<Preprocessed> {
+        let mut state = State {
+            out: Preprocessed {
+                name: path.to_string_lossy().into_owned(),
+                source: String::with_capacity(source.len()),
+                lines: LineMap::default(),
+                features: Vec::new(),
+                files: Vec::new(),
+            },
+            stack: Vec::new(),
+            once: HashSet::new(),
+        };
+        self.expand(&mut state, source, path, Some(key))?;
+        if let Some(feature) = key.features().iter().find(|f| !state.out.features.contains(f)) {
+            return Err(ShaderError::Preprocess(Diagnostic {
+                file: state.out.name.clone(),
+                line: 0,
+                message: format!(
+                    "Unknown feature {}, the shader declares {:?}.",
+                    feature, state.out.features
+                ),
+            }));
+        }
+        Ok(state.out)
+    }
+
+    /// `key` is only given for the main file, the defines go after its `#version`.
+    fn expand(&self, state: &mut State, source: &str, path: &Path, mut key: Option<&VariantKey>) -> Result<()> {
+        let file = path.to_string_lossy().into_owned();
+        state.stack.push(canonical(path));
+        state.out.files.push(path.to_path_buf());
+        let error = |line: usize, message: String| {
+            ShaderError::Preprocess(Diagnostic {
+                file: file.clone(),
+                line: line as u32 + 1,
+                message,
+            })
+        };
+        // Without `#version` the defines come first.
+        if !source.lines().any(|l| l.trim_start().starts_with("#version")) {
+            if let Some(key) = key.take() {
+                self.inject(state, key);
+            }
+        }
+        for (number, line) in source.lines().enumerate() {
+            let directive = line.trim_start();
+            if let Some(rest) = directive.strip_prefix("#include") {
+                let include = self
+                    .resolve(rest.trim(), path)
+                    .ok_or_else(|| error(number, format!("Can't find the include {}.", rest.trim())))?;
+                if state.stack.contains(&canonical(&include)) {
+                    return Err(error(number, format!("{} includes itself.", include.display())));
+                }
+                if state.once.contains(&canonical(&include)) {
+                    continue;
+                }
+                let source = read(&include)?;
+                self.expand(state, &source, &include, None)?;
+                continue;
+            }
+            if directive.starts_with("#pragma") {
+                let mut words = directive.split_whitespace().skip(1);
+                match words.next() {
+                    Some("once") => {
+                        state.once.insert(canonical(path));
+                        continue;
+                    }
+                    Some("features") => {
+                        for feature in words {
+                            if !state.out.features.iter().any(|f| f == feature) {
+                                state.out.features.push(feature.to_owned());
+                            }
+                        }
+                        continue;
+                    }
+                    _ => (),
+                }
+            }
+            state.out.source.push_str(line);
+            state.out.source.push('\n');
+            state.out.lines.push(&file, number as u32 + 1);
+            if directive.starts_with("#version") {
+                if let Some(key) = key.take() {
+                    self.inject(state, key);
+                }
+            }
+        }
+        state.stack.pop();
+        Ok(())
+    }
+
+    fn inject(&self, state: &mut State, key: &VariantKey) {
+        let defines = self
+            .defines
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .chain(key.features().iter().map(|f| (f.as_str(), "1")));
+        for (i, (name, value)) in defines.enumerate() {
+            state.out.source.push_str(&format!("#define {} {}\n", name, value));
+            state.out.lines.push(DEFINES_FILE, i as u32 + 1);
+        }
+    }
+
+    /// `"file"` or `<file>`.
+    fn resolve(&self, target: &str, from: &Path) -> Option<PathBuf> {
+        let (name, local) = if target.len() > 2 && target.starts_with('"') && target.ends_with('"') {
+            (&target[1..target.len() - 1], true)
+        } else if target.len() > 2 && target.starts_with('<') && target.ends_with('>') {
+            (&target[1..target.len() - 1], false)
+        } else {
+            return None;
+        };
+        let next_to = from.parent().filter(|_| local).map(|dir| dir.join(name));
+        next_to
+            .into_iter()
+            .chain(std::iter::once(self.root.join(name)))
+            .find(|p| p.is_file())
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn read(path: &Path) -> Result<String> {
+    std::fs::read_to_string(path).map_err(|e| ShaderError::Io(path.to_string_lossy().into_owned(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A directory of shaders removed with the test.
+    struct Shaders(PathBuf);
+
+    impl Shaders {
+        fn new(test: &str, files: &[(&str, &str)]) -> Self {
+            let root = std::env::temp_dir().join(format!("rseed_preprocess_{}_{}", test, std::process::id()));
+            for (name, source) in files {
+                let path = root.join(name);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(path, source).unwrap();
+            }
+            Self(root)
+        }
+
+        fn path(&self, name: &str) -> String {
+            self.0.join(name).to_string_lossy().into_owned()
+        }
+
+        fn process(&self, name: &str) -> Result<Preprocessed> {
+            Preprocessor::new(&self.0).process(Path::new(name), &VariantKey::default())
+        }
+    }
+
+    impl Drop for Shaders {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn preprocess_error(result: Result<Preprocessed>) -> Diagnostic {
+        match result {
+            Err(ShaderError::Preprocess(diagnostic)) => diagnostic,
+            Err(e) => panic!("Unexpected error {}", e),
+            Ok(_) => panic!("The preprocessing should fail."),
+        }
+    }
+
+    #[test]
+    fn includes() {
+        let shaders = Shaders::new(
+            "includes",
+            &[
+                ("main.frag", "#version 450\n#include \"lib/a.glsl\"\n#include <common.glsl>\nvoid main() {}\n"),
+                // Quoted includes look next to the file first, then in the root.
+                ("lib/a.glsl", "#include \"b.glsl\"\n#include \"common.glsl\"\nfloat a;\n"),
+                ("lib/b.glsl", "float b;\n"),
+                ("common.glsl", "#pragma once\nfloat common;\n"),
+            ],
+        );
+        let out = shaders.process("main.frag").unwrap();
+        assert_eq!(out.source, "#version 450\nfloat b;\nfloat common;\nfloat a;\nvoid main() {}\n");
+        assert_eq!(out.name, shaders.path("main.frag"));
+        assert_eq!(out.files.len(), 4);
+        assert_eq!(out.files[0], shaders.0.join("main.frag"));
+
+        // Each output line points back at its file.
+        let lines = &out.lines;
+        assert_eq!(lines.locate(1), Some((shaders.path("main.frag").as_str(), 1)));
+        assert_eq!(lines.locate(2), Some((shaders.path("lib/b.glsl").as_str(), 1)));
+        assert_eq!(lines.locate(3), Some((shaders.path("common.glsl").as_str(), 2)));
+        assert_eq!(lines.locate(4), Some((shaders.path("lib/a.glsl").as_str(), 3)));
+        assert_eq!(lines.locate(5), Some((shaders.path("main.frag").as_str(), 4)));
+        assert_eq!(lines.locate(0), None);
+        assert_eq!(lines.locate(6), None);
+
+        let message = |file: &str, line| Diagnostic {
+            file: file.to_owned(),
+            line,
+            message: String::from("error: 'a' : redefinition"),
+        };
+        assert_eq!(
+            lines.remap(&message(&out.name, 4)),
+            message(&shaders.path("lib/a.glsl"), 3)
+        );
+        assert_eq!(lines.remap(&message(&out.name, 9)), message(&out.name, 9));
+    }
+
+    #[test]
+    fn missing_include() {
+        // Angle includes are only searched in the root.
+        let shaders = Shaders::new(
+            "missing",
+            &[
+                ("main.frag", "#version 450\n#include \"lib/a.glsl\"\n"),
+                ("lib/a.glsl", "\n#include <b.glsl>\n"),
+                ("lib/b.glsl", ""),
+            ],
+        );
+        let error = preprocess_error(shaders.process("main.frag"));
+        assert_eq!(error.file, shaders.path("lib/a.glsl"));
+        assert_eq!(error.line, 2);
+        assert!(error.message.contains("<b.glsl>"));
+    }
+
+    #[test]
+    fn pragma_once() {
+        let shaders = Shaders::new(
+            "once",
+            &[
+                (
+                    "main.frag",
+                    "#include \"once.glsl\"\n#include \"twice.glsl\"\n#include \"once.glsl\"\n#include \"twice.glsl\"\n",
+                ),
+                ("once.glsl", "#pragma once\nfloat once;\n"),
+                ("twice.glsl", "float twice;\n"),
+            ],
+        );
+        let out = shaders.process("main.frag").unwrap();
+        assert_eq!(out.source, "float once;\nfloat twice;\nfloat twice;\n");
+    }
+
+    #[test]
+    fn recursive_include() {
+        let shaders = Shaders::new(
+            "recursion",
+            &[
+                ("main.frag", "#include \"a.glsl\"\n"),
+                ("a.glsl", "#include \"b.glsl\"\n"),
+                ("b.glsl", "float b;\n#include \"a.glsl\"\n"),
+            ],
+        );
+        let error = preprocess_error(shaders.process("main.frag"));
+        assert_eq!(error.file, shaders.path("b.glsl"));
+        assert_eq!(error.line, 2);
+        assert!(error.message.contains("includes itself"));
+    }
+
+    #[test]
+    fn defines_after_version() {
+        let shaders = Shaders::new("defines", &[]);
+        let preprocessor = Preprocessor::new(&shaders.0).define("MAX_LIGHTS", "4");
+        let key = VariantKey::new(vec!["SHADOWS"]);
+        let source = "// Lit shader\n#version 450\n#pragma features SHADOWS FOG\nvoid main() {}\n";
+        let out = preprocessor.process_source(source, "lit.frag", &key).unwrap();
+        assert_eq!(
+            out.source,
+            "// Lit shader\n#version 450\n#define MAX_LIGHTS 4\n#define SHADOWS 1\nvoid main() {}\n"
+        );
+        assert_eq!(out.features, vec![String::from("SHADOWS"), String::from("FOG")]);
+        assert_eq!(out.lines.locate(3), Some((DEFINES_FILE, 1)));
+        assert_eq!(out.lines.locate(4), Some((DEFINES_FILE, 2)));
+        assert_eq!(out.lines.locate(5), Some((shaders.path("lit.frag").as_str(), 4)));
+
+        // Without `#version` the defines come first.
+        let out = preprocessor
+            .process_source("#pragma features SHADOWS\nvoid main() {}\n", "lit.frag", &key)
+            .unwrap();
+        assert_eq!(out.source, "#define MAX_LIGHTS 4\n#define SHADOWS 1\nvoid main() {}\n");
+
+        let unknown = VariantKey::new(vec!["SHADOWS", "BLOOM"]);
+        let error = preprocess_error(preprocessor.process_source(source, "lit.frag", &unknown));
+        assert!(error.message.contains("BLOOM"));
+    }
+}
@@ -0,0 +1,583 @@
+use rseed_core::prelude::*;
+
+use std::collections::HashMap;
+
+use rseed_renderapi::{
+    buffer::{VertexFormat, VertexLayout},
+    descriptor::{BindingDesc, BindingType, ShaderStages},
+};
+
+use crate::ShaderStage;
+
+const MAGIC: u32 = 0x0723_0203;
+const HEADER_WORDS: usize = 5;
+
+/// The few opcodes describing the interface, the function bodies are skipped.
+mod op {
+    pub const NAME: u32 = 5;
+    pub const MEMBER_NAME: u32 = 6;
+    pub const TYPE_BOOL: u32 = 20;
+    pub const TYPE_INT: u32 = 21;
+    pub const TYPE_FLOAT: u32 = 22;
+    pub const TYPE_VECTOR: u32 = 23;
+    pub const TYPE_MATRIX: u32 = 24;
+    pub const TYPE_IMAGE: u32 = 25;
+    pub const TYPE_SAMPLER: u32 = 26;
+    pub const TYPE_SAMPLED_IMAGE: u32 = 27;
+    pub const TYPE_ARRAY: u32 = 28;
+    pub const TYPE_RUNTIME_ARRAY: u32 = 29;
+    pub const TYPE_STRUCT: u32 = 30;
+    pub const TYPE_POINTER: u32 = 32;
+    pub const CONSTANT: u32 = 43;
+    pub const VARIABLE: u32 = 59;
+    pub const DECORATE: u32 = 71;
+    pub const MEMBER_DECORATE: u32 = 72;
+}
+
+mod decoration {
+    pub const BLOCK: u32 = 2;
+    pub const BUFFER_BLOCK: u32 = 3;
+    pub const ARRAY_STRIDE: u32 = 6;
+    pub const MATRIX_STRIDE: u32 = 7;
+    pub const BUILT_IN: u32 = 11;
+    pub const LOCATION: u32 = 30;
+    pub const BINDING: u32 = 33;
+    pub const DESCRIPTOR_SET: u32 = 34;
+    pub const OFFSET: u32 = 35;
+}
+
+mod storage {
+    pub const UNIFORM_CONSTANT: u32 = 0;
+    pub const INPUT: u32 = 1;
+    pub const UNIFORM: u32 = 2;
+    pub const OUTPUT: u32 = 3;
+    pub const PUSH_CONSTANT: u32 = 9;
+    pub const STORAGE_BUFFER: u32 = 12;
+}
+
+#[derive(Debug, Error)]
+pub enum ReflectError {
+    #[error(display = "The module does not start with the SPIR-V magic number.")]
+    BadMagic,
+    #[error(display = "The instruction at word {} is cut.", _0)]
+    Truncated(usize),
+    #[error(display = "The id {} is not a known type.", _0)]
+    UnknownType(u32),
+}
+
+pub type Result<T> = std::result::Result<T, ReflectError>;
+
+/// Type of a shader variable, as far as the interface goes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DataType {
+    Bool,
+    Int { width: u32, signed: bool },
+    Float { width: u32 },
+    Vector { component: Box<DataType>, count: u32 },
+    Matrix { column: Box<DataType>, columns: u32 },
+    /// `len` is 0 for the arrays sized at runtime.
+    Array { element: Box<DataType>, len: u32, stride: u32 },
+    Struct { name: String, members: Vec<Member> },
+    Image,
+    Sampler,
+    SampledImage,
+}
+
+/// A member of a block or struct, placed by the layout of the block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Member {
+    pub name: String,
+    pub offset: u32,
+    pub size: u32,
+    pub ty: DataType,
+}
+
+/// The layout of a uniform, storage or push constant block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Block {
+    /// Name of the block type.
+    pub name: String,
+    /// Size in bytes, the size of the fixed part for blocks ending with a runtime array.
+    pub size: u32,
+    pub members: Vec<Member>,
+}
+
+impl Block {
+    pub fn member(&self, name: &str) -> Option<&Member> {
+        self.members.iter().find(|m| m.name == name)
+    }
+}
+
+/// An input or output of a stage.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InterfaceVar {
+    pub location: u32,
+    pub name: String,
+    pub ty: DataType,
+}
+
+impl InterfaceVar {
+    /// The format of a vertex attribute feeding this input, `None` for matrices and structs.
+    pub fn vertex_format(&self) -> Option<VertexFormat> {
+        let (component, count) = match &self.ty {
+            DataType::Vector { component, count } => (component.as_ref(), *count),
+            ty => (ty, 1),
+        };
+        let formats = match component {
+            DataType::Float { width: 32 } => [
+                VertexFormat::Float,
+                VertexFormat::Float2,
+                VertexFormat::Float3,
+                VertexFormat::Float4,
+            ],
+            DataType::Int { width: 32, signed: true } => [
+                VertexFormat::Int,
+                VertexFormat::Int2,
+                VertexFormat::Int3,
+                VertexFormat::Int4,
+            ],
+            DataType::Int { width: 32, signed: false } => [
+                VertexFormat::UInt,
+                VertexFormat::UInt2,
+                VertexFormat::UInt3,
+                VertexFormat::UInt4,
+            ],
+            _ => return None,
+        };
+        formats.get(count as usize - 1).copied()
+    }
+}
+
+/// A resource of a descriptor set.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResourceBinding {
+    pub set: u32,
+    /// Name of the variable, or of the block when the instance is anonymous.
+    pub name: String,
+    /// The count is 0 for the arrays sized at runtime, like the bindless textures.
+    pub desc: BindingDesc,
+    /// Layout of the uniform and storage buffers.
+    pub block: Option<Block>,
+}
+
+/// The interface of a shader read back from its SPIR-V.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reflection {
+    pub stages: ShaderStages,
+    /// Sorted by location, the built-in variables are left out.
+    pub inputs: Vec<InterfaceVar>,
+    pub outputs: Vec<InterfaceVar>,
+    /// Sorted by set and binding.
+    pub bindings: Vec<ResourceBinding>,
+    pub push_constants: Option<Block>,
+}
+
+impl Reflection {
+    pub fn parse(spirv: &[u32], stage: ShaderStage) -> Result<Self> {
+        let module = Module::parse(spirv)?;
+        module.reflect(stage.stages())
+    }
+
+    /// Add the resources of another stage of the same program. The inputs are those
+    /// of the first stage and the outputs those of the last.
+    pub fn merge(&mut self, other: &Reflection) {
+        self.stages = self.stages | other.stages;
+        if self.inputs.is_empty() {
+            self.inputs = other.inputs.clone();
+        }
+        self.outputs = other.outputs.clone();
+        for binding in other.bindings.iter() {
+            match self
+                .bindings
+                .iter_mut()
+                .find(|b| b.set == binding.set && b.desc.binding == binding.desc.binding)
+            {
+                Some(b) => b.desc.stages = b.desc.stages | binding.desc.stages,
+                None => self.bindings.push(binding.clone()),
+            }
+        }
+        self.bindings.sort_by_key(|b| (b.set, b.desc.binding));
+        if let Some(theirs) = &other.push_constants {
+            let larger = match &self.push_constants {
+                Some(ours) => theirs.size > ours.size,
+                None => true,
+            };
+            if larger {
+                self.push_constants = Some(theirs.clone());
+            }
+        }
+    }
+
+    /// The bindings grouped by set, a set without binding stays empty.
+    pub fn descriptor_sets(&self) -> Vec<Vec<BindingDesc>> {
+        let count = self.bindings.iter().map(|b| b.set + 1).max().unwrap_or(0);
+        let mut sets = vec![Vec::new(); count as usize];
+        for binding in self.bindings.iter() {
+            sets[binding.set as usize].push(binding.desc);
+        }
+        sets
+    }
+
+    /// A tightly packed layout feeding every input, `None` if one can't come from a buffer.
+    pub fn vertex_layout(&self) -> Option<VertexLayout> {
+        let mut layout = VertexLayout::new();
+        for input in self.inputs.iter() {
+            layout = layout.push_at(input.location, input.vertex_format()?);
+        }
+        Some(layout)
+    }
+
+    pub fn binding(&self, name: &str) -> Option<&ResourceBinding> {
+        self.bindings.iter().find(|b| b.name == name)
+    }
+
+    /// Find a member of the uniform blocks by name.
+    pub fn uniform(&self, name: &str) -> Option<(&ResourceBinding, &Member)> {
+        self.bindings
+            .iter()
+            .filter(|b| b.desc.ty == BindingType::UniformBuffer)
+            .find_map(|b| Some((b, b.block.as_ref()?.member(name)?)))
+    }
+}
+
+#[derive(Clone, Debug)]
+enum RawType {
+    Bool,
+    Int(u32, bool),
+    Float(u32),
+    Vector(u32, u32),
+    Matrix(u32, u32),
+    Image,
+    Sampler,
+    SampledImage,
+    /// Element type and id of the length constant.
+    Array(u32, u32),
+    RuntimeArray(u32),
+    Struct(Vec<u32>),
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+struct Decorations {
+    block: bool,
+    buffer_block: bool,
+    built_in: bool,
+    location: Option<u32>,
+    binding: Option<u32>,
+    set: Option<u32>,
+    offset: Option<u32>,
+    array_stride: Option<u32>,
+    matrix_stride: Option<u32>,
+}
+
+impl Decorations {
+    fn add(&mut self, decoration: u32, operands: &[u32]) {
+        let value = operands.first().copied();
+        match decoration {
+            decoration::BLOCK => self.block = true,
+            decoration::BUFFER_BLOCK => self.buffer_block = true,
+            decoration::BUILT_IN => self.built_in = true,
+            decoration::LOCATION => self.location = value,
+            decoration::BINDING => self.binding = value,
+            decoration::DESCRIPTOR_SET => self.set = value,
+            decoration::OFFSET => self.offset = value,
+            decoration::ARRAY_STRIDE => self.array_stride = value,
+            decoration::MATRIX_STRIDE => self.matrix_stride = value,
+            _ => (),
+        }
+    }
+}
+
+/// The declarations of a module, indexed by id.
+#[derive(Default)]
+struct Module {
+    names: HashMap<u32, String>,
+    member_names: HashMap<(u32, u32), String>,
+    decorations: HashMap<u32, Decorations>,
+    member_decorations: HashMap<(u32, u32), Decorations>,
+    types: HashMap<u32, RawType>,
+    constants: HashMap<u32, u32>,
+    /// Storage class and pointee of the pointer types.
+    pointers: HashMap<u32, (u32, u32)>,
+    /// Id, pointer type and storage class of the global variables.
+    variables: Vec<(u32, u32, u32)>,
+}
+
+/// Strings are nul terminated and packed in little endian words.
+fn read_string(words: &[u32]) -> String {
+    let bytes: Vec<u8> = words
+        .iter()
+        .flat_map(|w| w.to_le_bytes().to_vec())
+        .take_while(|b| *b != 0)
+        .collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+impl Module {
+    fn parse(spirv: &[u32]) -> Result<Self> {
+        if spirv.first() != Some(&MAGIC) || spirv.len() < HEADER_WORDS {
+            return Err(ReflectError::BadMagic);
+        }
+        let mut module = Self::default();
+        let mut position = HEADER_WORDS;
+        while position < spirv.len() {
+            let count = (spirv[position] >> 16) as usize;
+            let opcode = spirv[position] & 0xffff;
+            if count == 0 || position + count > spirv.len() {
+                return Err(ReflectError::Truncated(position));
+            }
+            let operands = &spirv[position + 1..position + count];
+            module.add(opcode, operands);
+            position += count;
+        }
+        Ok(module)
+    }
+
+    fn add(&mut self, opcode: u32, operands: &[u32]) {
+        let (id, rest) = match operands.split_first() {
+            Some(split) => split,
+            None => return,
+        };
+        let id = *id;
+        // Operands after the id, the instructions missing some are ignored.
+        let arg = |i: usize| rest.get(i).copied();
+        match (opcode, arg(0), arg(1)) {
+            (op::NAME, _, _) => {
+                self.names.insert(id, read_string(rest));
+            }
+            (op::MEMBER_NAME, Some(member), _) => {
+                self.member_names.insert((id, member), read_string(&rest[1..]));
+            }
+            (op::DECORATE, Some(decoration), _) => self
+                .decorations
+                .entry(id)
+                .or_default()
+                .add(decoration, &rest[1..]),
+            (op::MEMBER_DECORATE, Some(member), Some(decoration)) => self
+                .member_decorations
+                .entry((id, member))
+                .or_default()
+                .add(decoration, &rest[2..]),
+            (op::TYPE_BOOL, _, _) => self.add_type(id, RawType::Bool),
+            (op::TYPE_INT, Some(width), Some(signed)) => self.add_type(id, RawType::Int(width, signed != 0)),
+            (op::TYPE_FLOAT, Some(width), _) => self.add_type(id, RawType::Float(width)),
+            (op::TYPE_VECTOR, Some(component), Some(count)) => {
+                self.add_type(id, RawType::Vector(component, count))
+            }
+            (op::TYPE_MATRIX, Some(column), Some(count)) => self.add_type(id, RawType::Matrix(column, count)),
+            (op::TYPE_IMAGE, _, _) => self.add_type(id, RawType::Image),
+            (op::TYPE_SAMPLER, _, _) => self.add_type(id, RawType::Sampler),
+            (op::TYPE_SAMPLED_IMAGE, _, _) => self.add_type(id, RawType::SampledImage),
+            (op::TYPE_ARRAY, Some(element), Some(len)) => self.add_type(id, RawType::Array(element, len)),
+            (op::TYPE_RUNTIME_ARRAY, Some(element), _) => self.add_type(id, RawType::RuntimeArray(element)),
+            (op::TYPE_STRUCT, _, _) => self.add_type(id, RawType::Struct(rest.to_vec())),
+            (op::TYPE_POINTER, Some(class), Some(ty)) => {
+                self.pointers.insert(id, (class, ty));
+            }
+            // The result type comes before the result id for these two.
+            (op::CONSTANT, Some(result), Some(value)) => {
+                self.constants.insert(result, value);
+            }
+            (op::VARIABLE, Some(result), Some(class)) => self.variables.push((result, id, class)),
+            _ => (),
+        }
+    }
+
+    fn add_type(&mut self, id: u32, ty: RawType) {
+        self.types.insert(id, ty);
+    }
+
+    fn raw_type(&self, id: u32) -> Result<&RawType> {
+        self.types.get(&id).ok_or(ReflectError::UnknownType(id))
+    }
+
+    fn decorations(&self, id: u32) -> Decorations {
+        self.decorations.get(&id).copied().unwrap_or_default()
+    }
+
+    fn name(&self, id: u32) -> String {
+        self.names.get(&id).cloned().unwrap_or_default()
+    }
+
+    fn data_type(&self, id: u32) -> Result<DataType> {
+        Ok(match self.raw_type(id)? {
+            RawType::Bool => DataType::Bool,
+            RawType::Int(width, signed) => DataType::Int {
+                width: *width,
+                signed: *signed,
+            },
+            RawType::Float(width) => DataType::Float { width: *width },
+            RawType::Vector(component, count) => DataType::Vector {
+                component: Box::new(self.data_type(*component)?),
+                count: *count,
+            },
+            RawType::Matrix(column, columns) => DataType::Matrix {
+                column: Box::new(self.data_type(*column)?),
+                columns: *columns,
+            },
+            RawType::Image => DataType::Image,
+            RawType::Sampler => DataType::Sampler,
+            RawType::SampledImage => DataType::SampledImage,
+            RawType::Array(element, _) | RawType::RuntimeArray(element) => DataType::Array {
+                element: Box::new(self.data_type(*element)?),
+                len: self.array_len(id),
+                stride: self.decorations(id).array_stride.unwrap_or(0),
+            },
+            RawType::Struct(_) => DataType::Struct {
+                name: self.name(id),
+                members: self.members(id)?,
+            },
+        })
+    }
+
+    fn array_len(&self, id: u32) -> u32 {
+        match self.types.get(&id) {
+            Some(RawType::Array(_, len)) => self.constants.get(len).copied().unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    /// Size in bytes inside a block, the matrix stride is a decoration of the member.
+    fn size_of(&self, id: u32, matrix_stride: Option<u32>) -> Result<u32> {
+        Ok(match self.raw_type(id)? {
+            RawType::Bool => 4,
+            RawType::Int(width, _) | RawType::Float(width) => width / 8,
+            RawType::Vector(component, count) => self.size_of(*component, None)? * count,
+            RawType::Matrix(column, columns) => {
+                let stride = match matrix_stride {
+                    Some(stride) => stride,
+                    None => self.size_of(*column, None)?,
+                };
+                stride * columns
+            }
+            RawType::Array(element, _) => {
+                let stride = match self.decorations(id).array_stride {
+                    Some(stride) => stride,
+                    None => self.size_of(*element, matrix_stride)?,
+                };
+                stride * self.array_len(id)
+            }
+            RawType::Struct(_) => self
+                .members(id)?
+                .iter()
+                .map(|m| m.offset + m.size)
+                .max()
+                .unwrap_or(0),
+            RawType::RuntimeArray(_) | RawType::Image | RawType::Sampler | RawType::SampledImage => 0,
+        })
+    }
+
+    fn members(&self, id: u32) -> Result<Vec<Member>> {
+        let members = match self.raw_type(id)? {
+            RawType::Struct(members) => members,
+            _ => return Ok(Vec::new()),
+        };
+        members
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| {
+                let key = (id, i as u32);
+                let decorations = self.member_decorations.get(&key).copied().unwrap_or_default();
+                Ok(Member {
+                    name: self.member_names.get(&key).cloned().unwrap_or_default(),
+                    offset: decorations.offset.unwrap_or(0),
+                    size: self.size_of(*ty, decorations.matrix_stride)?,
+                    ty: self.data_type(*ty)?,
+                })
+            })
+            .collect()
+    }
+
+    fn block(&self, id: u32) -> Result<Block> {
+        Ok(Block {
+            name: self.name(id),
+            size: self.size_of(id, None)?,
+            members: self.members(id)?,
+        })
+    }
+
+    /// Built-in variables or blocks of built-ins, like `gl_PerVertex`.
+    fn is_built_in(&self, variable: u32, ty: u32) -> bool {
+        self.decorations(variable).built_in
+            || self
+                .member_decorations
+                .iter()
+                .any(|((s, _), d)| *s == ty && d.built_in)
+    }
+
+    fn reflect(&self, stages: ShaderStages) -> Result<Reflection> {
+        let mut reflection = Reflection {
+            stages,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            bindings: Vec::new(),
+            push_constants: None,
+        };
+        for (id, pointer, class) in self.variables.iter().copied() {
+            let ty = match self.pointers.get(&pointer) {
+                Some((_, ty)) => *ty,
+                None => continue,
+            };
+            let decorations = self.decorations(id);
+            match class {
+                storage::INPUT | storage::OUTPUT => {
+                    let location = match decorations.location {
+                        Some(location) if !self.is_built_in(id, ty) => location,
+                        _ => continue,
+                    };
+                    let var = InterfaceVar {
+                        location,
+                        name: self.name(id),
+                        ty: self.data_type(ty)?,
+                    };
+                    if class == storage::INPUT {
+                        reflection.inputs.push(var);
+                    } else {
+                        reflection.outputs.push(var);
+                    }
+                }
+                storage::UNIFORM_CONSTANT | storage::UNIFORM | storage::STORAGE_BUFFER => {
+                    let (base, count) = match self.raw_type(ty)? {
+                        RawType::Array(element, _) => (*element, self.array_len(ty)),
+                        RawType::RuntimeArray(element) => (*element, 0),
+                        _ => (ty, 1),
+                    };
+                    let block_decorations = self.decorations(base);
+                    let (binding_type, block) = match (class, self.raw_type(base)?) {
+                        (storage::UNIFORM_CONSTANT, RawType::Image) => (BindingType::Texture, None),
+                        (storage::UNIFORM_CONSTANT, RawType::Sampler) => (BindingType::Sampler, None),
+                        (storage::UNIFORM_CONSTANT, RawType::SampledImage) => {
+                            (BindingType::TextureSampler, None)
+                        }
+                        (storage::UNIFORM, RawType::Struct(_)) if block_decorations.buffer_block => {
+                            (BindingType::StorageBuffer, Some(self.block(base)?))
+                        }
+                        (storage::UNIFORM, RawType::Struct(_)) => {
+                            (BindingType::UniformBuffer, Some(self.block(base)?))
+                        }
+                        (storage::STORAGE_BUFFER, RawType::Struct(_)) => {
+                            (BindingType::StorageBuffer, Some(self.block(base)?))
+                        }
+                        _ => continue,
+                    };
+                    let name = match self.name(id) {
+                        name if name.is_empty() => self.name(base),
+                        name => name,
+                    };
+                    reflection.bindings.push(ResourceBinding {
+                        set: decorations.set.unwrap_or(0),
+                        name,
+                        desc: BindingDesc::new(decorations.binding.unwrap_or(0), binding_type, stages)
+                            .with_count(count),
+                        block,
+                    });
+                }
+                storage::PUSH_CONSTANT => reflection.push_constants = Some(self.block(ty)?),
+                _ => (),
+            }
+        }
+        reflection.inputs.sort_by_key(|v| v.location);
+        reflection.outputs.sort_by_key(|v| v.location);
+        reflection.bindings.sort_by_key(|b| (b.set, b.desc.binding));
+        Ok(reflection)
+    }
+}
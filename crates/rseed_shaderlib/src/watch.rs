@@ -0,0 +1,52 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Polls the modification time of files, for the hot reload during development.
+/// Polling spares a dependency on the notification API of each platform,
+/// checking a few shaders a couple of times per second costs nothing.
+#[derive(Clone, Debug, Default)]
+pub struct FileWatcher {
+    files: HashMap<PathBuf, Option<SystemTime>>,
+}
+
+impl FileWatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start watching a file, its current version is the reference.
+    pub fn watch(&mut self, path: &Path) {
+        if !self.files.contains_key(path) {
+            self.files.insert(path.to_path_buf(), modified(path));
+        }
+    }
+
+    pub fn unwatch(&mut self, path: &Path) {
+        self.files.remove(path);
+    }
+
+    pub fn is_watched(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+
+    /// The files modified since the last poll.
+    /// A file missing while an editor saves it is reported once it is back.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        for (path, last) in self.files.iter_mut() {
+            let now = modified(path);
+            if now.is_some() && now != *last {
+                *last = now;
+                changed.push(path.clone());
+            }
+        }
+        changed
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -1,16 +1,29 @@
 use rseed_core::prelude::*;
 
+pub mod buffer;
 pub mod debug;
+pub mod descriptor;
 pub mod device;
+pub mod frame;
 pub mod library;
+pub mod memory;
+pub mod physical;
 pub mod pipeline;
+pub mod resource;
 pub mod surface;
 pub mod swapchain;
+pub mod texture;
+pub mod transfer;
 pub mod window;
 
 use self::{
-    device::{Device, DeviceError},
+    descriptor::{DescriptorAllocator, DescriptorLayoutCache},
+    device::{Device, DeviceError, DeviceV1_0},
+    frame::{FrameError, FrameStatus, Frames},
     library::*,
+    memory::MemoryStats,
+    pipeline::{Pipeline, PipelineCache, PipelineDesc, PipelineError},
+    resource::{Resources, SharedResources},
     surface::{Surface, SurfaceError},
     swapchain::{Swapchain, SwapchainError},
     window::HasRawWindowHandle,
@@ -19,6 +32,33 @@ use self::{
 use rseed_core::{
     utils::Version,
 };
+use rseed_renderapi::{
+    buffer::{BufferUsage, IndexBufferT, IndexData, UniformBufferT, VertexBufferT, VertexLayout},
+    context::{self as api, ContextT, ResourceError},
+    handle::{BufferHandle, PipelineHandle, SamplerHandle, TextureHandle},
+    pipeline as api_pipeline,
+    renderer::Access,
+    settings::RenderSettings,
+    texture::{SamplerDesc, SamplerT, TextureDesc, TextureT},
+};
+
+use rseed_shaderlib::{CompiledShader, Preprocessor, ShaderLibrary, ShaderStage, VariantKey};
+
+use ash::{self, vk};
+use std::{
+    cell::{Cell, Ref, RefCell},
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// The stages a graphics pipeline is built from, in pipeline order.
+const GRAPHICS_STAGES: [ShaderStage; 5] = [
+    ShaderStage::Vertex,
+    ShaderStage::TessControl,
+    ShaderStage::TessEvaluation,
+    ShaderStage::Geometry,
+    ShaderStage::Fragment,
+];
 
 #[derive(Debug, Error)]
 pub enum ContextError {
@@ -30,15 +70,39 @@ pub enum ContextError {
     Device(DeviceError),
     #[error(display = "There was an error during swapchain creation : {:?}.", _0)]
     Swapchain(SwapchainError),
+    #[error(display = "There was an error with a pipeline : {}.", _0)]
+    Pipeline(PipelineError),
+    #[error(display = "There was an error while drawing a frame : {}.", _0)]
+    Frame(FrameError),
+    #[error(display = "There was an error while creating the resources : {}.", _0)]
+    Resource(ResourceError),
 }
 
 pub type Result<T> = std::result::Result<T, ContextError>;
 
+/// The fields holding a `Drop` are declared in reverse creation order,
+/// the device must be destroyed before the surface and the instance.
 pub struct VkContext {
-    library: Library,
-    surface: Surface,
+    swapchain: RefCell<Swapchain>,
+    frames: RefCell<Frames>,
+    pipeline_cache: PipelineCache,
+    /// Indexed by the pipeline handles, destroyed pipelines leave a hole.
+    pipelines: RefCell<Vec<Option<Pipeline>>>,
+    /// Set layouts of the pipelines.
+    layouts: RefCell<DescriptorLayoutCache>,
+    /// Compiles the shaders of the pipelines, created with the first one.
+    shaders: RefCell<Option<ShaderLibrary>>,
+    shader_root: PathBuf,
+    /// The pipelines built from the shader root, with the files their shaders read.
+    sources: RefCell<HashMap<PipelineHandle, (api_pipeline::PipelineDesc, Vec<PathBuf>)>>,
+    resources: SharedResources,
+    /// Size of the window, nothing is drawn while it is minimized.
+    size: Cell<vk::Extent2D>,
+    /// The last recreation of the swapchain failed, it is tried again before the next frame.
+    stale: Cell<bool>,
     device: Device,
-    swapchain: Swapchain,
+    surface: Surface,
+    library: Library,
 }
 
 impl VkContext {
@@ -46,7 +110,10 @@ impl VkContext {
         app_name: String,
         app_version: Version,
         window_handle: &dyn HasRawWindowHandle,
+        (width, height): (u32, u32),
+        settings: &RenderSettings,
     ) -> Result<Self> {
+        let cache_path = std::env::temp_dir().join(format!("{}.pipeline_cache", app_name));
         let library = Library::init(app_name, app_version, window_handle)
             .map_err(|e| ContextError::Library(e))?;
 
@@ -54,22 +121,353 @@ impl VkContext {
             Surface::init(&library, window_handle).map_err(|e| ContextError::Surface(e))?;
 
         // Device creation
-        let device = Device::init(&library, &surface).map_err(|e| ContextError::Device(e))?;
+        let device = Device::init(&library, &surface, settings.device.as_ref())
+            .map_err(|e| ContextError::Device(e))?;
         // Swapchain
-        let swapchain =
-            Swapchain::init(&library, &device, &surface).map_err(|e| ContextError::Swapchain(e))?;
+        let size = vk::Extent2D { width, height };
+        let swapchain = Swapchain::init(&library, &device, &surface, size, settings.present_mode)
+            .map_err(|e| ContextError::Swapchain(e))?;
+        let frames = Frames::init(&device, &swapchain).map_err(ContextError::Frame)?;
+        let pipeline_cache = PipelineCache::init(&device, Some(&cache_path))
+            .map_err(ContextError::Pipeline)?;
+        let resources = Resources::init(&library, &device).map_err(ContextError::Resource)?;
 
         Ok(Self {
-            library,
-            surface,
+            swapchain: RefCell::new(swapchain),
+            frames: RefCell::new(frames),
+            pipeline_cache,
+            pipelines: RefCell::new(Vec::new()),
+            layouts: RefCell::new(DescriptorLayoutCache::default()),
+            shaders: RefCell::new(None),
+            shader_root: settings.shader_root.clone(),
+            sources: RefCell::new(HashMap::new()),
+            resources,
+            size: Cell::new(size),
+            stale: Cell::new(false),
             device,
-            swapchain,
+            surface,
+            library,
         })
     }
+
+    /// Build a graphics pipeline for a subpass of the render pass.
+    pub fn create_raw_pipeline(
+        &self,
+        render_pass: vk::RenderPass,
+        subpass: u32,
+        desc: &PipelineDesc,
+    ) -> Result<PipelineHandle> {
+        let bindless = self.resources.borrow().bindless().map(|b| b.layout);
+        let pipeline = Pipeline::init(
+            &self.device,
+            &self.pipeline_cache,
+            &mut self.layouts.borrow_mut(),
+            bindless,
+            render_pass,
+            subpass,
+            desc,
+        )
+        .map_err(ContextError::Pipeline)?;
+        let mut pipelines = self.pipelines.borrow_mut();
+        let index = match pipelines.iter().position(|p| p.is_none()) {
+            Some(index) => index,
+            None => {
+                pipelines.push(None);
+                pipelines.len() - 1
+            }
+        };
+        pipelines[index] = Some(pipeline);
+        Ok(PipelineHandle(index as u32))
+    }
+
+    /// Build the pipeline of a handle again, for the shader hot reload.
+    /// The old pipeline is kept when the new one fails and destroyed once the GPU is idle otherwise.
+    pub fn rebuild_raw_pipeline(
+        &self,
+        handle: PipelineHandle,
+        render_pass: vk::RenderPass,
+        subpass: u32,
+        desc: &PipelineDesc,
+    ) -> Result<()> {
+        if self.pipeline(handle).is_none() {
+            return Err(ContextError::Pipeline(PipelineError::NoPipeline(handle.0)));
+        }
+        let bindless = self.resources.borrow().bindless().map(|b| b.layout);
+        let mut pipeline = Pipeline::init(
+            &self.device,
+            &self.pipeline_cache,
+            &mut self.layouts.borrow_mut(),
+            bindless,
+            render_pass,
+            subpass,
+            desc,
+        )
+        .map_err(ContextError::Pipeline)?;
+        if let Err(e) = unsafe { self.device.logical.device_wait_idle() } {
+            pipeline.drop(&self.device);
+            return Err(ContextError::Frame(FrameError::Draw(e)));
+        }
+        if let Some(mut old) = self.pipelines.borrow_mut()[handle.0 as usize].replace(pipeline) {
+            old.drop(&self.device);
+        }
+        Ok(())
+    }
+
+    /// The render pass drawing to the swapchain, the pipelines must be compatible with it.
+    pub fn render_pass(&self) -> vk::RenderPass {
+        self.frames.borrow().render_pass
+    }
+
+    pub fn extent(&self) -> vk::Extent2D {
+        self.swapchain.borrow().extent
+    }
+
+    pub(crate) fn pipeline(&self, handle: PipelineHandle) -> Option<Ref<'_, Pipeline>> {
+        Ref::filter_map(self.pipelines.borrow(), |p| p.get(handle.0 as usize)?.as_ref()).ok()
+    }
+
+    /// Compile the stages `name.vert`, `name.frag`... found in the shader root,
+    /// the files they read are returned with them.
+    fn compile_shaders(
+        &self,
+        name: &str,
+        key: &VariantKey,
+    ) -> api::Result<(Vec<CompiledShader>, Vec<PathBuf>)> {
+        let backend = |e: rseed_shaderlib::ShaderError| ResourceError::Backend(e.to_string());
+        let mut library = self.shaders.borrow_mut();
+        if library.is_none() {
+            *library = Some(ShaderLibrary::new(Preprocessor::new(self.shader_root.clone())).map_err(backend)?);
+        }
+        let library = library.as_mut().unwrap();
+        let mut shaders = Vec::new();
+        let mut files = Vec::new();
+        for stage in GRAPHICS_STAGES.iter() {
+            let file = format!("{}.{}", name, stage.extension());
+            if self.shader_root.join(&file).is_file() {
+                let path = Path::new(&file);
+                shaders.push(library.spirv(path, key).map_err(backend)?.clone());
+                for read in library.source(path, key).map_err(backend)?.files.iter() {
+                    if !files.contains(read) {
+                        files.push(read.clone());
+                    }
+                }
+            }
+        }
+        if shaders.is_empty() {
+            return Err(ResourceError::Backend(format!(
+                "No shader file found for {} in {}.",
+                name,
+                self.shader_root.display()
+            )));
+        }
+        Ok((shaders, files))
+    }
+
+    /// The buffer, the offset of the region the frame reads and its size.
+    pub(crate) fn buffer(&self, handle: BufferHandle) -> Option<(vk::Buffer, u64, u64)> {
+        self.resources.borrow().buffer(handle)
+    }
+
+    pub(crate) fn texture(&self, handle: TextureHandle) -> Option<vk::ImageView> {
+        self.resources.borrow().texture(handle)
+    }
+
+    /// Record the transition of a texture, false if the handle is not a texture.
+    pub(crate) fn barrier(
+        &self,
+        cmd: vk::CommandBuffer,
+        handle: TextureHandle,
+        from: Option<Access>,
+        to: Access,
+    ) -> bool {
+        self.resources.borrow().barrier(cmd, handle, from, to)
+    }
+
+    /// The default sampler when there is none for the handle.
+    pub(crate) fn sampler(&self, handle: Option<SamplerHandle>) -> vk::Sampler {
+        self.resources.borrow().sampler(handle)
+    }
+
+    /// The set holding every texture, indexed by the texture handles.
+    pub(crate) fn bindless_set(&self) -> Option<vk::DescriptorSet> {
+        self.resources.borrow().bindless().map(|b| b.set)
+    }
+
+    /// Capabilities of the device the context renders with.
+    pub fn device_report(&self) -> &physical::DeviceReport {
+        &self.device.report
+    }
+
+    /// Usage of the device memory, the allocations should drop to zero with the resources.
+    pub fn memory_stats(&self) -> MemoryStats {
+        self.resources.borrow().stats()
+    }
+
+    /// Draw and present a frame, the swapchain is recreated when it is out of date.
+    /// `prepare` records before the render pass of the window begins, `record` inside it
+    /// and allocates the descriptor sets of its draws from the frame.
+    pub(crate) fn draw_frame<P, F>(&self, clear_color: [f32; 4], prepare: P, record: F) -> Result<()>
+    where
+        P: FnOnce(vk::CommandBuffer),
+        F: FnOnce(&ash::Device, vk::CommandBuffer, &mut DescriptorAllocator),
+    {
+        let size = self.size.get();
+        if size.width == 0 || size.height == 0 {
+            return Ok(());
+        }
+        if self.stale.get() {
+            self.recreate_swapchain()?;
+        }
+        let status = self
+            .frames
+            .borrow_mut()
+            .draw(
+                &self.device,
+                &self.swapchain.borrow(),
+                clear_color,
+                |cmd| {
+                    // The fence of the frame signaled, the buffers dropped before can go.
+                    self.resources.borrow_mut().begin_frame();
+                    prepare(cmd)
+                },
+                record,
+            )
+            .map_err(ContextError::Frame)?;
+        if status == FrameStatus::OutOfDate {
+            self.recreate_swapchain()?;
+        }
+        Ok(())
+    }
+
+    /// Recreate the swapchain with the new size of the window.
+    /// On failure the next frame tries again before drawing.
+    pub fn resize(&self, width: u32, height: u32) -> Result<()> {
+        self.size.set(vk::Extent2D { width, height });
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        self.recreate_swapchain()
+    }
+
+    fn recreate_swapchain(&self) -> Result<()> {
+        let result = self.rebuild_swapchain();
+        self.stale.set(result.is_err());
+        result
+    }
+
+    fn rebuild_swapchain(&self) -> Result<()> {
+        unsafe {
+            self.device
+                .logical
+                .device_wait_idle()
+                .map_err(|e| ContextError::Frame(FrameError::Draw(e)))?
+        };
+        let mut swapchain = self.swapchain.borrow_mut();
+        swapchain
+            .recreate(&self.device, &self.surface, self.size.get())
+            .map_err(ContextError::Swapchain)?;
+        self.frames
+            .borrow_mut()
+            .recreate(&self.device, &swapchain)
+            .map_err(ContextError::Frame)
+    }
+
+    /// The pipeline must not be used by a frame in flight anymore.
+    pub fn destroy_pipeline(&mut self, handle: PipelineHandle) {
+        if let Some(mut pipeline) = self.pipelines.get_mut().get_mut(handle.0 as usize).and_then(|p| p.take()) {
+            pipeline.drop(&self.device);
+        }
+        self.sources.get_mut().remove(&handle);
+    }
+}
+
+impl ContextT for VkContext {
+    /// Presentation is driven by the renderer.
+    fn swap_buffers(&self) {}
+
+    fn create_vertex_buffer(
+        &self,
+        data: &[u8],
+        layout: VertexLayout,
+        usage: BufferUsage,
+    ) -> api::Result<Box<dyn VertexBufferT>> {
+        Ok(Box::new(buffer::create_vertex_buffer(&self.resources, data, layout, usage)?))
+    }
+
+    fn create_index_buffer(&self, data: IndexData, usage: BufferUsage) -> api::Result<Box<dyn IndexBufferT>> {
+        Ok(Box::new(buffer::create_index_buffer(&self.resources, data, usage)?))
+    }
+
+    fn create_uniform_buffer(&self, data: &[u8], usage: BufferUsage) -> api::Result<Box<dyn UniformBufferT>> {
+        Ok(Box::new(buffer::create_uniform_buffer(&self.resources, data, usage)?))
+    }
+
+    fn create_texture(&self, desc: TextureDesc, data: Option<&[u8]>) -> api::Result<Box<dyn TextureT>> {
+        Ok(Box::new(texture::create_texture(&self.resources, desc, data)?))
+    }
+
+    fn create_sampler(&self, desc: SamplerDesc) -> api::Result<Box<dyn SamplerT>> {
+        Ok(Box::new(texture::create_sampler(&self.resources, desc)?))
+    }
+
+    /// The pipeline draws in the render pass of the window.
+    fn create_pipeline(&self, desc: &api_pipeline::PipelineDesc) -> api::Result<PipelineHandle> {
+        let (shaders, files) = self.compile_shaders(&desc.shader, &VariantKey::new(desc.features.iter()))?;
+        let handle = self
+            .create_raw_pipeline(self.render_pass(), 0, &PipelineDesc::from_api(&shaders, desc))
+            .map_err(|e| ResourceError::Backend(e.to_string()))?;
+        self.sources.borrow_mut().insert(handle, (desc.clone(), files));
+        Ok(handle)
+    }
+
+    /// The variants reading the files of the pipeline are compiled again,
+    /// the old pipeline is destroyed once the GPU is idle.
+    fn rebuild_pipeline(&self, handle: PipelineHandle) -> api::Result<Vec<PathBuf>> {
+        let (desc, files) = self
+            .sources
+            .borrow()
+            .get(&handle)
+            .cloned()
+            .ok_or(ResourceError::NoPipeline(handle.0))?;
+        if let Some(library) = self.shaders.borrow_mut().as_mut() {
+            for file in files.iter() {
+                library.invalidate(file);
+            }
+        }
+        let (shaders, files) = self.compile_shaders(&desc.shader, &VariantKey::new(desc.features.iter()))?;
+        self.rebuild_raw_pipeline(handle, self.render_pass(), 0, &PipelineDesc::from_api(&shaders, &desc))
+            .map_err(|e| ResourceError::Backend(e.to_string()))?;
+        if let Some(source) = self.sources.borrow_mut().get_mut(&handle) {
+            source.1 = files.clone();
+        }
+        Ok(files)
+    }
+
+    fn pipeline_files(&self, handle: PipelineHandle) -> api::Result<Vec<PathBuf>> {
+        match self.sources.borrow().get(&handle) {
+            Some((_, files)) => Ok(files.clone()),
+            None => Err(ResourceError::NoPipeline(handle.0)),
+        }
+    }
+
+    fn supports_bindless(&self) -> bool {
+        self.resources.borrow().bindless().is_some()
+    }
 }
 
 impl Drop for VkContext {
     fn drop(&mut self) {
-        self.swapchain.drop(&self.device);
+        // Nothing can be destroyed while the GPU still uses it.
+        let _ = unsafe { self.device.logical.device_wait_idle() };
+        self.frames.get_mut().drop(&self.device);
+        for pipeline in self.pipelines.get_mut().iter_mut().flatten() {
+            pipeline.drop(&self.device);
+        }
+        // Losing the cache only slows down the next launch.
+        let _ = self.pipeline_cache.save(&self.device);
+        self.pipeline_cache.drop(&self.device);
+        self.layouts.get_mut().drop(&self.device.logical);
+        self.resources.borrow_mut().drop();
+        self.swapchain.get_mut().drop(&self.device);
     }
 }
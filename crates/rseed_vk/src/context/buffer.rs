@@ -0,0 +1,395 @@
+use ash::vk;
+use rseed_renderapi::{
+    buffer::{BufferUsage, IndexBufferT, IndexData, UniformBufferT, VertexBufferT, VertexLayout},
+    context::{ResourceError, Result},
+    handle::BufferHandle,
+    renderer::IndexFormat,
+};
+
+use super::device::DeviceV1_0;
+use super::frame::FRAMES_IN_FLIGHT;
+use super::memory::{self, Allocation, MemoryUsage};
+use super::resource::{free_slot, Resources, SharedResources};
+use super::transfer::create_buffer;
+
+/// Bits of a buffer handle holding the slot index, the others hold the generation of the slot
+/// so a handle kept after its buffer was dropped matches no later buffer of the slot.
+const INDEX_BITS: u32 = 20;
+const INDEX_MASK: u32 = (1 << INDEX_BITS) - 1;
+const GENERATION_MASK: u32 = u32::MAX >> INDEX_BITS;
+
+/// Regions of the buffers written from the CPU. The GPU may still read the regions of the
+/// frames in flight while the next frame is prepared in another.
+const REGIONS: u64 = FRAMES_IN_FLIGHT as u64 + 1;
+/// The regions are aligned for any uniform buffer offset, Vulkan asks at most 256 bytes.
+const REGION_ALIGNMENT: u64 = 256;
+
+fn buffer_handle(index: usize, generation: u32) -> BufferHandle {
+    BufferHandle(index as u32 | generation << INDEX_BITS)
+}
+
+fn split_handle(handle: BufferHandle) -> (usize, u32) {
+    ((handle.0 & INDEX_MASK) as usize, handle.0 >> INDEX_BITS)
+}
+
+pub(crate) struct BufferSlot {
+    buffer: vk::Buffer,
+    /// Size asked for, the allocation can be bigger.
+    size: u64,
+    allocation: Allocation,
+    usage: vk::BufferUsageFlags,
+    memory_usage: MemoryUsage,
+    /// Distance between the regions, one region for the buffers in GPU memory.
+    stride: u64,
+    regions: u64,
+    /// The region the draws read, the last one written.
+    region: u64,
+    /// The frame the last write is for.
+    frame: u64,
+}
+
+impl Resources {
+    /// The buffer, the offset of its current region and its size.
+    pub fn buffer(&self, handle: BufferHandle) -> Option<(vk::Buffer, u64, u64)> {
+        let index = self.buffer_index(handle)?;
+        self.buffers[index]
+            .as_ref()
+            .map(|slot| (slot.buffer, slot.region * slot.stride, slot.size))
+    }
+
+    /// The slot of a handle, `None` once its buffer was dropped.
+    fn buffer_index(&self, handle: BufferHandle) -> Option<usize> {
+        let (index, generation) = split_handle(handle);
+        match self.buffers.get(index) {
+            Some(Some(_)) if self.buffer_generations[index] == generation => Some(index),
+            _ => None,
+        }
+    }
+
+    /// Static buffers live in GPU memory and are filled with staging uploads,
+    /// the others are written directly from the CPU, in a region per frame.
+    fn create_buffer(
+        &mut self,
+        data: &[u8],
+        usage: vk::BufferUsageFlags,
+        buffer_usage: BufferUsage,
+    ) -> Result<BufferHandle> {
+        if data.is_empty() {
+            return Err(ResourceError::InvalidBuffer("a buffer can't be empty"));
+        }
+        let memory_usage = match buffer_usage {
+            BufferUsage::Static => MemoryUsage::GpuOnly,
+            BufferUsage::Dynamic | BufferUsage::Stream => MemoryUsage::Upload,
+        };
+        let index = free_slot(&mut self.buffers);
+        if index > INDEX_MASK as usize {
+            return Err(ResourceError::Backend(String::from("Too many buffers.")));
+        }
+        if self.buffer_generations.len() < self.buffers.len() {
+            self.buffer_generations.resize(self.buffers.len(), 0);
+        }
+        self.allocate_slot(index, data.len() as u64, usage | vk::BufferUsageFlags::TRANSFER_DST, memory_usage)?;
+        if let Err(e) = self.write_slot(index, data) {
+            self.release_buffer(index);
+            return Err(e.into());
+        }
+        Ok(buffer_handle(index, self.buffer_generations[index]))
+    }
+
+    fn allocate_slot(
+        &mut self,
+        index: usize,
+        size: u64,
+        usage: vk::BufferUsageFlags,
+        memory_usage: MemoryUsage,
+    ) -> memory::Result<()> {
+        let (stride, regions) = match memory_usage {
+            MemoryUsage::Upload => (size.div_ceil(REGION_ALIGNMENT) * REGION_ALIGNMENT, REGIONS),
+            _ => (size, 1),
+        };
+        let (buffer, allocation) = create_buffer(
+            &self.device,
+            &mut self.allocator,
+            stride * regions,
+            usage,
+            memory_usage,
+            &self.families,
+        )?;
+        self.buffers[index] = Some(BufferSlot {
+            buffer,
+            allocation,
+            size,
+            usage,
+            memory_usage,
+            stride,
+            regions,
+            region: 0,
+            frame: self.frame + 1,
+        });
+        Ok(())
+    }
+
+    /// Write the data for the next frame. The buffers written from the CPU move to their next
+    /// region on the first write of a frame. The buffers in GPU memory are uploaded in place
+    /// and grow when the data does not fit anymore, which must not happen while a frame
+    /// in flight uses them.
+    fn write_buffer(&mut self, handle: BufferHandle, data: &[u8]) -> Result<()> {
+        match self.buffer_index(handle) {
+            Some(index) => Ok(self.write_slot(index, data)?),
+            None => Ok(()),
+        }
+    }
+
+    fn write_slot(&mut self, index: usize, data: &[u8]) -> memory::Result<()> {
+        let (size, usage, memory_usage) = match &self.buffers[index] {
+            Some(slot) => (slot.size, slot.usage, slot.memory_usage),
+            None => return Ok(()),
+        };
+        if (data.len() as u64) > size {
+            if let Some(slot) = self.buffers[index].take() {
+                self.retire(slot);
+            }
+            self.allocate_slot(index, data.len() as u64, usage, memory_usage)?;
+        }
+        let next = self.frame + 1;
+        let slot = self.buffers[index].as_mut().unwrap();
+        if slot.allocation.is_mapped() {
+            if slot.frame != next {
+                let previous = slot.region;
+                slot.region = (slot.region + 1) % slot.regions;
+                slot.frame = next;
+                // The bytes after the data keep the content of the previous region.
+                if (data.len() as u64) < slot.size && slot.region != previous {
+                    slot.allocation
+                        .copy(previous * slot.stride, slot.region * slot.stride, slot.size);
+                }
+            }
+            slot.allocation.write(slot.region * slot.stride, data);
+            Ok(())
+        } else {
+            let buffer = slot.buffer;
+            self.transfer
+                .upload_buffer(&self.device, &mut self.allocator, buffer, 0, data)
+        }
+    }
+
+    fn free_buffer(&mut self, handle: BufferHandle) {
+        if let Some(index) = self.buffer_index(handle) {
+            self.release_buffer(index);
+        }
+    }
+
+    /// Empty the slot, the handles given for it stop matching.
+    pub(super) fn release_buffer(&mut self, index: usize) {
+        if self.destroyed {
+            return;
+        }
+        if let Some(slot) = self.buffers.get_mut(index).and_then(|b| b.take()) {
+            self.buffer_generations[index] = (self.buffer_generations[index] + 1) & GENERATION_MASK;
+            self.retire(slot);
+        }
+    }
+
+    /// The frames begun so far may still read the buffer, it is destroyed once they are done.
+    fn retire(&mut self, slot: BufferSlot) {
+        self.retired.push((self.frame, slot.buffer, slot.allocation));
+    }
+
+    /// Called once the fence of the frame about to be recorded signaled, the frames
+    /// before the ones in flight are done with the buffers retired during them.
+    pub fn begin_frame(&mut self) {
+        self.frame += 1;
+        let done = self.frame.saturating_sub(FRAMES_IN_FLIGHT as u64);
+        let (free, keep) = self.retired.drain(..).partition(|(frame, _, _)| *frame <= done);
+        self.retired = keep;
+        self.destroy_retired(free);
+    }
+
+    /// Destroy the retired buffers whatever their frame, the device must be idle.
+    pub(super) fn destroy_all_retired(&mut self) {
+        let retired = std::mem::take(&mut self.retired);
+        self.destroy_retired(retired);
+    }
+
+    fn destroy_retired(&mut self, retired: Vec<(u64, vk::Buffer, Allocation)>) {
+        for (_, buffer, allocation) in retired {
+            unsafe { self.device.destroy_buffer(buffer, None) };
+            self.allocator.free(&self.device, allocation);
+        }
+    }
+}
+
+pub(crate) fn create_vertex_buffer(
+    resources: &SharedResources,
+    data: &[u8],
+    layout: VertexLayout,
+    usage: BufferUsage,
+) -> Result<VkVertexBuffer> {
+    check_stride(data, &layout)?;
+    let handle = resources
+        .borrow_mut()
+        .create_buffer(data, vk::BufferUsageFlags::VERTEX_BUFFER, usage)?;
+    Ok(VkVertexBuffer {
+        resources: resources.clone(),
+        handle,
+        len: data.len() / layout.stride.max(1) as usize,
+        layout,
+    })
+}
+
+pub(crate) fn create_index_buffer(
+    resources: &SharedResources,
+    data: IndexData,
+    usage: BufferUsage,
+) -> Result<VkIndexBuffer> {
+    let handle = resources
+        .borrow_mut()
+        .create_buffer(data.as_bytes(), vk::BufferUsageFlags::INDEX_BUFFER, usage)?;
+    Ok(VkIndexBuffer {
+        resources: resources.clone(),
+        handle,
+        format: data.format(),
+        len: data.len(),
+    })
+}
+
+pub(crate) fn create_uniform_buffer(
+    resources: &SharedResources,
+    data: &[u8],
+    usage: BufferUsage,
+) -> Result<VkUniformBuffer> {
+    let handle = resources
+        .borrow_mut()
+        .create_buffer(data, vk::BufferUsageFlags::UNIFORM_BUFFER, usage)?;
+    Ok(VkUniformBuffer {
+        resources: resources.clone(),
+        handle,
+        size: data.len(),
+    })
+}
+
+fn check_stride(data: &[u8], layout: &VertexLayout) -> Result<()> {
+    let stride = layout.stride.max(1) as usize;
+    if !data.len().is_multiple_of(stride) {
+        return Err(ResourceError::SizeMismatch(data.len() / stride * stride, data.len()));
+    }
+    Ok(())
+}
+
+pub struct VkVertexBuffer {
+    resources: SharedResources,
+    handle: BufferHandle,
+    layout: VertexLayout,
+    len: usize,
+}
+
+impl VertexBufferT for VkVertexBuffer {
+    fn handle(&self) -> BufferHandle {
+        self.handle
+    }
+
+    fn layout(&self) -> &VertexLayout {
+        &self.layout
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn set_data(&mut self, data: &[u8]) -> Result<()> {
+        check_stride(data, &self.layout)?;
+        self.resources
+            .borrow_mut()
+            .write_buffer(self.handle, data)?;
+        self.len = data.len() / self.layout.stride.max(1) as usize;
+        Ok(())
+    }
+}
+
+impl Drop for VkVertexBuffer {
+    fn drop(&mut self) {
+        self.resources.borrow_mut().free_buffer(self.handle);
+    }
+}
+
+pub struct VkIndexBuffer {
+    resources: SharedResources,
+    handle: BufferHandle,
+    format: IndexFormat,
+    len: usize,
+}
+
+impl IndexBufferT for VkIndexBuffer {
+    fn handle(&self) -> BufferHandle {
+        self.handle
+    }
+
+    fn format(&self) -> IndexFormat {
+        self.format
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn set_data(&mut self, data: IndexData) -> Result<()> {
+        self.resources
+            .borrow_mut()
+            .write_buffer(self.handle, data.as_bytes())?;
+        self.format = data.format();
+        self.len = data.len();
+        Ok(())
+    }
+}
+
+impl Drop for VkIndexBuffer {
+    fn drop(&mut self) {
+        self.resources.borrow_mut().free_buffer(self.handle);
+    }
+}
+
+pub struct VkUniformBuffer {
+    resources: SharedResources,
+    handle: BufferHandle,
+    size: usize,
+}
+
+impl UniformBufferT for VkUniformBuffer {
+    fn handle(&self) -> BufferHandle {
+        self.handle
+    }
+
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn set_data(&mut self, data: &[u8]) -> Result<()> {
+        if data.len() > self.size {
+            return Err(ResourceError::SizeMismatch(self.size, data.len()));
+        }
+        self.resources
+            .borrow_mut()
+            .write_buffer(self.handle, data)?;
+        Ok(())
+    }
+}
+
+impl Drop for VkUniformBuffer {
+    fn drop(&mut self) {
+        self.resources.borrow_mut().free_buffer(self.handle);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handles_change_with_the_generation() {
+        let handle = buffer_handle(5, 3);
+        assert_eq!(split_handle(handle), (5, 3));
+        assert_ne!(buffer_handle(5, 4), handle);
+        let last = buffer_handle(INDEX_MASK as usize, GENERATION_MASK);
+        assert_eq!(split_handle(last), (INDEX_MASK as usize, GENERATION_MASK));
+    }
+}
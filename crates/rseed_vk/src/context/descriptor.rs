@@ -0,0 +1,304 @@
+use rseed_core::prelude::*;
+
+use std::collections::HashMap;
+
+use ash::{self, vk};
+use rseed_renderapi::descriptor::{BindingDesc, BindingType, ShaderStages};
+
+use super::device::DeviceV1_0;
+
+/// Size of the bindless texture array.
+pub const BINDLESS_CAPACITY: u32 = 4096;
+/// Sets of the first pool, each new pool holds twice as many up to `MAX_POOL_SETS`.
+const FIRST_POOL_SETS: u32 = 64;
+const MAX_POOL_SETS: u32 = 4096;
+/// Descriptors of each type in a pool, per set.
+const POOL_RATIOS: [(vk::DescriptorType, u32); 5] = [
+    (vk::DescriptorType::UNIFORM_BUFFER, 2),
+    (vk::DescriptorType::STORAGE_BUFFER, 1),
+    (vk::DescriptorType::SAMPLED_IMAGE, 2),
+    (vk::DescriptorType::SAMPLER, 1),
+    (vk::DescriptorType::COMBINED_IMAGE_SAMPLER, 4),
+];
+
+#[derive(Debug, Error)]
+pub enum DescriptorError {
+    #[error(display = "Error creating a descriptor set layout: {:?}", _0)]
+    Layout(vk::Result),
+    #[error(display = "Error creating a descriptor pool: {:?}", _0)]
+    Pool(vk::Result),
+    #[error(display = "Error allocating a descriptor set: {:?}", _0)]
+    Allocation(vk::Result),
+}
+
+pub type Result<T> = std::result::Result<T, DescriptorError>;
+
+pub fn vk_descriptor_type(ty: BindingType) -> vk::DescriptorType {
+    match ty {
+        BindingType::UniformBuffer => vk::DescriptorType::UNIFORM_BUFFER,
+        BindingType::StorageBuffer => vk::DescriptorType::STORAGE_BUFFER,
+        BindingType::Texture => vk::DescriptorType::SAMPLED_IMAGE,
+        BindingType::Sampler => vk::DescriptorType::SAMPLER,
+        BindingType::TextureSampler => vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+    }
+}
+
+pub fn vk_shader_stages(stages: ShaderStages) -> vk::ShaderStageFlags {
+    let mut flags = vk::ShaderStageFlags::empty();
+    if stages.contains(ShaderStages::VERTEX) {
+        flags |= vk::ShaderStageFlags::VERTEX;
+    }
+    if stages.contains(ShaderStages::FRAGMENT) {
+        flags |= vk::ShaderStageFlags::FRAGMENT;
+    }
+    if stages.contains(ShaderStages::COMPUTE) {
+        flags |= vk::ShaderStageFlags::COMPUTE;
+    }
+    if stages.contains(ShaderStages::GEOMETRY) {
+        flags |= vk::ShaderStageFlags::GEOMETRY;
+    }
+    if stages.contains(ShaderStages::TESS_CONTROL) {
+        flags |= vk::ShaderStageFlags::TESSELLATION_CONTROL;
+    }
+    if stages.contains(ShaderStages::TESS_EVALUATION) {
+        flags |= vk::ShaderStageFlags::TESSELLATION_EVALUATION;
+    }
+    flags
+}
+
+/// Set layouts shared by every pipeline declaring the same bindings.
+#[derive(Default)]
+pub(crate) struct DescriptorLayoutCache {
+    layouts: HashMap<Vec<BindingDesc>, vk::DescriptorSetLayout>,
+}
+
+impl DescriptorLayoutCache {
+    pub fn get(&mut self, device: &ash::Device, bindings: &[BindingDesc]) -> Result<vk::DescriptorSetLayout> {
+        let mut key = bindings.to_vec();
+        key.sort_by_key(|b| b.binding);
+        if let Some(layout) = self.layouts.get(&key) {
+            return Ok(*layout);
+        }
+        let vk_bindings: Vec<vk::DescriptorSetLayoutBinding> = key
+            .iter()
+            .map(|b| {
+                vk::DescriptorSetLayoutBinding::builder()
+                    .binding(b.binding)
+                    .descriptor_type(vk_descriptor_type(b.ty))
+                    .descriptor_count(b.count)
+                    .stage_flags(vk_shader_stages(b.stages))
+                    .build()
+            })
+            .collect();
+        let info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(&vk_bindings);
+        let layout = unsafe {
+            device
+                .create_descriptor_set_layout(&info, None)
+                .map_err(DescriptorError::Layout)?
+        };
+        self.layouts.insert(key, layout);
+        Ok(layout)
+    }
+
+    pub fn drop(&mut self, device: &ash::Device) {
+        for (_, layout) in self.layouts.drain() {
+            unsafe { device.destroy_descriptor_set_layout(layout, None) };
+        }
+    }
+}
+
+/// Hands out descriptor sets from pools created on demand, every set is freed at once
+/// with `reset`. Each frame in flight owns one and resets it when the frame starts.
+pub(crate) struct DescriptorAllocator {
+    current: Option<vk::DescriptorPool>,
+    /// Full pools, reused after the next reset.
+    used: Vec<vk::DescriptorPool>,
+    free: Vec<vk::DescriptorPool>,
+    sets_per_pool: u32,
+}
+
+impl Default for DescriptorAllocator {
+    fn default() -> Self {
+        Self {
+            current: None,
+            used: Vec::new(),
+            free: Vec::new(),
+            sets_per_pool: FIRST_POOL_SETS,
+        }
+    }
+}
+
+impl DescriptorAllocator {
+    fn create_pool(&mut self, device: &ash::Device) -> Result<vk::DescriptorPool> {
+        if let Some(pool) = self.free.pop() {
+            return Ok(pool);
+        }
+        let sizes: Vec<vk::DescriptorPoolSize> = POOL_RATIOS
+            .iter()
+            .map(|(ty, ratio)| vk::DescriptorPoolSize {
+                ty: *ty,
+                descriptor_count: ratio * self.sets_per_pool,
+            })
+            .collect();
+        let info = vk::DescriptorPoolCreateInfo::builder()
+            .max_sets(self.sets_per_pool)
+            .pool_sizes(&sizes);
+        let pool = unsafe {
+            device
+                .create_descriptor_pool(&info, None)
+                .map_err(DescriptorError::Pool)?
+        };
+        self.sets_per_pool = (self.sets_per_pool * 2).min(MAX_POOL_SETS);
+        Ok(pool)
+    }
+
+    pub fn allocate(&mut self, device: &ash::Device, layout: vk::DescriptorSetLayout) -> Result<vk::DescriptorSet> {
+        let pool = match self.current {
+            Some(pool) => pool,
+            None => {
+                let pool = self.create_pool(device)?;
+                self.current = Some(pool);
+                pool
+            }
+        };
+        let layouts = [layout];
+        let info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(pool)
+            .set_layouts(&layouts);
+        match unsafe { device.allocate_descriptor_sets(&info) } {
+            Ok(sets) => Ok(sets[0]),
+            Err(vk::Result::ERROR_OUT_OF_POOL_MEMORY) | Err(vk::Result::ERROR_FRAGMENTED_POOL) => {
+                // The pool is full, retry once with a new one.
+                self.used.push(pool);
+                let pool = self.create_pool(device)?;
+                self.current = Some(pool);
+                let info = vk::DescriptorSetAllocateInfo::builder()
+                    .descriptor_pool(pool)
+                    .set_layouts(&layouts);
+                unsafe {
+                    device
+                        .allocate_descriptor_sets(&info)
+                        .map(|sets| sets[0])
+                        .map_err(DescriptorError::Allocation)
+                }
+            }
+            Err(e) => Err(DescriptorError::Allocation(e)),
+        }
+    }
+
+    /// The sets must not be used by the GPU anymore.
+    pub fn reset(&mut self, device: &ash::Device) -> Result<()> {
+        for pool in self.used.drain(..).chain(self.current.take()) {
+            unsafe {
+                device
+                    .reset_descriptor_pool(pool, vk::DescriptorPoolResetFlags::empty())
+                    .map_err(DescriptorError::Pool)?
+            };
+            self.free.push(pool);
+        }
+        Ok(())
+    }
+
+    pub fn drop(&mut self, device: &ash::Device) {
+        for pool in self.used.drain(..).chain(self.current.take()).chain(self.free.drain(..)) {
+            unsafe { device.destroy_descriptor_pool(pool, None) };
+        }
+    }
+}
+
+/// A single set holding every texture, indexed in the shaders by the texture handle.
+/// Needs descriptor indexing: descriptors are updated while the set is bound and
+/// the slots of destroyed textures stay unbound.
+pub(crate) struct BindlessTable {
+    pub layout: vk::DescriptorSetLayout,
+    pool: vk::DescriptorPool,
+    pub set: vk::DescriptorSet,
+}
+
+impl BindlessTable {
+    pub fn init(device: &ash::Device) -> Result<Self> {
+        let bindings = [vk::DescriptorSetLayoutBinding::builder()
+            .binding(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(BINDLESS_CAPACITY)
+            .stage_flags(vk::ShaderStageFlags::ALL)
+            .build()];
+        let binding_flags = [vk::DescriptorBindingFlags::PARTIALLY_BOUND
+            | vk::DescriptorBindingFlags::UPDATE_AFTER_BIND
+            | vk::DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT];
+        let mut flags_info =
+            vk::DescriptorSetLayoutBindingFlagsCreateInfo::builder().binding_flags(&binding_flags);
+        let layout_info = vk::DescriptorSetLayoutCreateInfo::builder()
+            .flags(vk::DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL)
+            .bindings(&bindings)
+            .push_next(&mut flags_info);
+        let sizes = [vk::DescriptorPoolSize {
+            ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: BINDLESS_CAPACITY,
+        }];
+        let pool_info = vk::DescriptorPoolCreateInfo::builder()
+            .flags(vk::DescriptorPoolCreateFlags::UPDATE_AFTER_BIND)
+            .max_sets(1)
+            .pool_sizes(&sizes);
+        unsafe {
+            let layout = device
+                .create_descriptor_set_layout(&layout_info, None)
+                .map_err(DescriptorError::Layout)?;
+            let pool = match device.create_descriptor_pool(&pool_info, None) {
+                Ok(pool) => pool,
+                Err(e) => {
+                    device.destroy_descriptor_set_layout(layout, None);
+                    return Err(DescriptorError::Pool(e));
+                }
+            };
+            let mut table = Self {
+                layout,
+                pool,
+                set: vk::DescriptorSet::null(),
+            };
+            let counts = [BINDLESS_CAPACITY];
+            let mut count_info = vk::DescriptorSetVariableDescriptorCountAllocateInfo::builder()
+                .descriptor_counts(&counts);
+            let layouts = [layout];
+            let info = vk::DescriptorSetAllocateInfo::builder()
+                .descriptor_pool(pool)
+                .set_layouts(&layouts)
+                .push_next(&mut count_info);
+            match device.allocate_descriptor_sets(&info) {
+                Ok(sets) => table.set = sets[0],
+                Err(e) => {
+                    table.drop(device);
+                    return Err(DescriptorError::Allocation(e));
+                }
+            }
+            Ok(table)
+        }
+    }
+
+    /// Textures past the capacity are not reachable from the shaders.
+    pub fn write(&self, device: &ash::Device, index: u32, view: vk::ImageView, sampler: vk::Sampler) {
+        if index >= BINDLESS_CAPACITY {
+            return;
+        }
+        let images = [vk::DescriptorImageInfo {
+            sampler,
+            image_view: view,
+            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        }];
+        let write = vk::WriteDescriptorSet::builder()
+            .dst_set(self.set)
+            .dst_binding(0)
+            .dst_array_element(index)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(&images)
+            .build();
+        unsafe { device.update_descriptor_sets(&[write], &[]) };
+    }
+
+    pub fn drop(&mut self, device: &ash::Device) {
+        unsafe {
+            device.destroy_descriptor_pool(self.pool, None);
+            device.destroy_descriptor_set_layout(self.layout, None);
+        }
+    }
+}
@@ -5,7 +5,9 @@ use ash::{self, extensions::khr, vk};
 pub use ash::version::DeviceV1_0;
 
 use super::library::*;
+use super::physical::{select_device, DeviceReport};
 use super::surface::*;
+use rseed_renderapi::settings::DeviceSelector;
 
 #[derive(Clone, Debug)]
 pub enum DeviceError {
@@ -22,13 +24,25 @@ pub(crate) struct Device {
     pub transfert_queue: vk::Queue,
     pub compute_queue: vk::Queue,
     pub queue_inds: (u32, u32, u32),
+    pub report: DeviceReport,
 }
 
 impl Device {
-    pub(crate) fn init(lib: &Library, surface: &Surface) -> Result<Self> {
-        let (physical, _device_prop) = Self::chose_device(lib)?;
-        let (logical, graphic_queue, transfert_queue, compute_queue, queue_inds) =
-            Self::create_logical_device(lib, &physical, surface)?;
+    pub(crate) fn init(
+        lib: &Library,
+        surface: &Surface,
+        selector: Option<&DeviceSelector>,
+    ) -> Result<Self> {
+        let (physical, report) = select_device(lib, surface, selector)
+            .map_err(|e| DeviceError::PhysicalCreation(e))?
+            .ok_or(DeviceError::PhysicalCreation(
+                vk::Result::ERROR_INITIALIZATION_FAILED,
+            ))?;
+        // Only devices with a family presenting to the surface are selected.
+        let families = report.families.unwrap();
+        let queue_inds = (families.graphic, families.transfer, families.compute);
+        let (logical, graphic_queue, transfert_queue, compute_queue) =
+            Self::create_logical_device(lib, &physical, &report)?;
 
         Ok(Self {
             physical,
@@ -37,131 +51,65 @@ impl Device {
             transfert_queue,
             compute_queue,
             queue_inds,
+            report,
         })
     }
 
-    fn chose_device(lib: &Library) -> Result<(vk::PhysicalDevice, vk::PhysicalDeviceProperties)> {
-        let phys_devs = unsafe {
-            lib.instance
-                .enumerate_physical_devices()
-                .map_err(|e| DeviceError::PhysicalCreation(e))?
-        };
-
-        let mut chosen = None;
-        for p in phys_devs {
-            let props = unsafe { lib.instance.get_physical_device_properties(p) };
-            if props.device_type == vk::PhysicalDeviceType::DISCRETE_GPU {
-                chosen = Some((p, props));
-            } else if props.device_type == vk::PhysicalDeviceType::INTEGRATED_GPU {
-                match chosen {
-                    Some((_, prop)) => {
-                        if prop.device_type != vk::PhysicalDeviceType::DISCRETE_GPU {
-                            chosen = Some((p, props))
-                        }
-                    }
-                    None => chosen = Some((p, props)),
-                }
-            }
-        }
-        chosen.ok_or(DeviceError::PhysicalCreation(
-            vk::Result::ERROR_INITIALIZATION_FAILED,
-        ))
-    }
-
     fn create_logical_device(
         lib: &Library,
         physical_device: &vk::PhysicalDevice,
-        surface: &Surface,
-    ) -> Result<(
-        ash::Device,
-        vk::Queue,
-        vk::Queue,
-        vk::Queue,
-        (u32, u32, u32),
-    )> {
+        report: &DeviceReport,
+    ) -> Result<(ash::Device, vk::Queue, vk::Queue, vk::Queue)> {
         let enabled_layer_ptr: Vec<*const i8> =
             lib.enabled_layers.iter().map(|l| l.as_ptr()).collect();
-        let queue_fam_props = unsafe {
-            lib.instance
-                .get_physical_device_queue_family_properties(*physical_device)
-        };
-        let qfam_inds = {
-            let mut g_q = None;
-            let mut t_q = None;
-            let mut c_q = None;
-            for (index, qfam) in queue_fam_props.iter().enumerate() {
-                if qfam.queue_count > 0
-                    && qfam.queue_flags.contains(vk::QueueFlags::GRAPHICS)
-                    && unsafe {
-                        surface
-                            .loader
-                            .get_physical_device_surface_support(
-                                *physical_device,
-                                index as u32,
-                                surface.khr,
-                            )
-                            .map_err(|e| DeviceError::LogicalCreation(e))?
-                    }
-                {
-                    g_q = Some(index as u32);
-                }
-                if qfam.queue_count > 0 && qfam.queue_flags.contains(vk::QueueFlags::TRANSFER) {
-                    if t_q.is_none()
-                        && !(qfam.queue_flags.contains(vk::QueueFlags::GRAPHICS)
-                            && qfam.queue_flags.contains(vk::QueueFlags::COMPUTE))
-                    {
-                        t_q = Some(index as u32);
-                    }
-                }
-                if qfam.queue_count > 0 && qfam.queue_flags.contains(vk::QueueFlags::COMPUTE) {
-                    if c_q.is_none()
-                        && !(qfam.queue_flags.contains(vk::QueueFlags::GRAPHICS)
-                            && qfam.queue_flags.contains(vk::QueueFlags::TRANSFER))
-                    {
-                        c_q = Some(index as u32);
-                    }
-                }
-            }
-            (g_q.unwrap(), t_q.unwrap(), c_q.unwrap())
-        };
+        let qfam_inds = report.families.unwrap();
 
         let priorities = [1.0f32];
-        let queues_info = [
-            vk::DeviceQueueCreateInfo::builder()
-                .queue_family_index(qfam_inds.0)
-                .queue_priorities(&priorities)
-                .build(),
-            vk::DeviceQueueCreateInfo::builder()
-                .queue_family_index(qfam_inds.1)
-                .queue_priorities(&priorities)
-                .build(),
-            vk::DeviceQueueCreateInfo::builder()
-                .queue_family_index(qfam_inds.2)
-                .queue_priorities(&priorities)
-                .build(),
-        ];
+        let mut families = vec![qfam_inds.graphic, qfam_inds.transfer, qfam_inds.compute];
+        families.sort_unstable();
+        families.dedup();
+        let queues_info: Vec<vk::DeviceQueueCreateInfo> = families
+            .iter()
+            .map(|family| {
+                vk::DeviceQueueCreateInfo::builder()
+                    .queue_family_index(*family)
+                    .queue_priorities(&priorities)
+                    .build()
+            })
+            .collect();
 
         let device_extension_name_ptr: Vec<*const i8> = vec![khr::Swapchain::name().as_ptr()];
+        // Optional features, only enabled when the device has them.
+        let features = vk::PhysicalDeviceFeatures::builder()
+            .sampler_anisotropy(report.sampler_anisotropy)
+            .fill_mode_non_solid(report.fill_mode_non_solid);
+
+        let mut indexing = vk::PhysicalDeviceDescriptorIndexingFeatures::builder()
+            .shader_sampled_image_array_non_uniform_indexing(true)
+            .descriptor_binding_sampled_image_update_after_bind(true)
+            .descriptor_binding_partially_bound(true)
+            .descriptor_binding_variable_descriptor_count(true)
+            .runtime_descriptor_array(true);
 
         let device_info = vk::DeviceCreateInfo::builder()
             .queue_create_infos(&queues_info)
             .enabled_extension_names(&device_extension_name_ptr)
-            .enabled_layer_names(&enabled_layer_ptr);
+            .enabled_layer_names(&enabled_layer_ptr)
+            .enabled_features(&features);
+        let device_info = if report.descriptor_indexing {
+            device_info.push_next(&mut indexing)
+        } else {
+            device_info
+        };
         let device = unsafe {
             lib.instance
                 .create_device(*physical_device, &device_info, None)
                 .map_err(|e| DeviceError::LogicalCreation(e))
         }?;
-        let graphic_queue = unsafe { device.get_device_queue(qfam_inds.0, 0) };
-        let transfert_queue = unsafe { device.get_device_queue(qfam_inds.1, 0) };
-        let compute_queue = unsafe { device.get_device_queue(qfam_inds.2, 0) };
-        Ok((
-            device,
-            graphic_queue,
-            transfert_queue,
-            compute_queue,
-            qfam_inds,
-        ))
+        let graphic_queue = unsafe { device.get_device_queue(qfam_inds.graphic, 0) };
+        let transfert_queue = unsafe { device.get_device_queue(qfam_inds.transfer, 0) };
+        let compute_queue = unsafe { device.get_device_queue(qfam_inds.compute, 0) };
+        Ok((device, graphic_queue, transfert_queue, compute_queue))
     }
 }
 
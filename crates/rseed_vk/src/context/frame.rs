@@ -0,0 +1,319 @@
+use rseed_core::prelude::*;
+
+use ash::{self, vk};
+
+use super::descriptor::{DescriptorAllocator, DescriptorError};
+use super::device::*;
+use super::swapchain::*;
+
+/// Number of frames recorded by the CPU while the GPU renders the previous ones.
+pub const FRAMES_IN_FLIGHT: usize = 2;
+
+#[derive(Debug, Error)]
+pub enum FrameError {
+    #[error(display = "Error creating the render pass: {:?}", _0)]
+    RenderPass(vk::Result),
+    #[error(display = "Error creating a framebuffer: {:?}", _0)]
+    Framebuffer(vk::Result),
+    #[error(display = "Error creating the frame commands: {:?}", _0)]
+    Commands(vk::Result),
+    #[error(display = "Error creating the frame synchronization: {:?}", _0)]
+    Sync(vk::Result),
+    #[error(display = "Error drawing the frame: {:?}", _0)]
+    Draw(vk::Result),
+    #[error(display = "Error with the frame descriptors: {}", _0)]
+    Descriptor(DescriptorError),
+}
+
+pub type Result<T> = std::result::Result<T, FrameError>;
+
+/// What happened to the frame once presented.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FrameStatus {
+    Presented,
+    /// The swapchain does not match the surface anymore and must be recreated.
+    OutOfDate,
+}
+
+/// Resources owned by one frame in flight.
+struct Frame {
+    pool: vk::CommandPool,
+    commands: vk::CommandBuffer,
+    image_available: vk::Semaphore,
+    render_finished: vk::Semaphore,
+    in_flight: vk::Fence,
+    /// Sets of the draws, freed once the fence says the GPU is done with them.
+    descriptors: DescriptorAllocator,
+}
+
+impl Frame {
+    fn init(device: &Device) -> Result<Self> {
+        let logical = &device.logical;
+        let pool_info = vk::CommandPoolCreateInfo::builder()
+            .queue_family_index(device.queue_inds.0)
+            .flags(vk::CommandPoolCreateFlags::TRANSIENT);
+        let pool = unsafe {
+            logical
+                .create_command_pool(&pool_info, None)
+                .map_err(FrameError::Commands)?
+        };
+        let alloc_info = vk::CommandBufferAllocateInfo::builder()
+            .command_pool(pool)
+            .level(vk::CommandBufferLevel::PRIMARY)
+            .command_buffer_count(1);
+        let fence_info = vk::FenceCreateInfo::builder().flags(vk::FenceCreateFlags::SIGNALED);
+        let semaphore_info = vk::SemaphoreCreateInfo::default();
+        unsafe {
+            Ok(Self {
+                pool,
+                commands: logical
+                    .allocate_command_buffers(&alloc_info)
+                    .map_err(FrameError::Commands)?[0],
+                image_available: logical
+                    .create_semaphore(&semaphore_info, None)
+                    .map_err(FrameError::Sync)?,
+                render_finished: logical
+                    .create_semaphore(&semaphore_info, None)
+                    .map_err(FrameError::Sync)?,
+                in_flight: logical
+                    .create_fence(&fence_info, None)
+                    .map_err(FrameError::Sync)?,
+                descriptors: DescriptorAllocator::default(),
+            })
+        }
+    }
+
+    fn drop(&mut self, device: &Device) {
+        self.descriptors.drop(&device.logical);
+        unsafe {
+            device.logical.destroy_fence(self.in_flight, None);
+            device.logical.destroy_semaphore(self.render_finished, None);
+            device.logical.destroy_semaphore(self.image_available, None);
+            device.logical.destroy_command_pool(self.pool, None);
+        }
+    }
+}
+
+/// Per frame machinery: a render pass clearing the swapchain image, its framebuffers
+/// and the command buffers and synchronization of each frame in flight.
+pub(crate) struct Frames {
+    frames: Vec<Frame>,
+    current: usize,
+    pub render_pass: vk::RenderPass,
+    framebuffers: Vec<vk::Framebuffer>,
+}
+
+impl Frames {
+    pub fn init(device: &Device, swapchain: &Swapchain) -> Result<Self> {
+        let render_pass = Self::create_render_pass(device, swapchain.format)?;
+        let mut frames = Self {
+            frames: Vec::with_capacity(FRAMES_IN_FLIGHT),
+            current: 0,
+            render_pass,
+            framebuffers: Vec::new(),
+        };
+        let result = (0..FRAMES_IN_FLIGHT)
+            .try_for_each(|_| Frame::init(device).map(|f| frames.frames.push(f)))
+            .and_then(|_| frames.create_framebuffers(device, swapchain));
+        if let Err(e) = result {
+            frames.drop(device);
+            return Err(e);
+        }
+        Ok(frames)
+    }
+
+    fn create_render_pass(device: &Device, format: vk::Format) -> Result<vk::RenderPass> {
+        let attachments = [vk::AttachmentDescription::builder()
+            .format(format)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .store_op(vk::AttachmentStoreOp::STORE)
+            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .final_layout(vk::ImageLayout::PRESENT_SRC_KHR)
+            .build()];
+        let color_refs = [vk::AttachmentReference::builder()
+            .attachment(0)
+            .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .build()];
+        let subpasses = [vk::SubpassDescription::builder()
+            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+            .color_attachments(&color_refs)
+            .build()];
+        // Wait for the presentation engine to release the image before writing it.
+        let dependencies = [vk::SubpassDependency::builder()
+            .src_subpass(vk::SUBPASS_EXTERNAL)
+            .dst_subpass(0)
+            .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .dst_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .dst_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .build()];
+        let info = vk::RenderPassCreateInfo::builder()
+            .attachments(&attachments)
+            .subpasses(&subpasses)
+            .dependencies(&dependencies);
+        unsafe {
+            device
+                .logical
+                .create_render_pass(&info, None)
+                .map_err(FrameError::RenderPass)
+        }
+    }
+
+    fn create_framebuffers(&mut self, device: &Device, swapchain: &Swapchain) -> Result<()> {
+        for view in swapchain.views.iter() {
+            let attachments = [*view];
+            let info = vk::FramebufferCreateInfo::builder()
+                .render_pass(self.render_pass)
+                .attachments(&attachments)
+                .width(swapchain.extent.width)
+                .height(swapchain.extent.height)
+                .layers(1);
+            let framebuffer = unsafe {
+                device
+                    .logical
+                    .create_framebuffer(&info, None)
+                    .map_err(FrameError::Framebuffer)?
+            };
+            self.framebuffers.push(framebuffer);
+        }
+        Ok(())
+    }
+
+    fn destroy_framebuffers(&mut self, device: &Device) {
+        for framebuffer in self.framebuffers.drain(..) {
+            unsafe { device.logical.destroy_framebuffer(framebuffer, None) };
+        }
+    }
+
+    /// Rebuild the framebuffers after the swapchain was recreated.
+    /// The render pass is kept, the surface format does not change.
+    pub fn recreate(&mut self, device: &Device, swapchain: &Swapchain) -> Result<()> {
+        self.destroy_framebuffers(device);
+        self.create_framebuffers(device, swapchain)
+    }
+
+    /// Acquire an image, record the render pass with `record` and present it.
+    /// The descriptor sets allocated by `record` live until the frame comes back.
+    pub fn draw<P, F>(
+        &mut self,
+        device: &Device,
+        swapchain: &Swapchain,
+        clear_color: [f32; 4],
+        prepare: P,
+        record: F,
+    ) -> Result<FrameStatus>
+    where
+        P: FnOnce(vk::CommandBuffer),
+        F: FnOnce(&ash::Device, vk::CommandBuffer, &mut DescriptorAllocator),
+    {
+        let logical = &device.logical;
+        let frame = &mut self.frames[self.current];
+        unsafe {
+            logical
+                .wait_for_fences(&[frame.in_flight], true, u64::MAX)
+                .map_err(FrameError::Draw)?;
+            frame
+                .descriptors
+                .reset(logical)
+                .map_err(FrameError::Descriptor)?;
+            let image = match swapchain.loader.acquire_next_image(
+                swapchain.khr,
+                u64::MAX,
+                frame.image_available,
+                vk::Fence::null(),
+            ) {
+                Ok((image, _suboptimal)) => image,
+                Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => return Ok(FrameStatus::OutOfDate),
+                Err(e) => return Err(FrameError::Draw(e)),
+            };
+            // Only reset once sure to submit, or the next wait would never end.
+            logical
+                .reset_fences(&[frame.in_flight])
+                .map_err(FrameError::Draw)?;
+            let wait_semaphores = [frame.image_available];
+            let wait_stages = [vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
+            let signal_semaphores = [frame.render_finished];
+            let (render_pass, framebuffer) = (self.render_pass, self.framebuffers[image as usize]);
+            let submitted = (|| -> Result<()> {
+                logical
+                    .reset_command_pool(frame.pool, vk::CommandPoolResetFlags::empty())
+                    .map_err(FrameError::Draw)?;
+
+                let begin_info = vk::CommandBufferBeginInfo::builder()
+                    .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+                logical
+                    .begin_command_buffer(frame.commands, &begin_info)
+                    .map_err(FrameError::Draw)?;
+                // Barriers can't be recorded inside the render pass.
+                prepare(frame.commands);
+                let clear_values = [vk::ClearValue {
+                    color: vk::ClearColorValue {
+                        float32: clear_color,
+                    },
+                }];
+                let pass_info = vk::RenderPassBeginInfo::builder()
+                    .render_pass(render_pass)
+                    .framebuffer(framebuffer)
+                    .render_area(vk::Rect2D {
+                        offset: vk::Offset2D { x: 0, y: 0 },
+                        extent: swapchain.extent,
+                    })
+                    .clear_values(&clear_values);
+                logical.cmd_begin_render_pass(frame.commands, &pass_info, vk::SubpassContents::INLINE);
+                record(logical, frame.commands, &mut frame.descriptors);
+                logical.cmd_end_render_pass(frame.commands);
+                logical
+                    .end_command_buffer(frame.commands)
+                    .map_err(FrameError::Draw)?;
+
+                let command_buffers = [frame.commands];
+                let submit_info = vk::SubmitInfo::builder()
+                    .wait_semaphores(&wait_semaphores)
+                    .wait_dst_stage_mask(&wait_stages)
+                    .command_buffers(&command_buffers)
+                    .signal_semaphores(&signal_semaphores)
+                    .build();
+                logical
+                    .queue_submit(device.graphic_queue, &[submit_info], frame.in_flight)
+                    .map_err(FrameError::Draw)?;
+                Ok(())
+            })();
+            if let Err(e) = submitted {
+                // The fence is reset and the semaphore signaled, an empty batch waits on
+                // the one and signals the other so the frame can be drawn again.
+                let skip = vk::SubmitInfo::builder()
+                    .wait_semaphores(&wait_semaphores)
+                    .wait_dst_stage_mask(&wait_stages)
+                    .build();
+                let _ = logical.queue_submit(device.graphic_queue, &[skip], frame.in_flight);
+                return Err(e);
+            }
+
+            let swapchains = [swapchain.khr];
+            let images = [image];
+            let present_info = vk::PresentInfoKHR::builder()
+                .wait_semaphores(&signal_semaphores)
+                .swapchains(&swapchains)
+                .image_indices(&images);
+            self.current = (self.current + 1) % FRAMES_IN_FLIGHT;
+            match swapchain
+                .loader
+                .queue_present(device.graphic_queue, &present_info)
+            {
+                Ok(false) => Ok(FrameStatus::Presented),
+                Ok(true) | Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => Ok(FrameStatus::OutOfDate),
+                Err(e) => Err(FrameError::Draw(e)),
+            }
+        }
+    }
+
+    pub fn drop(&mut self, device: &Device) {
+        self.destroy_framebuffers(device);
+        for frame in self.frames.iter_mut() {
+            frame.drop(device);
+        }
+        unsafe { device.logical.destroy_render_pass(self.render_pass, None) };
+    }
+}
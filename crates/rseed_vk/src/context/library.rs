@@ -2,7 +2,7 @@ use rseed_core::prelude::*;
 
 use ash::{self, vk, Entry, Instance};
 
-pub(crate) use ash::version::{EntryV1_0, InstanceV1_0};
+pub(crate) use ash::version::{EntryV1_0, InstanceV1_0, InstanceV1_1};
 
 use super::debug::*;
 use super::window::{self, HasRawWindowHandle};
@@ -28,6 +28,8 @@ pub(crate) struct Library {
     pub(crate) entry: Entry,
     pub(crate) instance: Instance,
     pub(crate) enabled_layers: Vec<std::ffi::CString>,
+    /// Vulkan version of the instance, 1.2 when the loader supports it.
+    pub(crate) api_version: u32,
 }
 
 impl Library {
@@ -39,11 +41,20 @@ impl Library {
         let entry = unsafe { Entry::new().map_err(|e| LibraryError::LibLoadFail(e))? };
         let layer_names = Self::query_layers()?;
         //instance creation
-        let instance = Self::create_instance(&entry, app_name, app_version, window_handle)?;
+        // Loaders without vkEnumerateInstanceVersion only know about Vulkan 1.0.
+        let api_version = entry
+            .try_enumerate_instance_version()
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| vk::make_version(1, 0, 0))
+            .min(vk::make_version(1, 2, 0));
+        let instance =
+            Self::create_instance(&entry, app_name, app_version, api_version, window_handle)?;
         Ok(Self {
             entry,
             instance,
             enabled_layers: layer_names,
+            api_version,
         })
     }
 
@@ -51,15 +62,16 @@ impl Library {
         entry: &Entry,
         app_name: String,
         app_version: Version,
+        api_version: u32,
         window_handle: &dyn HasRawWindowHandle,
     ) -> Result<Instance> {
         // Instance creation
         let app_name = std::ffi::CString::new(app_name).unwrap();
         let engine_name = std::ffi::CString::new(ENGINE_NAME).unwrap();
         let app_info = vk::ApplicationInfo::builder()
-            .application_version(vk::make_version(2, 0, 0))
+            .application_version(app_version.into())
             .engine_version(ENGINE_VERSION.into())
-            .api_version(app_version.into())
+            .api_version(api_version)
             .application_name(&app_name)
             .engine_name(&engine_name);
 
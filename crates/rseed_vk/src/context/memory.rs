@@ -0,0 +1,366 @@
+use rseed_core::prelude::*;
+
+use std::{collections::HashMap, fmt, ptr};
+
+use ash::{self, vk};
+
+use super::device::*;
+use super::library::*;
+
+/// Smallest allocation of the buddy allocator, also its alignment.
+const MIN_ALLOCATION: u64 = 256;
+/// Blocks are 64 MiB, split in halves down to `MIN_ALLOCATION`.
+const BLOCK_ORDER: usize = 18;
+const BLOCK_SIZE: u64 = MIN_ALLOCATION << BLOCK_ORDER;
+
+#[derive(Debug, Error)]
+pub enum MemoryError {
+    #[error(display = "No memory type fits the resource.")]
+    NoMemoryType,
+    #[error(display = "Error allocating device memory: {:?}", _0)]
+    Allocation(vk::Result),
+    #[error(display = "Error mapping device memory: {:?}", _0)]
+    Map(vk::Result),
+    #[error(display = "Error creating a buffer: {:?}", _0)]
+    Buffer(vk::Result),
+    #[error(display = "Error creating an image: {:?}", _0)]
+    Image(vk::Result),
+    #[error(display = "Error creating a sampler: {:?}", _0)]
+    Sampler(vk::Result),
+    #[error(display = "Error binding memory to a resource: {:?}", _0)]
+    Bind(vk::Result),
+    #[error(display = "Error during a transfer: {:?}", _0)]
+    Transfer(vk::Result),
+}
+
+pub type Result<T> = std::result::Result<T, MemoryError>;
+
+/// Where a resource lives, decides the memory type.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MemoryUsage {
+    /// Only accessed by the GPU, filled through staging uploads.
+    GpuOnly,
+    /// Written by the CPU every frame or used as a staging buffer.
+    Upload,
+    /// Written by the GPU and read back by the CPU.
+    Readback,
+}
+
+impl MemoryUsage {
+    /// Required and preferred property flags.
+    fn flags(&self) -> (vk::MemoryPropertyFlags, vk::MemoryPropertyFlags) {
+        let host = vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT;
+        match self {
+            MemoryUsage::GpuOnly => (
+                vk::MemoryPropertyFlags::empty(),
+                vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            ),
+            MemoryUsage::Upload => (host, vk::MemoryPropertyFlags::empty()),
+            MemoryUsage::Readback => (host, vk::MemoryPropertyFlags::HOST_CACHED),
+        }
+    }
+}
+
+/// Usage of the device memory, to track leaks.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    pub blocks: usize,
+    /// Allocations too big for a block, with their own device memory.
+    pub dedicated: usize,
+    pub allocations: usize,
+    /// Bytes allocated from the device.
+    pub reserved: u64,
+    /// Bytes given to the resources, rounded up to a power of two inside the blocks.
+    pub used: u64,
+}
+
+impl fmt::Display for MemoryStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} allocations using {} KiB out of {} KiB in {} blocks and {} dedicated allocations",
+            self.allocations,
+            self.used / 1024,
+            self.reserved / 1024,
+            self.blocks,
+            self.dedicated
+        )
+    }
+}
+
+/// Buddy allocator over the offsets of a block.
+struct Buddy {
+    /// Free offsets for each order, order `n` being `MIN_ALLOCATION << n` bytes.
+    free: Vec<Vec<u64>>,
+    /// Order of the allocations by offset.
+    allocated: HashMap<u64, usize>,
+}
+
+impl Buddy {
+    fn new() -> Self {
+        let mut free = vec![Vec::new(); BLOCK_ORDER + 1];
+        free[BLOCK_ORDER].push(0);
+        Self {
+            free,
+            allocated: HashMap::new(),
+        }
+    }
+
+    fn order(size: u64) -> usize {
+        (size.max(MIN_ALLOCATION).next_power_of_two() / MIN_ALLOCATION).trailing_zeros() as usize
+    }
+
+    /// Offsets are multiple of the allocation size, which covers the alignment.
+    fn allocate(&mut self, order: usize) -> Option<u64> {
+        let mut current = (order..=BLOCK_ORDER).find(|o| !self.free[*o].is_empty())?;
+        let offset = self.free[current].pop()?;
+        while current > order {
+            current -= 1;
+            self.free[current].push(offset + (MIN_ALLOCATION << current));
+        }
+        self.allocated.insert(offset, order);
+        Some(offset)
+    }
+
+    fn free(&mut self, mut offset: u64) {
+        let mut order = match self.allocated.remove(&offset) {
+            Some(order) => order,
+            None => return,
+        };
+        // Merge with the buddy as long as it is free.
+        while order < BLOCK_ORDER {
+            let buddy = offset ^ (MIN_ALLOCATION << order);
+            match self.free[order].iter().position(|o| *o == buddy) {
+                Some(i) => {
+                    self.free[order].swap_remove(i);
+                    offset = offset.min(buddy);
+                    order += 1;
+                }
+                None => break,
+            }
+        }
+        self.free[order].push(offset);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.allocated.is_empty()
+    }
+}
+
+struct Block {
+    memory: vk::DeviceMemory,
+    /// Host visible blocks stay mapped for their whole life.
+    mapped: *mut u8,
+    buddy: Buddy,
+}
+
+/// Memory of one resource, given back with `Allocator::free`.
+pub(crate) struct Allocation {
+    pub memory: vk::DeviceMemory,
+    pub offset: u64,
+    pub size: u64,
+    /// Start of the allocation in host memory, null if it is not host visible.
+    mapped: *mut u8,
+    pool: (u32, bool),
+    dedicated: bool,
+}
+
+impl Allocation {
+    pub fn is_mapped(&self) -> bool {
+        !self.mapped.is_null()
+    }
+
+    /// Copy to host visible memory, which is always coherent.
+    pub fn write(&self, offset: u64, data: &[u8]) {
+        assert!(self.is_mapped() && offset + data.len() as u64 <= self.size);
+        unsafe {
+            ptr::copy_nonoverlapping(data.as_ptr(), self.mapped.add(offset as usize), data.len())
+        };
+    }
+
+    /// Copy between two regions of host visible memory, which must not overlap.
+    pub fn copy(&self, src: u64, dst: u64, len: u64) {
+        assert!(self.is_mapped() && src.max(dst) + len <= self.size);
+        assert!(src + len <= dst || dst + len <= src);
+        unsafe {
+            ptr::copy_nonoverlapping(self.mapped.add(src as usize), self.mapped.add(dst as usize), len as usize)
+        };
+    }
+}
+
+/// Sub-allocates resources in big blocks of device memory, one pool of blocks
+/// per memory type. Linear and optimal resources get separate pools so the
+/// buffer image granularity never has to be checked.
+pub(crate) struct Allocator {
+    properties: vk::PhysicalDeviceMemoryProperties,
+    pools: HashMap<(u32, bool), Vec<Block>>,
+    stats: MemoryStats,
+}
+
+impl Allocator {
+    pub fn init(lib: &Library, device: &Device) -> Self {
+        let properties = unsafe {
+            lib.instance
+                .get_physical_device_memory_properties(device.physical)
+        };
+        Self {
+            properties,
+            pools: HashMap::new(),
+            stats: MemoryStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> MemoryStats {
+        self.stats
+    }
+
+    fn memory_type(&self, type_bits: u32, usage: MemoryUsage) -> Option<u32> {
+        let (required, preferred) = usage.flags();
+        let types = &self.properties.memory_types[..self.properties.memory_type_count as usize];
+        let find = |flags: vk::MemoryPropertyFlags| {
+            types
+                .iter()
+                .enumerate()
+                .position(|(i, t)| type_bits & (1 << i) != 0 && t.property_flags.contains(flags))
+                .map(|i| i as u32)
+        };
+        find(required | preferred).or_else(|| find(required))
+    }
+
+    fn is_host_visible(&self, memory_type: u32) -> bool {
+        self.properties.memory_types[memory_type as usize]
+            .property_flags
+            .contains(vk::MemoryPropertyFlags::HOST_VISIBLE)
+    }
+
+    fn allocate_memory(
+        &self,
+        device: &ash::Device,
+        memory_type: u32,
+        size: u64,
+    ) -> Result<(vk::DeviceMemory, *mut u8)> {
+        let info = vk::MemoryAllocateInfo::builder()
+            .allocation_size(size)
+            .memory_type_index(memory_type);
+        unsafe {
+            let memory = device
+                .allocate_memory(&info, None)
+                .map_err(MemoryError::Allocation)?;
+            if !self.is_host_visible(memory_type) {
+                return Ok((memory, ptr::null_mut()));
+            }
+            match device.map_memory(memory, 0, vk::WHOLE_SIZE, vk::MemoryMapFlags::empty()) {
+                Ok(mapped) => Ok((memory, mapped as *mut u8)),
+                Err(e) => {
+                    device.free_memory(memory, None);
+                    Err(MemoryError::Map(e))
+                }
+            }
+        }
+    }
+
+    /// `linear` is true for buffers and linear images.
+    pub fn allocate(
+        &mut self,
+        device: &ash::Device,
+        requirements: vk::MemoryRequirements,
+        usage: MemoryUsage,
+        linear: bool,
+    ) -> Result<Allocation> {
+        let memory_type = self
+            .memory_type(requirements.memory_type_bits, usage)
+            .ok_or(MemoryError::NoMemoryType)?;
+        let size = requirements.size.max(requirements.alignment);
+
+        // Half a block would waste too much space, better get its own memory.
+        if size > BLOCK_SIZE / 2 {
+            let (memory, mapped) = self.allocate_memory(device, memory_type, size)?;
+            self.stats.dedicated += 1;
+            self.stats.allocations += 1;
+            self.stats.reserved += size;
+            self.stats.used += size;
+            return Ok(Allocation {
+                memory,
+                offset: 0,
+                size,
+                mapped,
+                pool: (memory_type, linear),
+                dedicated: true,
+            });
+        }
+
+        let order = Buddy::order(size);
+        let pool = (memory_type, linear);
+        let found = self.pools.get_mut(&pool).and_then(|blocks| {
+            blocks
+                .iter_mut()
+                .find_map(|b| b.buddy.allocate(order).map(|offset| (b.memory, b.mapped, offset)))
+        });
+        let (memory, mapped, offset) = match found {
+            Some(found) => found,
+            None => {
+                let (memory, mapped) = self.allocate_memory(device, memory_type, BLOCK_SIZE)?;
+                let mut block = Block {
+                    memory,
+                    mapped,
+                    buddy: Buddy::new(),
+                };
+                let offset = block.buddy.allocate(order).unwrap();
+                self.pools.entry(pool).or_default().push(block);
+                self.stats.blocks += 1;
+                self.stats.reserved += BLOCK_SIZE;
+                (memory, mapped, offset)
+            }
+        };
+        let size = MIN_ALLOCATION << order;
+        self.stats.allocations += 1;
+        self.stats.used += size;
+        Ok(Allocation {
+            memory,
+            offset,
+            size,
+            mapped: if mapped.is_null() {
+                mapped
+            } else {
+                unsafe { mapped.add(offset as usize) }
+            },
+            pool,
+            dedicated: false,
+        })
+    }
+
+    /// Empty blocks are given back to the device.
+    pub fn free(&mut self, device: &ash::Device, allocation: Allocation) {
+        self.stats.allocations -= 1;
+        self.stats.used -= allocation.size;
+        if allocation.dedicated {
+            self.stats.dedicated -= 1;
+            self.stats.reserved -= allocation.size;
+            unsafe { device.free_memory(allocation.memory, None) };
+            return;
+        }
+        let blocks = match self.pools.get_mut(&allocation.pool) {
+            Some(blocks) => blocks,
+            None => return,
+        };
+        if let Some(i) = blocks.iter().position(|b| b.memory == allocation.memory) {
+            blocks[i].buddy.free(allocation.offset);
+            if blocks[i].buddy.is_empty() {
+                let block = blocks.swap_remove(i);
+                self.stats.blocks -= 1;
+                self.stats.reserved -= BLOCK_SIZE;
+                unsafe { device.free_memory(block.memory, None) };
+            }
+        }
+    }
+
+    /// The allocations still alive are lost, check the stats for leaks.
+    pub fn drop(&mut self, device: &ash::Device) {
+        for (_, blocks) in self.pools.drain() {
+            for block in blocks {
+                unsafe { device.free_memory(block.memory, None) };
+            }
+        }
+        self.stats = MemoryStats::default();
+    }
+}
@@ -0,0 +1,288 @@
+use std::{ffi::CStr, fmt};
+
+use ash::{self, extensions::khr, vk};
+use rseed_log::Logger;
+use rseed_renderapi::settings::DeviceSelector;
+
+use super::library::{InstanceV1_0, InstanceV1_1, Library};
+use super::surface::Surface;
+
+pub(crate) type Result<T> = std::result::Result<T, vk::Result>;
+
+/// Queue families used by the context, transfers and compute share
+/// the graphic family when the device has no dedicated ones.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct QueueFamilies {
+    /// Supports graphics and presentation to the surface.
+    pub graphic: u32,
+    pub transfer: u32,
+    pub compute: u32,
+}
+
+impl QueueFamilies {
+    fn find(
+        lib: &Library,
+        physical: vk::PhysicalDevice,
+        surface: &Surface,
+    ) -> Result<Option<Self>> {
+        let families = unsafe {
+            lib.instance
+                .get_physical_device_queue_family_properties(physical)
+        };
+        let mut graphic = None;
+        let mut transfer = None;
+        let mut compute = None;
+        for (index, family) in families.iter().enumerate() {
+            let index = index as u32;
+            if family.queue_count == 0 {
+                continue;
+            }
+            let flags = family.queue_flags;
+            if graphic.is_none() && flags.contains(vk::QueueFlags::GRAPHICS) {
+                let present = unsafe {
+                    surface
+                        .loader
+                        .get_physical_device_surface_support(physical, index, surface.khr)?
+                };
+                if present {
+                    graphic = Some(index);
+                }
+            }
+            // Dedicated families run next to the graphic work.
+            if transfer.is_none()
+                && flags.contains(vk::QueueFlags::TRANSFER)
+                && !flags.intersects(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE)
+            {
+                transfer = Some(index);
+            }
+            if compute.is_none()
+                && flags.contains(vk::QueueFlags::COMPUTE)
+                && !flags.contains(vk::QueueFlags::GRAPHICS)
+            {
+                compute = Some(index);
+            }
+        }
+        Ok(graphic.map(|graphic| Self {
+            graphic,
+            transfer: transfer.unwrap_or(graphic),
+            compute: compute.unwrap_or(graphic),
+        }))
+    }
+
+    pub fn has_dedicated_transfer(&self) -> bool {
+        self.transfer != self.graphic
+    }
+
+    pub fn has_dedicated_compute(&self) -> bool {
+        self.compute != self.graphic
+    }
+}
+
+/// What a physical device can do, logged when the context starts.
+#[derive(Clone, Debug)]
+pub struct DeviceReport {
+    pub index: usize,
+    pub name: String,
+    pub device_type: vk::PhysicalDeviceType,
+    /// Major, minor and patch.
+    pub api_version: (u32, u32, u32),
+    pub driver_version: u32,
+    /// Size of the device local heaps in bytes.
+    pub local_memory: u64,
+    pub max_image_dimension_2d: u32,
+    pub max_push_constants_size: u32,
+    pub max_bound_descriptor_sets: u32,
+    pub max_sampler_anisotropy: f32,
+    pub sampler_anisotropy: bool,
+    pub fill_mode_non_solid: bool,
+    /// Textures can be indexed from a single array, needs Vulkan 1.2.
+    pub descriptor_indexing: bool,
+    /// `None` when no family can draw to the surface.
+    pub families: Option<QueueFamilies>,
+    pub swapchain: bool,
+    /// `None` when the device can't be used.
+    pub score: Option<u32>,
+}
+
+impl DeviceReport {
+    fn new(
+        lib: &Library,
+        index: usize,
+        physical: vk::PhysicalDevice,
+        surface: &Surface,
+    ) -> Result<Self> {
+        let (props, features, memory, extensions) = unsafe {
+            (
+                lib.instance.get_physical_device_properties(physical),
+                lib.instance.get_physical_device_features(physical),
+                lib.instance.get_physical_device_memory_properties(physical),
+                lib.instance
+                    .enumerate_device_extension_properties(physical)?,
+            )
+        };
+        let name = unsafe { CStr::from_ptr(props.device_name.as_ptr()) }
+            .to_string_lossy()
+            .into_owned();
+        let swapchain = extensions.iter().any(|e| {
+            let name = unsafe { CStr::from_ptr(e.extension_name.as_ptr()) };
+            name == khr::Swapchain::name()
+        });
+        let local_memory = memory.memory_heaps[..memory.memory_heap_count as usize]
+            .iter()
+            .filter(|h| h.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL))
+            .map(|h| h.size)
+            .sum();
+        let descriptor_indexing = lib.api_version >= vk::make_version(1, 2, 0)
+            && props.api_version >= vk::make_version(1, 2, 0)
+            && Self::supports_descriptor_indexing(lib, physical);
+        let limits = &props.limits;
+        let mut report = Self {
+            index,
+            name,
+            device_type: props.device_type,
+            api_version: (
+                vk::version_major(props.api_version),
+                vk::version_minor(props.api_version),
+                vk::version_patch(props.api_version),
+            ),
+            driver_version: props.driver_version,
+            local_memory,
+            max_image_dimension_2d: limits.max_image_dimension2_d,
+            max_push_constants_size: limits.max_push_constants_size,
+            max_bound_descriptor_sets: limits.max_bound_descriptor_sets,
+            max_sampler_anisotropy: limits.max_sampler_anisotropy,
+            sampler_anisotropy: features.sampler_anisotropy == vk::TRUE,
+            fill_mode_non_solid: features.fill_mode_non_solid == vk::TRUE,
+            descriptor_indexing,
+            families: QueueFamilies::find(lib, physical, surface)?,
+            swapchain,
+            score: None,
+        };
+        report.score = report.rate();
+        Ok(report)
+    }
+
+    /// The features used by the bindless textures.
+    fn supports_descriptor_indexing(lib: &Library, physical: vk::PhysicalDevice) -> bool {
+        let mut indexing = vk::PhysicalDeviceDescriptorIndexingFeatures::default();
+        // Chained by hand, the builder does not accept it as an extension.
+        let mut features = vk::PhysicalDeviceFeatures2 {
+            p_next: &mut indexing as *mut _ as *mut std::ffi::c_void,
+            ..Default::default()
+        };
+        unsafe {
+            lib.instance
+                .get_physical_device_features2(physical, &mut features)
+        };
+        indexing.shader_sampled_image_array_non_uniform_indexing == vk::TRUE
+            && indexing.descriptor_binding_sampled_image_update_after_bind == vk::TRUE
+            && indexing.descriptor_binding_partially_bound == vk::TRUE
+            && indexing.descriptor_binding_variable_descriptor_count == vk::TRUE
+            && indexing.runtime_descriptor_array == vk::TRUE
+    }
+
+    /// Discrete GPUs first, then integrated, virtual and CPU devices,
+    /// the limits and dedicated queues decide between devices of the same kind.
+    fn rate(&self) -> Option<u32> {
+        let families = self.families?;
+        if !self.swapchain {
+            return None;
+        }
+        let mut score = match self.device_type {
+            vk::PhysicalDeviceType::DISCRETE_GPU => 10_000,
+            vk::PhysicalDeviceType::INTEGRATED_GPU => 5_000,
+            vk::PhysicalDeviceType::VIRTUAL_GPU => 2_000,
+            vk::PhysicalDeviceType::CPU => 500,
+            _ => 100,
+        };
+        score += (self.local_memory >> 30).min(64) as u32 * 20;
+        score += self.max_image_dimension_2d / 1024;
+        if families.has_dedicated_transfer() {
+            score += 50;
+        }
+        if families.has_dedicated_compute() {
+            score += 50;
+        }
+        if self.sampler_anisotropy {
+            score += 10;
+        }
+        if self.fill_mode_non_solid {
+            score += 10;
+        }
+        if self.descriptor_indexing {
+            score += 10;
+        }
+        Some(score)
+    }
+}
+
+impl fmt::Display for DeviceReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (major, minor, patch) = self.api_version;
+        write!(
+            f,
+            "[{}] {} ({:?}), Vulkan {}.{}.{}, {} MiB local memory, 2D images up to {}, \
+             {} bytes of push constants, {} descriptor sets",
+            self.index,
+            self.name,
+            self.device_type,
+            major,
+            minor,
+            patch,
+            self.local_memory >> 20,
+            self.max_image_dimension_2d,
+            self.max_push_constants_size,
+            self.max_bound_descriptor_sets,
+        )?;
+        match (self.families, self.score) {
+            (Some(families), Some(score)) => write!(
+                f,
+                ", queues {}/{}/{}, score {}",
+                families.graphic, families.transfer, families.compute, score
+            ),
+            (None, _) => write!(f, ", can't present to the window"),
+            (_, None) => write!(f, ", no swapchain support"),
+        }
+    }
+}
+
+/// Choose the device to render with: the one asked by the user if it can be used,
+/// else the best rated one. Every device is logged with its capabilities.
+pub(crate) fn select_device(
+    lib: &Library,
+    surface: &Surface,
+    selector: Option<&DeviceSelector>,
+) -> Result<Option<(vk::PhysicalDevice, DeviceReport)>> {
+    let logger = Logger::new(String::from("Vulkan"));
+    let physicals = unsafe { lib.instance.enumerate_physical_devices()? };
+    let mut devices = Vec::with_capacity(physicals.len());
+    for (index, physical) in physicals.into_iter().enumerate() {
+        let report = DeviceReport::new(lib, index, physical, surface)?;
+        logger.info(&format_args!("{}", report));
+        devices.push((physical, report));
+    }
+
+    let usable = |(_, report): &&(vk::PhysicalDevice, DeviceReport)| report.score.is_some();
+    if let Some(selector) = selector {
+        let requested = devices
+            .iter()
+            .filter(usable)
+            .find(|(_, report)| selector.matches(report.index, &report.name));
+        match requested {
+            Some((physical, report)) => {
+                logger.info(&format_args!("Using {}, as requested", report.name));
+                return Ok(Some((*physical, report.clone())));
+            }
+            None => logger.warn(&format_args!("No usable device matches {:?}", selector)),
+        }
+    }
+    let best = devices
+        .iter()
+        .filter(usable)
+        .max_by_key(|(_, report)| report.score)
+        .map(|(physical, report)| (*physical, report.clone()));
+    if let Some((_, report)) = &best {
+        logger.info(&format_args!("Using {}", report.name));
+    }
+    Ok(best)
+}
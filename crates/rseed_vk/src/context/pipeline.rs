@@ -1,23 +1,772 @@
 use rseed_core::prelude::*;
 
+use std::{
+    ffi::CString,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
 use ash::{self, vk};
+use rseed_renderapi::{
+    buffer::{VertexFormat, VertexLayout},
+    descriptor::BindingDesc,
+    pipeline as api,
+};
+pub use rseed_renderapi::pipeline::BlendMode;
+use rseed_shaderlib::{self as shaderlib, reflect::Block, CompiledShader, Reflection};
+
+use super::descriptor::{vk_shader_stages, DescriptorError, DescriptorLayoutCache};
+use super::device::*;
 
+#[derive(Debug, Error)]
+pub enum PipelineError {
+    #[error(display = "Invalid SPIR-V: {}", _0)]
+    InvalidSpirv(std::io::Error),
+    #[error(display = "Invalid entry point name {}.", _0)]
+    InvalidEntryPoint(String),
+    #[error(display = "Error creating a shader module: {:?}", _0)]
+    ShaderModule(vk::Result),
+    #[error(display = "Error with a descriptor set layout: {}", _0)]
+    DescriptorSetLayout(DescriptorError),
+    #[error(display = "Error creating the pipeline layout: {:?}", _0)]
+    Layout(vk::Result),
+    #[error(display = "Error creating the pipeline: {:?}", _0)]
+    Creation(vk::Result),
+    #[error(display = "Error with the pipeline cache: {:?}", _0)]
+    Cache(vk::Result),
+    #[error(display = "Io error on the pipeline cache: {}", _0)]
+    CacheIo(std::io::Error),
+    #[error(display = "There is no pipeline with the handle {}.", _0)]
+    NoPipeline(u32),
+    #[error(display = "Instance divisor {} unsupported, core Vulkan only steps once per instance.", _0)]
+    Divisor(u32),
+}
 
+pub type Result<T> = std::result::Result<T, PipelineError>;
 
+pub fn vk_vertex_format(format: VertexFormat) -> vk::Format {
+    match format {
+        VertexFormat::Float => vk::Format::R32_SFLOAT,
+        VertexFormat::Float2 => vk::Format::R32G32_SFLOAT,
+        VertexFormat::Float3 => vk::Format::R32G32B32_SFLOAT,
+        VertexFormat::Float4 => vk::Format::R32G32B32A32_SFLOAT,
+        VertexFormat::Int => vk::Format::R32_SINT,
+        VertexFormat::Int2 => vk::Format::R32G32_SINT,
+        VertexFormat::Int3 => vk::Format::R32G32B32_SINT,
+        VertexFormat::Int4 => vk::Format::R32G32B32A32_SINT,
+        VertexFormat::UInt => vk::Format::R32_UINT,
+        VertexFormat::UInt2 => vk::Format::R32G32_UINT,
+        VertexFormat::UInt3 => vk::Format::R32G32B32_UINT,
+        VertexFormat::UInt4 => vk::Format::R32G32B32A32_UINT,
+        VertexFormat::Half2 => vk::Format::R16G16_SFLOAT,
+        VertexFormat::Half4 => vk::Format::R16G16B16A16_SFLOAT,
+        VertexFormat::Short2 => vk::Format::R16G16_SINT,
+        VertexFormat::Short2Norm => vk::Format::R16G16_SNORM,
+        VertexFormat::Short4 => vk::Format::R16G16B16A16_SINT,
+        VertexFormat::Short4Norm => vk::Format::R16G16B16A16_SNORM,
+        VertexFormat::UShort2 => vk::Format::R16G16_UINT,
+        VertexFormat::UShort2Norm => vk::Format::R16G16_UNORM,
+        VertexFormat::UShort4 => vk::Format::R16G16B16A16_UINT,
+        VertexFormat::UShort4Norm => vk::Format::R16G16B16A16_UNORM,
+        VertexFormat::Byte4 => vk::Format::R8G8B8A8_SINT,
+        VertexFormat::Byte4Norm => vk::Format::R8G8B8A8_SNORM,
+        VertexFormat::UByte2 => vk::Format::R8G8_UINT,
+        VertexFormat::UByte2Norm => vk::Format::R8G8_UNORM,
+        VertexFormat::UByte4 => vk::Format::R8G8B8A8_UINT,
+        VertexFormat::UByte4Norm => vk::Format::R8G8B8A8_UNORM,
+    }
+}
+
+/// One stage of the pipeline, the code is the SPIR-V binary.
 #[derive(Clone, Debug)]
-pub enum PipelineError {}
+pub struct ShaderStage {
+    pub stage: vk::ShaderStageFlags,
+    pub spirv: Vec<u8>,
+    pub entry_point: String,
+}
 
-pub type Result<T> = std::result::Result<T, PipelineError>;
+impl ShaderStage {
+    pub fn new(stage: vk::ShaderStageFlags, spirv: Vec<u8>) -> Self {
+        Self {
+            stage,
+            spirv,
+            entry_point: String::from("main"),
+        }
+    }
+}
+
+/// A vertex buffer binding, its index is the position in `PipelineDesc::vertex_bindings`.
+#[derive(Clone, Debug)]
+pub struct VertexBinding {
+    pub layout: VertexLayout,
+    pub input_rate: vk::VertexInputRate,
+}
+
+impl VertexBinding {
+    /// The rate follows the divisor of the layout.
+    pub fn new(layout: VertexLayout) -> Self {
+        let input_rate = if layout.is_instanced() {
+            vk::VertexInputRate::INSTANCE
+        } else {
+            vk::VertexInputRate::VERTEX
+        };
+        Self { layout, input_rate }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct RasterState {
+    pub polygon_mode: vk::PolygonMode,
+    pub cull_mode: vk::CullModeFlags,
+    pub front_face: vk::FrontFace,
+    pub line_width: f32,
+    pub samples: vk::SampleCountFlags,
+}
+
+impl Default for RasterState {
+    fn default() -> Self {
+        Self {
+            polygon_mode: vk::PolygonMode::FILL,
+            cull_mode: vk::CullModeFlags::BACK,
+            front_face: vk::FrontFace::COUNTER_CLOCKWISE,
+            line_width: 1f32,
+            samples: vk::SampleCountFlags::TYPE_1,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct DepthState {
+    pub test: bool,
+    pub write: bool,
+    pub compare: vk::CompareOp,
+}
+
+impl Default for DepthState {
+    fn default() -> Self {
+        Self {
+            test: true,
+            write: true,
+            compare: vk::CompareOp::LESS_OR_EQUAL,
+        }
+    }
+}
 
+pub fn blend_attachment_state(mode: BlendMode) -> vk::PipelineColorBlendAttachmentState {
+    let state = vk::PipelineColorBlendAttachmentState::builder()
+        .color_write_mask(vk::ColorComponentFlags::all());
+    match mode {
+        BlendMode::Opaque => state.blend_enable(false),
+        BlendMode::Alpha => state
+            .blend_enable(true)
+            .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+            .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .color_blend_op(vk::BlendOp::ADD)
+            .src_alpha_blend_factor(vk::BlendFactor::ONE)
+            .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .alpha_blend_op(vk::BlendOp::ADD),
+        BlendMode::Additive => state
+            .blend_enable(true)
+            .src_color_blend_factor(vk::BlendFactor::ONE)
+            .dst_color_blend_factor(vk::BlendFactor::ONE)
+            .color_blend_op(vk::BlendOp::ADD)
+            .src_alpha_blend_factor(vk::BlendFactor::ONE)
+            .dst_alpha_blend_factor(vk::BlendFactor::ONE)
+            .alpha_blend_op(vk::BlendOp::ADD),
+    }
+    .build()
+}
+
+/// Everything needed to build a graphics pipeline, viewport and scissor are dynamic.
+#[derive(Clone, Debug)]
+pub struct PipelineDesc {
+    pub stages: Vec<ShaderStage>,
+    pub vertex_bindings: Vec<VertexBinding>,
+    pub topology: vk::PrimitiveTopology,
+    pub raster: RasterState,
+    pub depth: DepthState,
+    /// One blend mode per color attachment of the subpass.
+    pub blend: Vec<BlendMode>,
+    /// Bindings of each descriptor set, by set index. The render commands fill the set 0.
+    pub descriptor_sets: Vec<Vec<BindingDesc>>,
+    /// Append the bindless texture array after the other sets,
+    /// ignored when the device does not support it.
+    pub bindless: bool,
+    pub push_constants: Vec<vk::PushConstantRange>,
+    /// Members of the push constant block, the `SetUniform` commands write them by name.
+    pub push_block: Option<Block>,
+}
+
+impl Default for PipelineDesc {
+    fn default() -> Self {
+        Self {
+            stages: Vec::new(),
+            vertex_bindings: Vec::new(),
+            topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+            raster: RasterState::default(),
+            depth: DepthState::default(),
+            blend: vec![BlendMode::Opaque],
+            descriptor_sets: Vec::new(),
+            bindless: false,
+            push_constants: Vec::new(),
+            push_block: None,
+        }
+    }
+}
+
+impl PipelineDesc {
+    /// Stages, descriptor sets and push constants reflected from compiled shaders,
+    /// the vertex shader inputs are fed by a single tightly packed buffer.
+    pub fn from_shaders(shaders: &[CompiledShader]) -> Self {
+        let mut desc = Self::default();
+        let mut reflection: Option<Reflection> = None;
+        for shader in shaders {
+            desc.stages
+                .push(ShaderStage::new(vk_shader_stage(shader.stage), shader.bytes()));
+            match reflection.as_mut() {
+                Some(reflection) => reflection.merge(&shader.reflection),
+                None => reflection = Some(shader.reflection.clone()),
+            }
+        }
+        let reflection = match reflection {
+            Some(reflection) => reflection,
+            None => return desc,
+        };
+        desc.descriptor_sets = reflection.descriptor_sets();
+        if let Some(block) = &reflection.push_constants {
+            desc.push_constants.push(vk::PushConstantRange {
+                stage_flags: vk_shader_stages(reflection.stages),
+                offset: 0,
+                size: block.size,
+            });
+            desc.push_block = Some(block.clone());
+        }
+        if let Some(layout) = reflection.vertex_layout().filter(|l| !l.attributes.is_empty()) {
+            desc.vertex_bindings.push(VertexBinding::new(layout));
+        }
+        desc
+    }
+
+    /// The reflected description with the state of a backend independent one,
+    /// the vertex layouts replace the reflected one when given.
+    pub fn from_api(shaders: &[CompiledShader], api: &api::PipelineDesc) -> Self {
+        let mut desc = Self::from_shaders(shaders);
+        if !api.vertex_layouts.is_empty() {
+            desc.vertex_bindings = api.vertex_layouts.iter().cloned().map(VertexBinding::new).collect();
+        }
+        desc.topology = crate::vk_topology(api.primitive);
+        desc.blend = vec![api.blend];
+        desc.depth.test = api.depth_test;
+        desc.depth.write = api.depth_test;
+        desc.raster.cull_mode = if api.cull {
+            vk::CullModeFlags::BACK
+        } else {
+            vk::CullModeFlags::NONE
+        };
+        desc
+    }
+}
+
+pub fn vk_shader_stage(stage: shaderlib::ShaderStage) -> vk::ShaderStageFlags {
+    match stage {
+        shaderlib::ShaderStage::Vertex => vk::ShaderStageFlags::VERTEX,
+        shaderlib::ShaderStage::TessControl => vk::ShaderStageFlags::TESSELLATION_CONTROL,
+        shaderlib::ShaderStage::TessEvaluation => vk::ShaderStageFlags::TESSELLATION_EVALUATION,
+        shaderlib::ShaderStage::Geometry => vk::ShaderStageFlags::GEOMETRY,
+        shaderlib::ShaderStage::Fragment => vk::ShaderStageFlags::FRAGMENT,
+        shaderlib::ShaderStage::Compute => vk::ShaderStageFlags::COMPUTE,
+    }
+}
+
+pub(crate) fn create_shader_module(device: &Device, spirv: &[u8]) -> Result<vk::ShaderModule> {
+    let code = ash::util::read_spv(&mut Cursor::new(spirv)).map_err(PipelineError::InvalidSpirv)?;
+    let info = vk::ShaderModuleCreateInfo::builder().code(&code);
+    unsafe {
+        device
+            .logical
+            .create_shader_module(&info, None)
+            .map_err(PipelineError::ShaderModule)
+    }
+}
+
+/// Driver cache of compiled pipelines, saved to disk to speed up the next launches.
+pub(crate) struct PipelineCache {
+    pub cache: vk::PipelineCache,
+    path: Option<PathBuf>,
+}
+
+impl PipelineCache {
+    /// Load the cache from the file if it exists, the driver ignores data from another device.
+    pub fn init(device: &Device, path: Option<&Path>) -> Result<Self> {
+        let data = path
+            .filter(|p| p.exists())
+            .map(std::fs::read)
+            .transpose()
+            .map_err(PipelineError::CacheIo)?
+            .unwrap_or_default();
+        let info = vk::PipelineCacheCreateInfo::builder().initial_data(&data);
+        let cache = unsafe {
+            match device.logical.create_pipeline_cache(&info, None) {
+                Ok(cache) => cache,
+                // A corrupted file should not prevent the engine from starting.
+                Err(_) => device
+                    .logical
+                    .create_pipeline_cache(&vk::PipelineCacheCreateInfo::default(), None)
+                    .map_err(PipelineError::Cache)?,
+            }
+        };
+        Ok(Self {
+            cache,
+            path: path.map(|p| p.to_path_buf()),
+        })
+    }
+
+    pub fn save(&self, device: &Device) -> Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let data = unsafe {
+            device
+                .logical
+                .get_pipeline_cache_data(self.cache)
+                .map_err(PipelineError::Cache)?
+        };
+        std::fs::write(path, data).map_err(PipelineError::CacheIo)
+    }
+
+    pub fn drop(&mut self, device: &Device) {
+        unsafe { device.logical.destroy_pipeline_cache(self.cache, None) };
+    }
+}
+
+/// The set layouts belong to the layout cache of the context.
 pub(crate) struct Pipeline {
     pub pipeline: vk::Pipeline,
     pub layout: vk::PipelineLayout,
+    pub set_layouts: Vec<vk::DescriptorSetLayout>,
+    /// Bindings of each set, by set index.
+    pub sets: Vec<Vec<BindingDesc>>,
+    /// Index of the bindless set, after the others.
+    pub bindless_set: Option<u32>,
+    pub push_block: Option<Block>,
+    /// Stages reading the push constants.
+    pub push_stages: vk::ShaderStageFlags,
+    /// The primitive the draws must use.
+    pub topology: vk::PrimitiveTopology,
 }
 
 impl Pipeline {
+    /// `bindless` is the layout of the bindless set, if the device supports it.
+    pub fn init(
+        device: &Device,
+        cache: &PipelineCache,
+        layouts: &mut DescriptorLayoutCache,
+        bindless: Option<vk::DescriptorSetLayout>,
+        render_pass: vk::RenderPass,
+        subpass: u32,
+        desc: &PipelineDesc,
+    ) -> Result<Self> {
+        if let Some(binding) = desc.vertex_bindings.iter().find(|b| b.layout.divisor > 1) {
+            return Err(PipelineError::Divisor(binding.layout.divisor));
+        }
+        let mut set_layouts = desc
+            .descriptor_sets
+            .iter()
+            .map(|bindings| layouts.get(&device.logical, bindings))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(PipelineError::DescriptorSetLayout)?;
+        let bindless_set = match bindless {
+            Some(layout) if desc.bindless => {
+                set_layouts.push(layout);
+                Some(set_layouts.len() as u32 - 1)
+            }
+            _ => None,
+        };
+        let layout_info = vk::PipelineLayoutCreateInfo::builder()
+            .set_layouts(&set_layouts)
+            .push_constant_ranges(&desc.push_constants);
+        let layout = unsafe {
+            device
+                .logical
+                .create_pipeline_layout(&layout_info, None)
+                .map_err(PipelineError::Layout)?
+        };
+        let mut pipeline = Self {
+            pipeline: vk::Pipeline::null(),
+            layout,
+            set_layouts,
+            sets: desc.descriptor_sets.clone(),
+            bindless_set,
+            push_block: desc.push_block.clone(),
+            push_stages: desc
+                .push_constants
+                .iter()
+                .fold(vk::ShaderStageFlags::empty(), |stages, range| stages | range.stage_flags),
+            topology: desc.topology,
+        };
+        match pipeline.create(device, cache, render_pass, subpass, desc) {
+            Ok(p) => {
+                pipeline.pipeline = p;
+                Ok(pipeline)
+            }
+            Err(e) => {
+                pipeline.drop(device);
+                Err(e)
+            }
+        }
+    }
+
+    fn create(
+        &self,
+        device: &Device,
+        cache: &PipelineCache,
+        render_pass: vk::RenderPass,
+        subpass: u32,
+        desc: &PipelineDesc,
+    ) -> Result<vk::Pipeline> {
+        let mut modules = Vec::with_capacity(desc.stages.len());
+        let result = self.create_with_modules(device, cache, render_pass, subpass, desc, &mut modules);
+        for module in modules {
+            unsafe { device.logical.destroy_shader_module(module, None) };
+        }
+        result
+    }
+
+    fn create_with_modules(
+        &self,
+        device: &Device,
+        cache: &PipelineCache,
+        render_pass: vk::RenderPass,
+        subpass: u32,
+        desc: &PipelineDesc,
+        modules: &mut Vec<vk::ShaderModule>,
+    ) -> Result<vk::Pipeline> {
+        let mut entry_points = Vec::with_capacity(desc.stages.len());
+        for stage in desc.stages.iter() {
+            modules.push(create_shader_module(device, &stage.spirv)?);
+            entry_points.push(
+                CString::new(stage.entry_point.as_str())
+                    .map_err(|_| PipelineError::InvalidEntryPoint(stage.entry_point.clone()))?,
+            );
+        }
+        let stages: Vec<vk::PipelineShaderStageCreateInfo> = desc
+            .stages
+            .iter()
+            .zip(modules.iter().zip(entry_points.iter()))
+            .map(|(stage, (module, entry))| {
+                vk::PipelineShaderStageCreateInfo::builder()
+                    .stage(stage.stage)
+                    .module(*module)
+                    .name(entry)
+                    .build()
+            })
+            .collect();
+
+        let bindings: Vec<vk::VertexInputBindingDescription> = desc
+            .vertex_bindings
+            .iter()
+            .enumerate()
+            .map(|(i, b)| {
+                vk::VertexInputBindingDescription::builder()
+                    .binding(i as u32)
+                    .stride(b.layout.stride)
+                    .input_rate(b.input_rate)
+                    .build()
+            })
+            .collect();
+        let attributes: Vec<vk::VertexInputAttributeDescription> = desc
+            .vertex_bindings
+            .iter()
+            .enumerate()
+            .flat_map(|(i, b)| {
+                b.layout.attributes.iter().map(move |a| {
+                    vk::VertexInputAttributeDescription::builder()
+                        .binding(i as u32)
+                        .location(a.location)
+                        .format(vk_vertex_format(a.format))
+                        .offset(a.offset)
+                        .build()
+                })
+            })
+            .collect();
+        let vertex_input = vk::PipelineVertexInputStateCreateInfo::builder()
+            .vertex_binding_descriptions(&bindings)
+            .vertex_attribute_descriptions(&attributes);
+        let input_assembly =
+            vk::PipelineInputAssemblyStateCreateInfo::builder().topology(desc.topology);
+
+        // Only the counts matter, viewport and scissor are set when recording.
+        let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
+            .viewport_count(1)
+            .scissor_count(1);
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic = vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(&dynamic_states);
+
+        let raster = vk::PipelineRasterizationStateCreateInfo::builder()
+            .polygon_mode(desc.raster.polygon_mode)
+            .cull_mode(desc.raster.cull_mode)
+            .front_face(desc.raster.front_face)
+            .line_width(desc.raster.line_width);
+        let multisample = vk::PipelineMultisampleStateCreateInfo::builder()
+            .rasterization_samples(desc.raster.samples);
+        let depth = vk::PipelineDepthStencilStateCreateInfo::builder()
+            .depth_test_enable(desc.depth.test)
+            .depth_write_enable(desc.depth.write)
+            .depth_compare_op(desc.depth.compare);
+        let attachments: Vec<vk::PipelineColorBlendAttachmentState> =
+            desc.blend.iter().map(|b| blend_attachment_state(*b)).collect();
+        let blend = vk::PipelineColorBlendStateCreateInfo::builder().attachments(&attachments);
 
-    pub fn init() -> () {
-        todo!()
+        let info = vk::GraphicsPipelineCreateInfo::builder()
+            .stages(&stages)
+            .vertex_input_state(&vertex_input)
+            .input_assembly_state(&input_assembly)
+            .viewport_state(&viewport_state)
+            .rasterization_state(&raster)
+            .multisample_state(&multisample)
+            .depth_stencil_state(&depth)
+            .color_blend_state(&blend)
+            .dynamic_state(&dynamic)
+            .layout(self.layout)
+            .render_pass(render_pass)
+            .subpass(subpass)
+            .build();
+        let pipelines = unsafe {
+            device
+                .logical
+                .create_graphics_pipelines(cache.cache, &[info], None)
+                .map_err(|(_, e)| PipelineError::Creation(e))?
+        };
+        Ok(pipelines[0])
     }
 
+    pub fn drop(&mut self, device: &Device) {
+        unsafe {
+            if self.pipeline != vk::Pipeline::null() {
+                device.logical.destroy_pipeline(self.pipeline, None);
+            }
+            device.logical.destroy_pipeline_layout(self.layout, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ash::version::{EntryV1_0, InstanceV1_0};
+
+    use super::super::physical::{DeviceReport, QueueFamilies};
+    use super::*;
+
+    const FORMATS: [VertexFormat; 28] = [
+        VertexFormat::Float,
+        VertexFormat::Float2,
+        VertexFormat::Float3,
+        VertexFormat::Float4,
+        VertexFormat::Half2,
+        VertexFormat::Half4,
+        VertexFormat::Int,
+        VertexFormat::Int2,
+        VertexFormat::Int3,
+        VertexFormat::Int4,
+        VertexFormat::UInt,
+        VertexFormat::UInt2,
+        VertexFormat::UInt3,
+        VertexFormat::UInt4,
+        VertexFormat::Short2,
+        VertexFormat::Short2Norm,
+        VertexFormat::Short4,
+        VertexFormat::Short4Norm,
+        VertexFormat::UShort2,
+        VertexFormat::UShort2Norm,
+        VertexFormat::UShort4,
+        VertexFormat::UShort4Norm,
+        VertexFormat::Byte4,
+        VertexFormat::Byte4Norm,
+        VertexFormat::UByte2,
+        VertexFormat::UByte2Norm,
+        VertexFormat::UByte4,
+        VertexFormat::UByte4Norm,
+    ];
+
+    /// The Vulkan format must agree with the size, the component count and the
+    /// interpretation the other backends give to the vertex format.
+    #[test]
+    fn vertex_formats() {
+        for format in FORMATS.iter() {
+            let name = format!("{:?}", vk_vertex_format(*format));
+            let (channels, kind) = name.split_at(name.find('_').unwrap());
+            let bits: Vec<u32> = channels
+                .split(|c: char| c.is_ascii_alphabetic())
+                .filter(|b| !b.is_empty())
+                .map(|b| b.parse().unwrap())
+                .collect();
+            assert_eq!(bits.len() as u32, format.components(), "{:?} is {}", format, name);
+            assert_eq!(bits.iter().sum::<u32>() / 8, format.size(), "{:?} is {}", format, name);
+            assert_eq!(kind.ends_with("INT"), format.is_integer(), "{:?} is {}", format, name);
+            assert_eq!(kind.ends_with("NORM"), format.is_normalized(), "{:?} is {}", format, name);
+        }
+        assert_eq!(vk_vertex_format(VertexFormat::Half2), vk::Format::R16G16_SFLOAT);
+        assert_eq!(vk_vertex_format(VertexFormat::UByte4Norm), vk::Format::R8G8B8A8_UNORM);
+    }
+
+    #[test]
+    fn blend_modes() {
+        let opaque = blend_attachment_state(BlendMode::Opaque);
+        assert_eq!(opaque.blend_enable, vk::FALSE);
+        assert_eq!(opaque.color_write_mask, vk::ColorComponentFlags::all());
+
+        let alpha = blend_attachment_state(BlendMode::Alpha);
+        assert_eq!(alpha.blend_enable, vk::TRUE);
+        assert_eq!(alpha.src_color_blend_factor, vk::BlendFactor::SRC_ALPHA);
+        assert_eq!(alpha.dst_color_blend_factor, vk::BlendFactor::ONE_MINUS_SRC_ALPHA);
+        assert_eq!(alpha.src_alpha_blend_factor, vk::BlendFactor::ONE);
+        assert_eq!(alpha.dst_alpha_blend_factor, vk::BlendFactor::ONE_MINUS_SRC_ALPHA);
+        assert_eq!(alpha.color_write_mask, vk::ColorComponentFlags::all());
+
+        let additive = blend_attachment_state(BlendMode::Additive);
+        assert_eq!(additive.blend_enable, vk::TRUE);
+        for factor in [
+            additive.src_color_blend_factor,
+            additive.dst_color_blend_factor,
+            additive.src_alpha_blend_factor,
+            additive.dst_alpha_blend_factor,
+        ]
+        .iter()
+        {
+            assert_eq!(*factor, vk::BlendFactor::ONE);
+        }
+        assert_eq!(additive.color_blend_op, vk::BlendOp::ADD);
+    }
+
+    /// A device on the first graphic family, without surface nor optional feature.
+    fn test_device(instance: &ash::Instance) -> Device {
+        let physicals = unsafe { instance.enumerate_physical_devices() }.unwrap();
+        let (physical, family) = physicals
+            .iter()
+            .find_map(|p| {
+                let families = unsafe { instance.get_physical_device_queue_family_properties(*p) };
+                families
+                    .iter()
+                    .position(|f| f.queue_flags.contains(vk::QueueFlags::GRAPHICS))
+                    .map(|f| (*p, f as u32))
+            })
+            .expect("No device can draw.");
+        let priorities = [1f32];
+        let queues = [vk::DeviceQueueCreateInfo::builder()
+            .queue_family_index(family)
+            .queue_priorities(&priorities)
+            .build()];
+        let info = vk::DeviceCreateInfo::builder().queue_create_infos(&queues);
+        let logical = unsafe { instance.create_device(physical, &info, None) }.unwrap();
+        let queue = unsafe { logical.get_device_queue(family, 0) };
+        let props = unsafe { instance.get_physical_device_properties(physical) };
+        let limits = &props.limits;
+        Device {
+            physical,
+            logical,
+            graphic_queue: queue,
+            transfert_queue: queue,
+            compute_queue: queue,
+            queue_inds: (family, family, family),
+            report: DeviceReport {
+                index: 0,
+                name: String::from("test"),
+                device_type: props.device_type,
+                api_version: (1, 0, 0),
+                driver_version: props.driver_version,
+                local_memory: 0,
+                max_image_dimension_2d: limits.max_image_dimension2_d,
+                max_push_constants_size: limits.max_push_constants_size,
+                max_bound_descriptor_sets: limits.max_bound_descriptor_sets,
+                max_sampler_anisotropy: 1f32,
+                sampler_anisotropy: false,
+                fill_mode_non_solid: false,
+                descriptor_indexing: false,
+                families: Some(QueueFamilies {
+                    graphic: family,
+                    transfer: family,
+                    compute: family,
+                }),
+                swapchain: false,
+                score: None,
+            },
+        }
+    }
+
+    /// A single color attachment, like the render pass of the frames.
+    fn test_render_pass(device: &Device) -> vk::RenderPass {
+        let attachments = [vk::AttachmentDescription::builder()
+            .format(vk::Format::R8G8B8A8_UNORM)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .store_op(vk::AttachmentStoreOp::STORE)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .final_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .build()];
+        let color_refs = [vk::AttachmentReference::builder()
+            .attachment(0)
+            .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .build()];
+        let subpasses = [vk::SubpassDescription::builder()
+            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+            .color_attachments(&color_refs)
+            .build()];
+        let info = vk::RenderPassCreateInfo::builder()
+            .attachments(&attachments)
+            .subpasses(&subpasses);
+        unsafe { device.logical.create_render_pass(&info, None) }.unwrap()
+    }
+
+    /// Needs a Vulkan driver and shaderc, lavapipe is enough:
+    /// `VK_ICD_FILENAMES=.../lvp_icd.x86_64.json cargo test -p rseed_vk -- --ignored`
+    #[test]
+    #[ignore]
+    fn rect_pipeline_and_cache() {
+        let assets = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../examples/base_app/assets");
+        let mut compiler = shaderlib::ShaderCompiler::new().unwrap();
+        let shaders: Vec<CompiledShader> = ["rect.vert", "rect.frag"]
+            .iter()
+            .map(|file| compiler.compile_file(&assets.join(file)).unwrap())
+            .collect();
+        let desc = PipelineDesc::from_shaders(&shaders);
+        assert_eq!(desc.stages.len(), 2);
+        assert_eq!(desc.vertex_bindings.len(), 1);
+        assert_eq!(desc.vertex_bindings[0].layout.stride, 16);
+        assert_eq!(desc.descriptor_sets.len(), 1);
+
+        let entry = unsafe { ash::Entry::new() }.unwrap();
+        let name = CString::new("rseed_vk tests").unwrap();
+        let app_info = vk::ApplicationInfo::builder()
+            .application_name(&name)
+            .api_version(vk::make_version(1, 1, 0));
+        let info = vk::InstanceCreateInfo::builder().application_info(&app_info);
+        let instance = unsafe { entry.create_instance(&info, None) }.unwrap();
+        let device = test_device(&instance);
+        let render_pass = test_render_pass(&device);
+        let mut layouts = DescriptorLayoutCache::default();
+        let path = std::env::temp_dir().join(format!("rseed_vk_cache_{}.bin", std::process::id()));
+
+        for reload in 0..2 {
+            // The second pass starts from the file written by the first one.
+            assert_eq!(path.exists(), reload == 1);
+            let mut cache = PipelineCache::init(&device, Some(&path)).unwrap();
+            let mut pipeline = Pipeline::init(&device, &cache, &mut layouts, None, render_pass, 0, &desc).unwrap();
+            assert_ne!(pipeline.pipeline, vk::Pipeline::null());
+            assert_eq!(pipeline.set_layouts.len(), 1);
+            cache.save(&device).unwrap();
+            // The header starts with its length then the version one.
+            let data = std::fs::read(&path).unwrap();
+            assert!(data.len() >= 32);
+            assert_eq!(&data[4..8], &1u32.to_le_bytes());
+            pipeline.drop(&device);
+            cache.drop(&device);
+        }
+
+        let _ = std::fs::remove_file(&path);
+        layouts.drop(&device.logical);
+        unsafe { device.logical.destroy_render_pass(render_pass, None) };
+        drop(device);
+        unsafe { instance.destroy_instance(None) };
+    }
 }
@@ -0,0 +1,130 @@
+use std::{cell::RefCell, rc::Rc};
+
+use ash::{self, vk};
+use rseed_renderapi::context::ResourceError;
+
+use super::buffer::BufferSlot;
+use super::descriptor::{BindlessTable, DescriptorError};
+use super::device::{Device, DeviceV1_0};
+use super::library::Library;
+use super::memory::{Allocation, Allocator, MemoryError, MemoryStats};
+use super::texture::{SamplerSlot, TextureSlot};
+use super::transfer::Transfer;
+
+impl From<MemoryError> for ResourceError {
+    fn from(e: MemoryError) -> Self {
+        ResourceError::Backend(e.to_string())
+    }
+}
+
+impl From<DescriptorError> for ResourceError {
+    fn from(e: DescriptorError) -> Self {
+        ResourceError::Backend(e.to_string())
+    }
+}
+
+/// State shared by the context and the resources it created,
+/// the resources need the allocator to update and free themselves.
+pub(crate) struct Resources {
+    pub(super) device: ash::Device,
+    pub(super) allocator: Allocator,
+    /// Buffer uploads.
+    pub(super) transfer: Transfer,
+    /// Image uploads and mip generation, which need layout transitions and blits.
+    pub(super) graphic: Transfer,
+    /// Queue families using the buffers.
+    pub(super) families: Vec<u32>,
+    /// The slots are indexed by the handles, destroyed resources leave a hole.
+    pub(super) buffers: Vec<Option<BufferSlot>>,
+    /// Incremented each time a buffer slot is emptied, part of the buffer handles.
+    pub(super) buffer_generations: Vec<u32>,
+    /// Dropped buffers with the last frame begun before, destroyed when it is done.
+    pub(super) retired: Vec<(u64, vk::Buffer, Allocation)>,
+    /// Frames begun since the creation, the buffers are written for the next one.
+    pub(super) frame: u64,
+    pub(super) textures: Vec<Option<TextureSlot>>,
+    pub(super) samplers: Vec<Option<SamplerSlot>>,
+    /// Used by the textures bound without sampler.
+    pub(super) default_sampler: vk::Sampler,
+    /// Every texture, when the device supports descriptor indexing.
+    pub(super) bindless: Option<BindlessTable>,
+    pub(super) anisotropy: bool,
+    /// Set when the context is dropped, the resources still alive can't be freed anymore.
+    pub(super) destroyed: bool,
+}
+
+pub(crate) type SharedResources = Rc<RefCell<Resources>>;
+
+impl Resources {
+    pub fn init(lib: &Library, device: &Device) -> Result<SharedResources, ResourceError> {
+        let (graphic, transfer, _) = device.queue_inds;
+        let mut families = vec![graphic];
+        if transfer != graphic {
+            families.push(transfer);
+        }
+        let logical = device.logical.clone();
+        let mut resources = Self {
+            allocator: Allocator::init(lib, device),
+            transfer: Transfer::init(device, transfer, device.transfert_queue)?,
+            graphic: Transfer::init(device, graphic, device.graphic_queue)?,
+            families,
+            buffers: Vec::new(),
+            buffer_generations: Vec::new(),
+            retired: Vec::new(),
+            frame: 0,
+            textures: Vec::new(),
+            samplers: Vec::new(),
+            default_sampler: vk::Sampler::null(),
+            bindless: None,
+            anisotropy: device.report.sampler_anisotropy,
+            destroyed: false,
+            device: logical,
+        };
+        resources.default_sampler = resources.create_vk_sampler(&Default::default())?;
+        if device.report.descriptor_indexing {
+            resources.bindless = Some(BindlessTable::init(&resources.device)?);
+        }
+        Ok(Rc::new(RefCell::new(resources)))
+    }
+
+    pub fn stats(&self) -> MemoryStats {
+        self.allocator.stats()
+    }
+
+    pub fn bindless(&self) -> Option<&BindlessTable> {
+        self.bindless.as_ref()
+    }
+
+    /// Called by the context before the device is destroyed.
+    pub fn drop(&mut self) {
+        for index in 0..self.buffers.len() {
+            self.release_buffer(index);
+        }
+        self.destroy_all_retired();
+        for index in 0..self.textures.len() {
+            self.free_texture(index);
+        }
+        for index in 0..self.samplers.len() {
+            self.free_sampler(index);
+        }
+        unsafe { self.device.destroy_sampler(self.default_sampler, None) };
+        if let Some(bindless) = self.bindless.as_mut() {
+            bindless.drop(&self.device);
+        }
+        self.transfer.drop(&self.device);
+        self.graphic.drop(&self.device);
+        self.allocator.drop(&self.device);
+        self.destroyed = true;
+    }
+}
+
+/// Index of the first hole of the table, grown if there is none.
+pub(super) fn free_slot<T>(slots: &mut Vec<Option<T>>) -> usize {
+    match slots.iter().position(|s| s.is_none()) {
+        Some(index) => index,
+        None => {
+            slots.push(None);
+            slots.len() - 1
+        }
+    }
+}
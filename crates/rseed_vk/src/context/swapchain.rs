@@ -1,4 +1,5 @@
 use ash::{self, extensions::khr, vk};
+use rseed_renderapi::settings::PresentMode;
 
 use super::device::*;
 use super::library::*;
@@ -17,10 +18,79 @@ pub(crate) struct Swapchain {
     pub khr: vk::SwapchainKHR,
     pub images: Vec<vk::Image>,
     pub views: Vec<vk::ImageView>,
+    pub format: vk::Format,
+    pub extent: vk::Extent2D,
+    /// Requested mode, the one used falls back to FIFO when it is not supported.
+    pub present_mode: vk::PresentModeKHR,
+}
+
+pub fn vk_present_mode(mode: PresentMode) -> vk::PresentModeKHR {
+    match mode {
+        PresentMode::FIFO => vk::PresentModeKHR::FIFO,
+        PresentMode::MAILBOX => vk::PresentModeKHR::MAILBOX,
+        PresentMode::IMMEDIATE => vk::PresentModeKHR::IMMEDIATE,
+    }
 }
 
 impl Swapchain {
-    pub fn init(lib: &Library, device: &Device, surface: &Surface) -> Result<Self> {
+    /// `size` is the size of the window, used when the surface does not impose its extent.
+    pub fn init(
+        lib: &Library,
+        device: &Device,
+        surface: &Surface,
+        size: vk::Extent2D,
+        present_mode: PresentMode,
+    ) -> Result<Self> {
+        let loader = khr::Swapchain::new(&lib.instance, &device.logical);
+        let present_mode = vk_present_mode(present_mode);
+        let (khr, format, extent) = Self::create_swapchain(
+            &loader,
+            device,
+            surface,
+            size,
+            present_mode,
+            vk::SwapchainKHR::null(),
+        )?;
+        let (images, views) = Self::create_images(&loader, &khr, device, format)?;
+        Ok(Self {
+            loader,
+            khr,
+            images,
+            views,
+            format,
+            extent,
+            present_mode,
+        })
+    }
+
+    /// Build a new swapchain when the surface changed, the images must not be in use anymore.
+    pub fn recreate(&mut self, device: &Device, surface: &Surface, size: vk::Extent2D) -> Result<()> {
+        let (khr, format, extent) = Self::create_swapchain(
+            &self.loader,
+            device,
+            surface,
+            size,
+            self.present_mode,
+            self.khr,
+        )?;
+        self.drop(device);
+        let (images, views) = Self::create_images(&self.loader, &khr, device, format)?;
+        self.khr = khr;
+        self.images = images;
+        self.views = views;
+        self.format = format;
+        self.extent = extent;
+        Ok(())
+    }
+
+    fn create_swapchain(
+        loader: &khr::Swapchain,
+        device: &Device,
+        surface: &Surface,
+        size: vk::Extent2D,
+        present_mode: vk::PresentModeKHR,
+        old_swapchain: vk::SwapchainKHR,
+    ) -> Result<(vk::SwapchainKHR, vk::Format, vk::Extent2D)> {
         let surface_capabilities = unsafe {
             surface
                 .loader
@@ -34,45 +104,90 @@ impl Swapchain {
                 .get_physical_device_surface_formats(device.physical, surface.khr)
                 .map_err(|e| SwapchainError::Creation(e))?
         };
+        let surface_format = Self::choose_format(&surface_formats)
+            .ok_or(SwapchainError::Creation(vk::Result::ERROR_FORMAT_NOT_SUPPORTED))?;
+        let present_modes = unsafe {
+            surface
+                .loader
+                .get_physical_device_surface_present_modes(device.physical, surface.khr)
+                .map_err(|e| SwapchainError::Creation(e))?
+        };
+        let present_mode = if present_modes.contains(&present_mode) {
+            present_mode
+        } else {
+            vk::PresentModeKHR::FIFO
+        };
+
+        // A max image count of 0 means there is no limit.
+        let mut image_count = 3.max(surface_capabilities.min_image_count);
+        if surface_capabilities.max_image_count > 0 {
+            image_count = image_count.min(surface_capabilities.max_image_count);
+        }
+        // Some platforms let the swapchain pick its extent, it then follows the window.
+        let extent = if surface_capabilities.current_extent.width == u32::MAX {
+            let (min, max) = (
+                surface_capabilities.min_image_extent,
+                surface_capabilities.max_image_extent,
+            );
+            vk::Extent2D {
+                width: size.width.clamp(min.width, max.width),
+                height: size.height.clamp(min.height, max.height),
+            }
+        } else {
+            surface_capabilities.current_extent
+        };
 
         let queue_fam = [device.queue_inds.0];
         let swapchain_create_info = vk::SwapchainCreateInfoKHR::builder()
             .surface(surface.khr)
-            .min_image_count(
-                3.max(surface_capabilities.min_image_count)
-                    .min(surface_capabilities.max_image_count),
-            )
-            .image_format(surface_formats.first().unwrap().format)
-            .image_color_space(surface_formats.first().unwrap().color_space)
-            .image_extent(surface_capabilities.current_extent)
+            .min_image_count(image_count)
+            .image_format(surface_format.format)
+            .image_color_space(surface_format.color_space)
+            .image_extent(extent)
             .image_array_layers(1)
             .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
             .image_sharing_mode(vk::SharingMode::EXCLUSIVE)
             .queue_family_indices(&queue_fam)
             .pre_transform(surface_capabilities.current_transform)
             .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
-            .present_mode(vk::PresentModeKHR::FIFO);
+            .present_mode(present_mode)
+            .clipped(true)
+            .old_swapchain(old_swapchain);
 
-        let loader = khr::Swapchain::new(&lib.instance, &device.logical);
         let khr = unsafe {
             loader
                 .create_swapchain(&swapchain_create_info, None)
                 .map_err(|e| SwapchainError::Creation(e))?
         };
+        Ok((khr, surface_format.format, extent))
+    }
 
-        let (images, views) = Self::create_images(&loader, &khr, device)?;
-        Ok(Self {
-            loader,
-            khr,
-            images,
-            views,
-        })
+    /// Prefer an sRGB format so the blending and the output are gamma correct.
+    fn choose_format(formats: &[vk::SurfaceFormatKHR]) -> Option<vk::SurfaceFormatKHR> {
+        // A single undefined format means any format can be used.
+        if let [f] = formats {
+            if f.format == vk::Format::UNDEFINED {
+                return Some(vk::SurfaceFormatKHR {
+                    format: vk::Format::B8G8R8A8_SRGB,
+                    color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+                });
+            }
+        }
+        formats
+            .iter()
+            .find(|f| {
+                matches!(f.format, vk::Format::B8G8R8A8_SRGB | vk::Format::R8G8B8A8_SRGB)
+                    && f.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR
+            })
+            .or_else(|| formats.first())
+            .copied()
     }
 
     fn create_images(
         loader: &khr::Swapchain,
         khr: &vk::SwapchainKHR,
         device: &Device,
+        format: vk::Format,
     ) -> Result<(Vec<vk::Image>, Vec<vk::ImageView>)> {
         let images = unsafe {
             loader
@@ -90,7 +205,7 @@ impl Swapchain {
             let view_create_info = vk::ImageViewCreateInfo::builder()
                 .image(*img)
                 .view_type(vk::ImageViewType::TYPE_2D)
-                .format(vk::Format::B8G8R8A8_UNORM)
+                .format(format)
                 .subresource_range(*subressource_range);
             let view = unsafe {
                 device
@@ -104,8 +219,8 @@ impl Swapchain {
     }
 
     pub fn drop(&mut self, device: &Device) {
-        for view in &self.views {
-            unsafe { device.logical.destroy_image_view(*view, None) };
+        for view in self.views.drain(..) {
+            unsafe { device.logical.destroy_image_view(view, None) };
         }
         unsafe { self.loader.destroy_swapchain(self.khr, None) };
     }
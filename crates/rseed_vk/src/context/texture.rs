@@ -0,0 +1,646 @@
+use ash::vk;
+use rseed_renderapi::{
+    context::{ResourceError, Result},
+    handle::{SamplerHandle, TextureHandle},
+    renderer::Access,
+    texture::{
+        Filter, SamplerDesc, SamplerT, TextureDesc, TextureFormat, TextureKind, TextureRegion, TextureT, Wrap,
+    },
+};
+
+use super::device::DeviceV1_0;
+use super::memory::{self, Allocation, MemoryError, MemoryUsage};
+use super::resource::{free_slot, Resources, SharedResources};
+use super::transfer::create_buffer;
+
+pub fn vk_texture_format(format: TextureFormat) -> vk::Format {
+    match format {
+        TextureFormat::R8 => vk::Format::R8_UNORM,
+        TextureFormat::Rg8 => vk::Format::R8G8_UNORM,
+        TextureFormat::Rgba8 => vk::Format::R8G8B8A8_UNORM,
+        TextureFormat::Srgba8 => vk::Format::R8G8B8A8_SRGB,
+        TextureFormat::R16F => vk::Format::R16_SFLOAT,
+        TextureFormat::Rgba16F => vk::Format::R16G16B16A16_SFLOAT,
+        TextureFormat::R32F => vk::Format::R32_SFLOAT,
+        TextureFormat::Rgba32F => vk::Format::R32G32B32A32_SFLOAT,
+        TextureFormat::Depth32F => vk::Format::D32_SFLOAT,
+        TextureFormat::Depth24Stencil8 => vk::Format::D24_UNORM_S8_UINT,
+    }
+}
+
+fn vk_aspect(format: TextureFormat) -> vk::ImageAspectFlags {
+    match format {
+        TextureFormat::Depth32F => vk::ImageAspectFlags::DEPTH,
+        // Only the depth is sampled.
+        TextureFormat::Depth24Stencil8 => vk::ImageAspectFlags::DEPTH,
+        _ => vk::ImageAspectFlags::COLOR,
+    }
+}
+
+pub fn vk_view_type(kind: TextureKind) -> vk::ImageViewType {
+    match kind {
+        TextureKind::D2 => vk::ImageViewType::TYPE_2D,
+        TextureKind::D2Array => vk::ImageViewType::TYPE_2D_ARRAY,
+        TextureKind::Cube => vk::ImageViewType::CUBE,
+    }
+}
+
+pub fn vk_filter(filter: Filter) -> vk::Filter {
+    match filter {
+        Filter::Nearest => vk::Filter::NEAREST,
+        Filter::Linear => vk::Filter::LINEAR,
+    }
+}
+
+pub fn vk_mipmap_mode(filter: Filter) -> vk::SamplerMipmapMode {
+    match filter {
+        Filter::Nearest => vk::SamplerMipmapMode::NEAREST,
+        Filter::Linear => vk::SamplerMipmapMode::LINEAR,
+    }
+}
+
+pub fn vk_wrap(wrap: Wrap) -> vk::SamplerAddressMode {
+    match wrap {
+        Wrap::Repeat => vk::SamplerAddressMode::REPEAT,
+        Wrap::MirroredRepeat => vk::SamplerAddressMode::MIRRORED_REPEAT,
+        Wrap::ClampToEdge => vk::SamplerAddressMode::CLAMP_TO_EDGE,
+        Wrap::ClampToBorder => vk::SamplerAddressMode::CLAMP_TO_BORDER,
+    }
+}
+
+/// Move some levels of every layer of an image from a layout to another, with full barriers:
+/// textures are only transitioned during the synchronous uploads.
+fn transition(
+    device: &ash::Device,
+    commands: vk::CommandBuffer,
+    image: vk::Image,
+    desc: &TextureDesc,
+    levels: (u32, u32),
+    from: vk::ImageLayout,
+    to: vk::ImageLayout,
+) {
+    let barrier = vk::ImageMemoryBarrier::builder()
+        .old_layout(from)
+        .new_layout(to)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .image(image)
+        .subresource_range(vk::ImageSubresourceRange {
+            aspect_mask: vk_aspect(desc.format),
+            base_mip_level: levels.0,
+            level_count: levels.1,
+            base_array_layer: 0,
+            layer_count: desc.layers.max(1),
+        })
+        .src_access_mask(vk::AccessFlags::MEMORY_WRITE)
+        .dst_access_mask(vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE)
+        .build();
+    unsafe {
+        device.cmd_pipeline_barrier(
+            commands,
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[barrier],
+        )
+    };
+}
+
+/// Layout, stages and memory accesses of a texture used by a frame graph pass.
+pub fn vk_access(access: Access) -> (vk::ImageLayout, vk::PipelineStageFlags, vk::AccessFlags) {
+    match access {
+        Access::ColorAttachment => (
+            vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+        ),
+        Access::DepthAttachment => (
+            vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+            vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+            vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+        ),
+        Access::ShaderRead => (
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            vk::PipelineStageFlags::VERTEX_SHADER | vk::PipelineStageFlags::FRAGMENT_SHADER,
+            vk::AccessFlags::SHADER_READ,
+        ),
+        Access::Storage => (
+            vk::ImageLayout::GENERAL,
+            vk::PipelineStageFlags::FRAGMENT_SHADER | vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE,
+        ),
+        Access::TransferSrc => (
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::AccessFlags::TRANSFER_READ,
+        ),
+        Access::TransferDst => (
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::AccessFlags::TRANSFER_WRITE,
+        ),
+        Access::Present => (
+            vk::ImageLayout::PRESENT_SRC_KHR,
+            vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+            vk::AccessFlags::empty(),
+        ),
+    }
+}
+
+/// The image stays in the `SHADER_READ_ONLY_OPTIMAL` layout between uploads.
+pub(crate) struct TextureSlot {
+    image: vk::Image,
+    pub view: vk::ImageView,
+    allocation: Allocation,
+    desc: TextureDesc,
+}
+
+pub(crate) struct SamplerSlot {
+    pub sampler: vk::Sampler,
+}
+
+impl Resources {
+    /// Record the transition of a texture between two uses, false if there is no such texture.
+    /// The uploads expect the texture to be left back in the `ShaderRead` access.
+    pub fn barrier(
+        &self,
+        commands: vk::CommandBuffer,
+        handle: TextureHandle,
+        from: Option<Access>,
+        to: Access,
+    ) -> bool {
+        let slot = match self.textures.get(handle.0 as usize) {
+            Some(Some(slot)) => slot,
+            _ => return false,
+        };
+        let (old_layout, src_stage, src_access) = match from {
+            Some(from) => vk_access(from),
+            None => (
+                vk::ImageLayout::UNDEFINED,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::AccessFlags::empty(),
+            ),
+        };
+        let (new_layout, dst_stage, dst_access) = vk_access(to);
+        // A layout transition covers both aspects of a depth stencil image.
+        let aspect_mask = match slot.desc.format {
+            TextureFormat::Depth24Stencil8 => vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL,
+            format => vk_aspect(format),
+        };
+        let barrier = vk::ImageMemoryBarrier::builder()
+            .old_layout(old_layout)
+            .new_layout(new_layout)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(slot.image)
+            .subresource_range(vk::ImageSubresourceRange {
+                aspect_mask,
+                base_mip_level: 0,
+                level_count: slot.desc.mip_levels.max(1),
+                base_array_layer: 0,
+                layer_count: slot.desc.layers.max(1),
+            })
+            .src_access_mask(src_access)
+            .dst_access_mask(dst_access)
+            .build();
+        unsafe {
+            self.device.cmd_pipeline_barrier(
+                commands,
+                src_stage,
+                dst_stage,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier],
+            )
+        };
+        true
+    }
+
+    pub fn texture(&self, handle: TextureHandle) -> Option<vk::ImageView> {
+        self.textures
+            .get(handle.0 as usize)?
+            .as_ref()
+            .map(|slot| slot.view)
+    }
+
+    /// The default sampler when there is no sampler for this handle.
+    pub fn sampler(&self, handle: Option<SamplerHandle>) -> vk::Sampler {
+        handle
+            .and_then(|h| self.samplers.get(h.0 as usize)?.as_ref())
+            .map(|slot| slot.sampler)
+            .unwrap_or(self.default_sampler)
+    }
+
+    fn create_texture(&mut self, desc: TextureDesc) -> memory::Result<u32> {
+        let format = vk_texture_format(desc.format);
+        let aspect = vk_aspect(desc.format);
+        let levels = desc.mip_levels.max(1);
+        let flags = match desc.kind {
+            TextureKind::Cube => vk::ImageCreateFlags::CUBE_COMPATIBLE,
+            _ => vk::ImageCreateFlags::empty(),
+        };
+        // The frame graph draws to its transient textures.
+        let attachment = if desc.format.is_depth() {
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT
+        } else {
+            vk::ImageUsageFlags::COLOR_ATTACHMENT
+        };
+        let info = vk::ImageCreateInfo::builder()
+            .flags(flags)
+            .image_type(vk::ImageType::TYPE_2D)
+            .format(format)
+            .extent(vk::Extent3D {
+                width: desc.width,
+                height: desc.height,
+                depth: 1,
+            })
+            .mip_levels(levels)
+            .array_layers(desc.layers.max(1))
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .usage(
+                vk::ImageUsageFlags::SAMPLED
+                    | vk::ImageUsageFlags::TRANSFER_SRC
+                    | vk::ImageUsageFlags::TRANSFER_DST
+                    | attachment,
+            )
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .initial_layout(vk::ImageLayout::UNDEFINED);
+        let device = &self.device;
+        let image = unsafe { device.create_image(&info, None).map_err(MemoryError::Image)? };
+        let requirements = unsafe { device.get_image_memory_requirements(image) };
+        let allocation = match self
+            .allocator
+            .allocate(device, requirements, MemoryUsage::GpuOnly, false)
+        {
+            Ok(allocation) => allocation,
+            Err(e) => {
+                unsafe { device.destroy_image(image, None) };
+                return Err(e);
+            }
+        };
+        let view_info = vk::ImageViewCreateInfo::builder()
+            .image(image)
+            .view_type(vk_view_type(desc.kind))
+            .format(format)
+            .subresource_range(vk::ImageSubresourceRange {
+                aspect_mask: aspect,
+                base_mip_level: 0,
+                level_count: levels,
+                base_array_layer: 0,
+                layer_count: desc.layers.max(1),
+            });
+        let view = unsafe {
+            device
+                .bind_image_memory(image, allocation.memory, allocation.offset)
+                .map_err(MemoryError::Bind)
+                .and_then(|_| {
+                    device
+                        .create_image_view(&view_info, None)
+                        .map_err(MemoryError::Image)
+                })
+        };
+        let view = match view {
+            Ok(view) => view,
+            Err(e) => {
+                unsafe { device.destroy_image(image, None) };
+                self.allocator.free(device, allocation);
+                return Err(e);
+            }
+        };
+        let index = free_slot(&mut self.textures);
+        self.textures[index] = Some(TextureSlot {
+            image,
+            view,
+            allocation,
+            desc,
+        });
+        // Sampling an image never written is valid, it only reads garbage.
+        let result = self.graphic.submit(device, |commands| {
+            transition(
+                device,
+                commands,
+                image,
+                &desc,
+                (0, levels),
+                vk::ImageLayout::UNDEFINED,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            )
+        });
+        if let Err(e) = result {
+            self.free_texture(index);
+            return Err(e);
+        }
+        // The bindless array only holds 2D textures.
+        if let Some(bindless) = self.bindless.as_ref().filter(|_| desc.kind == TextureKind::D2) {
+            bindless.write(device, index as u32, view, self.default_sampler);
+        }
+        Ok(index as u32)
+    }
+
+    fn update_texture(&mut self, index: usize, region: TextureRegion, data: &[u8]) -> Result<()> {
+        let (image, desc) = match &self.textures[index] {
+            Some(slot) => (slot.image, slot.desc),
+            None => return Ok(()),
+        };
+        if !desc.contains(&region) {
+            return Err(ResourceError::OutOfBounds);
+        }
+        let expected = (region.width * region.height * desc.format.pixel_size()) as usize;
+        if data.len() != expected {
+            return Err(ResourceError::SizeMismatch(expected, data.len()));
+        }
+        let device = &self.device;
+        let (staging, allocation) = create_buffer(
+            device,
+            &mut self.allocator,
+            data.len() as u64,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            MemoryUsage::Upload,
+            &[],
+        )?;
+        allocation.write(0, data);
+        let aspect = vk_aspect(desc.format);
+        let copy = vk::BufferImageCopy::builder()
+            .image_subresource(vk::ImageSubresourceLayers {
+                aspect_mask: aspect,
+                mip_level: region.level,
+                base_array_layer: region.layer,
+                layer_count: 1,
+            })
+            .image_offset(vk::Offset3D {
+                x: region.x as i32,
+                y: region.y as i32,
+                z: 0,
+            })
+            .image_extent(vk::Extent3D {
+                width: region.width,
+                height: region.height,
+                depth: 1,
+            })
+            .build();
+        let level = (region.level, 1);
+        let result = self.graphic.submit(device, |commands| unsafe {
+            transition(
+                device,
+                commands,
+                image,
+                &desc,
+                level,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            );
+            device.cmd_copy_buffer_to_image(
+                commands,
+                staging,
+                image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[copy],
+            );
+            transition(
+                device,
+                commands,
+                image,
+                &desc,
+                level,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            );
+        });
+        unsafe { device.destroy_buffer(staging, None) };
+        self.allocator.free(device, allocation);
+        Ok(result?)
+    }
+
+    /// Each level is blitted from the previous one, depth levels are point sampled.
+    fn generate_mips(&mut self, index: usize) -> memory::Result<()> {
+        let (image, desc) = match &self.textures[index] {
+            Some(slot) => (slot.image, slot.desc),
+            None => return Ok(()),
+        };
+        let aspect = vk_aspect(desc.format);
+        let filter = if desc.format.is_depth() {
+            vk::Filter::NEAREST
+        } else {
+            vk::Filter::LINEAR
+        };
+        let device = &self.device;
+        self.graphic.submit(device, |commands| {
+            for level in 1..desc.mip_levels.max(1) {
+                let offset = |l: u32| vk::Offset3D {
+                    x: (desc.width >> l).max(1) as i32,
+                    y: (desc.height >> l).max(1) as i32,
+                    z: 1,
+                };
+                let layers = |l: u32| vk::ImageSubresourceLayers {
+                    aspect_mask: aspect,
+                    mip_level: l,
+                    base_array_layer: 0,
+                    layer_count: desc.layers.max(1),
+                };
+                let blit = vk::ImageBlit {
+                    src_subresource: layers(level - 1),
+                    src_offsets: [vk::Offset3D::default(), offset(level - 1)],
+                    dst_subresource: layers(level),
+                    dst_offsets: [vk::Offset3D::default(), offset(level)],
+                };
+                transition(
+                    device,
+                    commands,
+                    image,
+                    &desc,
+                    (level - 1, 1),
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                );
+                transition(
+                    device,
+                    commands,
+                    image,
+                    &desc,
+                    (level, 1),
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                );
+                unsafe {
+                    device.cmd_blit_image(
+                        commands,
+                        image,
+                        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                        image,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        &[blit],
+                        filter,
+                    )
+                };
+                transition(
+                    device,
+                    commands,
+                    image,
+                    &desc,
+                    (level - 1, 1),
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                );
+                transition(
+                    device,
+                    commands,
+                    image,
+                    &desc,
+                    (level, 1),
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                );
+            }
+        })
+    }
+
+    pub(super) fn free_texture(&mut self, index: usize) {
+        if self.destroyed {
+            return;
+        }
+        if let Some(slot) = self.textures.get_mut(index).and_then(|t| t.take()) {
+            unsafe {
+                self.device.destroy_image_view(slot.view, None);
+                self.device.destroy_image(slot.image, None);
+            }
+            self.allocator.free(&self.device, slot.allocation);
+        }
+    }
+
+    pub(super) fn create_vk_sampler(&self, desc: &SamplerDesc) -> memory::Result<vk::Sampler> {
+        // Without mip filter only the base level is sampled.
+        let max_lod = if desc.mip_filter.is_some() {
+            vk::LOD_CLAMP_NONE
+        } else {
+            0.25
+        };
+        let anisotropy = self.anisotropy && desc.anisotropy > 1;
+        let info = vk::SamplerCreateInfo::builder()
+            .min_filter(vk_filter(desc.min_filter))
+            .mag_filter(vk_filter(desc.mag_filter))
+            .mipmap_mode(vk_mipmap_mode(desc.mip_filter.unwrap_or(Filter::Nearest)))
+            .address_mode_u(vk_wrap(desc.wrap_u))
+            .address_mode_v(vk_wrap(desc.wrap_v))
+            .address_mode_w(vk_wrap(desc.wrap_w))
+            .anisotropy_enable(anisotropy)
+            .max_anisotropy(desc.anisotropy as f32)
+            .min_lod(0f32)
+            .max_lod(max_lod);
+        unsafe {
+            self.device
+                .create_sampler(&info, None)
+                .map_err(MemoryError::Sampler)
+        }
+    }
+
+    fn create_sampler(&mut self, desc: &SamplerDesc) -> memory::Result<u32> {
+        let sampler = self.create_vk_sampler(desc)?;
+        let index = free_slot(&mut self.samplers);
+        self.samplers[index] = Some(SamplerSlot { sampler });
+        Ok(index as u32)
+    }
+
+    pub(super) fn free_sampler(&mut self, index: usize) {
+        if self.destroyed {
+            return;
+        }
+        if let Some(slot) = self.samplers.get_mut(index).and_then(|s| s.take()) {
+            unsafe { self.device.destroy_sampler(slot.sampler, None) };
+        }
+    }
+}
+
+pub(crate) fn create_texture(
+    resources: &SharedResources,
+    desc: TextureDesc,
+    data: Option<&[u8]>,
+) -> Result<VkTexture> {
+    desc.validate()?;
+    let index = resources.borrow_mut().create_texture(desc)?;
+    let mut texture = VkTexture {
+        resources: resources.clone(),
+        index,
+        desc,
+    };
+    if let Some(data) = data {
+        for (region, pixels) in desc.base_layers(data)? {
+            texture.update(region, pixels)?;
+        }
+        if desc.mip_levels > 1 {
+            resources.borrow_mut().generate_mips(index as usize)?;
+        }
+    }
+    Ok(texture)
+}
+
+pub(crate) fn create_sampler(resources: &SharedResources, desc: SamplerDesc) -> Result<VkSampler> {
+    let index = resources.borrow_mut().create_sampler(&desc)?;
+    Ok(VkSampler {
+        resources: resources.clone(),
+        index,
+        desc,
+    })
+}
+
+/// The handle is also the index of the texture in the bindless array.
+pub struct VkTexture {
+    resources: SharedResources,
+    index: u32,
+    desc: TextureDesc,
+}
+
+impl TextureT for VkTexture {
+    fn handle(&self) -> TextureHandle {
+        TextureHandle(self.index)
+    }
+
+    fn desc(&self) -> &TextureDesc {
+        &self.desc
+    }
+
+    fn update(&mut self, region: TextureRegion, data: &[u8]) -> Result<()> {
+        self.resources
+            .borrow_mut()
+            .update_texture(self.index as usize, region, data)
+    }
+
+    fn generate_mips(&mut self) {
+        // The trait can't report it, a failed blit leaves the levels as they were.
+        let _ = self
+            .resources
+            .borrow_mut()
+            .generate_mips(self.index as usize);
+    }
+}
+
+impl Drop for VkTexture {
+    fn drop(&mut self) {
+        self.resources.borrow_mut().free_texture(self.index as usize);
+    }
+}
+
+pub struct VkSampler {
+    resources: SharedResources,
+    index: u32,
+    desc: SamplerDesc,
+}
+
+impl SamplerT for VkSampler {
+    fn handle(&self) -> SamplerHandle {
+        SamplerHandle(self.index)
+    }
+
+    fn desc(&self) -> &SamplerDesc {
+        &self.desc
+    }
+}
+
+impl Drop for VkSampler {
+    fn drop(&mut self) {
+        self.resources.borrow_mut().free_sampler(self.index as usize);
+    }
+}
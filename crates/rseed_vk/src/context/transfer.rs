@@ -0,0 +1,164 @@
+use ash::{self, vk};
+
+use super::device::{Device, DeviceV1_0};
+use super::memory::*;
+
+/// Uploads data to GPU only memory through a staging buffer. Buffers go through the
+/// transfer queue, images through the graphic queue which can also blit their mips.
+/// Uploads are synchronous: the call returns once the copy is done.
+pub(crate) struct Transfer {
+    queue: vk::Queue,
+    pool: vk::CommandPool,
+    commands: vk::CommandBuffer,
+    fence: vk::Fence,
+}
+
+impl Transfer {
+    pub fn init(device: &Device, family: u32, queue: vk::Queue) -> Result<Self> {
+        let logical = &device.logical;
+        let pool_info = vk::CommandPoolCreateInfo::builder()
+            .queue_family_index(family)
+            .flags(vk::CommandPoolCreateFlags::TRANSIENT);
+        unsafe {
+            let pool = logical
+                .create_command_pool(&pool_info, None)
+                .map_err(MemoryError::Transfer)?;
+            let alloc_info = vk::CommandBufferAllocateInfo::builder()
+                .command_pool(pool)
+                .level(vk::CommandBufferLevel::PRIMARY)
+                .command_buffer_count(1);
+            let commands = match logical.allocate_command_buffers(&alloc_info) {
+                Ok(commands) => commands[0],
+                Err(e) => {
+                    logical.destroy_command_pool(pool, None);
+                    return Err(MemoryError::Transfer(e));
+                }
+            };
+            let fence = match logical.create_fence(&vk::FenceCreateInfo::default(), None) {
+                Ok(fence) => fence,
+                Err(e) => {
+                    logical.destroy_command_pool(pool, None);
+                    return Err(MemoryError::Transfer(e));
+                }
+            };
+            Ok(Self {
+                queue,
+                pool,
+                commands,
+                fence,
+            })
+        }
+    }
+
+    /// Copy `data` at `offset` in `dst`, which must have the `TRANSFER_DST` usage.
+    pub fn upload_buffer(
+        &mut self,
+        device: &ash::Device,
+        allocator: &mut Allocator,
+        dst: vk::Buffer,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let (staging, allocation) = create_buffer(
+            device,
+            allocator,
+            data.len() as u64,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            MemoryUsage::Upload,
+            &[],
+        )?;
+        allocation.write(0, data);
+        let region = vk::BufferCopy {
+            src_offset: 0,
+            dst_offset: offset,
+            size: data.len() as u64,
+        };
+        let result = self.submit(device, |commands| unsafe {
+            device.cmd_copy_buffer(commands, staging, dst, &[region])
+        });
+        unsafe { device.destroy_buffer(staging, None) };
+        allocator.free(device, allocation);
+        result
+    }
+
+    /// Record commands and wait for the queue to execute them.
+    pub fn submit<F: FnOnce(vk::CommandBuffer)>(&mut self, device: &ash::Device, record: F) -> Result<()> {
+        unsafe {
+            device
+                .reset_command_pool(self.pool, vk::CommandPoolResetFlags::empty())
+                .map_err(MemoryError::Transfer)?;
+            let begin_info = vk::CommandBufferBeginInfo::builder()
+                .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+            device
+                .begin_command_buffer(self.commands, &begin_info)
+                .map_err(MemoryError::Transfer)?;
+            record(self.commands);
+            device
+                .end_command_buffer(self.commands)
+                .map_err(MemoryError::Transfer)?;
+            let command_buffers = [self.commands];
+            let submit_info = vk::SubmitInfo::builder()
+                .command_buffers(&command_buffers)
+                .build();
+            device
+                .queue_submit(self.queue, &[submit_info], self.fence)
+                .map_err(MemoryError::Transfer)?;
+            device
+                .wait_for_fences(&[self.fence], true, u64::MAX)
+                .map_err(MemoryError::Transfer)?;
+            device
+                .reset_fences(&[self.fence])
+                .map_err(MemoryError::Transfer)
+        }
+    }
+
+    pub fn drop(&mut self, device: &ash::Device) {
+        unsafe {
+            device.destroy_fence(self.fence, None);
+            device.destroy_command_pool(self.pool, None);
+        }
+    }
+}
+
+/// Create a buffer with its memory. Buffers used by several queue families
+/// are shared between them, so no ownership transfer is needed.
+pub(crate) fn create_buffer(
+    device: &ash::Device,
+    allocator: &mut Allocator,
+    size: u64,
+    usage: vk::BufferUsageFlags,
+    memory_usage: MemoryUsage,
+    families: &[u32],
+) -> Result<(vk::Buffer, Allocation)> {
+    let info = vk::BufferCreateInfo::builder()
+        .size(size.max(1))
+        .usage(usage);
+    let info = if families.len() > 1 {
+        info.sharing_mode(vk::SharingMode::CONCURRENT)
+            .queue_family_indices(families)
+    } else {
+        info.sharing_mode(vk::SharingMode::EXCLUSIVE)
+    };
+    unsafe {
+        let buffer = device
+            .create_buffer(&info, None)
+            .map_err(MemoryError::Buffer)?;
+        let requirements = device.get_buffer_memory_requirements(buffer);
+        let allocation = match allocator.allocate(device, requirements, memory_usage, true) {
+            Ok(allocation) => allocation,
+            Err(e) => {
+                device.destroy_buffer(buffer, None);
+                return Err(e);
+            }
+        };
+        if let Err(e) = device.bind_buffer_memory(buffer, allocation.memory, allocation.offset) {
+            device.destroy_buffer(buffer, None);
+            allocator.free(device, allocation);
+            return Err(MemoryError::Bind(e));
+        }
+        Ok((buffer, allocation))
+    }
+}
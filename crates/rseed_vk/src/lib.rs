@@ -1,9 +1,28 @@
-use rseed_renderapi::renderer::RendererT;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+};
+
+use ash::vk;
+use rseed_renderapi::{
+    context::ContextT,
+    descriptor::BindingType,
+    handle::{BufferHandle, SamplerHandle, TextureHandle},
+    renderer::{CommandBuffer, IndexFormat, Primitive, RenderCommand, RendererT, UniformValue},
+};
+use rseed_log::Logger;
+
+use context::descriptor::{vk_descriptor_type, DescriptorAllocator};
 
 pub mod context;
 
 pub struct VkRenderer {
     ctx : context::VkContext,
+    /// Commands submitted since the last frame, they are recorded when the frame is built.
+    pending : RefCell<CommandBuffer>,
+    logger : Logger,
+    /// The commands the backend can't execute, reported once each.
+    reported : RefCell<HashSet<String>>,
 }
 
 impl VkRenderer {
@@ -11,11 +30,330 @@ impl VkRenderer {
     pub fn new(ctx : context::VkContext) -> Self {
         Self {
             ctx,
+            pending : RefCell::new(CommandBuffer::new()),
+            logger : Logger::new(String::from("Vulkan")),
+            reported : RefCell::new(HashSet::new()),
+        }
+    }
+
+    /// Log a command the backend ignores, once so the frames do not flood the log.
+    fn report(&self, message : String) {
+        if self.reported.borrow_mut().insert(message.clone()) {
+            self.logger.warn(&message);
+        }
+    }
+
+    /// Write a uniform in the push constant block of the pipeline, the only uniforms
+    /// outside of the buffers Vulkan has.
+    fn push_uniform(
+        &self,
+        device : &ash::Device,
+        cmd : vk::CommandBuffer,
+        pipeline : Option<&context::pipeline::Pipeline>,
+        name : &str,
+        value : &UniformValue,
+    ) {
+        use ash::version::DeviceV1_0;
+        let pipeline = match pipeline {
+            Some(pipeline) => pipeline,
+            None => return self.report(format!("The uniform {} is set without a pipeline.", name)),
+        };
+        let member = match pipeline.push_block.as_ref().and_then(|b| b.member(name)) {
+            Some(member) => member,
+            None => return self.report(format!("The pipeline has no push constant named {}.", name)),
+        };
+        let bytes = push_constant_bytes(value);
+        if bytes.len() != member.size as usize {
+            return self.report(format!(
+                "The push constant {} is {} bytes, the value {}.",
+                name,
+                member.size,
+                bytes.len()
+            ));
+        }
+        unsafe {
+            device.cmd_push_constants(cmd, pipeline.layout, pipeline.push_stages, member.offset, &bytes);
+        }
+    }
+
+    pub fn ctx(&self) -> &context::VkContext {
+        &self.ctx
+    }
+
+    pub fn ctx_mut(&mut self) -> &mut context::VkContext {
+        &mut self.ctx
+    }
+
+    /// Translate the pending commands inside the render pass of the frame.
+    /// The uniform buffers and textures bound to the slots fill the set 0 of the pipeline,
+    /// a new set is written before a draw when the bindings changed.
+    fn record(
+        &self,
+        device : &ash::Device,
+        cmd : vk::CommandBuffer,
+        descriptors : &mut DescriptorAllocator,
+        commands : &CommandBuffer,
+    ) {
+        use ash::version::DeviceV1_0;
+        let extent = self.ctx.extent();
+        let full = vk::Rect2D {
+            offset : vk::Offset2D { x : 0, y : 0 },
+            extent,
+        };
+        let mut clear_color = [0f32; 4];
+        let mut pipeline = None;
+        let mut bindings = Bindings::default();
+        unsafe {
+            device.cmd_set_viewport(cmd, 0, &[vk::Viewport {
+                x : 0f32,
+                y : 0f32,
+                width : extent.width as f32,
+                height : extent.height as f32,
+                min_depth : 0f32,
+                max_depth : 1f32,
+            }]);
+            device.cmd_set_scissor(cmd, 0, &[full]);
+            for command in commands.commands() {
+                match command {
+                    RenderCommand::SetClearColor(c) => clear_color = [c.x, c.y, c.z, c.w],
+                    RenderCommand::Clear => {
+                        let attachment = vk::ClearAttachment {
+                            aspect_mask : vk::ImageAspectFlags::COLOR,
+                            color_attachment : 0,
+                            clear_value : vk::ClearValue {
+                                color : vk::ClearColorValue { float32 : clear_color },
+                            },
+                        };
+                        let rect = vk::ClearRect {
+                            rect : full,
+                            base_array_layer : 0,
+                            layer_count : 1,
+                        };
+                        device.cmd_clear_attachments(cmd, &[attachment], &[rect]);
+                    }
+                    RenderCommand::SetViewport { x, y, width, height } => {
+                        device.cmd_set_viewport(cmd, 0, &[vk::Viewport {
+                            x : *x as f32,
+                            y : *y as f32,
+                            width : *width as f32,
+                            height : *height as f32,
+                            min_depth : 0f32,
+                            max_depth : 1f32,
+                        }]);
+                        device.cmd_set_scissor(cmd, 0, &[vk::Rect2D {
+                            offset : vk::Offset2D { x : *x, y : *y },
+                            extent : vk::Extent2D { width : *width, height : *height },
+                        }]);
+                    }
+                    RenderCommand::SetPipeline(handle) => {
+                        pipeline = self.ctx.pipeline(*handle);
+                        if let Some(pipeline) = pipeline.as_deref() {
+                            device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::GRAPHICS, pipeline.pipeline);
+                            if let (Some(set), Some(index)) = (self.ctx.bindless_set(), pipeline.bindless_set) {
+                                device.cmd_bind_descriptor_sets(
+                                    cmd,
+                                    vk::PipelineBindPoint::GRAPHICS,
+                                    pipeline.layout,
+                                    index,
+                                    &[set],
+                                    &[],
+                                );
+                            }
+                            bindings.dirty = true;
+                        }
+                    }
+                    RenderCommand::SetUniform(name, value) => {
+                        self.push_uniform(device, cmd, pipeline.as_deref(), name, value)
+                    }
+                    RenderCommand::SetUniformBuffer { slot, buffer } => {
+                        bindings.buffers.insert(*slot, *buffer);
+                        bindings.dirty = true;
+                    }
+                    RenderCommand::SetTexture { slot, texture, sampler } => {
+                        bindings.textures.insert(*slot, (*texture, *sampler));
+                        bindings.dirty = true;
+                    }
+                    RenderCommand::SetVertexBuffer { binding, buffer } => {
+                        if let Some((buffer, offset, _)) = self.ctx.buffer(*buffer) {
+                            device.cmd_bind_vertex_buffers(cmd, *binding, &[buffer], &[offset]);
+                        }
+                    }
+                    RenderCommand::SetIndexBuffer(handle, format) => {
+                        if let Some((buffer, offset, _)) = self.ctx.buffer(*handle) {
+                            device.cmd_bind_index_buffer(cmd, buffer, offset, vk_index_type(*format));
+                        }
+                    }
+                    RenderCommand::Draw {
+                        primitive,
+                        first,
+                        count,
+                        instances,
+                    } => {
+                        self.check_topology(pipeline.as_deref(), *primitive);
+                        self.bind_descriptors(device, cmd, descriptors, pipeline.as_deref(), &mut bindings);
+                        device.cmd_draw(cmd, *count, *instances, *first, 0);
+                    }
+                    RenderCommand::DrawIndexed {
+                        primitive,
+                        first,
+                        count,
+                        instances,
+                    } => {
+                        self.check_topology(pipeline.as_deref(), *primitive);
+                        self.bind_descriptors(device, cmd, descriptors, pipeline.as_deref(), &mut bindings);
+                        device.cmd_draw_indexed(cmd, *count, *instances, *first, 0, 0);
+                    }
+                    // The frames drawing offscreen are rejected by `swap_buffers`.
+                    RenderCommand::SetRenderTarget(_) => (),
+                    // The whole frame is submitted at once when the buffers are swapped.
+                    RenderCommand::Flush => (),
+                    // Recorded by `record_barriers` before the render pass.
+                    RenderCommand::Barrier { .. } => (),
+                }
+            }
+        }
+    }
+
+    /// The topology is baked in the pipeline, a draw with another primitive draws with
+    /// the one of the pipeline.
+    fn check_topology(&self, pipeline : Option<&context::pipeline::Pipeline>, primitive : Primitive) {
+        if let Some(pipeline) = pipeline {
+            if pipeline.topology != vk_topology(primitive) {
+                self.report(format!(
+                    "A draw of {:?} uses a pipeline built for {:?}, the pipeline topology is used.",
+                    primitive,
+                    pipeline.topology
+                ));
+            }
+        }
+    }
+
+    /// Record the barriers of the frame before its render pass, in submission order.
+    /// The frame has no offscreen pass, every draw goes to the window pass, which reads
+    /// the textures in the access the last barrier leaves them.
+    fn record_barriers(&self, cmd : vk::CommandBuffer, commands : &CommandBuffer) {
+        for command in commands.commands() {
+            if let RenderCommand::Barrier { texture, from, to } = command {
+                if !self.ctx.barrier(cmd, *texture, *from, *to) {
+                    self.report(format!("The barrier targets the texture {}, which does not exist.", texture.0));
+                }
+            }
+        }
+    }
+
+    /// Write the set 0 of the pipeline from the bound slots, the slots it does not
+    /// declare are ignored and the missing resources left unwritten.
+    fn bind_descriptors(
+        &self,
+        device : &ash::Device,
+        cmd : vk::CommandBuffer,
+        descriptors : &mut DescriptorAllocator,
+        pipeline : Option<&context::pipeline::Pipeline>,
+        bindings : &mut Bindings,
+    ) {
+        use ash::version::DeviceV1_0;
+        let pipeline = match pipeline {
+            Some(pipeline) if bindings.dirty => pipeline,
+            _ => return,
+        };
+        bindings.dirty = false;
+        let (layout, set_bindings) = match (pipeline.set_layouts.first(), pipeline.sets.first()) {
+            (Some(layout), Some(set_bindings)) if pipeline.bindless_set != Some(0) => (*layout, set_bindings),
+            _ => return,
+        };
+        let set = match descriptors.allocate(device, layout) {
+            Ok(set) => set,
+            Err(_) => return,
+        };
+        // Reserved first, the writes point inside these vectors.
+        let mut buffer_infos = Vec::with_capacity(set_bindings.len());
+        let mut image_infos = Vec::with_capacity(set_bindings.len());
+        let mut writes = Vec::with_capacity(set_bindings.len());
+        for binding in set_bindings {
+            let write = vk::WriteDescriptorSet::builder()
+                .dst_set(set)
+                .dst_binding(binding.binding)
+                .descriptor_type(vk_descriptor_type(binding.ty));
+            match binding.ty {
+                BindingType::UniformBuffer | BindingType::StorageBuffer => {
+                    let buffer = bindings.buffers.get(&binding.binding).and_then(|h| self.ctx.buffer(*h));
+                    if let Some((buffer, offset, size)) = buffer {
+                        buffer_infos.push([vk::DescriptorBufferInfo { buffer, offset, range : size }]);
+                        writes.push(write.buffer_info(buffer_infos.last().unwrap()).build());
+                    }
+                }
+                BindingType::Texture | BindingType::Sampler | BindingType::TextureSampler => {
+                    let (texture, sampler) = match bindings.textures.get(&binding.binding) {
+                        Some(bound) => *bound,
+                        None => continue,
+                    };
+                    let view = match self.ctx.texture(texture) {
+                        Some(view) => view,
+                        None => continue,
+                    };
+                    image_infos.push([vk::DescriptorImageInfo {
+                        sampler : self.ctx.sampler(sampler),
+                        image_view : view,
+                        image_layout : vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    }]);
+                    writes.push(write.image_info(image_infos.last().unwrap()).build());
+                }
+            }
+        }
+        unsafe {
+            device.update_descriptor_sets(&writes, &[]);
+            device.cmd_bind_descriptor_sets(cmd, vk::PipelineBindPoint::GRAPHICS, pipeline.layout, 0, &[set], &[]);
         }
     }
 
 }
 
+/// Resources bound to the slots by the render commands.
+#[derive(Default)]
+struct Bindings {
+    buffers : HashMap<u32, BufferHandle>,
+    textures : HashMap<u32, (TextureHandle, Option<SamplerHandle>)>,
+    /// The set must be written again before the next draw.
+    dirty : bool,
+}
+
+/// The bytes of a uniform in a push constant block, laid out by std430 with the
+/// matrices by columns as GLSL stores them by default.
+pub fn push_constant_bytes(value : &UniformValue) -> Vec<u8> {
+    let floats : Vec<f32> = match value {
+        UniformValue::Int(v) => return v.to_ne_bytes().to_vec(),
+        UniformValue::Float(v) => vec![*v],
+        UniformValue::Vec2(v) => vec![v.x, v.y],
+        UniformValue::Vec3(v) => vec![v.x, v.y, v.z],
+        UniformValue::Vec4(v) => vec![v.x, v.y, v.z, v.w],
+        // The columns of a mat3 are padded to vec4.
+        UniformValue::Mat3(m) => (0..3)
+            .flat_map(|j| vec![m.get(0, j), m.get(1, j), m.get(2, j), 0f32])
+            .collect(),
+        UniformValue::Mat4(m) => (0..4).flat_map(|j| (0..4).map(move |i| m.get(i, j))).collect(),
+    };
+    floats.iter().flat_map(|f| f.to_ne_bytes().to_vec()).collect()
+}
+
+pub fn vk_index_type(format : IndexFormat) -> vk::IndexType {
+    match format {
+        IndexFormat::U16 => vk::IndexType::UINT16,
+        IndexFormat::U32 => vk::IndexType::UINT32,
+    }
+}
+
+/// The topology is part of a Vulkan pipeline, it can't change between draws.
+pub fn vk_topology(primitive : Primitive) -> vk::PrimitiveTopology {
+    match primitive {
+        Primitive::Points => vk::PrimitiveTopology::POINT_LIST,
+        Primitive::Lines => vk::PrimitiveTopology::LINE_LIST,
+        Primitive::LineStrip => vk::PrimitiveTopology::LINE_STRIP,
+        Primitive::Triangles => vk::PrimitiveTopology::TRIANGLE_LIST,
+        Primitive::TriangleStrip => vk::PrimitiveTopology::TRIANGLE_STRIP,
+        Primitive::TriangleFan => vk::PrimitiveTopology::TRIANGLE_FAN,
+    }
+}
+
 impl Drop for VkRenderer {
     fn drop(&mut self) {
 
@@ -24,6 +362,54 @@ impl Drop for VkRenderer {
 
 impl RendererT for VkRenderer {
 
-    fn swap_buffers(&self) {}
-}
+    /// Render targets are not supported yet, a frame drawing to one is skipped: its barriers
+    /// can't be recorded between the passes of the single window render pass.
+    fn swap_buffers(&self) {
+        let mut pending = self.pending.borrow_mut();
+        let offscreen = pending
+            .commands()
+            .iter()
+            .any(|c| matches!(c, RenderCommand::SetRenderTarget(Some(_))));
+        if offscreen {
+            self.report(String::from("Render targets are not supported yet, the frames drawing to them are skipped."));
+            pending.reset();
+            return;
+        }
+        // The render pass clears with the first clear color, before any command runs.
+        let clear_color = pending
+            .commands()
+            .iter()
+            .find_map(|c| match c {
+                RenderCommand::SetClearColor(c) => Some([c.x, c.y, c.z, c.w]),
+                _ => None,
+            })
+            .unwrap_or([0f32, 0f32, 0f32, 1f32]);
+        let result = self.ctx.draw_frame(
+            clear_color,
+            |cmd| self.record_barriers(cmd, &pending),
+            |device, cmd, descriptors| self.record(device, cmd, descriptors, &pending),
+        );
+        // A lost frame is not worth stopping the game, the next one starts over.
+        if let Err(e) = result {
+            self.logger.error(&format!("The frame was skipped: {}", e));
+        }
+        pending.reset();
+    }
+
+    fn context(&self) -> &dyn ContextT {
+        &self.ctx
+    }
 
+    fn resize(&self, width : u32, height : u32) {
+        if let Err(e) = self.ctx.resize(width, height) {
+            self.logger.warn(&format!("The swapchain will be recreated with the next frame: {}", e));
+        }
+    }
+
+    fn submit(&self, commands : &CommandBuffer) {
+        let mut pending = self.pending.borrow_mut();
+        for command in commands.commands() {
+            pending.push(command.clone());
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use rseed_math::Vec2D;
+
+/// Integer coordinates of a chunk on the world plane.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ChunkCoord {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl ChunkCoord {
+    pub fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    /// Returns the chunk containing the world position.
+    pub fn from_world(pos: Vec2D, chunk_size: f32) -> Self {
+        Self {
+            x: (pos.x / chunk_size).floor() as i32,
+            y: (pos.y / chunk_size).floor() as i32,
+        }
+    }
+
+    /// World position of the chunk lower corner.
+    pub fn origin(&self, chunk_size: f32) -> Vec2D {
+        Vec2D::new(self.x as f32 * chunk_size, self.y as f32 * chunk_size)
+    }
+
+    /// Chebyshev distance in chunks, the rings around an observer are squares.
+    pub fn distance(&self, other: &Self) -> u32 {
+        let dx = (self.x as i64 - other.x as i64).unsigned_abs();
+        let dy = (self.y as i64 - other.y as i64).unsigned_abs();
+        dx.max(dy) as u32
+    }
+
+    /// All the chunks at a distance lower or equal to `radius`, closest first.
+    pub fn ring(&self, radius: u32) -> Vec<Self> {
+        let r = radius as i32;
+        let mut coords = Vec::with_capacity(((2 * r + 1) * (2 * r + 1)) as usize);
+        for y in -r..=r {
+            for x in -r..=r {
+                coords.push(Self::new(self.x + x, self.y + y));
+            }
+        }
+        coords.sort_by_key(|c| (c.distance(self), (c.x - self.x).abs() + (c.y - self.y).abs()));
+        coords
+    }
+
+    /// Deterministic seed of the chunk derived from the world seed.
+    pub fn seed(&self, world_seed: u64) -> u64 {
+        let mut h = world_seed;
+        h = splitmix64(h ^ (self.x as u32 as u64));
+        h = splitmix64(h ^ ((self.y as u32 as u64) << 32));
+        h
+    }
+}
+
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+impl std::fmt::Display for ChunkCoord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{};{}]", self.x, self.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeds_are_deterministic_and_distinct() {
+        let coords: Vec<ChunkCoord> = ChunkCoord::new(0, 0).ring(3);
+        let seeds: Vec<u64> = coords.iter().map(|c| c.seed(42)).collect();
+        assert_eq!(seeds, coords.iter().map(|c| c.seed(42)).collect::<Vec<_>>());
+        let mut unique = seeds.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(unique.len(), seeds.len());
+        // Swapping the axes or changing the world seed changes the chunk seed.
+        assert_ne!(ChunkCoord::new(1, 2).seed(42), ChunkCoord::new(2, 1).seed(42));
+        assert_ne!(ChunkCoord::new(1, 2).seed(42), ChunkCoord::new(1, 2).seed(43));
+    }
+
+    #[test]
+    fn ring_is_a_square_closest_first() {
+        let center = ChunkCoord::new(-3, 5);
+        let ring = center.ring(2);
+        assert_eq!(ring.len(), 25);
+        assert_eq!(ring[0], center);
+        assert!(ring.iter().all(|c| c.distance(&center) <= 2));
+        assert!(ring.windows(2).all(|w| w[0].distance(&center) <= w[1].distance(&center)));
+    }
+
+    #[test]
+    fn from_world_floors_negative_positions() {
+        assert_eq!(ChunkCoord::from_world(Vec2D::new(-0.5, 31.9), 32f32), ChunkCoord::new(-1, 0));
+        assert_eq!(ChunkCoord::from_world(Vec2D::new(64f32, -64f32), 32f32), ChunkCoord::new(2, -2));
+    }
+}
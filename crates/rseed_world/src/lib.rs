@@ -0,0 +1,10 @@
+/// This crate streams procedurally generated chunks around observers.
+pub mod cache;
+pub mod coord;
+pub mod manager;
+pub mod pool;
+pub mod terrain;
+
+pub use coord::ChunkCoord;
+pub use manager::{ChunkEvent, ChunkGenerator, ChunkManager, ObserverId, StreamingConfig};
+pub use terrain::{ChunkMesh, TerrainChunk, TerrainGenerator};
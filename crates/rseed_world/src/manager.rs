@@ -0,0 +1,308 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc,
+    },
+};
+
+use rseed_math::Vec2D;
+
+use super::{cache::LruCache, coord::ChunkCoord, pool::ThreadPool};
+
+/// Generates the content of a chunk, called from the worker threads.
+/// The same coordinates and seed must always give the same chunk.
+pub trait ChunkGenerator: Send + Sync + 'static {
+    type Chunk: Send + 'static;
+
+    fn generate(&self, coord: ChunkCoord, seed: u64) -> Self::Chunk;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StreamingConfig {
+    /// Size of a chunk side in world units.
+    pub chunk_size: f32,
+    /// Chunks up to this distance from an observer are loaded.
+    pub load_radius: u32,
+    /// Chunks farther than this from every observer are unloaded.
+    /// Keeping it above `load_radius` avoids reloading chunks on a border.
+    pub unload_radius: u32,
+    /// Number of unloaded chunks kept around in case they are needed again.
+    pub cache_capacity: usize,
+    /// Maximum number of generation jobs in flight.
+    pub max_jobs: usize,
+    /// Number of worker threads, `0` uses one per core.
+    pub workers: usize,
+    pub world_seed: u64,
+}
+
+impl Default for StreamingConfig {
+    fn default() -> Self {
+        Self {
+            chunk_size: 32f32,
+            load_radius: 4,
+            unload_radius: 6,
+            cache_capacity: 64,
+            max_jobs: 16,
+            workers: 0,
+            world_seed: 0,
+        }
+    }
+}
+
+pub type ObserverId = usize;
+
+/// What changed during an update, so the caller can attach or detach
+/// the chunk subtree from the scene.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ChunkEvent {
+    Loaded(ChunkCoord),
+    Unloaded(ChunkCoord),
+}
+
+/// Keep the chunks around the observers loaded, generating them on a thread pool.
+pub struct ChunkManager<G: ChunkGenerator> {
+    config: StreamingConfig,
+    generator: Arc<G>,
+    pool: ThreadPool,
+    observers: HashMap<ObserverId, ChunkCoord>,
+    next_observer: ObserverId,
+    loaded: HashMap<ChunkCoord, G::Chunk>,
+    pending: HashSet<ChunkCoord>,
+    cache: LruCache<ChunkCoord, G::Chunk>,
+    sender: Sender<(ChunkCoord, G::Chunk)>,
+    receiver: Receiver<(ChunkCoord, G::Chunk)>,
+}
+
+impl<G: ChunkGenerator> ChunkManager<G> {
+    pub fn new(generator: G, config: StreamingConfig) -> Self {
+        let pool = match config.workers {
+            0 => ThreadPool::with_available_parallelism(),
+            n => ThreadPool::new(n),
+        };
+        let (sender, receiver) = channel();
+        Self {
+            config,
+            generator: Arc::new(generator),
+            pool,
+            observers: HashMap::new(),
+            next_observer: 0,
+            loaded: HashMap::new(),
+            pending: HashSet::new(),
+            cache: LruCache::new(config.cache_capacity),
+            sender,
+            receiver,
+        }
+    }
+
+    pub fn config(&self) -> &StreamingConfig {
+        &self.config
+    }
+
+    pub fn add_observer(&mut self, pos: Vec2D) -> ObserverId {
+        let id = self.next_observer;
+        self.next_observer += 1;
+        self.observers
+            .insert(id, ChunkCoord::from_world(pos, self.config.chunk_size));
+        id
+    }
+
+    pub fn move_observer(&mut self, id: ObserverId, pos: Vec2D) {
+        if let Some(coord) = self.observers.get_mut(&id) {
+            *coord = ChunkCoord::from_world(pos, self.config.chunk_size);
+        }
+    }
+
+    pub fn remove_observer(&mut self, id: ObserverId) {
+        self.observers.remove(&id);
+    }
+
+    pub fn get(&self, coord: &ChunkCoord) -> Option<&G::Chunk> {
+        self.loaded.get(coord)
+    }
+
+    pub fn is_loaded(&self, coord: &ChunkCoord) -> bool {
+        self.loaded.contains_key(coord)
+    }
+
+    pub fn loaded(&self) -> impl Iterator<Item = (&ChunkCoord, &G::Chunk)> {
+        self.loaded.iter()
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    fn is_wanted(&self, coord: &ChunkCoord, radius: u32) -> bool {
+        self.observers.values().any(|o| o.distance(coord) <= radius)
+    }
+
+    /// Collect finished jobs, unload far chunks and schedule the missing ones.
+    pub fn update(&mut self) -> Vec<ChunkEvent> {
+        let mut events = Vec::new();
+
+        // Finished generation jobs.
+        while let Ok((coord, chunk)) = self.receiver.try_recv() {
+            self.pending.remove(&coord);
+            if self.is_wanted(&coord, self.config.unload_radius) {
+                self.loaded.insert(coord, chunk);
+                events.push(ChunkEvent::Loaded(coord));
+            } else {
+                self.cache.insert(coord, chunk);
+            }
+        }
+
+        // Unload the chunks out of every observer range. The farthest enter the cache first
+        // so they are evicted first, whatever the order of the map.
+        let mut far: Vec<(u32, ChunkCoord)> = self
+            .loaded
+            .keys()
+            .filter(|c| !self.is_wanted(c, self.config.unload_radius))
+            .map(|c| (self.observers.values().map(|o| o.distance(c)).min().unwrap_or(u32::MAX), *c))
+            .collect();
+        far.sort_by(|a, b| b.cmp(a));
+        for (_, coord) in far {
+            if let Some(chunk) = self.loaded.remove(&coord) {
+                self.cache.insert(coord, chunk);
+                events.push(ChunkEvent::Unloaded(coord));
+            }
+        }
+
+        // Load the chunks in range, closest first.
+        let mut centers: Vec<ChunkCoord> = self.observers.values().cloned().collect();
+        centers.sort();
+        centers.dedup();
+        for center in centers {
+            for coord in center.ring(self.config.load_radius) {
+                if self.loaded.contains_key(&coord) || self.pending.contains(&coord) {
+                    continue;
+                }
+                if let Some(chunk) = self.cache.remove(&coord) {
+                    self.loaded.insert(coord, chunk);
+                    events.push(ChunkEvent::Loaded(coord));
+                } else if self.pending.len() < self.config.max_jobs {
+                    self.spawn(coord);
+                }
+            }
+        }
+        events
+    }
+
+    fn spawn(&mut self, coord: ChunkCoord) {
+        self.pending.insert(coord);
+        let generator = self.generator.clone();
+        let sender = self.sender.clone();
+        let seed = coord.seed(self.config.world_seed);
+        self.pool.execute(move || {
+            let chunk = generator.generate(coord, seed);
+            // The manager may be gone, the chunk is simply dropped then.
+            let _ = sender.send((coord, chunk));
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct SeedGenerator;
+
+    impl ChunkGenerator for SeedGenerator {
+        type Chunk = u64;
+
+        fn generate(&self, _coord: ChunkCoord, seed: u64) -> u64 {
+            seed
+        }
+    }
+
+    fn config() -> StreamingConfig {
+        StreamingConfig {
+            chunk_size: 10f32,
+            load_radius: 1,
+            unload_radius: 2,
+            cache_capacity: 4,
+            max_jobs: 64,
+            workers: 2,
+            world_seed: 7,
+        }
+    }
+
+    /// Update until every job is done, returns all the events.
+    fn settle(manager: &mut ChunkManager<SeedGenerator>) -> Vec<ChunkEvent> {
+        let mut events = manager.update();
+        for _ in 0..1000 {
+            if manager.pending_count() == 0 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            events.extend(manager.update());
+        }
+        assert_eq!(manager.pending_count(), 0);
+        events.extend(manager.update());
+        events
+    }
+
+    #[test]
+    fn loads_the_ring_around_an_observer() {
+        let mut manager = ChunkManager::new(SeedGenerator, config());
+        manager.add_observer(Vec2D::new(5f32, 5f32));
+        let events = settle(&mut manager);
+        assert_eq!(events.len(), 9);
+        for coord in ChunkCoord::new(0, 0).ring(1) {
+            assert_eq!(manager.get(&coord), Some(&coord.seed(7)));
+        }
+        assert_eq!(manager.loaded().count(), 9);
+    }
+
+    #[test]
+    fn unloads_past_the_unload_ring() {
+        let mut manager = ChunkManager::new(SeedGenerator, config());
+        let observer = manager.add_observer(Vec2D::new(5f32, 5f32));
+        settle(&mut manager);
+
+        // Two chunks away only the column three chunks behind leaves the unload ring.
+        manager.move_observer(observer, Vec2D::new(25f32, 5f32));
+        let events = settle(&mut manager);
+        let unloaded: Vec<&ChunkEvent> = events.iter().filter(|e| matches!(e, ChunkEvent::Unloaded(_))).collect();
+        assert_eq!(unloaded.len(), 3);
+        assert!(events.contains(&ChunkEvent::Unloaded(ChunkCoord::new(-1, 0))));
+        assert!(manager.is_loaded(&ChunkCoord::new(0, 0)));
+
+        manager.move_observer(observer, Vec2D::new(45f32, 5f32));
+        let events = settle(&mut manager);
+        assert!(events.contains(&ChunkEvent::Unloaded(ChunkCoord::new(0, 0))));
+        assert!(!manager.is_loaded(&ChunkCoord::new(1, 0)));
+        assert!(manager.loaded().all(|(c, _)| c.distance(&ChunkCoord::new(4, 0)) <= 2));
+    }
+
+    #[test]
+    fn reloads_from_the_cache() {
+        // The two moves unload 18 chunks, all of them stay cached.
+        let config = StreamingConfig {
+            cache_capacity: 18,
+            ..config()
+        };
+        let mut manager = ChunkManager::new(SeedGenerator, config);
+        let observer = manager.add_observer(Vec2D::new(5f32, 5f32));
+        settle(&mut manager);
+        manager.move_observer(observer, Vec2D::new(105f32, 5f32));
+        settle(&mut manager);
+        assert!(!manager.is_loaded(&ChunkCoord::new(0, 0)));
+
+        // Back home, the cached chunk comes back in the same update without a job.
+        manager.move_observer(observer, Vec2D::new(5f32, 5f32));
+        manager.update();
+        assert!(manager.is_loaded(&ChunkCoord::new(0, 0)));
+    }
+
+    #[test]
+    fn removed_observer_unloads_everything() {
+        let mut manager = ChunkManager::new(SeedGenerator, config());
+        let observer = manager.add_observer(Vec2D::new(5f32, 5f32));
+        settle(&mut manager);
+        manager.remove_observer(observer);
+        let events = manager.update();
+        assert_eq!(events.len(), 9);
+        assert_eq!(manager.loaded().count(), 0);
+    }
+}
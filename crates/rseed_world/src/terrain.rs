@@ -0,0 +1,148 @@
+use rseed_math::{noise::FractalNoise, Vec2D, Vec3D};
+
+use super::{coord::ChunkCoord, manager::ChunkGenerator};
+
+/// The triangles of a chunk, positions relative to the chunk origin, Z up.
+#[derive(Debug, Clone, Default)]
+pub struct ChunkMesh {
+    pub positions: Vec<Vec3D>,
+    pub normals: Vec<Vec3D>,
+    pub indices: Vec<u32>,
+}
+
+/// A generated piece of terrain, ready to be attached under a node placed at `origin`.
+#[derive(Debug, Clone)]
+pub struct TerrainChunk {
+    pub coord: ChunkCoord,
+    /// World position of the chunk lower corner.
+    pub origin: Vec3D,
+    /// Heights of the `(resolution + 1)²` grid points, row by row.
+    pub heights: Vec<f32>,
+    pub mesh: ChunkMesh,
+}
+
+/// Height map terrain sampled from fractal noise and meshed as a regular grid.
+/// The noise is seeded by the world seed so neighbouring chunks share their borders.
+pub struct TerrainGenerator {
+    noise: FractalNoise,
+    chunk_size: f32,
+    /// Number of grid cells along a chunk side.
+    resolution: u32,
+    /// Height of the terrain where the noise is 1.
+    height_scale: f32,
+}
+
+impl TerrainGenerator {
+    /// `chunk_size` must be the one of the `StreamingConfig` of the manager.
+    pub fn new(world_seed: u64, chunk_size: f32, resolution: u32, height_scale: f32) -> Self {
+        Self {
+            noise: FractalNoise::new(world_seed as i64, 1f32 / (4f32 * chunk_size)),
+            chunk_size,
+            resolution: resolution.max(1),
+            height_scale,
+        }
+    }
+
+    pub fn with_noise(mut self, noise: FractalNoise) -> Self {
+        self.noise = noise;
+        self
+    }
+
+    pub fn resolution(&self) -> u32 {
+        self.resolution
+    }
+
+    /// Terrain height at a world position.
+    pub fn height_at(&self, pos: Vec2D) -> f32 {
+        0.5 * (self.noise.eval(pos) + 1f32) * self.height_scale
+    }
+
+    /// Normal from the central differences of the height, continuous across the chunks.
+    fn normal_at(&self, pos: Vec2D, step: f32) -> Vec3D {
+        let dx = self.height_at(pos + Vec2D::new(step, 0f32)) - self.height_at(pos - Vec2D::new(step, 0f32));
+        let dy = self.height_at(pos + Vec2D::new(0f32, step)) - self.height_at(pos - Vec2D::new(0f32, step));
+        Vec3D::new(-dx, -dy, 2f32 * step).normalized()
+    }
+}
+
+impl ChunkGenerator for TerrainGenerator {
+    type Chunk = TerrainChunk;
+
+    fn generate(&self, coord: ChunkCoord, _seed: u64) -> TerrainChunk {
+        let origin = coord.origin(self.chunk_size);
+        let n = self.resolution;
+        let step = self.chunk_size / n as f32;
+        let side = (n + 1) as usize;
+
+        let mut heights = Vec::with_capacity(side * side);
+        let mut mesh = ChunkMesh::default();
+        for j in 0..=n {
+            for i in 0..=n {
+                let local = Vec2D::new(i as f32 * step, j as f32 * step);
+                let world = origin + local;
+                let height = self.height_at(world);
+                heights.push(height);
+                mesh.positions.push(Vec3D::new(local.x, local.y, height));
+                mesh.normals.push(self.normal_at(world, step));
+            }
+        }
+        // Two triangles per cell, counter clockwise seen from above.
+        for j in 0..n {
+            for i in 0..n {
+                let a = j * (n + 1) + i;
+                let b = a + 1;
+                let c = a + n + 1;
+                let d = c + 1;
+                mesh.indices.extend_from_slice(&[a, b, d, a, d, c]);
+            }
+        }
+        TerrainChunk {
+            coord,
+            origin: Vec3D::new(origin.x, origin.y, 0f32),
+            heights,
+            mesh,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_counts() {
+        let generator = TerrainGenerator::new(3, 16f32, 8, 10f32);
+        let chunk = generator.generate(ChunkCoord::new(0, 0), 0);
+        assert_eq!(chunk.heights.len(), 81);
+        assert_eq!(chunk.mesh.positions.len(), 81);
+        assert_eq!(chunk.mesh.normals.len(), 81);
+        assert_eq!(chunk.mesh.indices.len(), 8 * 8 * 6);
+        assert!(chunk.mesh.indices.iter().all(|i| *i < 81));
+        assert!(chunk.heights.iter().all(|h| (0f32..=10f32).contains(h)));
+    }
+
+    #[test]
+    fn neighbours_share_their_border() {
+        let generator = TerrainGenerator::new(3, 16f32, 8, 10f32);
+        let left = generator.generate(ChunkCoord::new(-1, 2), 0);
+        let right = generator.generate(ChunkCoord::new(0, 2), 0);
+        for j in 0..=8 {
+            let a = j * 9 + 8;
+            let b = j * 9;
+            assert_eq!(left.heights[a], right.heights[b]);
+            assert_eq!(left.mesh.normals[a], right.mesh.normals[b]);
+        }
+        assert_eq!(right.origin, Vec3D::new(0f32, 32f32, 0f32));
+    }
+
+    #[test]
+    fn triangles_face_up() {
+        let generator = TerrainGenerator::new(3, 16f32, 4, 1f32);
+        let chunk = generator.generate(ChunkCoord::new(1, 1), 0);
+        let p = &chunk.mesh.positions;
+        for t in chunk.mesh.indices.chunks(3) {
+            let (a, b, c) = (p[t[0] as usize], p[t[1] as usize], p[t[2] as usize]);
+            assert!((b - a).cross(&(c - a)).z > 0f32);
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{
+    parse_macro_input, spanned::Spanned, Attribute, Data, DeriveInput, Fields, Ident, Index, Lit, Member, Meta,
+    NestedMeta,
+};
+
+/// How a field becomes an attribute, from its `#[vertex(...)]` attributes.
+#[derive(Default)]
+struct FieldArgs {
+    location: Option<u32>,
+    format: Option<Ident>,
+    normalized: bool,
+    skip: bool,
+}
+
+/// The `#[vertex(...)]` attributes of the struct.
+#[derive(Default)]
+struct StructArgs {
+    divisor: u32,
+}
+
+/// The `key = value` and `key` items of the `#[vertex(...)]` attributes.
+fn vertex_items(attrs: &[Attribute]) -> syn::Result<Vec<Meta>> {
+    let mut items = Vec::new();
+    for attr in attrs.iter().filter(|a| a.path.is_ident("vertex")) {
+        match attr.parse_meta()? {
+            Meta::List(list) => {
+                for nested in list.nested {
+                    match nested {
+                        NestedMeta::Meta(meta) => items.push(meta),
+                        NestedMeta::Lit(lit) => return Err(syn::Error::new(lit.span(), "Expected a vertex option.")),
+                    }
+                }
+            }
+            meta => return Err(syn::Error::new(meta.span(), "Expected #[vertex(...)].")),
+        }
+    }
+    Ok(items)
+}
+
+fn int_value(lit: &Lit) -> syn::Result<u32> {
+    match lit {
+        Lit::Int(int) => int.base10_parse(),
+        lit => Err(syn::Error::new(lit.span(), "Expected an integer.")),
+    }
+}
+
+impl FieldArgs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut args = Self::default();
+        for item in vertex_items(attrs)? {
+            match &item {
+                Meta::Path(path) if path.is_ident("normalized") => args.normalized = true,
+                Meta::Path(path) if path.is_ident("skip") => args.skip = true,
+                Meta::NameValue(nv) if nv.path.is_ident("location") => args.location = Some(int_value(&nv.lit)?),
+                Meta::NameValue(nv) if nv.path.is_ident("format") => match &nv.lit {
+                    Lit::Str(s) => args.format = Some(Ident::new(&s.value(), s.span())),
+                    lit => return Err(syn::Error::new(lit.span(), "Expected the name of a VertexFormat.")),
+                },
+                item => {
+                    return Err(syn::Error::new(
+                        item.span(),
+                        "Invalid vertex field option, expected location, format, normalized or skip.",
+                    ))
+                }
+            }
+        }
+        if args.normalized && args.format.is_some() {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "An explicit format already tells whether it is normalized.",
+            ));
+        }
+        Ok(args)
+    }
+}
+
+impl StructArgs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut args = Self::default();
+        for item in vertex_items(attrs)? {
+            match &item {
+                Meta::Path(path) if path.is_ident("instanced") => args.divisor = 1,
+                Meta::NameValue(nv) if nv.path.is_ident("divisor") => args.divisor = int_value(&nv.lit)?,
+                item => {
+                    return Err(syn::Error::new(
+                        item.span(),
+                        "Invalid vertex option, expected instanced or divisor.",
+                    ))
+                }
+            }
+        }
+        Ok(args)
+    }
+}
+
+fn is_repr_c(attrs: &[Attribute]) -> bool {
+    attrs.iter().filter(|a| a.path.is_ident("repr")).any(|a| match a.parse_meta() {
+        Ok(Meta::List(list)) => list
+            .nested
+            .iter()
+            .any(|n| matches!(n, NestedMeta::Meta(Meta::Path(p)) if p.is_ident("C"))),
+        _ => false,
+    })
+}
+
+fn impl_vertex(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new(input.generics.span(), "A vertex can't be generic."));
+    }
+    if !is_repr_c(&input.attrs) {
+        return Err(syn::Error::new(
+            name.span(),
+            "A vertex must be #[repr(C)], the field order is the memory layout.",
+        ));
+    }
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => return Err(syn::Error::new(name.span(), "Only a struct can be a vertex.")),
+    };
+    let args = StructArgs::parse(&input.attrs)?;
+    let divisor = args.divisor;
+
+    let mut sizes = Vec::new();
+    let mut attributes = Vec::new();
+    let members: Vec<Member> = match fields {
+        Fields::Named(named) => named
+            .named
+            .iter()
+            .map(|f| Member::Named(f.ident.clone().unwrap()))
+            .collect(),
+        Fields::Unnamed(unnamed) => (0..unnamed.unnamed.len())
+            .map(|i| Member::Unnamed(Index::from(i)))
+            .collect(),
+        Fields::Unit => Vec::new(),
+    };
+    for (field, member) in fields.iter().zip(members) {
+        let ty = &field.ty;
+        sizes.push(quote! { ::std::mem::size_of::<#ty>() });
+        let field_args = FieldArgs::parse(&field.attrs)?;
+        if field_args.skip {
+            continue;
+        }
+        let format = match (&field_args.format, field_args.normalized) {
+            (Some(format), _) => quote! { ::rseed_renderapi::buffer::VertexFormat::#format },
+            (None, true) => quote! { <#ty as ::rseed_renderapi::buffer::NormalizedFormat>::FORMAT },
+            (None, false) => quote! { <#ty as ::rseed_renderapi::buffer::AttributeFormat>::FORMAT },
+        };
+        let location = match field_args.location {
+            Some(location) => quote! { #location },
+            None => quote! { layout.next_location() },
+        };
+        let check_size = field_args.format.as_ref().map(|_| {
+            quote! {
+                debug_assert_eq!(format.size() as usize, ::std::mem::size_of::<#ty>(), "Format of a vertex field.");
+            }
+        });
+        attributes.push(quote! {
+            let format = #format;
+            #check_size
+            let offset = unsafe {
+                (::std::ptr::addr_of!((*base).#member) as *const u8).offset_from(base as *const u8)
+            };
+            layout.attributes.push(::rseed_renderapi::buffer::VertexAttribute {
+                location: #location,
+                format,
+                offset: offset as u32,
+            });
+        });
+    }
+
+    Ok(quote! {
+        unsafe impl ::rseed_renderapi::buffer::Vertex for #name {
+            fn layout() -> ::rseed_renderapi::buffer::VertexLayout {
+                // The padding bytes would be uploaded uninitialized, the fields must fill the struct.
+                let _: [(); ::std::mem::size_of::<#name>()] = [(); 0 #(+ #sizes)*];
+                let vertex = ::std::mem::MaybeUninit::<#name>::uninit();
+                let base = vertex.as_ptr();
+                let mut layout = ::rseed_renderapi::buffer::VertexLayout::new();
+                #(#attributes)*
+                layout.stride = ::std::mem::size_of::<#name>() as u32;
+                layout.divisor = #divisor;
+                layout
+            }
+        }
+    })
+}
+
+/// Implement `rseed_renderapi::buffer::Vertex` for a `#[repr(C)]` struct without padding.
+///
+/// Each field is an attribute at the location following the previous one, its format comes
+/// from its type, `f32`, `Vec2D`, `[u8; 4]` and so on. The options of a field are:
+/// - `#[vertex(location = 3)]` an explicit location, the others take the one after the highest so far.
+/// - `#[vertex(normalized)]` integers read as floats in `[0, 1]` or `[-1, 1]`.
+/// - `#[vertex(format = "Half2")]` a `VertexFormat` the type can't tell, like half floats.
+/// - `#[vertex(skip)]` not an attribute, the field is still uploaded.
+///
+/// `#[vertex(instanced)]` or `#[vertex(divisor = 2)]` on the struct makes it per instance data.
+///
+/// ```ignore
+/// #[repr(C)]
+/// #[derive(Copy, Clone, Vertex)]
+/// struct ColorVertex {
+///     position: Vec3D,
+///     #[vertex(normalized)]
+///     color: [u8; 4],
+/// }
+/// ```
+#[proc_macro_derive(Vertex, attributes(vertex))]
+pub fn vertex_derive(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    impl_vertex(input).unwrap_or_else(|e| e.to_compile_error()).into()
+}
@@ -1,6 +1,11 @@
+#version 450
 
+layout(location = 0) in vec2 v_uv;
+
+layout(location = 0) out vec4 f_color;
+
+layout(binding = 1) uniform sampler2D u_texture;
 
 void main() {
-    vec2 uv = gl_FragCoord.xy;
-    
-}
\ No newline at end of file
+    f_color = texture(u_texture, v_uv);
+}
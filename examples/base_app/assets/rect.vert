@@ -1,6 +1,16 @@
-#version 
+#version 450
 
+layout(location = 0) in vec2 a_position;
+layout(location = 1) in vec2 a_uv;
+
+layout(location = 0) out vec2 v_uv;
+
+// Mat4 is uploaded by rows.
+layout(binding = 0, row_major) uniform Transform {
+    mat4 mvp;
+};
 
 void main() {
-    gl_Position
-}
\ No newline at end of file
+    v_uv = a_uv;
+    gl_Position = mvp * vec4(a_position, 0.0, 1.0);
+}
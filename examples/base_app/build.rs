@@ -0,0 +1,9 @@
+use std::{env, path::{Path, PathBuf}};
+
+/// The shaders are compiled to SPIR-V with the example, for the Vulkan backend.
+fn main() {
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap()).join("shaders");
+    if let Err(e) = rseed_shaderlib::build::compile_dir(Path::new("assets"), &out) {
+        panic!("{}", e);
+    }
+}